@@ -12,6 +12,8 @@ const PORT: u16 = 8080;
 
 #[tokio::main]
 async fn main() -> () {
+    let tablebase = router::load_tablebase();
+
     let app = axum::Router::new()
         .layer(CorsLayer::permissive())
         .route("/", get(|| async { "Chess Server!" }))
@@ -19,7 +21,9 @@ async fn main() -> () {
         .nest(
             "/rspc",
             router::create()
-                .endpoint(|| MyCtx {})
+                .endpoint(move || MyCtx {
+                    tablebase: tablebase.clone(),
+                })
                 .axum()
                 .layer(CorsLayer::permissive()),
         )
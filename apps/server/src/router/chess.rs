@@ -1,14 +1,24 @@
-use std::str::FromStr;
+use std::{
+    str::FromStr,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
+use async_stream::stream;
 use engine::{
-    game::{Board, Move, BoardStatus},
+    game::{
+        clock::{Clock, TimeControl},
+        Board, BoardStatus, Move,
+    },
     primitives::{ChessPiece, Square, Piece, Team}, boards::ChessBoard,
+    search::{mate_in, skill::Skill, Limits, SearchResult, Searcher, StopSignal},
+    syzygy::Wdl,
 };
-use rspc::{RouterBuilder, Router};
+use rspc::{internal::MiddlewareBuilderLike, Router, RouterBuilder};
 
 use super::MyCtx;
 
-pub fn router() -> RouterBuilder<MyCtx> {
+pub fn router() -> RouterBuilder<MyCtx, (), impl MiddlewareBuilderLike<MyCtx, LayerContext = MyCtx> + Send> {
     Router::<MyCtx>::new()
         .query("start", |t| {
             t(|_ctx, _args: ()| ChessJSON::from(Board::default()))
@@ -26,8 +36,106 @@ pub fn router() -> RouterBuilder<MyCtx> {
                 return Ok(ChessJSON::from(board));
             })
         })
+        // The endgame review of a position, `None` when it isn't in the tablebase
+        .query("tablebase", |t| {
+            t(|ctx: MyCtx, fen: String| {
+                let board = Board::from_fen(&fen).map_err(|err| rspc::Error::new(rspc::ErrorCode::BadRequest, err.to_string()))?;
+
+                let Some(tablebase) = ctx.tablebase else {
+                    return Ok(None);
+                };
+                let Some(wdl) = board.probe_wdl(&tablebase) else {
+                    return Ok(None);
+                };
+
+                return Ok(Some(TablebaseJSON {
+                    wdl,
+                    dtz: board.probe_dtz(&tablebase),
+                    best_moves: tablebase
+                        .best_moves(&board)
+                        .unwrap_or_default()
+                        .into_iter()
+                        .map(|m| MoveJSON::from_move(board, m))
+                        .collect(),
+                    review: wdl.review(board.team_to_move),
+                }));
+            })
+        })
+        // The levels of the difficulty slider, weakest first
+        .query("skills", |t| {
+            t(|_ctx, _args: ()| {
+                (0..=Skill::MAX_LEVEL)
+                    .map(|level| SkillJSON::from(Skill::new(level)))
+                    .collect::<Vec<_>>()
+            })
+        })
+        // The starting clock of a time control written like the PGN `TimeControl` tag, e.g. "180+2"
+        .query("timeControl", |t| {
+            t(|_ctx, time_control: String| {
+                let time_control = TimeControl::from_str(&time_control)
+                    .map_err(|err| rspc::Error::new(rspc::ErrorCode::BadRequest, err.to_string()))?;
+
+                return Ok(Clock::new(time_control).state());
+            })
+        })
+        // The move of the computer at the given difficulty, `None` when the game is over
+        .query("computerMove", |t| {
+            t(|ctx: MyCtx, input: ComputerMoveJSON| async move {
+                let board = Board::from_fen(&input.fen).map_err(|err| rspc::Error::new(rspc::ErrorCode::BadRequest, err.to_string()))?;
+                let seed = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_nanos() as u64);
+
+                let mut searcher = Searcher::new(Limits::time(COMPUTER_MOVE_TIME)).with_skill(Skill::new(input.level), seed);
+                if let Some(tablebase) = ctx.tablebase {
+                    searcher = searcher.with_tablebase(Arc::clone(&tablebase));
+                }
+                let result = tokio::task::spawn_blocking(move || searcher.search(&board))
+                    .await
+                    .map_err(|err| rspc::Error::new(rspc::ErrorCode::InternalServerError, err.to_string()))?;
+
+                return Ok(result.best_move.map(|m| MoveJSON::from_move(board, m)));
+            })
+        })
         // TODO: fen router
         .merge("fen.", Router::<MyCtx>::new())
+        // A subscription can't fail once its stream is created, so its FEN is checked beforehand
+        .middleware(|mw| {
+            mw.middleware(|mw| async move {
+                if let Some(fen) = mw.input.get("fen").and_then(|fen| fen.as_str()) {
+                    Board::from_fen(fen).map_err(|err| rspc::Error::new(rspc::ErrorCode::BadRequest, err.to_string()))?;
+                }
+                return Ok(mw);
+            })
+        })
+        // The live analysis of a position, an update being sent at every depth until the client unsubscribes
+        .subscription("analysis", |t| {
+            t(|ctx: MyCtx, input: AnalysisInputJSON| {
+                stream! {
+                    let Ok(board) = Board::from_fen(&input.fen) else {
+                        return;
+                    };
+
+                    let mut searcher = Searcher::new(Limits::default()).with_multi_pv(input.lines.into());
+                    if let Some(tablebase) = ctx.tablebase {
+                        searcher = searcher.with_tablebase(Arc::clone(&tablebase));
+                    }
+                    let _stop = StopOnDrop(searcher.stop_signal());
+
+                    // The results are waited for on a blocking thread, then forwarded to the stream
+                    let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+                    tokio::task::spawn_blocking(move || {
+                        for result in searcher.analyze(&board) {
+                            if sender.send(AnalysisJSON::new(board, &result)).is_err() {
+                                break;
+                            }
+                        }
+                    });
+
+                    while let Some(update) = receiver.recv().await {
+                        yield update;
+                    }
+                }
+            })
+        })
 }
 
 #[derive(Debug, rspc::Type, serde::Serialize, serde::Deserialize)]
@@ -63,6 +171,120 @@ impl MoveJSON {
     
 }
 
+/// The time the computer thinks about its moves
+const COMPUTER_MOVE_TIME: Duration = Duration::from_secs(1);
+
+#[derive(Debug, rspc::Type, serde::Serialize, serde::Deserialize)]
+struct ComputerMoveJSON {
+    fen: String,
+    /// From 0 to `Skill::MAX_LEVEL` (full strength)
+    level: u8,
+}
+
+#[derive(Debug, rspc::Type, serde::Serialize, serde::Deserialize)]
+struct SkillJSON {
+    level: u8,
+    /// The approximate Elo rating of the level
+    elo: u32,
+}
+
+impl From<Skill> for SkillJSON {
+    fn from(skill: Skill) -> Self {
+        Self {
+            level: skill.level(),
+            elo: skill.elo(),
+        }
+    }
+}
+
+/// Stops the search of an analysis once the client unsubscribes, dropping its stream
+struct StopOnDrop(StopSignal);
+
+impl Drop for StopOnDrop {
+    fn drop(&mut self) {
+        self.0.stop();
+    }
+}
+
+#[derive(Debug, rspc::Type, serde::Serialize, serde::Deserialize)]
+struct AnalysisInputJSON {
+    fen: String,
+    /// The number of best lines to analyse, at least one
+    lines: u8,
+}
+
+#[derive(Debug, rspc::Type, serde::Serialize, serde::Deserialize)]
+struct AnalysisJSON {
+    depth: u8,
+    /// A float as rspc doesn't export 64 bit integers, the count outgrowing a `u32` in long analyses
+    nodes: f64,
+    /// Nodes per second
+    nps: u32,
+    /// The permille of the transposition table in use
+    hashfull: u32,
+    /// In milliseconds
+    time: u32,
+    /// The best lines, best first
+    lines: Vec<AnalysisLineJSON>,
+}
+
+impl AnalysisJSON {
+    fn new(board: Board, result: &SearchResult) -> Self {
+        Self {
+            depth: result.depth,
+            nodes: result.nodes as f64,
+            nps: result.nps().try_into().unwrap_or(u32::MAX),
+            hashfull: result.hashfull as u32,
+            time: result.time.as_millis().try_into().unwrap_or(u32::MAX),
+            lines: result
+                .lines
+                .iter()
+                .map(|line| AnalysisLineJSON::new(board, line.score, &line.pv))
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, rspc::Type, serde::Serialize, serde::Deserialize)]
+struct AnalysisLineJSON {
+    /// The score in centipawns for the team to move, `None` when there is a mate
+    score: Option<i32>,
+    /// The number of moves until mate, positive when the team to move mates
+    mate: Option<i32>,
+    /// The principal variation, starting with the best move
+    moves: Vec<MoveJSON>,
+}
+
+impl AnalysisLineJSON {
+    fn new(mut board: Board, score: i32, pv: &[Move]) -> Self {
+        let mate = mate_in(score);
+
+        Self {
+            score: if mate.is_none() { Some(score) } else { None },
+            mate,
+            moves: pv
+                .iter()
+                .map(|&chess_move| {
+                    let json = MoveJSON::from_move(board, chess_move);
+                    board.make_move(chess_move);
+                    json
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, rspc::Type, serde::Serialize, serde::Deserialize)]
+struct TablebaseJSON {
+    wdl: Wdl,
+    /// The distance to the next capture or pawn move, in plies, positive for the team to move winning
+    dtz: Option<i32>,
+    #[serde(rename = "bestMoves")]
+    best_moves: Vec<MoveJSON>,
+    /// e.g. "This is a tablebase win for White"
+    review: String,
+}
+
 #[derive(Debug, rspc::Type, serde::Serialize, serde::Deserialize)]
 struct ChessJSON {
     #[serde(rename = "teamToMove")]
@@ -100,6 +322,7 @@ impl Into<Board> for ChessJSON {
     fn into(self) -> Board {
         let mut board = Board::new(self.board.0);
         board.team_to_move = self.team_to_move;
+        board.refresh_state();
 
         return board;
     }
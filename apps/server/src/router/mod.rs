@@ -1,9 +1,31 @@
+use std::sync::Arc;
+
+use engine::syzygy::Tablebase;
 use rspc::{Config, Router};
 
 mod chess;
 
-#[derive(Clone, Copy, Debug)]
-pub struct MyCtx {}
+#[derive(Clone, Debug)]
+pub struct MyCtx {
+    /// The Syzygy tablebases of the endgame review, if any
+    pub tablebase: Option<Arc<Tablebase>>,
+}
+
+/// Opens the tablebases of the directories in the `SYZYGY_PATH` environment variable, if set
+pub fn load_tablebase() -> Option<Arc<Tablebase>> {
+    let path = std::env::var_os("SYZYGY_PATH")?;
+
+    match Tablebase::open(&path) {
+        Ok(tablebase) => {
+            println!("Found {} tablebase files (up to {} pieces)", tablebase.len(), tablebase.max_pieces());
+            Some(Arc::new(tablebase))
+        }
+        Err(error) => {
+            println!("{error}");
+            None
+        }
+    }
+}
 
 pub fn create() -> std::sync::Arc<Router<MyCtx>> {
     Router::<MyCtx>::new()
@@ -4,26 +4,72 @@
 
 export type Procedures = {
     queries: 
+        { key: "chess.computerMove", input: ComputerMoveJSON, result: MoveJSON | null } | 
+        { key: "chess.skills", input: never, result: SkillJSON[] } | 
         { key: "chess.start", input: never, result: ChessJSON } | 
+        { key: "chess.tablebase", input: string, result: TablebaseJSON | null } | 
+        { key: "chess.timeControl", input: string, result: ClockState } | 
         { key: "echo", input: string, result: string },
     mutations: 
         { key: "chess.move", input: [MoveJSON, ChessJSON], result: ChessJSON },
-    subscriptions: never
+    subscriptions: 
+        { key: "chess.analysis", input: AnalysisInputJSON, result: AnalysisJSON }
 };
 
+/**
+ * What is the status of this game?
+ */
+export type BoardStatus = "Ongoing" | "Stalemate" | "Checkmate" | { Draw: DrawReason }
+
 export type Team = "White" | "Black"
 
-export type ChessJSON = { teamToMove: Team; moves: MoveJSON[]; board: ChessBoard; status: BoardStatus }
+/**
+ * A snapshot of a `Clock`, with the remaining times in milliseconds
+ */
+export type ClockState = { white: number; black: number; running: Team | null; flagged: Team | null; timeControl: TimeControl }
 
-export type MoveJSON = { origin: string; destination: string; takes: boolean; piece: Piece; promotion: Piece | null }
+export type Piece = "Pawn" | "Knight" | "Bishop" | "Rook" | "Queen" | "King"
+
+export type AnalysisLineJSON = { score: number | null; mate: number | null; moves: MoveJSON[] }
+
+export type AnalysisInputJSON = { fen: string; lines: number }
+
+/**
+ * The reasons a game ends in a draw, besides stalemate
+ */
+export type DrawReason = "Agreement" | "ThreefoldRepetition" | "FivefoldRepetition" | "FiftyMoveRule" | "SeventyFiveMoveRule" | "InsufficientMaterial" | "TimeoutVsInsufficientMaterial"
+
+export type ChessJSON = { teamToMove: Team; moves: MoveJSON[]; board: ChessBoard; status: BoardStatus }
 
 export type ChessBoard = (ChessPiece | null)[]
 
+/**
+ * The time each team gets to play its moves, as a list of periods.
+ * 
+ * When the moves of the last period are played, the last period starts again.
+ */
+export type TimeControl = { periods: Period[] }
+
+export type TablebaseJSON = { wdl: Wdl; dtz: number | null; bestMoves: MoveJSON[]; review: string }
+
+export type ComputerMoveJSON = { fen: string; level: number }
+
 export type ChessPiece = { team: Team; variant: Piece }
 
+export type AnalysisJSON = { depth: number; nodes: number; nps: number; hashfull: number; time: number; lines: AnalysisLineJSON[] }
+
 /**
- * What is the status of this game?
+ * The outcome of a position with perfect play, for the team to move
+ * 
+ * The tables tell the wins that can't be forced before the fifty-move rule applies from the other ones.
  */
-export type BoardStatus = "Ongoing" | "Stalemate" | "Checkmate"
+export type Wdl = "Loss" | "BlessedLoss" | "Draw" | "CursedWin" | "Win"
 
-export type Piece = "Pawn" | "Knight" | "Bishop" | "Rook" | "Queen" | "King"
+/**
+ * A period of a `TimeControl`, all times are in milliseconds (`u32` as rspc doesn't export 64 bit integers)
+ */
+export type Period = { moves: number | null; time: number; increment: number; delay: number }
+
+export type SkillJSON = { level: number; elo: number }
+
+export type MoveJSON = { origin: string; destination: string; takes: boolean; piece: Piece; promotion: Piece | null }
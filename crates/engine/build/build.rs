@@ -38,4 +38,9 @@ fn main() {
     generators::PawnDoubleMoveDestinationsGenerator::write_generated_value(&mut file).unwrap();
 
     generators::MagicGenerator::write_generated_array(&mut file).unwrap();
+
+    generators::ZobristPiecesGenerator::write_generated_array(&mut file).unwrap();
+    generators::ZobristCastlesGenerator::write_generated_array(&mut file).unwrap();
+    generators::ZobristEnPassantGenerator::write_generated_array(&mut file).unwrap();
+    generators::ZobristSideGenerator::write_generated_value(&mut file).unwrap();
 }
@@ -21,5 +21,8 @@ pub use pawn::*;
 mod magic;
 pub use magic::*;
 
+mod zobrist;
+pub use zobrist::*;
+
 // mod bitboard_data;
 // pub use bitboard_data::*;
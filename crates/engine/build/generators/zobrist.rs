@@ -0,0 +1,79 @@
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+
+use crate::{
+    primitives::{CastleRights, Piece, Square},
+    utils::enums::ArrayEnum,
+};
+
+use super::prelude::{ArrayGenerator, ValueGenerator};
+
+/// The seed of the random number generator, so the keys stay the same between builds.
+const SEED: u64 = 0x5EED_CAFE_F00D_BABE;
+
+/// Creates a random number generator for a key table.
+/// Every table gets a different `salt` so they don't share any keys.
+fn rng(salt: u64) -> SmallRng {
+    SmallRng::seed_from_u64(SEED ^ salt.wrapping_mul(0x9E37_79B9_7F4A_7C15))
+}
+
+/// Generates the Zobrist keys for every piece of a team on every square.
+///
+/// ```ignore
+/// const ZOBRIST_PIECES = [
+///     WHITE_KEYS[piece][square],
+///     BLACK_KEYS[piece][square],
+/// ]
+/// ```
+pub struct ZobristPiecesGenerator;
+impl ArrayGenerator<[[u64; Square::AMOUNT]; 6 /* Piece::SIZE */], 2 /* Team::SIZE */>
+    for ZobristPiecesGenerator
+{
+    const NAME: &'static str = "ZOBRIST_PIECES";
+
+    // For every team
+    fn generate_index_value(index: usize) -> [[u64; Square::AMOUNT]; Piece::SIZE] {
+        let mut rng = rng(1 + index as u64);
+
+        return [(); Piece::SIZE].map(|_| [(); Square::AMOUNT].map(|_| rng.gen::<u64>()));
+    }
+}
+
+/// Generates the Zobrist keys for the `CastleRights` of a team.
+///
+/// `CastleRights::NoRights` has a key of `0`, so positions without castle rights hash the same.
+pub struct ZobristCastlesGenerator;
+impl ArrayGenerator<[u64; CastleRights::SIZE], 2 /* Team::SIZE */> for ZobristCastlesGenerator {
+    const NAME: &'static str = "ZOBRIST_CASTLES";
+
+    // For every team
+    fn generate_index_value(index: usize) -> [u64; CastleRights::SIZE] {
+        let mut rng = rng(3 + index as u64);
+
+        return CastleRights::ALL.map(|rights| match rights {
+            CastleRights::NoRights => 0,
+            _ => rng.gen::<u64>(),
+        });
+    }
+}
+
+/// Generates the Zobrist keys for the file of an en passant square.
+pub struct ZobristEnPassantGenerator;
+impl ArrayGenerator<u64, 8 /* File::SIZE */> for ZobristEnPassantGenerator {
+    const NAME: &'static str = "ZOBRIST_EN_PASSANT";
+
+    fn generate_index_value(index: usize) -> u64 {
+        let mut rng = rng(5);
+
+        return (0..=index).map(|_| rng.gen::<u64>()).last().unwrap();
+    }
+}
+
+/// Generates the Zobrist key that is toggled when black is to move.
+pub struct ZobristSideGenerator;
+impl ValueGenerator<u64> for ZobristSideGenerator {
+    const NAME: &'static str = "ZOBRIST_SIDE";
+
+    fn generate_value() -> u64 {
+        rng(6).gen::<u64>()
+    }
+}
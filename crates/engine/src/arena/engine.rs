@@ -0,0 +1,461 @@
+use std::{
+    fmt::Display,
+    io::{self, BufRead, BufReader, Read, Write},
+    process::{Child, Command, Stdio},
+    sync::mpsc::{self, Receiver, RecvTimeoutError, Sender},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    eval::Score,
+    search::MATE,
+    uci::{Go, Uci},
+};
+
+/// How long an engine gets to answer the commands that don't search
+const ANSWER_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long an engine gets to exit after `quit`, before it is killed
+const QUIT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// How to start an engine, and the UCI options to set once it started
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EngineConfig {
+    /// The name of the engine in the games
+    pub name: String,
+    pub command: String,
+    pub args: Vec<String>,
+    pub options: Vec<(String, String)>,
+}
+
+impl EngineConfig {
+    /// Runs `command`, which also names the engine
+    pub fn new(command: &str) -> Self {
+        Self {
+            name: command.to_owned(),
+            command: command.to_owned(),
+            args: Vec::new(),
+            options: Vec::new(),
+        }
+    }
+
+    pub fn with_name(mut self, name: &str) -> Self {
+        name.clone_into(&mut self.name);
+        self
+    }
+
+    pub fn with_arg(mut self, arg: &str) -> Self {
+        self.args.push(arg.to_owned());
+        self
+    }
+
+    pub fn with_option(mut self, name: &str, value: &str) -> Self {
+        self.options.push((name.to_owned(), value.to_owned()));
+        self
+    }
+}
+
+/// Everything that can go wrong while talking to an engine
+#[derive(Debug)]
+pub enum EngineError {
+    Io(io::Error),
+    /// The engine quit, or closed its output
+    Disconnected,
+    /// The answer the engine did not send in time
+    Timeout(&'static str),
+}
+
+impl Display for EngineError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "Could not talk to the engine: {error}"),
+            Self::Disconnected => write!(f, "The engine quit"),
+            Self::Timeout(answer) => write!(f, "The engine did not answer {answer} in time"),
+        }
+    }
+}
+
+impl std::error::Error for EngineError {}
+
+impl From<io::Error> for EngineError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+/// What an engine answered to `go`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Answer {
+    /// The move, in long algebraic notation
+    pub best_move: String,
+    /// The reply the engine expects, to ponder on
+    pub ponder: Option<String>,
+    /// The last score of the main line the engine reported, from its point of view
+    pub score: Option<Score>,
+    /// The last depth the engine reported
+    pub depth: Option<u32>,
+    pub time: Duration,
+}
+
+/// An engine speaking UCI, usually a child process
+///
+/// Its output is read on a separate thread, so that every answer can be waited for with a timeout.
+///
+/// ```
+/// use std::time::Duration;
+/// use engine::{arena::engine::UciEngine, uci::Go};
+///
+/// let mut engine = UciEngine::in_process("engine", &[]).unwrap();
+/// let go = Go { depth: Some(2), ..Default::default() };
+/// let answer = engine.go("position startpos moves e2e4", &go, Duration::from_secs(10)).unwrap();
+///
+/// assert_eq!(answer.depth, Some(2));
+/// assert!(answer.score.is_some());
+/// ```
+pub struct UciEngine {
+    name: String,
+    input: Box<dyn Write + Send>,
+    lines: Receiver<String>,
+    child: Option<Child>,
+}
+
+impl UciEngine {
+    /// Starts the engine of the `config`, and waits for it to be ready
+    pub fn spawn(config: &EngineConfig) -> Result<Self, EngineError> {
+        let mut child = Command::new(&config.command)
+            .args(&config.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let input = child.stdin.take().expect("The input of the engine to be piped");
+        let output = child.stdout.take().expect("The output of the engine to be piped");
+
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(output).lines().map_while(Result::ok) {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let engine = Self {
+            name: config.name.clone(),
+            input: Box::new(input),
+            lines,
+            child: Some(child),
+        };
+
+        return engine.init(&config.options);
+    }
+
+    /// Runs the engine of this crate on a separate thread, talking to it through channels instead of pipes
+    pub fn in_process(name: &str, options: &[(String, String)]) -> Result<Self, EngineError> {
+        let (input, commands) = mpsc::channel();
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            let mut uci = Uci::new(LineWriter::new(sender));
+            let _ = uci.run(BufReader::new(LineReader::new(commands)));
+        });
+
+        let engine = Self {
+            name: name.to_owned(),
+            input: Box::new(LineWriter::new(input)),
+            lines,
+            child: None,
+        };
+
+        return engine.init(options);
+    }
+
+    fn init(mut self, options: &[(String, String)]) -> Result<Self, EngineError> {
+        self.send("uci")?;
+        self.wait_for("uciok", Instant::now() + ANSWER_TIMEOUT)?;
+
+        for (name, value) in options.iter() {
+            self.send(format!("setoption name {name} value {value}"))?;
+        }
+        self.ready()?;
+
+        return Ok(self);
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Waits for the engine to be done with the previous commands
+    pub fn ready(&mut self) -> Result<(), EngineError> {
+        self.send("isready")?;
+        self.wait_for("readyok", Instant::now() + ANSWER_TIMEOUT)?;
+
+        return Ok(());
+    }
+
+    /// Tells the engine that the next position is from another game
+    pub fn new_game(&mut self) -> Result<(), EngineError> {
+        self.send("ucinewgame")?;
+
+        return self.ready();
+    }
+
+    /// Sends the `position` command and searches it with `go`, stopping the search if it has not answered within
+    /// `timeout`
+    pub fn go(&mut self, position: &str, go: &Go, timeout: Duration) -> Result<Answer, EngineError> {
+        self.send(position)?;
+        self.send(go)?;
+
+        return self.answer(timeout);
+    }
+
+    /// Starts searching the `position` of the predicted reply with `go ponder`, on the opponent's time
+    ///
+    /// The search then has to be ended with `ponderhit` or `stop_pondering`.
+    pub fn ponder(&mut self, position: &str, go: &Go) -> Result<(), EngineError> {
+        self.send(position)?;
+        self.send(Go { ponder: true, ..go.clone() })?;
+
+        return Ok(());
+    }
+
+    /// Tells the pondering engine that the predicted reply was played, and waits for its move like `go`
+    pub fn ponderhit(&mut self, timeout: Duration) -> Result<Answer, EngineError> {
+        self.send("ponderhit")?;
+
+        return self.answer(timeout);
+    }
+
+    /// Stops pondering, the predicted reply not being played, and drops the move of the search
+    pub fn stop_pondering(&mut self) -> Result<(), EngineError> {
+        self.send("stop")?;
+        self.wait_for("bestmove", Instant::now() + ANSWER_TIMEOUT)?;
+
+        return Ok(());
+    }
+
+    /// Reads the `info` lines of the search until its `bestmove`, stopping it after `timeout`
+    fn answer(&mut self, timeout: Duration) -> Result<Answer, EngineError> {
+        let start = Instant::now();
+        let mut score = None;
+        let mut depth = None;
+        loop {
+            let line = match self.read_line(start + timeout, "bestmove") {
+                Ok(line) => line,
+                Err(EngineError::Timeout(answer)) => {
+                    // Drain the late move, so that it isn't taken for the answer of the next search
+                    self.send("stop")?;
+                    self.wait_for("bestmove", Instant::now() + ANSWER_TIMEOUT)?;
+                    return Err(EngineError::Timeout(answer));
+                }
+                Err(error) => return Err(error),
+            };
+
+            let mut tokens = line.split_whitespace();
+            match tokens.next() {
+                Some("info") => parse_info(tokens, &mut score, &mut depth),
+                Some("bestmove") => {
+                    return Ok(Answer {
+                        best_move: tokens.next().unwrap_or("0000").to_owned(),
+                        ponder: tokens.skip_while(|token| *token != "ponder").nth(1).map(str::to_owned),
+                        score,
+                        depth,
+                        time: start.elapsed(),
+                    })
+                }
+                _ => {}
+            }
+        }
+    }
+
+    pub fn send(&mut self, command: impl Display) -> Result<(), EngineError> {
+        writeln!(self.input, "{command}")?;
+        self.input.flush()?;
+
+        return Ok(());
+    }
+
+    /// Skips the lines of the engine until the one starting with `answer`
+    fn wait_for(&mut self, answer: &'static str, deadline: Instant) -> Result<String, EngineError> {
+        loop {
+            let line = self.read_line(deadline, answer)?;
+            if line.split_whitespace().next() == Some(answer) {
+                return Ok(line);
+            }
+        }
+    }
+
+    fn read_line(&mut self, deadline: Instant, answer: &'static str) -> Result<String, EngineError> {
+        match self.lines.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+            Ok(line) => Ok(line),
+            Err(RecvTimeoutError::Timeout) => Err(EngineError::Timeout(answer)),
+            Err(RecvTimeoutError::Disconnected) => Err(EngineError::Disconnected),
+        }
+    }
+}
+
+/// Asks the engine to quit, killing it if it doesn't
+impl Drop for UciEngine {
+    fn drop(&mut self) {
+        let _ = self.send("quit");
+
+        if let Some(child) = &mut self.child {
+            let deadline = Instant::now() + QUIT_TIMEOUT;
+            while matches!(child.try_wait(), Ok(None)) && Instant::now() < deadline {
+                thread::sleep(Duration::from_millis(10));
+            }
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+/// Reads the score and depth of an `info` line, the lines of the other MultiPV lines being ignored
+fn parse_info<'a>(mut tokens: impl Iterator<Item = &'a str>, score: &mut Option<Score>, depth: &mut Option<u32>) {
+    let (mut line_score, mut line_depth) = (None, None);
+
+    while let Some(token) = tokens.next() {
+        match token {
+            "depth" => line_depth = tokens.next().and_then(|depth| depth.parse().ok()),
+            "multipv" if tokens.next() != Some("1") => return,
+            "score" => {
+                line_score = match (tokens.next(), tokens.next().and_then(|value| value.parse::<Score>().ok())) {
+                    (Some("cp"), Some(score)) => Some(score),
+                    (Some("mate"), Some(moves)) if moves > 0 => Some(MATE - (2 * moves - 1)),
+                    (Some("mate"), Some(moves)) => Some(-MATE - 2 * moves),
+                    _ => None,
+                }
+            }
+            // The rest of the line is the PV or a string
+            "pv" | "string" => break,
+            _ => {}
+        }
+    }
+
+    *score = line_score.or(*score);
+    *depth = line_depth.or(*depth);
+}
+
+/// Sends every line written to it through a channel
+struct LineWriter {
+    sender: Sender<String>,
+    buffer: Vec<u8>,
+}
+
+impl LineWriter {
+    fn new(sender: Sender<String>) -> Self {
+        Self {
+            sender,
+            buffer: Vec::new(),
+        }
+    }
+}
+
+impl Write for LineWriter {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(bytes);
+
+        while let Some(end) = self.buffer.iter().position(|byte| *byte == b'\n') {
+            let line = String::from_utf8_lossy(&self.buffer[..end]).into_owned();
+            self.buffer.drain(..=end);
+            self.sender
+                .send(line)
+                .map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe))?;
+        }
+
+        return Ok(bytes.len());
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Reads the lines received from a channel, until it is closed
+struct LineReader {
+    receiver: Receiver<String>,
+    line: io::Cursor<Vec<u8>>,
+}
+
+impl LineReader {
+    fn new(receiver: Receiver<String>) -> Self {
+        Self {
+            receiver,
+            line: io::Cursor::default(),
+        }
+    }
+}
+
+impl Read for LineReader {
+    fn read(&mut self, bytes: &mut [u8]) -> io::Result<usize> {
+        if self.line.position() as usize == self.line.get_ref().len() {
+            match self.receiver.recv() {
+                Ok(line) => self.line = io::Cursor::new((line + "\n").into_bytes()),
+                Err(_) => return Ok(0),
+            }
+        }
+
+        return self.line.read(bytes);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn info() {
+        let parse = |lines: &[&str]| {
+            let (mut score, mut depth) = (None, None);
+            for line in lines {
+                parse_info(line.split_whitespace().skip(1), &mut score, &mut depth);
+            }
+            (score, depth)
+        };
+
+        assert_eq!(parse(&["info depth 3 score cp 25 nodes 100 pv e2e4"]), (Some(25), Some(3)));
+        assert_eq!(parse(&["info depth 5 score mate 2 pv e2e4", "info string depth 9"]), (Some(MATE - 3), Some(5)));
+        assert_eq!(parse(&["info depth 5 score mate -1"]), (Some(-MATE + 2), Some(5)));
+        assert_eq!(parse(&["info depth 4 score cp 10", "info depth 4 multipv 2 score cp -50"]), (Some(10), Some(4)));
+        assert_eq!(parse(&["info depth 4 score cp 10", "info nodes 5000"]), (Some(10), Some(4)));
+    }
+
+    #[test]
+    fn engines() {
+        let mut engine = UciEngine::in_process("test", &[("Hash".to_owned(), "4".to_owned())]).unwrap();
+        assert_eq!(engine.name(), "test");
+        engine.new_game().unwrap();
+
+        // The search is stopped when it takes too long, and the engine can still play
+        let infinite = Go {
+            infinite: true,
+            ..Default::default()
+        };
+        let error = engine.go("position startpos", &infinite, Duration::from_millis(50));
+        assert!(matches!(error, Err(EngineError::Timeout("bestmove"))));
+
+        let go = Go {
+            depth: Some(1),
+            ..Default::default()
+        };
+        let answer = engine.go("position startpos", &go, Duration::from_secs(10)).unwrap();
+        assert!(answer.best_move.parse::<crate::Move>().is_ok());
+
+        // Pondering on the expected reply, played or not
+        let go = Go {
+            depth: Some(3),
+            ..Default::default()
+        };
+        let answer = engine.go("position startpos moves e2e4", &go, Duration::from_secs(10)).unwrap();
+        let position = format!("position startpos moves e2e4 {} {}", answer.best_move, answer.ponder.unwrap());
+        engine.ponder(&position, &go).unwrap();
+        let answer = engine.ponderhit(Duration::from_secs(10)).unwrap();
+        assert_eq!(answer.depth, Some(3));
+
+        engine.ponder("position startpos moves d2d4 d7d5 c2c4", &go).unwrap();
+        engine.stop_pondering().unwrap();
+        engine.ready().unwrap();
+
+        assert!(UciEngine::spawn(&EngineConfig::new("./no-such-engine")).is_err());
+    }
+}
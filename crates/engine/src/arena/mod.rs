@@ -0,0 +1,792 @@
+use std::{
+    fmt::Display,
+    fs,
+    io,
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc, Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+use crate::{
+    eval::Score,
+    game::{
+        actions::EndOfGameState,
+        clock::{Clock, TimeControl},
+        Board, Chess, DrawReason, Move,
+    },
+    notations::{
+        FEN::{self, FenError},
+        PGN::{self, GameResult, PgnError, PgnGame, PgnMove},
+    },
+    primitives::team::Team,
+    search::mate_in,
+    syzygy::{Tablebase, Wdl},
+    uci::Go,
+    utils::enums::ArrayEnum,
+};
+
+pub mod engine;
+pub mod sprt;
+
+use engine::{Answer, EngineError, UciEngine};
+use sprt::{MatchStats, Sprt, SprtStatus};
+
+/// How long an engine can go over its time before it is stopped, its game being lost on time anyway
+const TIMEOUT_GRACE: Duration = Duration::from_secs(1);
+
+/// A position the games of a match start from, as a starting position and the moves played from it
+#[derive(Debug, Clone, Default)]
+pub struct Opening {
+    pub board: Board,
+    pub moves: Vec<Move>,
+}
+
+/// Everything that can go wrong while reading openings
+#[derive(Debug)]
+pub enum OpeningError {
+    Io(io::Error),
+    /// The (1 based) line of an invalid FEN
+    Fen(usize, FenError),
+    Pgn(PgnError),
+    /// The file has no openings
+    Empty,
+}
+
+impl Display for OpeningError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "Could not read the openings: {error}"),
+            Self::Fen(line, error) => write!(f, "Invalid opening on line {line}: {error:?}"),
+            Self::Pgn(error) => write!(f, "Invalid opening: {error}"),
+            Self::Empty => write!(f, "No openings"),
+        }
+    }
+}
+
+impl std::error::Error for OpeningError {}
+
+impl From<io::Error> for OpeningError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+/// Reads the openings of a PGN file (`.pgn`), or of a file with a FEN or EPD position per line
+pub fn read_openings(path: impl AsRef<Path>) -> Result<Vec<Opening>, OpeningError> {
+    let path = path.as_ref();
+    let text = fs::read_to_string(path)?;
+
+    return match path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("pgn")) {
+        true => parse_pgn_openings(&text),
+        false => parse_fen_openings(&text),
+    };
+}
+
+/// Reads the games of a PGN database as openings: their starting positions and main lines
+///
+/// ```
+/// use engine::arena;
+///
+/// let openings = arena::parse_pgn_openings("1. e4 e5 2. Nf3 *\n\n1. d4 d5 *").unwrap();
+///
+/// assert_eq!(openings.len(), 2);
+/// assert_eq!(openings[0].moves.len(), 3);
+/// ```
+pub fn parse_pgn_openings(pgn: &str) -> Result<Vec<Opening>, OpeningError> {
+    let openings = PGN::parse_all(pgn)
+        .map_err(OpeningError::Pgn)?
+        .into_iter()
+        .map(|game| Opening {
+            board: game.starting_position,
+            moves: game.mainline().collect(),
+        })
+        .collect::<Vec<_>>();
+
+    if openings.is_empty() {
+        return Err(OpeningError::Empty);
+    }
+
+    return Ok(openings);
+}
+
+/// Reads a position per line, as FEN or EPD (whose operations are ignored), skipping empty lines and `#` comments
+///
+/// ```
+/// use engine::arena;
+///
+/// let openings = arena::parse_fen_openings(
+///     "# Two openings\nrnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1\n\n4k3/8/8/8/8/8/4P3/4K3 w - - id \"KPK\";\n",
+/// )
+/// .unwrap();
+///
+/// assert_eq!(openings.len(), 2);
+/// assert_eq!(openings[1].board.to_fen(), "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1");
+/// ```
+pub fn parse_fen_openings(text: &str) -> Result<Vec<Opening>, OpeningError> {
+    let mut openings = Vec::new();
+
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        // EPD has the first 4 fields of FEN, followed by operations
+        let fields = line.split_whitespace().collect::<Vec<_>>();
+        let counters = fields.len() >= 6 && fields[4..6].iter().all(|field| field.parse::<u32>().is_ok());
+        let fen = fields[..fields.len().min(if counters { 6 } else { 4 })].join(" ");
+
+        openings.push(Opening {
+            board: Board::from_fen(&fen).map_err(|error| OpeningError::Fen(index + 1, error))?,
+            moves: Vec::new(),
+        });
+    }
+
+    if openings.is_empty() {
+        return Err(OpeningError::Empty);
+    }
+
+    return Ok(openings);
+}
+
+/// Ends a game before checkmate once both engines agree that it is lost: the losing engine scored its position at
+/// `-score` or below for `moves` moves in a row, and the winning engine scored its last position at `score` or above
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Resign {
+    pub score: Score,
+    pub moves: usize,
+}
+
+/// Why a game ended, as written in the PGN `Termination` tag
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Termination {
+    /// The game ended by the rules: checkmate, stalemate or a draw
+    Normal,
+    /// By tablebase or resignation
+    Adjudication,
+    TimeForfeit,
+    /// An engine played an illegal move
+    RulesInfraction,
+}
+
+impl Display for Termination {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Normal => "normal",
+            Self::Adjudication => "adjudication",
+            Self::TimeForfeit => "time forfeit",
+            Self::RulesInfraction => "rules infraction",
+        })
+    }
+}
+
+/// A game played by `Match::play`
+#[derive(Debug, Clone)]
+pub struct GameRecord {
+    /// The (0 based) index of the game in the match
+    pub index: usize,
+    /// Whether the first engine played white
+    pub first_engine_white: bool,
+    pub result: GameResult,
+    pub termination: Termination,
+    /// How the game ended, e.g. "White mates"
+    pub reason: String,
+    pub pgn: PgnGame,
+}
+
+impl GameRecord {
+    /// The points scored by the first engine: 1, 0.5 or 0
+    pub fn first_engine_score(&self) -> f64 {
+        let first_engine = match self.first_engine_white {
+            true => Team::White,
+            false => Team::Black,
+        };
+
+        return match self.result {
+            GameResult::Win(team) if team == first_engine => 1.0,
+            GameResult::Win(_) => 0.0,
+            _ => 0.5,
+        };
+    }
+}
+
+/// A match between two engines, speaking UCI
+///
+/// Every opening is played twice, the engines swapping colours, and the openings are repeated until all the games
+/// are played. The games follow the rules of `Chess`, the draws that can be claimed being claimed, and can be
+/// adjudicated by tablebase or resignation. The time control is enforced: an engine that runs out of time loses.
+///
+/// When pondering, the engines search the reply they expect on the opponent's time, and go on searching on their own
+/// time when it is played (`ponderhit`).
+///
+/// ```
+/// use std::time::Duration;
+/// use engine::{
+///     arena::{engine::UciEngine, Match},
+///     game::clock::TimeControl,
+/// };
+///
+/// let time_control = TimeControl::fischer(Duration::from_secs(60), Duration::from_secs(1));
+/// let arena = Match::new(time_control).with_games(2).with_depth(1).with_max_plies(40);
+/// let engines = || Ok([UciEngine::in_process("first", &[])?, UciEngine::in_process("second", &[])?]);
+///
+/// let stats = arena.run(engines, |game, _| assert_eq!(game.pgn.tag("Round"), Some((game.index + 1).to_string().as_str()))).unwrap();
+/// assert_eq!(stats.games(), 2);
+/// ```
+#[derive(Clone)]
+pub struct Match {
+    time_control: TimeControl,
+    depth: Option<u8>,
+    nodes: Option<u64>,
+    openings: Vec<Opening>,
+    games: usize,
+    concurrency: usize,
+    max_plies: Option<usize>,
+    tablebase: Option<Arc<Tablebase>>,
+    resign: Option<Resign>,
+    sprt: Option<Sprt>,
+    ponder: bool,
+    event: String,
+}
+
+impl Match {
+    /// A match of two games from the starting position
+    pub fn new(time_control: TimeControl) -> Self {
+        Self {
+            time_control,
+            depth: None,
+            nodes: None,
+            openings: vec![Opening::default()],
+            games: 2,
+            concurrency: 1,
+            max_plies: None,
+            tablebase: None,
+            resign: None,
+            sprt: None,
+            ponder: false,
+            event: "Engine match".to_owned(),
+        }
+    }
+
+    /// Limits every search to `depth`, on top of the time control
+    pub fn with_depth(mut self, depth: u8) -> Self {
+        self.depth = Some(depth);
+        self
+    }
+
+    /// Limits every search to `nodes`, on top of the time control
+    pub fn with_nodes(mut self, nodes: u64) -> Self {
+        self.nodes = Some(nodes);
+        self
+    }
+
+    /// The openings to play, the starting position being kept if there are none
+    pub fn with_openings(mut self, openings: Vec<Opening>) -> Self {
+        if !openings.is_empty() {
+            self.openings = openings;
+        }
+        self
+    }
+
+    pub fn with_games(mut self, games: usize) -> Self {
+        self.games = games;
+        self
+    }
+
+    /// The number of games played at the same time, each with its own pair of engines
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Adjudicates the games that last longer than `plies` (openings included) as draws
+    pub fn with_max_plies(mut self, plies: usize) -> Self {
+        self.max_plies = Some(plies);
+        self
+    }
+
+    /// Adjudicates the positions of the `tablebase`
+    pub fn with_tablebase(mut self, tablebase: Arc<Tablebase>) -> Self {
+        self.tablebase = Some(tablebase);
+        self
+    }
+
+    pub fn with_resign(mut self, resign: Resign) -> Self {
+        self.resign = Some(resign);
+        self
+    }
+
+    /// Stops the match early once the `sprt` is conclusive
+    pub fn with_sprt(mut self, sprt: Sprt) -> Self {
+        self.sprt = Some(sprt);
+        self
+    }
+
+    /// Lets the engines ponder on the opponent's time
+    pub fn with_ponder(mut self, ponder: bool) -> Self {
+        self.ponder = ponder;
+        self
+    }
+
+    /// The `Event` tag of the games
+    pub fn with_event(mut self, event: &str) -> Self {
+        event.clone_into(&mut self.event);
+        self
+    }
+
+    /// Plays the games of the match, calling `on_game` with each game and the results so far as soon as it is over
+    ///
+    /// `engines` starts the first and second engine, once for every concurrent game. The match stops at the first
+    /// error of an engine, or once the SPRT is conclusive: the games being played are then finished, and counted.
+    pub fn run(
+        &self,
+        engines: impl Fn() -> Result<[UciEngine; 2], EngineError> + Sync,
+        mut on_game: impl FnMut(&GameRecord, &MatchStats),
+    ) -> Result<MatchStats, EngineError> {
+        let next = AtomicUsize::new(0);
+        let stop = AtomicBool::new(false);
+        let (sender, games) = mpsc::channel();
+
+        return thread::scope(|scope| {
+            for _ in 0..self.concurrency.min(self.games) {
+                let sender = sender.clone();
+                let (next, stop, engines) = (&next, &stop, &engines);
+
+                scope.spawn(move || {
+                    let mut engines = match engines() {
+                        Ok(engines) => engines,
+                        Err(error) => {
+                            let _ = sender.send(Err(error));
+                            return;
+                        }
+                    };
+
+                    while !stop.load(Ordering::SeqCst) {
+                        let index = next.fetch_add(1, Ordering::SeqCst);
+                        if index >= self.games {
+                            break;
+                        }
+
+                        let game = self.play(index, &mut engines);
+                        if game.is_err() || sender.send(game).is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+            drop(sender);
+
+            let mut stats = MatchStats::default();
+            for game in games {
+                let game = match game {
+                    Ok(game) => game,
+                    Err(error) => {
+                        stop.store(true, Ordering::SeqCst);
+                        return Err(error);
+                    }
+                };
+
+                stats.add(&game);
+                on_game(&game, &stats);
+
+                if self.sprt.is_some_and(|sprt| sprt.status(&stats) != SprtStatus::Continue) {
+                    stop.store(true, Ordering::SeqCst);
+                }
+            }
+
+            return Ok(stats);
+        });
+    }
+
+    /// Plays the game of the given `index`, the first engine playing white in the even games
+    pub fn play(&self, index: usize, engines: &mut [UciEngine; 2]) -> Result<GameRecord, EngineError> {
+        let opening = &self.openings[index / 2 % self.openings.len()];
+        let first_engine_white = index % 2 == 0;
+        let [first, second] = engines;
+        let mut players = match first_engine_white {
+            true => [first, second],
+            false => [second, first],
+        };
+
+        let mut pgn = PgnGame::new(opening.board);
+        pgn.set_tag("Event", &self.event);
+        pgn.set_tag("Round", &(index + 1).to_string());
+        pgn.set_tag("White", players[Team::White.to_index()].name());
+        pgn.set_tag("Black", players[Team::Black.to_index()].name());
+        pgn.set_tag("TimeControl", &self.time_control.to_string());
+
+        let mut chess = Chess::new(opening.board);
+        for chess_move in opening.moves.iter() {
+            chess.make_move(*chess_move).expect("The opening moves to be legal");
+
+            let mut pgn_move = PgnMove::new(*chess_move);
+            pgn_move.comments.push("book".to_owned());
+            pgn.moves.push(pgn_move);
+        }
+
+        for player in players.iter_mut() {
+            player.new_game()?;
+        }
+
+        let mut chess = chess.with_clock(Clock::new(self.time_control.clone()));
+        // The last score of each team, and the number of moves in a row it scored itself as lost
+        let mut scores = [None; Team::SIZE];
+        let mut lost_moves = [0; Team::SIZE];
+        // The reply each team ponders on, while its opponent thinks
+        let mut predicted = [None; Team::SIZE];
+
+        let (result, termination, reason) = loop {
+            if let Some(end) = self.adjudicate(&chess, &scores, &lost_moves) {
+                break end;
+            }
+
+            let team = chess.board().team_to_move;
+            let clock = chess.clock_mut().expect("The game to be timed");
+            if clock.running().is_none() {
+                clock.start(team);
+            }
+
+            let clock = chess.clock().expect("The game to be timed");
+            let player = &mut players[team.to_index()];
+            let timeout = clock.remaining(team) + TIMEOUT_GRACE;
+            let last_move = chess.moves().next_back().map(|(chess_move, _)| chess_move);
+            let answer = match predicted[team.to_index()].take() {
+                Some(reply) if last_move == Some(reply) => player.ponderhit(timeout),
+                Some(_) => {
+                    player.stop_pondering()?;
+                    player.go(&position(&chess, None), &self.go(clock, team), timeout)
+                }
+                None => player.go(&position(&chess, None), &self.go(clock, team), timeout),
+            };
+
+            let answer = match answer {
+                Ok(answer) => answer,
+                Err(EngineError::Timeout(_)) => break time_forfeit(&chess, team),
+                Err(error) => return Err(error),
+            };
+
+            let Some(chess_move) = legal_move(chess.board(), &answer.best_move) else {
+                break (
+                    GameResult::Win(!team),
+                    Termination::RulesInfraction,
+                    format!("{team:?} plays an illegal move: {}", answer.best_move),
+                );
+            };
+            // The move only counts if the clock was pressed in time
+            if chess.make_move(chess_move).is_err() {
+                break time_forfeit(&chess, team);
+            }
+
+            let reply = answer.ponder.as_deref().and_then(|reply| legal_move(chess.board(), reply));
+            if let Some(reply) = reply.filter(|_| self.ponder) {
+                let clock = chess.clock().expect("The game to be timed");
+                players[team.to_index()].ponder(&position(&chess, Some(reply)), &self.go(clock, team))?;
+                predicted[team.to_index()] = Some(reply);
+            }
+
+            let mut pgn_move = PgnMove::new(chess_move);
+            pgn_move.comments.push(comment(&answer));
+            pgn.moves.push(pgn_move);
+
+            scores[team.to_index()] = answer.score;
+            lost_moves[team.to_index()] = match (self.resign, answer.score) {
+                (Some(resign), Some(score)) if score <= -resign.score => lost_moves[team.to_index()] + 1,
+                _ => 0,
+            };
+        };
+
+        // The engines still pondering when the game ends
+        for (player, reply) in players.iter_mut().zip(predicted) {
+            if reply.is_some() {
+                player.stop_pondering()?;
+            }
+        }
+
+        pgn.result = result;
+        pgn.set_tag("Termination", &termination.to_string());
+        if let Some(last_move) = pgn.moves.last_mut() {
+            last_move.comments.push(reason.clone());
+        }
+
+        return Ok(GameRecord {
+            index,
+            first_engine_white,
+            result,
+            termination,
+            reason,
+            pgn,
+        });
+    }
+
+    /// The `go` command of the move of `team`, with the times of the `clock`
+    fn go(&self, clock: &Clock, team: Team) -> Go {
+        Go {
+            depth: self.depth,
+            nodes: self.nodes,
+            wtime: Some(clock.remaining(Team::White).as_millis() as u64),
+            btime: Some(clock.remaining(Team::Black).as_millis() as u64),
+            winc: Some(clock.period(Team::White).increment.into()),
+            binc: Some(clock.period(Team::Black).increment.into()),
+            movestogo: clock.moves_to_go(team),
+            ..Default::default()
+        }
+    }
+
+    /// The result of the game if it is over by the rules or can be adjudicated
+    fn adjudicate(
+        &self,
+        chess: &Chess,
+        scores: &[Option<Score>; Team::SIZE],
+        lost_moves: &[usize; Team::SIZE],
+    ) -> Option<(GameResult, Termination, String)> {
+        let board = chess.board();
+
+        match chess.outcome() {
+            Some(EndOfGameState::Checkmate(team)) => {
+                return Some((GameResult::Win(team), Termination::Normal, format!("{team:?} mates")))
+            }
+            Some(EndOfGameState::Stalemate) => {
+                return Some((GameResult::Draw, Termination::Normal, "Draw by stalemate".to_owned()))
+            }
+            // Only the team to move can run out of time
+            Some(EndOfGameState::Timeout(_) | EndOfGameState::Draw(DrawReason::TimeoutVsInsufficientMaterial)) => {
+                return Some(time_forfeit(chess, board.team_to_move))
+            }
+            Some(EndOfGameState::Draw(reason)) => return Some((GameResult::Draw, Termination::Normal, draw(reason))),
+            _ => {}
+        }
+
+        if let Some(reason) = chess.claimable_draw() {
+            return Some((GameResult::Draw, Termination::Normal, draw(reason)));
+        }
+
+        if let Some(wdl) = self.tablebase.as_ref().and_then(|tablebase| tablebase.probe_wdl(board)) {
+            let team = board.team_to_move;
+
+            return Some(match wdl {
+                Wdl::Win => (GameResult::Win(team), Termination::Adjudication, format!("Tablebase win for {team:?}")),
+                Wdl::Loss => (
+                    GameResult::Win(!team),
+                    Termination::Adjudication,
+                    format!("Tablebase win for {:?}", !team),
+                ),
+                _ => (GameResult::Draw, Termination::Adjudication, "Tablebase draw".to_owned()),
+            });
+        }
+
+        if let Some(resign) = self.resign {
+            for team in Team::ALL {
+                let winning = scores[(!team).to_index()].is_some_and(|score| score >= resign.score);
+
+                if lost_moves[team.to_index()] >= resign.moves && winning {
+                    return Some((GameResult::Win(!team), Termination::Adjudication, format!("{team:?} resigns")));
+                }
+            }
+        }
+
+        if self.max_plies.is_some_and(|plies| chess.ply() >= plies) {
+            return Some((GameResult::Draw, Termination::Adjudication, "Draw by game length".to_owned()));
+        }
+
+        return None;
+    }
+}
+
+/// The result of a game lost on time by `team`, a draw if its opponent can't checkmate
+fn time_forfeit(chess: &Chess, team: Team) -> (GameResult, Termination, String) {
+    match chess.outcome() {
+        Some(EndOfGameState::Draw(_)) => (
+            GameResult::Draw,
+            Termination::TimeForfeit,
+            format!("{team:?} runs out of time against insufficient material"),
+        ),
+        _ => (GameResult::Win(!team), Termination::TimeForfeit, format!("{team:?} loses on time")),
+    }
+}
+
+/// The `position` command of the current position of the game with the moves that led to it, followed by the
+/// `reply` to ponder on, if any
+fn position(chess: &Chess, reply: Option<Move>) -> String {
+    let fen = chess.starting_position().to_fen();
+    let mut command = match fen == FEN::START {
+        true => "position startpos".to_owned(),
+        false => format!("position fen {fen}"),
+    };
+
+    let moves = chess
+        .moves()
+        .map(|(chess_move, _)| chess_move)
+        .chain(reply)
+        .map(|chess_move| chess_move.to_string())
+        .collect::<Vec<_>>();
+    if !moves.is_empty() {
+        command.push_str(" moves ");
+        command.push_str(&moves.join(" "));
+    }
+
+    return command;
+}
+
+/// The `chess_move` in long algebraic notation, if it is legal on the `board`
+fn legal_move(board: &Board, chess_move: &str) -> Option<Move> {
+    chess_move
+        .parse::<Move>()
+        .ok()
+        .filter(|chess_move| board.iter_moves().any(|legal_move| legal_move == *chess_move))
+}
+
+/// The score, depth and time of a move, e.g. `+0.35/12 0.512s`
+fn comment(answer: &Answer) -> String {
+    let score = match answer.score {
+        Some(score) => match mate_in(score) {
+            Some(moves) if moves > 0 => format!("+M{moves}"),
+            Some(moves) => format!("-M{}", -moves),
+            None => format!("{:+.2}", score as f64 / 100.0),
+        },
+        None => "?".to_owned(),
+    };
+    let depth = answer.depth.map(|depth| format!("/{depth}")).unwrap_or_default();
+
+    return format!("{score}{depth} {:.3}s", answer.time.as_secs_f64());
+}
+
+fn draw(reason: DrawReason) -> String {
+    let reason = match reason {
+        DrawReason::Agreement => "agreement",
+        DrawReason::ThreefoldRepetition => "threefold repetition",
+        DrawReason::FivefoldRepetition => "fivefold repetition",
+        DrawReason::FiftyMoveRule => "the fifty-move rule",
+        DrawReason::SeventyFiveMoveRule => "the seventy-five-move rule",
+        DrawReason::InsufficientMaterial => "insufficient material",
+        DrawReason::TimeoutVsInsufficientMaterial => "timeout against insufficient material",
+    };
+
+    return format!("Draw by {reason}");
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::syzygy::TABLES;
+
+    fn engines() -> Result<[UciEngine; 2], EngineError> {
+        Ok([
+            UciEngine::in_process("first", &[])?,
+            UciEngine::in_process("second", &[("Hash".to_owned(), "2".to_owned())])?,
+        ])
+    }
+
+    fn minutes(minutes: u64) -> TimeControl {
+        TimeControl::sudden_death(Duration::from_secs(60 * minutes))
+    }
+
+    #[test]
+    fn openings() {
+        assert!(matches!(parse_fen_openings("# Nothing\n\n"), Err(OpeningError::Empty)));
+        assert!(matches!(parse_fen_openings("8/8 w\n"), Err(OpeningError::Fen(1, _))));
+        assert!(matches!(parse_pgn_openings("1. e4 e5 2. Ke3 *"), Err(OpeningError::Pgn(_))));
+
+        let openings = parse_fen_openings(&format!("{}\n4k3/8/8/8/8/8/8/3QK3 b - - 12 40\n", FEN::START)).unwrap();
+        assert_eq!(openings[0].board.to_fen(), FEN::START);
+        assert_eq!(openings[1].board.halfmove_clock, 12);
+
+        let chess = Chess::from_moves(FEN::START, parse_pgn_openings("1. e4 e5 *").unwrap()[0].moves.clone()).unwrap();
+        assert_eq!(position(&chess, None), "position startpos moves e2e4 e7e5");
+        assert_eq!(position(&Chess::new(openings[1].board), None), "position fen 4k3/8/8/8/8/8/8/3QK3 b - - 12 40");
+
+        let reply = Move::new(crate::Square::G1, crate::Square::F3);
+        assert_eq!(position(&chess, Some(reply)), "position startpos moves e2e4 e7e5 g1f3");
+        assert_eq!(position(&Chess::default(), Some(reply)), "position startpos moves g1f3");
+    }
+
+    #[test]
+    fn adjudication() {
+        let openings = parse_fen_openings("4k3/8/8/8/8/8/8/3QK3 w - - 0 1\nk7/8/8/8/8/8/P7/K7 w - - 0 1\n").unwrap();
+        let tablebase = Arc::new(Tablebase::open(TABLES).unwrap());
+        let arena = Match::new(minutes(1))
+            .with_openings(openings)
+            .with_games(4)
+            .with_tablebase(tablebase);
+
+        let mut games = Vec::new();
+        let stats = arena.run(engines, |game, _| games.push(game.clone())).unwrap();
+        games.sort_by_key(|game| game.index);
+
+        // The queen wins for the first engine, then for the second, and the rook pawn draws
+        assert_eq!((stats.wins, stats.draws, stats.losses), (1, 2, 1));
+        assert_eq!(games[0].result, GameResult::Win(Team::White));
+        assert_eq!(games[1].first_engine_score(), 0.0);
+        assert_eq!(games[2].reason, "Tablebase draw");
+        assert!(games.iter().all(|game| game.termination == Termination::Adjudication));
+        assert!(games.iter().all(|game| game.pgn.moves.is_empty()));
+
+        let pgn = PGN::format(&games[1].pgn);
+        assert!(pgn.contains("[White \"second\"]\n[Black \"first\"]\n[Result \"1-0\"]"), "{pgn}");
+        assert!(pgn.contains("[FEN \"4k3/8/8/8/8/8/8/3QK3 w - - 0 1\"]"));
+        assert!(pgn.contains("[Termination \"adjudication\"]"));
+    }
+
+    #[test]
+    fn games() {
+        let openings = parse_pgn_openings("1. e4 e5 2. Nf3 Nc6 *\n\n1. d4 d5 *").unwrap();
+        let arena = Match::new(minutes(5))
+            .with_openings(openings)
+            .with_games(4)
+            .with_concurrency(2)
+            .with_depth(2)
+            .with_max_plies(30)
+            .with_resign(Resign { score: 500, moves: 2 });
+
+        let mut games = Vec::new();
+        let stats = arena.run(engines, |game, stats| games.push((game.clone(), *stats))).unwrap();
+        assert_eq!(stats.games(), 4);
+        assert_eq!(games.last().unwrap().1, stats);
+        games.sort_by_key(|(game, _)| game.index);
+
+        for (game, _) in games.iter() {
+            let pgn = PGN::parse(&PGN::format(&game.pgn)).unwrap();
+            let book = pgn.moves.iter().take_while(|pgn_move| pgn_move.comments == ["book"]).count();
+
+            assert_eq!(game.first_engine_white, game.index % 2 == 0);
+            assert_eq!(book, if game.index < 2 { 4 } else { 2 });
+            assert!(pgn.moves.len() <= 30);
+            assert!(pgn.moves[book].comments[0].ends_with('s'), "{:?}", pgn.moves[book].comments);
+            assert_eq!(pgn.result, game.result);
+        }
+
+        // Any result of the first game is conclusive for such a test, which stops the match once the game being
+        // played meanwhile is over
+        let sprt = Sprt::new(0.0, 1000.0).with_error_rates(0.45, 0.45);
+        let stats = arena.clone().with_concurrency(1).with_sprt(sprt).run(engines, |_, _| {}).unwrap();
+        assert!(stats.games() <= 2);
+        assert_ne!(sprt.status(&stats), SprtStatus::Continue);
+    }
+
+    #[test]
+    fn ponder() {
+        let arena = Match::new(minutes(1)).with_games(2).with_depth(3).with_max_plies(16).with_ponder(true);
+
+        // The engines keep answering the right moves, whether they predicted the reply or not
+        let stats = arena
+            .run(engines, |game, _| {
+                let pgn = PGN::format(&game.pgn);
+                assert!(matches!(game.termination, Termination::Normal | Termination::Adjudication), "{pgn}");
+                assert!(game.pgn.moves.len() <= 16);
+            })
+            .unwrap();
+        assert_eq!(stats.games(), 2);
+    }
+
+    #[test]
+    fn time_forfeit() {
+        // Without any time, white loses on its first move
+        let arena = Match::new(TimeControl::sudden_death(Duration::ZERO)).with_games(1);
+
+        let stats = arena.run(engines, |game, _| assert_eq!(game.termination, Termination::TimeForfeit)).unwrap();
+        assert_eq!(stats.losses, 1);
+    }
+}
@@ -0,0 +1,245 @@
+use std::fmt::Display;
+
+use super::GameRecord;
+
+/// The z-score of the 95% confidence interval of the Elo difference
+const Z_95: f64 = 1.959964;
+/// Added to the counts of the results, so that a match without a draw or a loss still has a variance
+const PSEUDO_COUNT: f64 = 1e-3;
+
+/// The results of a match, from the point of view of the first engine
+///
+/// ```
+/// use engine::arena::sprt::MatchStats;
+///
+/// let stats = MatchStats { wins: 30, draws: 40, losses: 30 };
+/// let (elo, error) = stats.elo().unwrap();
+///
+/// assert_eq!(stats.score(), 0.5);
+/// assert!(elo.abs() < 1e-9);
+/// assert!(error > 40.0 && error < 60.0);
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MatchStats {
+    pub wins: usize,
+    pub draws: usize,
+    pub losses: usize,
+}
+
+impl MatchStats {
+    pub fn add(&mut self, game: &GameRecord) {
+        match game.first_engine_score() {
+            score if score == 1.0 => self.wins += 1,
+            score if score == 0.0 => self.losses += 1,
+            _ => self.draws += 1,
+        }
+    }
+
+    pub fn games(&self) -> usize {
+        self.wins + self.draws + self.losses
+    }
+
+    /// The points scored per game, from 0 to 1
+    pub fn score(&self) -> f64 {
+        (self.wins as f64 + self.draws as f64 / 2.0) / self.games().max(1) as f64
+    }
+
+    /// The Elo difference between the engines, with the half width of its 95% confidence interval, once a game was
+    /// played
+    pub fn elo(&self) -> Option<(f64, f64)> {
+        if self.games() == 0 {
+            return None;
+        }
+
+        let (score, variance) = self.distribution();
+        let deviation = (variance / self.games() as f64).sqrt();
+        let low = elo(score - Z_95 * deviation);
+        let high = elo(score + Z_95 * deviation);
+
+        return Some((elo(score), (high - low) / 2.0));
+    }
+
+    /// The mean and variance of the score of a game
+    fn distribution(&self) -> (f64, f64) {
+        let [wins, draws, losses] = [self.wins, self.draws, self.losses].map(|count| count as f64 + PSEUDO_COUNT);
+        let games = wins + draws + losses;
+        let score = (wins + draws / 2.0) / games;
+        let variance = (wins * (1.0 - score).powi(2) + draws * (0.5 - score).powi(2) + losses * score.powi(2)) / games;
+
+        return (score, variance);
+    }
+}
+
+impl Display for MatchStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Games: {} (+{} ={} -{}), score {:.1}%",
+            self.games(),
+            self.wins,
+            self.draws,
+            self.losses,
+            self.score() * 100.0
+        )?;
+
+        if let Some((elo, error)) = self.elo() {
+            // Adding zero turns a negative zero positive
+            write!(f, ", Elo {:.1} +/- {error:.1}", elo + 0.0)?;
+        }
+
+        return Ok(());
+    }
+}
+
+/// The Elo difference that scores `score` points per game
+fn elo(score: f64) -> f64 {
+    let score = score.clamp(1e-6, 1.0 - 1e-6);
+
+    return -400.0 * (1.0 / score - 1.0).log10();
+}
+
+/// The points per game scored with an Elo difference of `elo`
+fn expected_score(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+/// What the `Sprt` concluded so far
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SprtStatus {
+    /// More games are needed
+    Continue,
+    /// The Elo difference is at least `elo1` (H1)
+    Accepted,
+    /// The Elo difference is at most `elo0` (H0)
+    Rejected,
+}
+
+/// A sequential probability ratio test, telling whether the first engine is stronger by `elo0` (H0) or `elo1` (H1)
+///
+/// The log-likelihood ratio of the results is updated after every game, until it crosses one of the bounds given by
+/// the error rates: `alpha` is the chance to accept H1 when H0 is true, and `beta` the chance to reject it when H1 is
+/// true. The ratio uses the normal approximation of the logistic Elo model.
+///
+/// ```
+/// use engine::arena::sprt::{MatchStats, Sprt, SprtStatus};
+///
+/// let sprt = Sprt::new(0.0, 10.0);
+/// let (lower, upper) = sprt.bounds();
+/// assert!((upper - 2.944).abs() < 1e-3 && lower == -upper);
+///
+/// assert_eq!(sprt.status(&MatchStats { wins: 10, draws: 10, losses: 8 }), SprtStatus::Continue);
+/// assert_eq!(sprt.status(&MatchStats { wins: 600, draws: 1000, losses: 400 }), SprtStatus::Accepted);
+/// assert_eq!(sprt.status(&MatchStats { wins: 400, draws: 1000, losses: 450 }), SprtStatus::Rejected);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sprt {
+    pub elo0: f64,
+    pub elo1: f64,
+    pub alpha: f64,
+    pub beta: f64,
+}
+
+impl Sprt {
+    /// A test with both error rates at 5%
+    pub fn new(elo0: f64, elo1: f64) -> Self {
+        Self {
+            elo0,
+            elo1,
+            alpha: 0.05,
+            beta: 0.05,
+        }
+    }
+
+    pub fn with_error_rates(mut self, alpha: f64, beta: f64) -> Self {
+        self.alpha = alpha;
+        self.beta = beta;
+        self
+    }
+
+    /// The log-likelihood ratios below which H0 is accepted, and above which H1 is
+    pub fn bounds(&self) -> (f64, f64) {
+        ((self.beta / (1.0 - self.alpha)).ln(), ((1.0 - self.beta) / self.alpha).ln())
+    }
+
+    /// The log-likelihood ratio of H1 against H0
+    pub fn llr(&self, stats: &MatchStats) -> f64 {
+        if stats.games() == 0 {
+            return 0.0;
+        }
+
+        let (score, variance) = stats.distribution();
+        let score0 = expected_score(self.elo0);
+        let score1 = expected_score(self.elo1);
+
+        return stats.games() as f64 * (score1 - score0) * (2.0 * score - score0 - score1) / (2.0 * variance);
+    }
+
+    pub fn status(&self, stats: &MatchStats) -> SprtStatus {
+        let llr = self.llr(stats);
+        let (lower, upper) = self.bounds();
+
+        return match llr {
+            _ if llr >= upper => SprtStatus::Accepted,
+            _ if llr <= lower => SprtStatus::Rejected,
+            _ => SprtStatus::Continue,
+        };
+    }
+
+    /// The ratio, its bounds and the status of the test, on one line
+    pub fn report(&self, stats: &MatchStats) -> String {
+        let (lower, upper) = self.bounds();
+        let status = match self.status(stats) {
+            SprtStatus::Continue => "continue",
+            SprtStatus::Accepted => "H1 accepted",
+            SprtStatus::Rejected => "H0 accepted",
+        };
+
+        return format!(
+            "SPRT [{}, {}]: LLR {:.2} ({lower:.2}, {upper:.2}), {status}",
+            self.elo0,
+            self.elo1,
+            self.llr(stats)
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn elo() {
+        assert!((super::elo(0.75) - 190.85).abs() < 0.01);
+        assert!((super::elo(expected_score(-35.0)) + 35.0).abs() < 1e-9);
+        assert!(super::elo(1.0).is_finite());
+        assert_eq!(MatchStats::default().elo(), None);
+
+        // More games narrow the error bars
+        let few = MatchStats { wins: 12, draws: 10, losses: 8 };
+        let many = MatchStats { wins: 1200, draws: 1000, losses: 800 };
+        assert!(few.elo().unwrap().0 > 0.0);
+        assert!(many.elo().unwrap().1 < few.elo().unwrap().1 / 5.0);
+
+        // Even without losses
+        let (elo, error) = MatchStats { wins: 5, draws: 0, losses: 0 }.elo().unwrap();
+        assert!(elo > 1000.0 && error.is_finite());
+    }
+
+    #[test]
+    fn sprt() {
+        let sprt = Sprt::new(0.0, 5.0).with_error_rates(0.05, 0.1);
+        let (lower, upper) = sprt.bounds();
+        assert!((lower - (0.1f64 / 0.95).ln()).abs() < 1e-9);
+        assert!((upper - 18f64.ln()).abs() < 1e-9);
+
+        // The ratio grows with the evidence for H1
+        let even = MatchStats { wins: 100, draws: 100, losses: 100 };
+        let ahead = MatchStats { wins: 110, draws: 100, losses: 90 };
+        assert!(sprt.llr(&even) < 0.0);
+        assert!(sprt.llr(&ahead) > sprt.llr(&even));
+        assert_eq!(sprt.llr(&MatchStats::default()), 0.0);
+
+        assert!(sprt.report(&even).starts_with("SPRT [0, 5]: LLR "));
+        assert!(Sprt::new(0.0, 5.0).report(&MatchStats { wins: 50, draws: 0, losses: 0 }).ends_with("H1 accepted"));
+    }
+}
@@ -223,6 +223,19 @@ impl BitBoard {
     /// The value of the `BitBoard` is equal to `0xFFFF_FFFF_FFFF_FFFF`
     pub const FULL: BitBoard = BitBoard(u64::MAX);
 
+    /// A `BitBoard` with all the light squares (b1, a2, ...) set to `1`
+    ///
+    /// ```
+    /// use engine::{bitboard::BitBoard, Square};
+    ///
+    /// assert!((BitBoard::LIGHT_SQUARES & BitBoard::from(Square::A1)).is_empty());
+    /// assert!(!(BitBoard::LIGHT_SQUARES & BitBoard::from(Square::H1)).is_empty());
+    /// assert_eq!(BitBoard::LIGHT_SQUARES | BitBoard::DARK_SQUARES, BitBoard::FULL);
+    /// ```
+    pub const LIGHT_SQUARES: BitBoard = BitBoard(0x55AA_55AA_55AA_55AA);
+    /// A `BitBoard` with all the dark squares (a1, c1, ...) set to `1`
+    pub const DARK_SQUARES: BitBoard = BitBoard(!BitBoard::LIGHT_SQUARES.0);
+
 
     // [constants:magic]
     #[rustfmt::skip]
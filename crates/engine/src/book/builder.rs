@@ -0,0 +1,212 @@
+use std::{collections::HashMap, io::BufRead};
+
+use crate::{
+    game::{
+        corpus::{self, CorpusError},
+        Board, Move,
+    },
+    notations::PGN::{GameResult, PgnError, PgnReader},
+};
+
+use super::{polyglot, Book};
+
+/// The results of a move in the games added to a `BookBuilder`, for the team that played it
+#[derive(Debug, Clone, Copy, Default)]
+struct MoveStats {
+    games: u32,
+    wins: u32,
+    draws: u32,
+}
+
+impl MoveStats {
+    /// The ratio of points scored, in `[0, 1]`
+    fn score(&self) -> f64 {
+        (self.wins as f64 + self.draws as f64 / 2.0) / self.games.max(1) as f64
+    }
+
+    /// The Polyglot weight of the move: two per win, one per draw
+    fn weight(&self) -> u16 {
+        (2 * self.wins + self.draws).min(u16::MAX as u32) as u16
+    }
+}
+
+/// Builds a `Book` from the openings of finished games
+///
+/// Only the moves played in at least `min_games` games, and scoring at least `min_score` of the points for the team
+/// that played them, are kept. The moves that never scored are left out.
+///
+/// ```
+/// use engine::{book::{BookBuilder, Selection}, Board, Move, Square};
+///
+/// let mut builder = BookBuilder::new().with_min_games(2);
+/// builder.add_pgn("1. e4 e5 1-0\n\n1. e4 c5 0-1\n\n1. d4 d5 1-0").unwrap();
+/// let book = builder.build();
+///
+/// // 1. d4 was only played once
+/// assert_eq!(book.moves(&Board::default()).len(), 1);
+/// assert_eq!(book.pick(&Board::default(), Selection::Best), Some(Move::new(Square::E2, Square::E4)));
+/// ```
+#[derive(Debug, Clone)]
+pub struct BookBuilder {
+    max_ply: usize,
+    min_games: u32,
+    min_score: f64,
+    /// The statistics of the moves, by Polyglot key of the position and Polyglot move
+    positions: HashMap<u64, HashMap<u16, MoveStats>>,
+}
+
+impl BookBuilder {
+    /// The default number of plies of each game added to the book
+    pub const DEFAULT_MAX_PLY: usize = 20;
+
+    pub fn new() -> Self {
+        Self {
+            max_ply: Self::DEFAULT_MAX_PLY,
+            min_games: 1,
+            min_score: 0.0,
+            positions: HashMap::new(),
+        }
+    }
+
+    /// Only adds the first `max_ply` moves of each game
+    pub fn with_max_ply(mut self, max_ply: usize) -> Self {
+        self.max_ply = max_ply;
+        self
+    }
+
+    /// Only keeps the moves played in at least `min_games` games
+    pub fn with_min_games(mut self, min_games: u32) -> Self {
+        self.min_games = min_games;
+        self
+    }
+
+    /// Only keeps the moves that scored at least `min_score` (in `[0, 1]`) for the team that played them
+    pub fn with_min_score(mut self, min_score: f64) -> Self {
+        self.min_score = min_score;
+        self
+    }
+
+    /// Adds the opening of a game, the games without a result being ignored
+    pub fn add_game(&mut self, starting_position: &Board, moves: impl IntoIterator<Item = Move>, result: GameResult) {
+        if result == GameResult::Ongoing {
+            return;
+        }
+
+        let mut board = *starting_position;
+        for chess_move in moves.into_iter().take(self.max_ply) {
+            let stats = self
+                .positions
+                .entry(polyglot::key(&board))
+                .or_default()
+                .entry(polyglot::encode_move(&board, chess_move))
+                .or_default();
+
+            stats.games += 1;
+            match result {
+                GameResult::Win(team) if team == board.team_to_move => stats.wins += 1,
+                GameResult::Draw => stats.draws += 1,
+                _ => {}
+            }
+
+            board.make_move(chess_move);
+        }
+    }
+
+    /// Adds every game of a PGN database, returning the number of games read
+    pub fn add_pgn(&mut self, pgn: &str) -> Result<usize, PgnError> {
+        let mut count = 0;
+
+        for game in PgnReader::new(pgn) {
+            let game = game?;
+            self.add_game(&game.starting_position, game.mainline(), game.result);
+            count += 1;
+        }
+
+        return Ok(count);
+    }
+
+    /// Adds every game of a corpus of bare SAN games (see `corpus::GAMES`), returning the number of games read
+    pub fn add_corpus(&mut self, reader: impl BufRead) -> Result<usize, CorpusError> {
+        let mut count = 0;
+
+        for game in corpus::read_games(reader) {
+            let game = game?;
+            self.add_game(&Board::default(), game.moves, game.result);
+            count += 1;
+        }
+
+        return Ok(count);
+    }
+
+    /// The book of the moves that pass the filters
+    pub fn build(&self) -> Book {
+        let mut entries = self
+            .positions
+            .iter()
+            .flat_map(|(key, moves)| {
+                moves
+                    .iter()
+                    .filter(|(_, stats)| stats.games >= self.min_games && stats.score() >= self.min_score)
+                    .filter(|(_, stats)| stats.weight() > 0)
+                    .map(|(chess_move, stats)| (*key, *chess_move, stats.weight(), 0))
+            })
+            .collect::<Vec<_>>();
+        entries.sort_unstable();
+
+        return Book { entries };
+    }
+}
+
+impl Default for BookBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{book::Selection, primitives::Square};
+
+    #[test]
+    fn filters() {
+        let pgn = "1. e4 e5 2. Nf3 1-0\n\n1. e4 e5 2. Bc4 1/2-1/2\n\n1. e4 c5 0-1\n\n1. d4 d5 *";
+
+        let mut builder = BookBuilder::new();
+        assert_eq!(builder.add_pgn(pgn).unwrap(), 4);
+
+        let book = builder.build();
+        let moves = book.moves(&Board::default());
+        // The unfinished game is ignored: a win, a draw and a loss for 1. e4
+        assert_eq!(moves.len(), 1);
+        assert_eq!((moves[0].chess_move, moves[0].weight), (Move::new(Square::E2, Square::E4), 3));
+
+        let mut board = Board::default();
+        board.make_move(Move::new(Square::E2, Square::E4));
+        let moves = book.moves(&board);
+        assert_eq!(moves.len(), 2);
+        assert_eq!((moves[0].chess_move, moves[0].weight), (Move::new(Square::C7, Square::C5), 2));
+
+        let book = builder.clone().with_min_score(0.5).build();
+        assert_eq!(book.moves(&board).len(), 1);
+        let book = builder.clone().with_min_games(2).build();
+        assert_eq!(book.pick(&board, Selection::Best), Some(Move::new(Square::E7, Square::E5)));
+
+        let mut builder = BookBuilder::new().with_max_ply(1);
+        builder.add_pgn(pgn).unwrap();
+        assert_eq!(builder.build().len(), 1);
+    }
+
+    #[test]
+    fn corpus() {
+        let mut builder = BookBuilder::new();
+        assert!(builder.add_corpus("e4 e5 Nf3 Nc6 1-0\ne4 e5 O-O 1-0\n".as_bytes()).is_err());
+
+        let mut builder = BookBuilder::new().with_max_ply(4);
+        assert_eq!(builder.add_corpus("e4 e5 Nf3 Nc6 1-0\nd4 d5 1/2-1/2\n".as_bytes()).unwrap(), 2);
+        let book = builder.build();
+        assert_eq!(book.moves(&Board::default()).len(), 2);
+        // The moves of the losing team are left out
+        assert_eq!(book.len(), 4);
+    }
+}
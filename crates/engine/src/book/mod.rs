@@ -0,0 +1,251 @@
+use std::{fmt::Display, io, path::Path};
+
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+
+use crate::game::{moves::MoveGen, Board, Move};
+
+mod builder;
+pub mod polyglot;
+pub use builder::BookBuilder;
+
+/// The size of an entry of a Polyglot book: key (8 bytes), move (2), weight (2) and learn (4), all big-endian
+const ENTRY_SIZE: usize = 16;
+
+/// A move of the book, with its weight among the other moves of the position
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BookMove {
+    pub chess_move: Move,
+    pub weight: u16,
+    /// Data used by the programs that learn from their games, unused here
+    pub learn: u32,
+}
+
+/// How to pick a move among the candidates of the book
+///
+/// The random selections are deterministic for a given seed and position, the seed can change from game to game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Selection {
+    /// The move with the greatest weight
+    Best,
+    /// A random move, each one being picked proportionally to its weight
+    Weighted(u64),
+    /// A random move, each one being as likely
+    Uniform(u64),
+}
+
+/// Everything that can go wrong while reading a book
+#[derive(Debug)]
+pub enum BookError {
+    Io(io::Error),
+    /// The size of the book, in bytes, is not a multiple of the size of an entry
+    Size(usize),
+}
+
+impl Display for BookError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "Could not read the book: {error}"),
+            Self::Size(size) => write!(f, "Invalid book size: {size} bytes is not a multiple of {ENTRY_SIZE}"),
+        }
+    }
+}
+
+impl std::error::Error for BookError {}
+
+impl From<io::Error> for BookError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+/// An opening book in the Polyglot format, the entries being sorted by `polyglot::key`
+///
+/// ```
+/// use engine::{book::{Book, BookBuilder, Selection}, Board, Move, Square};
+///
+/// let mut builder = BookBuilder::new();
+/// builder.add_pgn("1. e4 e5 2. Nf3 1-0\n\n1. e4 c5 0-1\n\n1. d4 d5 1/2-1/2").unwrap();
+/// let book = Book::from_bytes(&builder.build().to_bytes()).unwrap();
+///
+/// let moves = book.moves(&Board::default());
+/// assert_eq!(moves.len(), 2);
+/// assert_eq!(book.pick(&Board::default(), Selection::Best), Some(Move::new(Square::E2, Square::E4)));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Book {
+    /// The key of the position, the move in the Polyglot format, its weight and learn data
+    entries: Vec<(u64, u16, u16, u32)>,
+}
+
+impl Book {
+    /// Reads the book at `path`
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, BookError> {
+        Self::from_bytes(&std::fs::read(path)?)
+    }
+
+    /// Reads a book from the content of a `.bin` file
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, BookError> {
+        if bytes.len() % ENTRY_SIZE != 0 {
+            return Err(BookError::Size(bytes.len()));
+        }
+
+        let mut entries = bytes
+            .chunks_exact(ENTRY_SIZE)
+            .map(|entry| {
+                (
+                    u64::from_be_bytes(entry[0..8].try_into().unwrap()),
+                    u16::from_be_bytes(entry[8..10].try_into().unwrap()),
+                    u16::from_be_bytes(entry[10..12].try_into().unwrap()),
+                    u32::from_be_bytes(entry[12..16].try_into().unwrap()),
+                )
+            })
+            .collect::<Vec<_>>();
+        // The format requires it, but some tools don't
+        entries.sort_by_key(|(key, ..)| *key);
+
+        return Ok(Self { entries });
+    }
+
+    /// The content of the `.bin` file of the book
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.entries.len() * ENTRY_SIZE);
+
+        for (key, chess_move, weight, learn) in self.entries.iter() {
+            bytes.extend(key.to_be_bytes());
+            bytes.extend(chess_move.to_be_bytes());
+            bytes.extend(weight.to_be_bytes());
+            bytes.extend(learn.to_be_bytes());
+        }
+
+        return bytes;
+    }
+
+    /// Writes the book to `path`, as a Polyglot `.bin` file
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        std::fs::write(path, self.to_bytes())
+    }
+
+    /// The number of entries of the book, one per position and move
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The legal moves the book knows for the `board`, by decreasing weight
+    pub fn moves(&self, board: &Board) -> Vec<BookMove> {
+        let key = polyglot::key(board);
+        let start = self.entries.partition_point(|(entry_key, ..)| *entry_key < key);
+        let legal = MoveGen::new_legal(board);
+
+        let mut moves = self.entries[start..]
+            .iter()
+            .take_while(|(entry_key, ..)| *entry_key == key)
+            .map(|(_, chess_move, weight, learn)| BookMove {
+                chess_move: polyglot::decode_move(board, *chess_move),
+                weight: *weight,
+                learn: *learn,
+            })
+            // The keys can collide
+            .filter(|book_move| legal.contains(book_move.chess_move))
+            .collect::<Vec<_>>();
+        moves.sort_by_key(|book_move| std::cmp::Reverse(book_move.weight));
+
+        return moves;
+    }
+
+    /// Picks one of the book moves of the `board`, if there are any
+    ///
+    /// The moves with a null weight are only picked by `Selection::Uniform`.
+    pub fn pick(&self, board: &Board, selection: Selection) -> Option<Move> {
+        let moves = self.moves(board);
+
+        let index = match selection {
+            Selection::Best => 0,
+            Selection::Weighted(seed) => {
+                let total = moves.iter().map(|book_move| book_move.weight as u64).sum::<u64>();
+                if total == 0 {
+                    return None;
+                }
+
+                let mut target = SmallRng::seed_from_u64(seed ^ board.hash()).gen_range(0..total);
+                moves
+                    .iter()
+                    .position(|book_move| match target.checked_sub(book_move.weight as u64) {
+                        Some(left) => {
+                            target = left;
+                            false
+                        }
+                        None => true,
+                    })
+                    .unwrap_or(0)
+            }
+            Selection::Uniform(_) if moves.is_empty() => return None,
+            Selection::Uniform(seed) => SmallRng::seed_from_u64(seed ^ board.hash()).gen_range(0..moves.len()),
+        };
+
+        return moves
+            .get(index)
+            .filter(|book_move| book_move.weight > 0 || matches!(selection, Selection::Uniform(_)))
+            .map(|book_move| book_move.chess_move);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::primitives::Square;
+
+    fn book(entries: &[(&Board, Move, u16)]) -> Book {
+        let mut book = Book {
+            entries: entries
+                .iter()
+                .map(|(board, chess_move, weight)| (polyglot::key(board), polyglot::encode_move(board, *chess_move), *weight, 0))
+                .collect(),
+        };
+        book.entries.sort_by_key(|(key, ..)| *key);
+
+        return book;
+    }
+
+    #[test]
+    fn selection() {
+        let board = Board::default();
+        let e4 = Move::new(Square::E2, Square::E4);
+        let d4 = Move::new(Square::D2, Square::D4);
+        let a4 = Move::new(Square::A2, Square::A4);
+        let book = book(&[(&board, d4, 10), (&board, e4, 30), (&board, a4, 0)]);
+
+        assert_eq!(book.moves(&board).iter().map(|book_move| book_move.chess_move).collect::<Vec<_>>(), [e4, d4, a4]);
+        assert_eq!(book.pick(&board, Selection::Best), Some(e4));
+
+        let picks = (0..400).map(|seed| book.pick(&board, Selection::Weighted(seed)).unwrap()).collect::<Vec<_>>();
+        let e4_count = picks.iter().filter(|chess_move| **chess_move == e4).count();
+        assert!(!picks.contains(&a4));
+        assert!((250..350).contains(&e4_count), "{e4_count}");
+        assert_eq!(book.pick(&board, Selection::Weighted(7)), book.pick(&board, Selection::Weighted(7)));
+
+        let picks = (0..300).map(|seed| book.pick(&board, Selection::Uniform(seed)).unwrap()).collect::<Vec<_>>();
+        assert!([e4, d4, a4].iter().all(|chess_move| picks.contains(chess_move)));
+
+        // Out of book
+        let mut board = board;
+        board.make_move(e4);
+        assert!(book.moves(&board).is_empty());
+        assert_eq!(book.pick(&board, Selection::Uniform(0)), None);
+    }
+
+    #[test]
+    fn files() {
+        let board = Board::default();
+        let book = book(&[(&board, Move::new(Square::G1, Square::F3), 1)]);
+        let bytes = book.to_bytes();
+
+        assert_eq!(bytes.len(), ENTRY_SIZE);
+        assert_eq!(bytes[..8], 0x463B96181691FC9C_u64.to_be_bytes());
+        assert_eq!(Book::from_bytes(&bytes).unwrap(), book);
+        assert!(matches!(Book::from_bytes(&bytes[1..]), Err(BookError::Size(15))));
+    }
+}
@@ -0,0 +1,347 @@
+use std::{
+    fmt::Display,
+    io::{self, BufRead, Write},
+    str::FromStr,
+    sync::Arc,
+};
+
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+
+use crate::{
+    eval::Score,
+    game::{actions::EndOfGameState, moves::MoveGen, Board, Chess},
+    notations::{FEN::FenError, PGN::GameResult},
+    primitives::team::Team,
+    search::{self, tt::TranspositionTable, Limits, SearchResult, Searcher, MAX_PLY, TB_WIN},
+};
+
+/// A position of a game, labelled with its search score and the result of the game
+///
+/// Records are written one per line as `<fen> | <score> | <result>`, the score being in centipawns from white's point
+/// of view and the result a PGN game termination marker.
+///
+/// ```
+/// use engine::{datagen::Record, notations::PGN::GameResult, Board, Team};
+///
+/// let record = "4k3/8/8/8/8/8/8/3QK3 b - - 0 1 | 950 | 1-0".parse::<Record>().unwrap();
+///
+/// assert_eq!(record.score, 950);
+/// assert_eq!(record.result, GameResult::Win(Team::White));
+/// assert_eq!(record.to_string(), "4k3/8/8/8/8/8/8/3QK3 b - - 0 1 | 950 | 1-0");
+/// ```
+#[derive(Debug, Clone)]
+pub struct Record {
+    pub board: Board,
+    /// The score of the position, in centipawns from white's point of view
+    pub score: Score,
+    pub result: GameResult,
+}
+
+impl Display for Record {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} | {} | {}", self.board.to_fen(), self.score, self.result)
+    }
+}
+
+/// Everything that can go wrong while reading a `Record`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecordError {
+    /// The line doesn't have the three fields of a record
+    Fields(String),
+    Fen(FenError),
+    Score(String),
+    Result(String),
+}
+
+impl Display for RecordError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Fields(line) => write!(f, r#"Invalid record "{line}": expected "<fen> | <score> | <result>""#),
+            Self::Fen(error) => write!(f, "Invalid record position: {error}"),
+            Self::Score(score) => write!(f, "Invalid record score {score}"),
+            Self::Result(result) => write!(f, "Invalid record result {result}"),
+        }
+    }
+}
+
+impl std::error::Error for RecordError {}
+
+impl FromStr for Record {
+    type Err = RecordError;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let fields = line.split('|').map(str::trim).collect::<Vec<_>>();
+        let [fen, score, result] = fields[..] else {
+            return Err(RecordError::Fields(line.to_owned()));
+        };
+
+        return Ok(Self {
+            board: Board::from_fen(fen).map_err(RecordError::Fen)?,
+            score: score.parse().map_err(|_| RecordError::Score(score.to_owned()))?,
+            result: GameResult::from_marker(result)
+                .filter(|result| *result != GameResult::Ongoing)
+                .ok_or_else(|| RecordError::Result(result.to_owned()))?,
+        });
+    }
+}
+
+/// Reads the records of a file written by `Generator::generate`, skipping the empty lines
+pub fn read(input: impl BufRead) -> Result<Vec<Record>, Box<dyn std::error::Error>> {
+    let mut records = Vec::new();
+
+    for line in input.lines() {
+        let line = line?;
+        if !line.trim().is_empty() {
+            records.push(line.parse()?);
+        }
+    }
+
+    return Ok(records);
+}
+
+/// The games and positions written by `Generator::generate`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Stats {
+    pub games: usize,
+    pub positions: usize,
+    /// The white wins, draws and black wins
+    pub results: [usize; 3],
+}
+
+impl Display for Stats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let [white, draws, black] = self.results;
+
+        writeln!(f, "Games: {} (+{white} ={draws} -{black})", self.games)?;
+        writeln!(f, "Positions: {}", self.positions)
+    }
+}
+
+/// Generates training data by playing games against itself, from random openings
+///
+/// Every move is searched with the same limits, usually a fixed depth or number of nodes so that the games only
+/// depend on the seed. The positions in check, the ones whose best move is a capture or a promotion and the ones
+/// with a mate or tablebase score are left out, as their score says little about the evaluation.
+///
+/// ```
+/// use engine::{datagen::Generator, search::Limits};
+///
+/// let generator = Generator::new(Limits::depth(1)).with_seed(7).with_max_plies(20);
+/// let mut output = Vec::new();
+/// let stats = generator.generate(2, &mut output).unwrap();
+///
+/// assert_eq!(stats.games, 2);
+/// assert_eq!(String::from_utf8(output).unwrap().lines().count(), stats.positions);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Generator {
+    limits: Limits,
+    seed: u64,
+    random_plies: usize,
+    max_plies: usize,
+    max_opening_score: Score,
+}
+
+impl Generator {
+    pub fn new(limits: Limits) -> Self {
+        Self {
+            limits,
+            seed: 0,
+            random_plies: 8,
+            max_plies: 400,
+            max_opening_score: 300,
+        }
+    }
+
+    /// The seed of the random openings, the same seed always giving the same games with depth or node limits
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// The number of random moves of the openings
+    pub fn with_random_plies(mut self, plies: usize) -> Self {
+        self.random_plies = plies;
+        self
+    }
+
+    /// The number of moves after which a game is adjudicated a draw
+    pub fn with_max_plies(mut self, plies: usize) -> Self {
+        self.max_plies = plies;
+        self
+    }
+
+    /// The largest score (in centipawns) an opening can start with, the more unbalanced ones being drawn again
+    pub fn with_max_opening_score(mut self, score: Score) -> Self {
+        self.max_opening_score = score;
+        self
+    }
+
+    /// Plays `games` games, writing their records to `output` as soon as each game is over
+    pub fn generate(&self, games: usize, mut output: impl Write) -> io::Result<Stats> {
+        let mut stats = Stats::default();
+
+        for index in 0..games {
+            let (records, result) = self.play(index as u64);
+
+            for record in records.iter() {
+                writeln!(output, "{record}")?;
+            }
+
+            stats.games += 1;
+            stats.positions += records.len();
+            stats.results[match result {
+                GameResult::Win(Team::White) => 0,
+                GameResult::Win(Team::Black) => 2,
+                _ => 1,
+            }] += 1;
+        }
+        output.flush()?;
+
+        return Ok(stats);
+    }
+
+    /// Plays the game of the given `index`, returning its records and result
+    pub fn play(&self, index: u64) -> (Vec<Record>, GameResult) {
+        let mut rng = SmallRng::seed_from_u64(self.seed ^ index.wrapping_mul(0x9E37_79B9_7F4A_7C15));
+        let tt = Arc::new(TranspositionTable::new(4));
+        let mut chess = self.opening(&mut rng, &tt);
+
+        let mut positions = Vec::new();
+        let result = loop {
+            if let Some(result) = self.result(&chess) {
+                break result;
+            }
+
+            let board = *chess.board();
+            let analysis = self.search(&chess, &tt);
+            let Some(best_move) = analysis.best_move else {
+                break GameResult::Draw;
+            };
+
+            let quiet = board.checkers.is_empty()
+                && search::is_quiet(&board, best_move)
+                && analysis.score.abs() < TB_WIN - MAX_PLY as Score;
+            if quiet {
+                let score = match board.team_to_move {
+                    Team::White => analysis.score,
+                    Team::Black => -analysis.score,
+                };
+                positions.push((board, score));
+            }
+
+            chess.make_move(best_move).expect("The best move to be legal");
+        };
+
+        let records = positions
+            .into_iter()
+            .map(|(board, score)| Record { board, score, result })
+            .collect();
+
+        return (records, result);
+    }
+
+    /// Plays random moves from the starting position until the game is ongoing and balanced
+    fn opening(&self, rng: &mut SmallRng, tt: &Arc<TranspositionTable>) -> Chess {
+        loop {
+            let mut chess = Chess::default();
+
+            for _ in 0..self.random_plies {
+                let moves = MoveGen::new_legal(chess.board()).collect::<Vec<_>>();
+                if moves.is_empty() {
+                    break;
+                }
+                chess.make_move(moves[rng.gen_range(0..moves.len())]).expect("Legal moves to be legal");
+            }
+
+            if chess.outcome().is_some() {
+                continue;
+            }
+
+            let analysis = self.search(&chess, tt);
+            if analysis.score.abs() <= self.max_opening_score {
+                return chess;
+            }
+        }
+    }
+
+    /// The result of the game if it is over, claimed draws and games that lasted too long included
+    fn result(&self, chess: &Chess) -> Option<GameResult> {
+        if chess.claimable_draw().is_some() || chess.ply() >= self.max_plies {
+            return Some(GameResult::Draw);
+        }
+
+        return match chess.outcome()? {
+            EndOfGameState::Checkmate(team) => Some(GameResult::Win(team)),
+            _ => Some(GameResult::Draw),
+        };
+    }
+
+    /// Searches the current position of the game, with the positions played before it
+    fn search(&self, chess: &Chess, tt: &Arc<TranspositionTable>) -> SearchResult {
+        let mut hashes = std::iter::once(chess.starting_position())
+            .chain(chess.moves().map(|(_, board)| board))
+            .map(Board::hash)
+            .collect::<Vec<_>>();
+        hashes.pop();
+
+        return Searcher::new(self.limits)
+            .with_transposition_table(Arc::clone(tt))
+            .with_history(hashes)
+            .search(chess.board());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::notations::FEN;
+
+    #[test]
+    fn records() {
+        let record = Record {
+            board: Board::default(),
+            score: -25,
+            result: GameResult::Draw,
+        };
+        let line = record.to_string();
+        assert_eq!(line, "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 | -25 | 1/2-1/2");
+        assert_eq!(line.parse::<Record>().unwrap().to_string(), line);
+
+        assert!(matches!("8/8 w | 1 | 1-0".parse::<Record>(), Err(RecordError::Fen(_))));
+        assert!(matches!(format!("{} | x | 1-0", FEN::START).parse::<Record>(), Err(RecordError::Score(_))));
+        assert!(matches!(format!("{} | 1 | *", FEN::START).parse::<Record>(), Err(RecordError::Result(_))));
+        assert!(matches!(FEN::START.parse::<Record>(), Err(RecordError::Fields(_))));
+
+        let records = read(format!("{line}\n\n{line}\n").as_bytes()).unwrap();
+        assert_eq!(records.len(), 2);
+    }
+
+    #[test]
+    fn generate() {
+        let generator = Generator::new(Limits::depth(2)).with_seed(1).with_max_plies(60);
+        let mut output = Vec::new();
+        let stats = generator.generate(3, &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        assert_eq!(stats.games, 3);
+        assert_eq!(stats.results.iter().sum::<usize>(), 3);
+        assert!(stats.positions > 0);
+
+        // Reproducible
+        let mut again = Vec::new();
+        generator.generate(3, &mut again).unwrap();
+        assert_eq!(String::from_utf8(again).unwrap(), output);
+
+        // The positions are quiet
+        for record in read(output.as_bytes()).unwrap() {
+            assert!(record.board.checkers.is_empty());
+            assert!(record.score.abs() < TB_WIN - MAX_PLY as Score);
+        }
+
+        // Another seed plays other openings
+        let (records, _) = generator.clone().with_seed(2).play(0);
+        let (first, _) = generator.play(0);
+        assert_ne!(records.first().map(ToString::to_string), first.first().map(ToString::to_string));
+    }
+}
@@ -0,0 +1,572 @@
+use std::{
+    fmt::Display,
+    ops::{Add, AddAssign, Mul, Neg, Sub},
+};
+
+use crate::{
+    bitboard::BitBoard,
+    game::Board,
+    magic,
+    primitives::{piece::Piece, team::Team, Square},
+    utils::enums::ArrayEnum,
+};
+
+pub mod nnue;
+mod tables;
+pub mod tune;
+use tables::*;
+
+/// A score in centipawns, from the point of view of the team to move
+pub type Score = i32;
+
+/// The value of each `Piece`, in centipawns
+pub const PIECE_VALUES: [Score; Piece::SIZE] = [100, 320, 330, 500, 900, 0];
+
+/// The value of the `piece`, in centipawns
+#[inline]
+pub fn piece_value(piece: Piece) -> Score {
+    PIECE_VALUES[piece.to_index()]
+}
+
+/// A pair of middlegame and endgame scores, interpolated by the phase of the game
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, rspc::Type, serde::Serialize)]
+pub struct Tapered {
+    pub mg: Score,
+    pub eg: Score,
+}
+
+impl Tapered {
+    pub const ZERO: Tapered = Tapered::new(0, 0);
+
+    pub const fn new(mg: Score, eg: Score) -> Self {
+        Self { mg, eg }
+    }
+
+    /// Interpolates between the middlegame (`phase == PHASE_TOTAL`) and the endgame (`phase == 0`) scores
+    ///
+    /// ```
+    /// use engine::eval::Tapered;
+    ///
+    /// let score = Tapered::new(100, 200);
+    ///
+    /// assert_eq!(score.taper(24), 100);
+    /// assert_eq!(score.taper(12), 150);
+    /// assert_eq!(score.taper(0), 200);
+    /// ```
+    #[inline]
+    pub fn taper(&self, phase: i32) -> Score {
+        let phase = phase.clamp(0, PHASE_TOTAL);
+
+        return (self.mg * phase + self.eg * (PHASE_TOTAL - phase)) / PHASE_TOTAL;
+    }
+}
+
+// [Operators]
+impl Add for Tapered {
+    type Output = Tapered;
+
+    #[inline]
+    fn add(self, rhs: Tapered) -> Tapered {
+        Tapered::new(self.mg + rhs.mg, self.eg + rhs.eg)
+    }
+}
+
+impl AddAssign for Tapered {
+    #[inline]
+    fn add_assign(&mut self, rhs: Tapered) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub for Tapered {
+    type Output = Tapered;
+
+    #[inline]
+    fn sub(self, rhs: Tapered) -> Tapered {
+        Tapered::new(self.mg - rhs.mg, self.eg - rhs.eg)
+    }
+}
+
+impl Neg for Tapered {
+    type Output = Tapered;
+
+    #[inline]
+    fn neg(self) -> Tapered {
+        Tapered::new(-self.mg, -self.eg)
+    }
+}
+
+impl Mul<i32> for Tapered {
+    type Output = Tapered;
+
+    #[inline]
+    fn mul(self, rhs: i32) -> Tapered {
+        Tapered::new(self.mg * rhs, self.eg * rhs)
+    }
+}
+
+/// The terms the evaluation is made of
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, rspc::Type, serde::Serialize)]
+pub enum Term {
+    Material,
+    /// The placement of each piece, from the piece-square tables
+    PieceSquares,
+    /// Doubled, isolated and passed pawns
+    PawnStructure,
+    /// The number of squares each piece can move to
+    Mobility,
+    /// The pawn shield in front of the king and the pieces attacking the squares around it
+    KingSafety,
+    BishopPair,
+}
+
+impl ArrayEnum<6> for Term {
+    const ALL: [Self; Self::SIZE] = [
+        Self::Material,
+        Self::PieceSquares,
+        Self::PawnStructure,
+        Self::Mobility,
+        Self::KingSafety,
+        Self::BishopPair,
+    ];
+
+    #[inline]
+    fn to_index(&self) -> usize {
+        *self as usize
+    }
+}
+
+/// A weight of the evaluation that can be tuned (see `tune`), every term but the king attacks being made of them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Param {
+    Material(Piece),
+    /// The piece-square table of the piece, at the index of the square in the table (see `table_index`)
+    PieceSquare(Piece, usize),
+    BishopPair,
+    DoubledPawn,
+    IsolatedPawn,
+    /// By relative rank
+    PassedPawn(usize),
+    /// Per reachable square above the baseline of the piece
+    Mobility(Piece),
+    /// Per pawn in front of the king
+    PawnShield,
+}
+
+impl Param {
+    pub const COUNT: usize = Piece::SIZE * 66 + 12;
+
+    /// Every param, ordered by `index`
+    pub fn all() -> impl Iterator<Item = Param> {
+        (0..Self::COUNT).map(Self::from_index)
+    }
+
+    /// The position of the param in `Param::all`
+    pub fn index(&self) -> usize {
+        match *self {
+            Self::Material(piece) => piece.to_index(),
+            Self::PieceSquare(piece, index) => Piece::SIZE + piece.to_index() * 64 + index,
+            Self::BishopPair => Piece::SIZE * 65,
+            Self::DoubledPawn => Piece::SIZE * 65 + 1,
+            Self::IsolatedPawn => Piece::SIZE * 65 + 2,
+            Self::PassedPawn(rank) => Piece::SIZE * 65 + 3 + rank,
+            Self::Mobility(piece) => Piece::SIZE * 65 + 11 + piece.to_index(),
+            Self::PawnShield => Piece::SIZE * 66 + 11,
+        }
+    }
+
+    /// The param at `index` in `Param::all`
+    ///
+    /// ```
+    /// use engine::eval::Param;
+    ///
+    /// assert!(Param::all().enumerate().all(|(index, param)| param.index() == index));
+    /// ```
+    pub fn from_index(index: usize) -> Self {
+        const TABLES: usize = Piece::SIZE * 65;
+
+        match index {
+            _ if index < Piece::SIZE => Self::Material(Piece::ALL[index]),
+            _ if index < TABLES => Self::PieceSquare(Piece::ALL[(index - Piece::SIZE) / 64], (index - Piece::SIZE) % 64),
+            _ if index == TABLES => Self::BishopPair,
+            _ if index == TABLES + 1 => Self::DoubledPawn,
+            _ if index == TABLES + 2 => Self::IsolatedPawn,
+            _ if index < TABLES + 11 => Self::PassedPawn(index - TABLES - 3),
+            _ if index < TABLES + 11 + Piece::SIZE => Self::Mobility(Piece::ALL[index - TABLES - 11]),
+            _ => Self::PawnShield,
+        }
+    }
+
+    /// The weight of the param used by the evaluation
+    #[inline]
+    pub fn weight(&self) -> Tapered {
+        match *self {
+            Self::Material(piece) => MATERIAL[piece.to_index()],
+            Self::PieceSquare(piece, index) => {
+                let (mg_table, eg_table) = PIECE_SQUARES[piece.to_index()];
+                Tapered::new(mg_table[index], eg_table[index])
+            }
+            Self::BishopPair => BISHOP_PAIR,
+            Self::DoubledPawn => DOUBLED_PAWN,
+            Self::IsolatedPawn => ISOLATED_PAWN,
+            Self::PassedPawn(rank) => PASSED_PAWN[rank],
+            Self::Mobility(piece) => MOBILITY[piece.to_index()],
+            Self::PawnShield => PAWN_SHIELD,
+        }
+    }
+}
+
+impl Display for Term {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Material => "Material",
+            Self::PieceSquares => "Piece-square tables",
+            Self::PawnStructure => "Pawn structure",
+            Self::Mobility => "Mobility",
+            Self::KingSafety => "King safety",
+            Self::BishopPair => "Bishop pair",
+        };
+
+        return f.pad(name);
+    }
+}
+
+/// The evaluation of a position, term by term and team by team
+///
+/// ```
+/// use engine::{eval::{self, Term}, Board, Team};
+///
+/// let board = Board::from_fen("4k3/8/8/8/8/8/8/2B1KB2 w - - 0 1").unwrap();
+/// let breakdown = eval::breakdown(&board);
+///
+/// assert!(breakdown.get(Term::BishopPair, Team::White).eg > 0);
+/// assert_eq!(breakdown.get(Term::BishopPair, Team::Black).eg, 0);
+/// assert_eq!(breakdown.total(), eval::evaluate(&board));
+/// assert!(breakdown.score(Term::Material) > 600);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, rspc::Type, serde::Serialize)]
+pub struct Breakdown {
+    /// The scores of each term, for white and black
+    pub terms: [[Tapered; Team::SIZE]; Term::SIZE],
+    /// From `PHASE_TOTAL` (all the pieces are on the board) down to 0 (only pawns and kings are left)
+    pub phase: i32,
+    #[serde(rename = "teamToMove")]
+    pub team_to_move: Team,
+}
+
+impl Breakdown {
+    /// The score of the `term` for the `team`, before tapering
+    pub fn get(&self, term: Term, team: Team) -> Tapered {
+        self.terms[term.to_index()][team.to_index()]
+    }
+
+    /// The tapered score of the `term`, from the point of view of the team to move
+    ///
+    /// The terms are tapered separately, so their sum may be off by a few centipawns from `total`
+    pub fn score(&self, term: Term) -> Score {
+        let score = self.get(term, self.team_to_move) - self.get(term, !self.team_to_move);
+
+        return score.taper(self.phase);
+    }
+
+    /// The tapered score of the position, from the point of view of the team to move
+    pub fn total(&self) -> Score {
+        let score = Term::ALL.iter().fold(Tapered::ZERO, |score, term| {
+            score + self.get(*term, self.team_to_move) - self.get(*term, !self.team_to_move)
+        });
+
+        return score.taper(self.phase);
+    }
+}
+
+/// Where the evaluation adds its terms: the scores of a `Breakdown`, or the params of a `tune::Trace`
+trait Sink {
+    /// Adds the weight of the `param`, `count` times, to the `term` of the `team`
+    fn add_param(&mut self, term: Term, team: Team, param: Param, count: i32);
+
+    /// Adds a score that is not made of params to the `term` of the `team`
+    fn add_score(&mut self, term: Term, team: Team, score: Tapered);
+}
+
+impl Sink for Breakdown {
+    #[inline]
+    fn add_param(&mut self, term: Term, team: Team, param: Param, count: i32) {
+        self.add_score(term, team, param.weight() * count);
+    }
+
+    #[inline]
+    fn add_score(&mut self, term: Term, team: Team, score: Tapered) {
+        self.terms[term.to_index()][team.to_index()] += score;
+    }
+}
+
+/// Prints a table of the terms, every score being from white's point of view
+impl Display for Breakdown {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let sign = match self.team_to_move {
+            Team::White => 1,
+            Team::Black => -1,
+        };
+
+        writeln!(f, "{:<20}|     White     |     Black     |     Total", "Term")?;
+        writeln!(f, "{:<20}|   MG      EG  |   MG      EG  |   MG      EG", "")?;
+        writeln!(f, "{}", "-".repeat(65))?;
+        for term in Term::ALL {
+            let white = self.get(term, Team::White);
+            let black = self.get(term, Team::Black);
+            let total = white - black;
+
+            writeln!(
+                f,
+                "{term:<20}| {:>5}   {:>5} | {:>5}   {:>5} | {:>5}   {:>5}",
+                white.mg, white.eg, black.mg, black.eg, total.mg, total.eg
+            )?;
+        }
+        writeln!(f, "{}", "-".repeat(65))?;
+        write!(f, "Phase: {}/{PHASE_TOTAL}, total: {} (white side)", self.phase, sign * self.total())
+    }
+}
+
+/// Evaluates the `board` from the point of view of the team to move
+///
+/// ```
+/// use engine::{eval, Board};
+///
+/// assert_eq!(eval::evaluate(&Board::default()), 0);
+/// assert!(eval::evaluate(&Board::from_fen("4k3/8/8/8/8/8/8/3QK3 b - - 0 1").unwrap()) < -900);
+/// ```
+pub fn evaluate(board: &Board) -> Score {
+    breakdown(board).total()
+}
+
+/// Evaluates the `board`, keeping the score of each term for each team
+pub fn breakdown(board: &Board) -> Breakdown {
+    let mut breakdown = Breakdown {
+        terms: [[Tapered::ZERO; Team::SIZE]; Term::SIZE],
+        phase: phase(board),
+        team_to_move: board.team_to_move,
+    };
+
+    add_terms(board, &mut breakdown);
+
+    return breakdown;
+}
+
+/// From `PHASE_TOTAL` (all the pieces are on the board) down to 0 (only pawns and kings are left)
+fn phase(board: &Board) -> i32 {
+    let phase = Piece::ALL
+        .iter()
+        .map(|piece| PHASE_WEIGHTS[piece.to_index()] * board.piece_mask(*piece).count_bits() as i32)
+        .sum::<i32>();
+
+    return phase.min(PHASE_TOTAL);
+}
+
+fn add_terms(board: &Board, sink: &mut impl Sink) {
+    for team in Team::ALL {
+        material(board, team, sink);
+        pawn_structure(board, team, sink);
+        pieces(board, team, sink);
+        king_shield(board, team, sink);
+    }
+}
+
+/// The index of the `square` in the piece-square tables, which are written from white's point of view
+#[inline]
+fn table_index(square: Square, team: Team) -> usize {
+    let (file, rank) = square.to_coord_indices();
+    let rank = match team {
+        Team::White => rank,
+        Team::Black => 7 - rank,
+    };
+
+    return (7 - rank) * 8 + file;
+}
+
+/// The rank of the `square`, counted from the backrank of the `team` (0 to 7)
+#[inline]
+fn relative_rank(square: Square, team: Team) -> usize {
+    let rank = square.get_rank().to_index();
+
+    return match team {
+        Team::White => rank,
+        Team::Black => 7 - rank,
+    };
+}
+
+/// The squares of the ranks in front of the `square`, from the point of view of the `team`
+#[inline]
+fn ranks_ahead(square: Square, team: Team) -> BitBoard {
+    let rank = square.get_rank().to_index();
+
+    return match team {
+        Team::White if rank == 7 => BitBoard::EMPTY,
+        Team::White => BitBoard(u64::MAX << (8 * (rank + 1))),
+        Team::Black => BitBoard((1u64 << (8 * rank)) - 1),
+    };
+}
+
+fn pawn_attacks(board: &Board, team: Team) -> BitBoard {
+    board
+        .get_mask(Piece::Pawn, team)
+        .fold(BitBoard::EMPTY, |attacks, square| {
+            attacks | magic::pawn::get_attacks(square, team, BitBoard::FULL)
+        })
+}
+
+// [Terms]
+fn material(board: &Board, team: Team, sink: &mut impl Sink) {
+    for piece in Piece::ALL {
+        let mask = board.get_mask(piece, team);
+
+        sink.add_param(Term::Material, team, Param::Material(piece), mask.count_bits() as i32);
+        for square in mask {
+            sink.add_param(Term::PieceSquares, team, Param::PieceSquare(piece, table_index(square, team)), 1);
+        }
+    }
+
+    if board.get_mask(Piece::Bishop, team).count_bits() >= 2 {
+        sink.add_param(Term::BishopPair, team, Param::BishopPair, 1);
+    }
+}
+
+fn pawn_structure(board: &Board, team: Team, sink: &mut impl Sink) {
+    let pawns = board.get_mask(Piece::Pawn, team);
+    let enemy_pawns = board.get_mask(Piece::Pawn, !team);
+
+    for file in 0..8 {
+        let count = (pawns & BitBoard::FILES[file]).count_bits() as i32;
+        if count > 1 {
+            sink.add_param(Term::PawnStructure, team, Param::DoubledPawn, count - 1);
+        }
+    }
+
+    for square in pawns {
+        let file = square.get_file().to_index();
+
+        if (pawns & BitBoard::ADJACENT_FILES[file]).is_empty() {
+            sink.add_param(Term::PawnStructure, team, Param::IsolatedPawn, 1);
+        }
+
+        let front_span = (BitBoard::FILES[file] | BitBoard::ADJACENT_FILES[file]) & ranks_ahead(square, team);
+        if (enemy_pawns & front_span).is_empty() {
+            sink.add_param(Term::PawnStructure, team, Param::PassedPawn(relative_rank(square, team)), 1);
+        }
+    }
+}
+
+/// Mobility of the pieces, and their attacks on the enemy king zone
+fn pieces(board: &Board, team: Team, sink: &mut impl Sink) {
+    let blockers = board.all_mask();
+    // The squares attacked by enemy pawns are not worth moving to
+    let available = !board.team_mask(team) & !pawn_attacks(board, !team);
+    let enemy_king = board.king_square(!team);
+    let king_zone = magic::king::get_moves(enemy_king) | BitBoard::from_square(enemy_king);
+
+    let mut attackers = 0;
+    let mut attack = 0;
+
+    for piece in [Piece::Knight, Piece::Bishop, Piece::Rook, Piece::Queen] {
+        for square in board.get_mask(piece, team) {
+            let attacks = match piece {
+                Piece::Knight => magic::knight::get_moves(square),
+                Piece::Bishop => magic::bishop::get_moves(square, blockers),
+                Piece::Rook => magic::rook::get_moves(square, blockers),
+                _ => magic::bishop::get_moves(square, blockers) | magic::rook::get_moves(square, blockers),
+            };
+
+            let mobility = (attacks & available).count_bits() as i32 - MOBILITY_BASELINE[piece.to_index()];
+            sink.add_param(Term::Mobility, team, Param::Mobility(piece), mobility);
+
+            let zone_attacks = (attacks & king_zone).count_bits() as i32;
+            if zone_attacks > 0 {
+                attackers += 1;
+                attack += zone_attacks * KING_ATTACK_WEIGHTS[piece.to_index()];
+            }
+        }
+    }
+
+    // A single piece can rarely mate, the attack only matters when several pieces join in
+    let danger = attack * KING_ATTACKERS_SCALE[attackers.min(7)] / 100;
+    sink.add_score(Term::KingSafety, !team, Tapered::new(-danger, 0));
+}
+
+fn king_shield(board: &Board, team: Team, sink: &mut impl Sink) {
+    let king = board.king_square(team);
+    // `Square::translate` counts the ranks downwards
+    let direction = match team {
+        Team::White => -1,
+        Team::Black => 1,
+    };
+
+    let shield = (1..=2)
+        .flat_map(|ranks| (-1..=1).filter_map(move |files| king.translate(files, ranks * direction)))
+        .filter(|square| board.get_mask(Piece::Pawn, team).has_square(*square))
+        .count() as i32;
+
+    sink.add_param(Term::KingSafety, team, Param::PawnShield, shield);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn breakdown_fen(fen: &str) -> Breakdown {
+        breakdown(&Board::from_fen(fen).unwrap())
+    }
+
+    #[test]
+    fn symmetry() {
+        for (fen, mirrored) in [
+            (
+                "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4",
+                "rnbqk2r/pppp1ppp/5n2/2b1p3/4P3/2N2N2/PPPP1PPP/R1BQKB1R b KQkq - 4 4",
+            ),
+            ("8/5k2/3p4/1p1P4/1P6/8/4K3/8 w - - 0 1", "8/4k3/8/1p6/1P1p4/3P4/5K2/8 b - - 0 1"),
+        ] {
+            let (breakdown, mirrored) = (breakdown_fen(fen), breakdown_fen(mirrored));
+
+            assert_eq!(breakdown.total(), mirrored.total());
+            assert_eq!(breakdown.phase, mirrored.phase);
+            for term in Term::ALL {
+                assert_eq!(breakdown.get(term, Team::White), mirrored.get(term, Team::Black), "{term}");
+            }
+        }
+
+        assert_eq!(evaluate(&Board::default()), 0);
+        assert_eq!(breakdown(&Board::default()).phase, PHASE_TOTAL);
+    }
+
+    #[test]
+    fn pawn_structure() {
+        // Doubled and isolated pawns on the a-file for white, a passed pawn on d5
+        let breakdown = breakdown_fen("4k3/8/8/3P4/8/P7/P7/4K3 w - - 0 1");
+        let structure = breakdown.get(Term::PawnStructure, Team::White);
+        let expected = DOUBLED_PAWN + ISOLATED_PAWN * 3 + PASSED_PAWN[4] + PASSED_PAWN[2] + PASSED_PAWN[1];
+        assert_eq!(structure, expected);
+
+        // The d5 pawn is not passed anymore
+        let breakdown = breakdown_fen("4k3/2p5/8/3P4/8/8/8/4K3 w - - 0 1");
+        assert_eq!(breakdown.get(Term::PawnStructure, Team::White), ISOLATED_PAWN);
+        assert_eq!(breakdown.get(Term::PawnStructure, Team::Black), ISOLATED_PAWN);
+    }
+
+    #[test]
+    fn terms() {
+        // A centralized knight is more mobile than one in the corner
+        let center = breakdown_fen("4k3/8/8/8/3N4/8/8/4K3 w - - 0 1");
+        let corner = breakdown_fen("4k3/8/8/8/8/8/8/N3K3 w - - 0 1");
+        assert!(center.score(Term::Mobility) > corner.score(Term::Mobility));
+        assert!(center.score(Term::PieceSquares) > corner.score(Term::PieceSquares));
+        assert!(center.total() > corner.total());
+
+        // Castled king behind its pawns, then attacked by a queen and a knight
+        let safe = breakdown_fen("6k1/5ppp/8/8/8/8/5PPP/6K1 w - - 0 1");
+        let attacked = breakdown_fen("6k1/5ppp/8/8/8/5n1q/5PPP/6K1 w - - 0 1");
+        assert_eq!(safe.get(Term::KingSafety, Team::White), PAWN_SHIELD * 3);
+        assert!(attacked.get(Term::KingSafety, Team::White).mg < safe.get(Term::KingSafety, Team::White).mg);
+
+        // Only the pawns and kings are left
+        assert_eq!(breakdown_fen("4k3/pppp4/8/8/8/8/4PPPP/4K3 b - - 0 1").phase, 0);
+    }
+}
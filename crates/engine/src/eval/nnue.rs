@@ -0,0 +1,461 @@
+use std::{
+    fmt::Display,
+    io,
+    path::Path,
+    simd::prelude::*,
+};
+
+use crate::{
+    eval::Score,
+    game::Board,
+    primitives::{piece::Piece, team::Team, Square},
+    utils::enums::ArrayEnum,
+};
+
+/// A tiny network with material weights and random noise, only good for testing
+pub const TEST_NETWORK: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/data/nnue/test.nnue");
+
+/// The first bytes of a network file, followed by the version of the format
+const MAGIC: &[u8; 4] = b"NNUE";
+const VERSION: u32 = 1;
+/// The size of the header: magic, version, number of inputs and hidden size
+const HEADER_SIZE: usize = 16;
+
+/// The king buckets of each perspective, see `Orientation`
+const KING_BUCKETS: usize = 4;
+/// The number of features of each perspective: a king bucket, then the team, piece and square of a piece
+pub const INPUTS: usize = KING_BUCKETS * Team::SIZE * Piece::SIZE * 64;
+/// The largest hidden layer, keeping the output from overflowing
+pub const MAX_HIDDEN: usize = 1024;
+
+/// The number of `i16` computed at once, the hidden size being a multiple of it
+const LANES: usize = 16;
+type Lanes = Simd<i16, LANES>;
+
+/// The hidden layer is clipped to `[0, QA]`, `QA` standing for 1.0
+const QA: i32 = 255;
+/// The quantization of the output weights
+const QB: i32 = 64;
+/// The centipawns of an output of 1.0
+const SCALE: i32 = 400;
+
+/// Everything that can go wrong while reading a network
+#[derive(Debug)]
+pub enum NnueError {
+    Io(io::Error),
+    /// The file is not a network, or one with other features
+    Header,
+    /// The hidden size is not a multiple of 16, up to `MAX_HIDDEN`
+    HiddenSize(usize),
+    /// The size of the file, in bytes, doesn't match its hidden size
+    Size(usize),
+}
+
+impl Display for NnueError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "Could not read the network: {error}"),
+            Self::Header => write!(f, "Invalid network: not a version {VERSION} network with {INPUTS} inputs"),
+            Self::HiddenSize(size) => write!(f, "Invalid network: unsupported hidden size {size}"),
+            Self::Size(size) => write!(f, "Invalid network size: {size} bytes"),
+        }
+    }
+}
+
+impl std::error::Error for NnueError {}
+
+impl From<io::Error> for NnueError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+/// How a team sees the board: its backrank is the first rank, its king is on the a-d files, and its position picks
+/// the bucket of the features
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Orientation {
+    /// The bits to flip in the index of a square: the rank for black, the file when the king is on the e-h files
+    flip: usize,
+    bucket: usize,
+}
+
+impl Orientation {
+    fn new(perspective: Team, king: Square) -> Self {
+        let mut flip = match perspective {
+            Team::White => 0,
+            Team::Black => 56,
+        };
+        if (king.to_index() ^ flip) % 8 >= 4 {
+            flip ^= 7;
+        }
+
+        let king = king.to_index() ^ flip;
+        let bucket = match (king / 8, king % 8) {
+            // Castled
+            (0, 0 | 1) => 0,
+            (0, _) => 1,
+            (1, _) => 2,
+            _ => 3,
+        };
+
+        return Self { flip, bucket };
+    }
+
+    /// The index of the feature of a piece
+    #[inline]
+    fn feature(&self, perspective: Team, piece: Piece, team: Team, square: Square) -> usize {
+        let relative_team = (team != perspective) as usize;
+
+        return ((self.bucket * Team::SIZE + relative_team) * Piece::SIZE + piece.to_index()) * 64
+            + (square.to_index() ^ self.flip);
+    }
+}
+
+/// The hidden layer of a `Network` for each perspective, before the activation
+///
+/// It only depends on the placement of the pieces, so it can be updated with the pieces changed by a move rather
+/// than computed from every piece.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Accumulator {
+    values: [Vec<i16>; Team::SIZE],
+    orientations: [Orientation; Team::SIZE],
+}
+
+/// A quantized neural network evaluating positions with a single hidden layer, in the style of NNUE
+///
+/// The features are HalfKA-like: every piece is seen from both perspectives, relative to the king bucket of the
+/// perspective. The hidden layer is an `Accumulator`, updated incrementally with `Board::changes`, and the output
+/// combines the hidden layer of the team to move with the one of its opponent, each being clipped to `[0, 1]`.
+///
+/// The network files are little endian: `NNUE`, the version, the number of inputs and the hidden size as `u32`, then
+/// the feature weights (input by input), the hidden biases, the output weights (team to move first) and the output
+/// bias as `i16`.
+///
+/// ```
+/// use engine::{eval::nnue::{Network, TEST_NETWORK}, Board, Move, Square};
+///
+/// let network = Network::open(TEST_NETWORK).unwrap();
+/// let mut board = Board::default();
+/// assert_eq!(network.evaluate_board(&board), 0);
+///
+/// let parent = network.accumulator(&board);
+/// let mut accumulator = parent.clone();
+/// board.make_move(Move::new(Square::E2, Square::E4));
+/// network.update(&mut accumulator, &parent, &board);
+///
+/// assert_eq!(accumulator, network.accumulator(&board));
+/// assert_eq!(network.evaluate(&accumulator, board.team_to_move), network.evaluate_board(&board));
+/// ```
+#[derive(Clone, PartialEq, Eq)]
+pub struct Network {
+    hidden: usize,
+    /// `INPUTS` rows of `hidden` weights
+    feature_weights: Vec<i16>,
+    feature_biases: Vec<i16>,
+    /// The weights of the team to move, then the ones of its opponent
+    output_weights: Vec<i16>,
+    output_bias: i16,
+}
+
+impl Network {
+    /// A network with every weight set to 0, evaluating every position as a draw
+    pub fn new(hidden: usize) -> Result<Self, NnueError> {
+        if hidden == 0 || hidden % LANES != 0 || hidden > MAX_HIDDEN {
+            return Err(NnueError::HiddenSize(hidden));
+        }
+
+        return Ok(Self {
+            hidden,
+            feature_weights: vec![0; INPUTS * hidden],
+            feature_biases: vec![0; hidden],
+            output_weights: vec![0; 2 * hidden],
+            output_bias: 0,
+        });
+    }
+
+    /// Reads the network at `path`
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, NnueError> {
+        Self::from_bytes(&std::fs::read(path)?)
+    }
+
+    /// Reads a network from the content of its file
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, NnueError> {
+        if bytes.len() < HEADER_SIZE {
+            return Err(NnueError::Size(bytes.len()));
+        }
+
+        let header = |index: usize| u32::from_le_bytes(bytes[4 * index..4 * index + 4].try_into().unwrap()) as usize;
+        if &bytes[0..4] != MAGIC || header(1) != VERSION as usize || header(2) != INPUTS {
+            return Err(NnueError::Header);
+        }
+
+        let mut network = Self::new(header(3))?;
+        let weights = (INPUTS + 3) * network.hidden + 1;
+        if bytes.len() != HEADER_SIZE + 2 * weights {
+            return Err(NnueError::Size(bytes.len()));
+        }
+
+        let mut values = bytes[HEADER_SIZE..]
+            .chunks_exact(2)
+            .map(|value| i16::from_le_bytes([value[0], value[1]]));
+        for weight in network
+            .feature_weights
+            .iter_mut()
+            .chain(network.feature_biases.iter_mut())
+            .chain(network.output_weights.iter_mut())
+        {
+            *weight = values.next().unwrap();
+        }
+        network.output_bias = values.next().unwrap();
+
+        return Ok(network);
+    }
+
+    /// The content of the file of the network
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_SIZE + 2 * ((INPUTS + 3) * self.hidden + 1));
+
+        bytes.extend(MAGIC);
+        for value in [VERSION, INPUTS as u32, self.hidden as u32] {
+            bytes.extend(value.to_le_bytes());
+        }
+        for weight in self
+            .feature_weights
+            .iter()
+            .chain(self.feature_biases.iter())
+            .chain(self.output_weights.iter())
+            .chain([self.output_bias].iter())
+        {
+            bytes.extend(weight.to_le_bytes());
+        }
+
+        return bytes;
+    }
+
+    /// Writes the network to `path`
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        std::fs::write(path, self.to_bytes())
+    }
+
+    /// The number of neurons of the hidden layer, for each perspective
+    pub fn hidden_size(&self) -> usize {
+        self.hidden
+    }
+
+    /// The weights of the hidden layer for the `feature`, to be tuned
+    pub fn feature_weights_mut(&mut self, feature: usize) -> &mut [i16] {
+        &mut self.feature_weights[feature * self.hidden..(feature + 1) * self.hidden]
+    }
+
+    /// The biases of the hidden layer, to be tuned
+    pub fn feature_biases_mut(&mut self) -> &mut [i16] {
+        &mut self.feature_biases
+    }
+
+    /// The weights of the output for the hidden layer of the team to move, then of its opponent, to be tuned
+    pub fn output_weights_mut(&mut self) -> &mut [i16] {
+        &mut self.output_weights
+    }
+
+    pub fn set_output_bias(&mut self, bias: i16) {
+        self.output_bias = bias;
+    }
+
+    /// The accumulator of the `board`, computed from every piece
+    pub fn accumulator(&self, board: &Board) -> Accumulator {
+        let mut accumulator = Accumulator {
+            values: [Vec::new(), Vec::new()],
+            orientations: Team::ALL.map(|team| Orientation::new(team, board.king_square(team))),
+        };
+
+        for perspective in Team::ALL {
+            self.refresh(&mut accumulator, board, perspective);
+        }
+
+        return accumulator;
+    }
+
+    /// Sets the `accumulator` to the one of the `board`, from the accumulator of the position it was in before its
+    /// last move
+    ///
+    /// The perspectives whose king changed bucket, and the boards without `Board::changes`, are computed from every
+    /// piece.
+    pub fn update(&self, accumulator: &mut Accumulator, parent: &Accumulator, board: &Board) {
+        for perspective in Team::ALL {
+            let index = perspective.to_index();
+            let orientation = Orientation::new(perspective, board.king_square(perspective));
+
+            let changes = match board.changes() {
+                Some(changes) if orientation == parent.orientations[index] => changes,
+                _ => {
+                    accumulator.orientations[index] = orientation;
+                    self.refresh(accumulator, board, perspective);
+                    continue;
+                }
+            };
+
+            accumulator.orientations[index] = orientation;
+            let values = &mut accumulator.values[index];
+            values.clone_from(&parent.values[index]);
+
+            for change in changes {
+                let feature = orientation.feature(perspective, change.piece, change.team, change.square);
+                let weights = &self.feature_weights[feature * self.hidden..(feature + 1) * self.hidden];
+
+                match change.added {
+                    true => add(values, weights),
+                    false => sub(values, weights),
+                }
+            }
+        }
+    }
+
+    /// Computes the values of the `perspective` in the `accumulator` from every piece of the `board`
+    fn refresh(&self, accumulator: &mut Accumulator, board: &Board, perspective: Team) {
+        let index = perspective.to_index();
+        let orientation = accumulator.orientations[index];
+        let values = &mut accumulator.values[index];
+        values.clone_from(&self.feature_biases);
+
+        for team in Team::ALL {
+            for piece in Piece::ALL {
+                for square in board.get_mask(piece, team) {
+                    let feature = orientation.feature(perspective, piece, team, square);
+                    add(values, &self.feature_weights[feature * self.hidden..(feature + 1) * self.hidden]);
+                }
+            }
+        }
+    }
+
+    /// The score of the position of the `accumulator`, from the point of view of the `team_to_move`
+    pub fn evaluate(&self, accumulator: &Accumulator, team_to_move: Team) -> Score {
+        let (us, them) = self.output_weights.split_at(self.hidden);
+        let output = activate(&accumulator.values[team_to_move.to_index()], us)
+            + activate(&accumulator.values[(!team_to_move).to_index()], them);
+
+        return (output / QA + self.output_bias as i32) * SCALE / (QA * QB);
+    }
+
+    /// Evaluates the `board` from the point of view of the team to move, computing its accumulator from every piece
+    pub fn evaluate_board(&self, board: &Board) -> Score {
+        self.evaluate(&self.accumulator(board), board.team_to_move)
+    }
+}
+
+impl std::fmt::Debug for Network {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Network")
+            .field("hidden", &self.hidden)
+            .field("output_bias", &self.output_bias)
+            .finish()
+    }
+}
+
+// [SIMD]
+#[inline]
+fn add(values: &mut [i16], weights: &[i16]) {
+    for (values, weights) in values.chunks_exact_mut(LANES).zip(weights.chunks_exact(LANES)) {
+        (Lanes::from_slice(values) + Lanes::from_slice(weights)).copy_to_slice(values);
+    }
+}
+
+#[inline]
+fn sub(values: &mut [i16], weights: &[i16]) {
+    for (values, weights) in values.chunks_exact_mut(LANES).zip(weights.chunks_exact(LANES)) {
+        (Lanes::from_slice(values) - Lanes::from_slice(weights)).copy_to_slice(values);
+    }
+}
+
+/// The dot product of the clipped `values` and the `weights`
+#[inline]
+fn activate(values: &[i16], weights: &[i16]) -> i32 {
+    let (min, max) = (Lanes::splat(0), Lanes::splat(QA as i16));
+    let mut sum = Simd::<i32, LANES>::splat(0);
+
+    for (values, weights) in values.chunks_exact(LANES).zip(weights.chunks_exact(LANES)) {
+        let values = Lanes::from_slice(values).simd_clamp(min, max);
+        sum += values.cast::<i32>() * Lanes::from_slice(weights).cast::<i32>();
+    }
+
+    return sum.reduce_sum();
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::game::moves::MoveGen;
+    use rand::{rngs::SmallRng, Rng, SeedableRng};
+
+    /// Plays random games, checking that the updated accumulators match the ones computed from every piece
+    #[test]
+    fn incremental() {
+        let network = Network::open(TEST_NETWORK).unwrap();
+        let mut rng = SmallRng::seed_from_u64(0);
+
+        for fen in [
+            crate::notations::FEN::START,
+            "r3k2r/pPppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/1PPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        ] {
+            for _ in 0..20 {
+                let mut board = Board::from_fen(fen).unwrap();
+                let mut accumulator = network.accumulator(&board);
+
+                for _ in 0..60 {
+                    let moves = MoveGen::new_legal(&board).collect::<Vec<_>>();
+                    if moves.is_empty() {
+                        break;
+                    }
+
+                    let chess_move = moves[rng.gen_range(0..moves.len())];
+                    board.make_move(chess_move);
+                    let parent = accumulator.clone();
+                    network.update(&mut accumulator, &parent, &board);
+
+                    assert_eq!(accumulator, network.accumulator(&board), "{} after {chess_move}", board.to_fen());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn evaluate() {
+        let network = Network::open(TEST_NETWORK).unwrap();
+        assert_eq!(network.hidden_size(), 16);
+        assert_eq!(Network::from_bytes(&network.to_bytes()).unwrap(), network);
+
+        // Both perspectives see the same position
+        for (fen, mirrored) in [
+            (
+                "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4",
+                "rnbqk2r/pppp1ppp/5n2/2b1p3/4P3/2N2N2/PPPP1PPP/R1BQKB1R b KQkq - 4 4",
+            ),
+            ("8/5k2/3p4/1p1P4/1P6/8/4K3/8 w - - 0 1", "8/4k3/8/1p6/1P1p4/3P4/5K2/8 b - - 0 1"),
+        ] {
+            let board = Board::from_fen(fen).unwrap();
+            assert_eq!(network.evaluate_board(&board), network.evaluate_board(&Board::from_fen(mirrored).unwrap()));
+        }
+
+        // Mostly material
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/3QK3 w - - 0 1").unwrap();
+        assert!(network.evaluate_board(&board) > 600, "{}", network.evaluate_board(&board));
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/3QK3 b - - 0 1").unwrap();
+        assert!(network.evaluate_board(&board) < -600);
+
+        assert_eq!(Network::new(16).unwrap().evaluate_board(&board), 0);
+    }
+
+    #[test]
+    fn errors() {
+        let bytes = Network::new(16).unwrap().to_bytes();
+        assert!(matches!(Network::from_bytes(&bytes[..bytes.len() - 2]), Err(NnueError::Size(_))));
+        assert!(matches!(Network::from_bytes(b"NNUE"), Err(NnueError::Size(4))));
+        assert!(matches!(Network::from_bytes(&[0; 64]), Err(NnueError::Header)));
+        assert!(matches!(Network::new(24), Err(NnueError::HiddenSize(24))));
+        assert!(matches!(Network::open("/nonexistent.nnue"), Err(NnueError::Io(_))));
+
+        let mut bytes = bytes;
+        bytes[12] = 8;
+        assert!(matches!(Network::from_bytes(&bytes), Err(NnueError::HiddenSize(8))));
+    }
+}
@@ -0,0 +1,518 @@
+use std::{
+    f64::consts::LN_10,
+    fmt::Write,
+    io::BufRead,
+    num::NonZeroUsize,
+    thread,
+};
+
+use super::{add_terms, phase, tables::*, Param, Sink, Tapered, Term};
+use crate::{
+    datagen::Record,
+    game::{
+        corpus::{self, CorpusError},
+        Board,
+    },
+    notations::PGN::GameResult,
+    primitives::{piece::Piece, team::Team},
+    search,
+    utils::enums::ArrayEnum,
+};
+
+/// The plies of the corpus games that are left out, being mostly opening theory
+const SKIPPED_PLIES: usize = 8;
+
+/// The names of the piece-square tables in the generated source, by piece
+const TABLE_NAMES: [&str; Piece::SIZE] = ["PAWN", "KNIGHT", "BISHOP", "ROOK", "QUEEN", "KING"];
+
+/// How many times each param is used by the evaluation of a position, from white's point of view
+///
+/// The evaluation is linear in the params, so the evaluation of the position with any weights is the sum of the
+/// weights times their count, plus the terms that are not made of params, tapered by the phase.
+///
+/// ```
+/// use engine::{
+///     eval::{self, tune::Trace, Param},
+///     Board,
+/// };
+///
+/// let board = Board::from_fen("4k3/8/8/8/8/8/8/3QK3 b - - 0 1").unwrap();
+/// let trace = Trace::new(&board);
+/// let weights = Param::all().map(|param| [param.weight().mg as f64, param.weight().eg as f64]).collect::<Vec<_>>();
+///
+/// assert_eq!(trace.count(Param::Material(engine::Piece::Queen)), 1);
+/// assert!((trace.evaluate(&weights) + eval::evaluate(&board) as f64).abs() < 1.0);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Trace {
+    /// The index of each param that is used, with its white count minus its black count
+    coefficients: Vec<(u16, i16)>,
+    /// The terms that are not made of params, white minus black
+    fixed: Tapered,
+    phase: i32,
+}
+
+impl Trace {
+    pub fn new(board: &Board) -> Self {
+        let mut trace = Self {
+            coefficients: Vec::new(),
+            fixed: Tapered::ZERO,
+            phase: phase(board),
+        };
+        add_terms(board, &mut trace);
+
+        trace.coefficients.sort_unstable_by_key(|(index, _)| *index);
+        trace.coefficients.dedup_by(|(index, count), (kept_index, kept_count)| {
+            let duplicate = index == kept_index;
+            if duplicate {
+                *kept_count += *count;
+            }
+            duplicate
+        });
+        trace.coefficients.retain(|(_, count)| *count != 0);
+
+        return trace;
+    }
+
+    /// The white count minus the black count of the `param`
+    pub fn count(&self, param: Param) -> i32 {
+        self.coefficients
+            .iter()
+            .find(|(index, _)| *index as usize == param.index())
+            .map_or(0, |(_, count)| *count as i32)
+    }
+
+    /// The evaluation of the position from white's point of view, with the `[mg, eg]` weights of each param (ordered
+    /// as `Param::all`)
+    pub fn evaluate(&self, weights: &[[f64; 2]]) -> f64 {
+        let mut mg = self.fixed.mg as f64;
+        let mut eg = self.fixed.eg as f64;
+
+        for (index, count) in self.coefficients.iter() {
+            let [weight_mg, weight_eg] = weights[*index as usize];
+            mg += weight_mg * *count as f64;
+            eg += weight_eg * *count as f64;
+        }
+
+        let phase = self.phase as f64 / PHASE_TOTAL as f64;
+
+        return mg * phase + eg * (1.0 - phase);
+    }
+}
+
+impl Sink for Trace {
+    fn add_param(&mut self, _: Term, team: Team, param: Param, count: i32) {
+        let count = match team {
+            Team::White => count,
+            Team::Black => -count,
+        };
+
+        self.coefficients.push((param.index() as u16, count as i16));
+    }
+
+    fn add_score(&mut self, _: Term, team: Team, score: Tapered) {
+        match team {
+            Team::White => self.fixed += score,
+            Team::Black => self.fixed = self.fixed - score,
+        }
+    }
+}
+
+/// Tunes the weights of the evaluation on positions labelled with the result of their game (Texel's method)
+///
+/// The evaluation of each position is turned into an expected result with `1 / (1 + 10^(-k * eval / 400))`, `k`
+/// being fitted to the positions first. The weights are then moved by gradient descent (with Adam) to minimize the
+/// mean squared error between the expected and actual results. The king attacks are not linear and stay as they are.
+///
+/// ```
+/// use engine::{eval::tune::Tuner, notations::PGN::GameResult, Board, Team};
+///
+/// let mut tuner = Tuner::new();
+/// tuner.add_position(&Board::from_fen("4k3/8/8/8/8/8/8/3QK3 w - - 0 1").unwrap(), GameResult::Win(Team::White));
+/// tuner.add_position(&Board::default(), GameResult::Draw);
+///
+/// let error = tuner.error();
+/// tuner.tune(10, |_, _| {});
+/// assert!(tuner.error() < error);
+/// assert!(tuner.source().contains("pub const MATERIAL"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Tuner {
+    /// The trace of each position, with the result of its game for white (1, 0.5 or 0)
+    positions: Vec<(Trace, f64)>,
+    /// The `[mg, eg]` weights of each param, ordered as `Param::all`
+    weights: Vec<[f64; 2]>,
+    k: f64,
+    learning_rate: f64,
+    /// The first and second moments of the gradient, kept by Adam
+    moments: Vec<[[f64; 2]; 2]>,
+    epochs: usize,
+}
+
+impl Tuner {
+    /// A tuner starting from the current weights of the evaluation
+    pub fn new() -> Self {
+        Self {
+            positions: Vec::new(),
+            weights: Param::all()
+                .map(|param| [param.weight().mg as f64, param.weight().eg as f64])
+                .collect(),
+            k: 1.0,
+            learning_rate: 1.0,
+            moments: vec![[[0.0; 2]; 2]; Param::COUNT],
+            epochs: 0,
+        }
+    }
+
+    /// How far (in centipawns) a weight can move at each epoch
+    pub fn with_learning_rate(mut self, learning_rate: f64) -> Self {
+        self.learning_rate = learning_rate;
+        self
+    }
+
+    /// Adds a position labelled with the result of its game, ongoing games being ignored
+    pub fn add_position(&mut self, board: &Board, result: GameResult) {
+        let result = match result {
+            GameResult::Win(Team::White) => 1.0,
+            GameResult::Win(Team::Black) => 0.0,
+            GameResult::Draw => 0.5,
+            GameResult::Ongoing => return,
+        };
+
+        self.positions.push((Trace::new(board), result));
+    }
+
+    /// Adds the positions written by `datagen::Generator`
+    pub fn add_records(&mut self, records: &[Record]) {
+        for record in records.iter() {
+            self.add_position(&record.board, record.result);
+        }
+    }
+
+    /// Adds the quiet positions of a corpus of bare SAN games (see `corpus::GAMES`), returning the number of games
+    /// read
+    ///
+    /// The openings, the positions in check and the ones where a capture or a promotion is played are left out.
+    pub fn add_corpus(&mut self, reader: impl BufRead) -> Result<usize, CorpusError> {
+        let mut count = 0;
+
+        for game in corpus::read_games(reader) {
+            let game = game?;
+            let mut board = Board::default();
+
+            for (ply, chess_move) in game.moves.into_iter().enumerate() {
+                if ply >= SKIPPED_PLIES && board.checkers.is_empty() && search::is_quiet(&board, chess_move) {
+                    self.add_position(&board, game.result);
+                }
+                board.make_move(chess_move);
+            }
+            count += 1;
+        }
+
+        return Ok(count);
+    }
+
+    /// The number of positions
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    /// The scaling constant of the sigmoid, see `fit_k`
+    pub fn k(&self) -> f64 {
+        self.k
+    }
+
+    /// The mean squared error between the results and the expected results of the positions
+    pub fn error(&self) -> f64 {
+        self.error_with(self.k)
+    }
+
+    fn error_with(&self, k: f64) -> f64 {
+        let errors = self.map_positions(|positions| {
+            positions
+                .iter()
+                .map(|(trace, result)| (result - sigmoid(trace.evaluate(&self.weights), k)).powi(2))
+                .sum::<f64>()
+        });
+
+        return errors.into_iter().sum::<f64>() / self.positions.len().max(1) as f64;
+    }
+
+    /// Finds the `k` with the smallest error for the current weights, and keeps it for the tuning
+    pub fn fit_k(&mut self) -> f64 {
+        let (mut low, mut high) = (0.0, 10.0);
+
+        for _ in 0..50 {
+            let left = low + (high - low) / 3.0;
+            let right = high - (high - low) / 3.0;
+
+            if self.error_with(left) < self.error_with(right) {
+                high = right;
+            } else {
+                low = left;
+            }
+        }
+        self.k = (low + high) / 2.0;
+
+        return self.k;
+    }
+
+    /// Runs `epochs` steps of gradient descent on every position, calling `on_epoch` with the number of epochs done
+    /// so far and the error after each one
+    pub fn tune(&mut self, epochs: usize, mut on_epoch: impl FnMut(usize, f64)) {
+        const BETA1: f64 = 0.9;
+        const BETA2: f64 = 0.999;
+        const EPSILON: f64 = 1e-8;
+
+        for _ in 0..epochs {
+            let gradient = self.gradient();
+            self.epochs += 1;
+
+            for ((weights, moments), gradient) in self.weights.iter_mut().zip(self.moments.iter_mut()).zip(gradient) {
+                for phase in 0..2 {
+                    let [first, second] = &mut moments[phase];
+                    *first = BETA1 * *first + (1.0 - BETA1) * gradient[phase];
+                    *second = BETA2 * *second + (1.0 - BETA2) * gradient[phase].powi(2);
+
+                    let first = *first / (1.0 - BETA1.powi(self.epochs as i32));
+                    let second = *second / (1.0 - BETA2.powi(self.epochs as i32));
+                    weights[phase] -= self.learning_rate * first / (second.sqrt() + EPSILON);
+                }
+            }
+
+            on_epoch(self.epochs, self.error());
+        }
+    }
+
+    /// The gradient of the error with respect to the `[mg, eg]` weight of each param
+    fn gradient(&self) -> Vec<[f64; 2]> {
+        let scale = self.k * LN_10 / 400.0;
+        let gradients = self.map_positions(|positions| {
+            let mut gradient = vec![[0.0; 2]; Param::COUNT];
+
+            for (trace, result) in positions.iter() {
+                let expected = sigmoid(trace.evaluate(&self.weights), self.k);
+                let delta = 2.0 * (expected - result) * expected * (1.0 - expected) * scale;
+                let phase = trace.phase as f64 / PHASE_TOTAL as f64;
+
+                for (index, count) in trace.coefficients.iter() {
+                    gradient[*index as usize][0] += delta * *count as f64 * phase;
+                    gradient[*index as usize][1] += delta * *count as f64 * (1.0 - phase);
+                }
+            }
+
+            gradient
+        });
+
+        let count = self.positions.len().max(1) as f64;
+        let mut gradient = vec![[0.0; 2]; Param::COUNT];
+        for partial in gradients {
+            for (total, partial) in gradient.iter_mut().zip(partial) {
+                total[0] += partial[0] / count;
+                total[1] += partial[1] / count;
+            }
+        }
+
+        return gradient;
+    }
+
+    /// Runs `f` on chunks of the positions, one per thread
+    fn map_positions<T: Send>(&self, f: impl Fn(&[(Trace, f64)]) -> T + Sync) -> Vec<T> {
+        let threads = thread::available_parallelism().map_or(1, NonZeroUsize::get);
+        let chunk_size = self.positions.len().div_ceil(threads).max(1);
+
+        return thread::scope(|scope| {
+            let handles = self
+                .positions
+                .chunks(chunk_size)
+                .map(|positions| scope.spawn(|| f(positions)))
+                .collect::<Vec<_>>();
+
+            handles
+                .into_iter()
+                .map(|handle| handle.join().expect("The tuning threads not to panic"))
+                .collect()
+        });
+    }
+
+    /// The tuned weight of the `param`, rounded to the centipawn
+    pub fn weight(&self, param: Param) -> Tapered {
+        let [mg, eg] = self.weights[param.index()];
+
+        return Tapered::new(mg.round() as i32, eg.round() as i32);
+    }
+
+    /// The Rust source of the weights, meant to replace `src/eval/tables.rs`
+    ///
+    /// The weights that are not tuned (the phases, mobility baselines and king attacks) are copied as they are.
+    pub fn source(&self) -> String {
+        let mut source = String::new();
+        self.write_source(&mut source).expect("Writing to a string not to fail");
+
+        return source;
+    }
+
+    fn write_source(&self, f: &mut String) -> std::fmt::Result {
+        let weights = |params: &mut dyn Iterator<Item = Param>| {
+            params
+                .map(|param| self.weight(param))
+                .map(|weight| format!("s({}, {})", weight.mg, weight.eg))
+                .collect::<Vec<_>>()
+        };
+        let by_piece = |param: fn(Piece) -> Param| weights(&mut Piece::ALL.into_iter().map(param)).join(", ");
+        let single = |param: Param| weights(&mut std::iter::once(param)).remove(0);
+
+        writeln!(f, "// The weights of the evaluation, as `(middlegame, endgame)` pairs")?;
+        writeln!(f, "//")?;
+        writeln!(f, "// The piece-square tables are written from white's point of view, as seen on a diagram:")?;
+        writeln!(f, "// the first row is the eighth rank and the last row the first rank.")?;
+        writeln!(f, "//")?;
+        writeln!(f, "// Tuned by `engine-bin tune` on {} positions (k = {:.3}).", self.len(), self.k)?;
+        writeln!(f)?;
+        writeln!(f, "use super::Tapered;")?;
+        writeln!(f)?;
+        writeln!(f, "const fn s(mg: i32, eg: i32) -> Tapered {{")?;
+        writeln!(f, "    Tapered::new(mg, eg)")?;
+        writeln!(f, "}}")?;
+        writeln!(f)?;
+        writeln!(f, "// [Material]")?;
+        writeln!(f, "pub const MATERIAL: [Tapered; 6] = [{}];", by_piece(Param::Material))?;
+        writeln!(f)?;
+        writeln!(f, "/// The phase weight of each piece, the sum for the starting position being `PHASE_TOTAL`")?;
+        writeln!(f, "pub const PHASE_WEIGHTS: [i32; 6] = {PHASE_WEIGHTS:?};")?;
+        writeln!(f, "pub const PHASE_TOTAL: i32 = {PHASE_TOTAL};")?;
+        writeln!(f)?;
+        writeln!(f, "pub const BISHOP_PAIR: Tapered = {};", single(Param::BishopPair))?;
+        writeln!(f)?;
+        writeln!(f, "// [Pawn structure]")?;
+        writeln!(f, "pub const DOUBLED_PAWN: Tapered = {};", single(Param::DoubledPawn))?;
+        writeln!(f, "pub const ISOLATED_PAWN: Tapered = {};", single(Param::IsolatedPawn))?;
+        writeln!(f, "/// By relative rank")?;
+        writeln!(f, "pub const PASSED_PAWN: [Tapered; 8] = [")?;
+        for weight in weights(&mut (0..8).map(Param::PassedPawn)) {
+            writeln!(f, "    {weight},")?;
+        }
+        writeln!(f, "];")?;
+        writeln!(f)?;
+        writeln!(f, "// [Mobility]")?;
+        writeln!(f, "/// Per reachable square, above or below the `MOBILITY_BASELINE` of the piece")?;
+        writeln!(f, "pub const MOBILITY: [Tapered; 6] = [{}];", by_piece(Param::Mobility))?;
+        writeln!(f, "pub const MOBILITY_BASELINE: [i32; 6] = {MOBILITY_BASELINE:?};")?;
+        writeln!(f)?;
+        writeln!(f, "// [King safety]")?;
+        writeln!(f, "/// Per pawn in front of the king")?;
+        writeln!(f, "pub const PAWN_SHIELD: Tapered = {};", single(Param::PawnShield))?;
+        writeln!(f, "/// Per square of the king zone attacked by each piece")?;
+        writeln!(f, "pub const KING_ATTACK_WEIGHTS: [i32; 6] = {KING_ATTACK_WEIGHTS:?};")?;
+        writeln!(f, "/// The percentage of the king attack that counts, by the number of attacking pieces")?;
+        writeln!(f, "pub const KING_ATTACKERS_SCALE: [i32; 8] = {KING_ATTACKERS_SCALE:?};")?;
+        writeln!(f)?;
+        writeln!(f, "// [Piece-square tables]")?;
+        for piece in Piece::ALL {
+            for (phase, suffix) in ["MG", "EG"].into_iter().enumerate() {
+                writeln!(f, "#[rustfmt::skip]")?;
+                writeln!(f, "const {}_{suffix}: [i32; 64] = [", TABLE_NAMES[piece.to_index()])?;
+                for row in 0..8 {
+                    let values = (0..8)
+                        .map(|file| self.weights[Param::PieceSquare(piece, row * 8 + file).index()][phase])
+                        .map(|weight| format!("{:>3},", weight.round() as i32))
+                        .collect::<Vec<_>>();
+                    writeln!(f, "    {}", values.join(" "))?;
+                }
+                writeln!(f, "];")?;
+            }
+        }
+        writeln!(f)?;
+        writeln!(f, "/// The middlegame and endgame tables of each piece")?;
+        writeln!(f, "pub const PIECE_SQUARES: [(&[i32; 64], &[i32; 64]); 6] = [")?;
+        for name in TABLE_NAMES {
+            writeln!(f, "    (&{name}_MG, &{name}_EG),")?;
+        }
+        writeln!(f, "];")
+    }
+}
+
+impl Default for Tuner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The expected result of a position evaluated `eval` centipawns for white
+fn sigmoid(eval: f64, k: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-k * eval / 400.0))
+}
+
+#[cfg(test)]
+mod test {
+    use std::{fs::File, io::BufReader};
+
+    use super::*;
+    use crate::eval;
+
+    /// The first games of the corpus
+    fn corpus(games: usize) -> String {
+        let file = BufReader::new(File::open(corpus::GAMES).unwrap());
+
+        return file.lines().take(games).map(|line| line.unwrap() + "\n").collect();
+    }
+
+    #[test]
+    fn trace() {
+        let tuner = Tuner::new();
+        let mut board = Board::default();
+
+        for game in corpus::read_games(corpus(20).as_bytes()) {
+            for chess_move in game.unwrap().moves {
+                let trace = Trace::new(&board);
+                let score = match board.team_to_move {
+                    Team::White => eval::evaluate(&board),
+                    Team::Black => -eval::evaluate(&board),
+                };
+
+                // Only the rounding of the tapering differs
+                assert!((trace.evaluate(&tuner.weights) - score as f64).abs() < 1.0, "{}", board.to_fen());
+                board.make_move(chess_move);
+            }
+            board = Board::default();
+        }
+    }
+
+    #[test]
+    fn tune() {
+        let mut tuner = Tuner::new().with_learning_rate(2.0);
+        assert_eq!(tuner.add_corpus(corpus(200).as_bytes()).unwrap(), 200);
+        assert!(tuner.len() > 1000);
+
+        let k = tuner.fit_k();
+        assert!(k > 0.0 && k < 10.0);
+        assert!(tuner.error() <= tuner.error_with(k * 2.0));
+
+        let error = tuner.error();
+        let mut errors = Vec::new();
+        tuner.tune(20, |epoch, error| errors.push((epoch, error)));
+        assert_eq!(errors.len(), 20);
+        assert_eq!(errors.last().unwrap().0, 20);
+        assert!(errors.last().unwrap().1 < error);
+
+        // The weights that no position uses are kept
+        assert_eq!(tuner.weight(Param::Material(Piece::King)), Tapered::ZERO);
+        assert_eq!(tuner.weight(Param::PieceSquare(Piece::Pawn, 0)), Param::PieceSquare(Piece::Pawn, 0).weight());
+    }
+
+    #[test]
+    fn source() {
+        let source = Tuner::new().source();
+
+        assert!(source.contains("pub const MATERIAL: [Tapered; 6] = [s(82, 94), s(337, 281), s(365, 297)"));
+        assert!(source.contains("pub const PHASE_WEIGHTS: [i32; 6] = [0, 1, 1, 2, 4, 0];"));
+        assert!(source.contains("pub const KING_ATTACKERS_SCALE: [i32; 8] = [0, 0, 50, 75, 88, 94, 97, 99];"));
+        assert!(source.contains("    (&KNIGHT_MG, &KNIGHT_EG),"));
+        assert!(source.contains("const PAWN_MG: [i32; 64] = [\n      0,   0,   0,   0,   0,   0,   0,   0,\n     50,"));
+        assert!(source.contains("    s(70, 140),\n    s(0, 0),\n];"));
+    }
+}
@@ -1,6 +1,6 @@
 use crate::primitives::team::Team;
 
-use super::moves::Move;
+use super::{moves::Move, DrawReason};
 
 // #[derive(Debug, PartialEq)]
 // pub enum CastlingMove {
@@ -13,14 +13,17 @@ use super::moves::Move;
 //     Ongoingj
 // }
 
-#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, rspc::Type, serde::Serialize)]
 pub enum EndOfGameState {
-    Draw,
+    /// The game was drawn, for the given reason
+    Draw(DrawReason),
     Stalemate,
     /// The `Team` that checkmated the other and thus won
     Checkmate(Team),
     /// The `Team` that resigned and thus lost
     Resignation(Team),
+    /// The `Team` that ran out of time and thus lost
+    Timeout(Team),
 }
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
@@ -30,11 +33,15 @@ pub enum CheckKind {
 }
 
 /// Contains all actions supported within the game
-#[derive(Debug, rspc::Type, serde::Serialize, PartialEq)]
+///
+/// A draw offer is pending until it is accepted by the opponent of the offering team, or that opponent moves.
+/// `DeclareDraw` claims a draw by threefold repetition or the fifty-move rule.
+#[derive(Debug, Clone, Copy, rspc::Type, serde::Serialize, PartialEq, Eq)]
 pub enum Action {
     Move(Move),
     OfferDraw(Team),
-    AcceptDraw,
+    /// The `Team` that accepts the draw offer of its opponent
+    AcceptDraw(Team),
     DeclareDraw,
     Resign(Team),
 
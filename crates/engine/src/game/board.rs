@@ -3,7 +3,7 @@ use std::fmt::Display;
 use crate::bitboard::BitBoard;
 use crate::game::moves::{Move, MoveGen};
 use crate::magic;
-use crate::notations::FEN;
+use crate::notations::{FEN, SAN};
 use crate::primitives::{CastleRights, ChessPiece, File, Piece, Rank, Square, Team};
 use crate::utils::enums::ArrayEnum;
 
@@ -28,14 +28,110 @@ pub struct Board {
     pub checkers: BitBoard,
     hash: u64,
     pub en_passant: Option<Square>,
+
+    /// The number of halfmoves since the last capture or pawn advance, used for the fifty-move rule.
+    pub halfmove_clock: u16,
+    /// The number of the full moves. It starts at 1 and is incremented after Black's move.
+    pub fullmove_clock: u16,
+
+    /// The pieces added and removed by the last move, see `Board::changes`
+    changes: PieceChanges,
+}
+
+/// A piece added to or removed from a square
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PieceChange {
+    pub piece: Piece,
+    pub team: Team,
+    pub square: Square,
+    /// Whether the piece was added to the square, or removed from it
+    pub added: bool,
+}
+
+/// The pieces changed since the last `Board::make_move`, unknown once there are more than a move can change
+#[derive(Debug, Clone, Copy)]
+struct PieceChanges {
+    changes: [PieceChange; PieceChanges::CAPACITY],
+    len: u8,
+    /// Whether some changes could not be kept
+    overflowed: bool,
+}
+
+impl PieceChanges {
+    /// Castling moves two pieces, the most a move can change
+    const CAPACITY: usize = 4;
+
+    /// No change is known, e.g. for a position that was set up piece by piece
+    const UNKNOWN: PieceChanges = PieceChanges {
+        changes: [PieceChange {
+            piece: Piece::Pawn,
+            team: Team::White,
+            square: Square::A1,
+            added: false,
+        }; PieceChanges::CAPACITY],
+        len: 0,
+        overflowed: true,
+    };
+
+    /// No change at all
+    const NONE: PieceChanges = PieceChanges {
+        overflowed: false,
+        ..PieceChanges::UNKNOWN
+    };
+
+    /// Adds a change, cancelling out the opposite change of the same piece on the same square (e.g. the pawn put on the
+    /// promotion square then replaced)
+    #[inline]
+    fn push(&mut self, piece: Piece, square: Square, team: Team, added: bool) {
+        let len = self.len as usize;
+        let opposite = self.changes[..len].iter().position(|change| {
+            change.piece == piece && change.square == square && change.team == team && change.added != added
+        });
+
+        if let Some(index) = opposite {
+            self.changes.copy_within(index + 1..len, index);
+            self.len -= 1;
+        } else if len < Self::CAPACITY {
+            self.changes[len] = PieceChange {
+                piece,
+                team,
+                square,
+                added,
+            };
+            self.len += 1;
+        } else {
+            self.overflowed = true;
+        }
+    }
 }
 
 /// What is the status of this game?
-#[derive(Copy, Clone, PartialEq, PartialOrd, Debug, serde::Serialize, serde::Deserialize, rspc::Type)]
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Debug, serde::Serialize, serde::Deserialize, rspc::Type)]
 pub enum BoardStatus {
     Ongoing,
     Stalemate,
     Checkmate,
+    /// The game ended in a draw because of the given rule
+    Draw(DrawReason),
+}
+
+/// The reasons a game ends in a draw, besides stalemate
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Debug, Hash, serde::Serialize, serde::Deserialize, rspc::Type)]
+pub enum DrawReason {
+    /// Both players agreed to a draw
+    Agreement,
+    /// The same position occurred three times, the draw has to be claimed
+    ThreefoldRepetition,
+    /// The same position occurred five times
+    FivefoldRepetition,
+    /// No capture or pawn move was made in the last fifty moves, the draw has to be claimed
+    FiftyMoveRule,
+    /// No capture or pawn move was made in the last seventy-five moves
+    SeventyFiveMoveRule,
+    /// Neither team has enough material left to checkmate
+    InsufficientMaterial,
+    /// A team ran out of time, but its opponent doesn't have enough material left to checkmate
+    TimeoutVsInsufficientMaterial,
 }
 
 impl Board {
@@ -43,7 +139,7 @@ impl Board {
     // pub const EMPTY_CELL: Option<ChessPiece> = None;
 
     // [Constructor functions]
-    /// Creates a `Board` with the given `cells`
+    /// Creates a `Board` with the given `cells`, White to move and no castle rights
     pub fn new(cells: [Option<ChessPiece>; Board::SIZE]) -> Self {
         let mut board = Self::empty();
 
@@ -53,9 +149,26 @@ impl Board {
             }
         }
 
+        board.refresh_state();
+
         return board;
     }
 
+    /// Parses a FEN string into a validated `Board`.
+    ///
+    /// ```
+    /// use engine::{Board, Square, Team};
+    ///
+    /// let board = Board::from_fen("4k3/8/8/8/8/8/8/4K2R b K - 3 40").unwrap();
+    ///
+    /// assert_eq!(board.team_to_move, Team::Black);
+    /// assert_eq!(board.king_square(Team::White), Square::E1);
+    /// assert_eq!(board.to_fen(), "4k3/8/8/8/8/8/8/4K2R b K - 3 40");
+    /// ```
+    pub fn from_fen(fen: &str) -> Result<Self, FEN::FenError> {
+        FEN::parse(fen)
+    }
+
     /// Creates a `ChessBoard` with all squares being empty
     pub fn empty() -> Self {
         // TODO: make this better
@@ -68,6 +181,9 @@ impl Board {
             checkers: BitBoard::EMPTY,
             team_to_move: Team::White,
             castle_rights: [CastleRights::NoRights; Team::SIZE],
+            halfmove_clock: 0,
+            fullmove_clock: 1,
+            changes: PieceChanges::UNKNOWN,
         }
     }
 
@@ -102,34 +218,39 @@ impl Board {
         self.piece_mask(piece) & self.team_mask(team)
     }
     pub fn set(&mut self, piece: Piece, square: Square, team: Team) -> &mut Self {
-        let bb = BitBoard::from(square);
-
-        self.team_masks[team.to_index()] |= bb;
-        self.piece_masks[piece.to_index()] |= bb;
-
-        return self;
+        self.set_mask(piece, BitBoard::from(square), team)
     }
     pub fn set_mask(&mut self, piece: Piece, mask: BitBoard, team: Team) -> &mut Self {
+        for square in mask & !self.get_mask(piece, team) {
+            self.hash ^= magic::zobrist::piece(piece, square, team);
+            self.changes.push(piece, square, team, true);
+        }
+
         self.team_masks[team.to_index()] |= mask;
         self.piece_masks[piece.to_index()] |= mask;
 
         return self;
     }
     pub fn remove(&mut self, piece: Piece, square: Square, team: Team) -> &mut Self {
-        let bb = !BitBoard::from(square);
-
-        self.team_masks[team.to_index()] &= bb;
-        self.piece_masks[piece.to_index()] &= bb;
-
-        return self;
+        self.remove_mask(piece, BitBoard::from(square), team)
     }
     pub fn remove_mask(&mut self, piece: Piece, mask: BitBoard, team: Team) -> &mut Self {
+        for square in mask & self.get_mask(piece, team) {
+            self.hash ^= magic::zobrist::piece(piece, square, team);
+            self.changes.push(piece, square, team, false);
+        }
+
         self.team_masks[team.to_index()] &= !mask;
         self.piece_masks[piece.to_index()] &= !mask;
 
         return self;
     }
     pub fn remove_any(&mut self, square: Square) -> &mut Self {
+        if let Some(ChessPiece { team, variant }) = self.get(square) {
+            self.hash ^= magic::zobrist::piece(variant, square, team);
+            self.changes.push(variant, square, team, false);
+        }
+
         let bb = !BitBoard::from(square);
 
         self.team_masks
@@ -143,12 +264,17 @@ impl Board {
     }
     /// Add or remove a piece from the bitboards in this struct.
     /// It `xor`s the given `bitboard` with the piece, team and combined masks
+    ///
+    /// NOTE: `bitboard` should only contain a single square
     fn xor(&mut self, piece: Piece, bitboard: BitBoard, team: Team) {
+        let added = (self.get_mask(piece, team) & bitboard).is_empty();
+        self.changes.push(piece, bitboard.to_square(), team, added);
+
         unsafe {
             *self.piece_masks.get_unchecked_mut(piece.to_index()) ^= bitboard;
             *self.team_masks.get_unchecked_mut(team.to_index()) ^= bitboard;
-            // self.hash ^= Zobrist::piece(piece, bitboard.to_square(), team);
         }
+        self.hash ^= magic::zobrist::piece(piece, bitboard.to_square(), team);
     }
     /// Swaps the values of cell indices `a` and `b`.
     /// `a <-> b`
@@ -228,6 +354,244 @@ impl Board {
         self.castle_rights[team.to_index()]
     }
 
+    /// The Zobrist hash of this position.
+    ///
+    /// It is updated incrementally by `make_move` and takes the pieces, team to move,
+    /// castle rights and en passant file (only when the capture is possible) into account.
+    ///
+    /// ```
+    /// use engine::{Board, Move, Square};
+    ///
+    /// let mut board = Board::default();
+    /// let start = board.hash();
+    ///
+    /// board
+    ///     .make_move(Move::new(Square::G1, Square::F3))
+    ///     .make_move(Move::new(Square::G8, Square::F6))
+    ///     .make_move(Move::new(Square::F3, Square::G1))
+    ///     .make_move(Move::new(Square::F6, Square::G8));
+    ///
+    /// assert_eq!(board.hash(), start);
+    /// assert_eq!(board.hash(), board.compute_hash());
+    /// ```
+    #[inline]
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+
+    /// Computes the Zobrist hash of this position from scratch.
+    pub fn compute_hash(&self) -> u64 {
+        let mut hash = 0;
+
+        for team in Team::ALL {
+            for piece in Piece::ALL {
+                for square in self.get_mask(piece, team) {
+                    hash ^= magic::zobrist::piece(piece, square, team);
+                }
+            }
+
+            hash ^= magic::zobrist::castles(self.castle_rights(team), team);
+        }
+
+        if self.team_to_move == Team::Black {
+            hash ^= magic::zobrist::side();
+        }
+
+        return hash ^ self.en_passant_hash();
+    }
+
+    /// The Zobrist key of the en passant square.
+    /// It is only used when a pawn of the team to move could capture it,
+    /// so positions that only differ in an unusable en passant square hash the same.
+    #[inline]
+    fn en_passant_hash(&self) -> u64 {
+        match self.en_passant {
+            Some(square)
+                if !(BitBoard::mask_rank(square.get_rank())
+                    & BitBoard::mask_adjacent_files(square.get_file())
+                    & self.get_mask(Piece::Pawn, self.team_to_move))
+                .is_empty() =>
+            {
+                magic::zobrist::en_passant(square.get_file())
+            }
+            _ => 0,
+        }
+    }
+
+    /// Formats this `Board` as a FEN string.
+    pub fn to_fen(&self) -> String {
+        FEN::format(self)
+    }
+
+    /// Resolves a SAN move (e.g. `Nbd7`, `exd6`, `O-O`, `e8=Q+`) to one of the legal moves of this `Board`.
+    ///
+    /// ```
+    /// use engine::{Board, Move, Square};
+    ///
+    /// let board = Board::default();
+    ///
+    /// assert_eq!(board.parse_san("Nc3").unwrap(), Move::new(Square::B1, Square::C3));
+    /// ```
+    pub fn parse_san(&self, san: &str) -> Result<Move, SAN::SanError> {
+        SAN::parse(self, san)
+    }
+
+    /// Formats a legal `move` of this `Board` in SAN, with minimal disambiguation and a check/mate suffix.
+    ///
+    /// ```
+    /// use engine::{Board, Move, Square};
+    ///
+    /// let board = Board::default();
+    ///
+    /// assert_eq!(board.to_san(Move::new(Square::G1, Square::F3)), "Nf3");
+    /// ```
+    pub fn to_san(&self, m: Move) -> String {
+        SAN::format(self, m)
+    }
+
+    /// Returns a mask of all the `team` pieces that attack the given `square`,
+    /// with `occupied` as the pieces blocking the sliding pieces.
+    pub fn attackers_to(&self, square: Square, team: Team, occupied: BitBoard) -> BitBoard {
+        let rooks = self.piece_mask(Piece::Rook) | self.piece_mask(Piece::Queen);
+        let bishops = self.piece_mask(Piece::Bishop) | self.piece_mask(Piece::Queen);
+
+        let attackers = (magic::rook::get_moves(square, occupied) & rooks)
+            | (magic::bishop::get_moves(square, occupied) & bishops)
+            | (magic::knight::get_moves(square) & self.piece_mask(Piece::Knight))
+            | (magic::king::get_moves(square) & self.piece_mask(Piece::King))
+            | magic::pawn::get_attacks(square, !team, self.piece_mask(Piece::Pawn));
+
+        return attackers & self.team_mask(team) & occupied;
+    }
+
+    /// Static exchange evaluation: the material won (in centipawns) by the team to move when playing `m`,
+    /// assuming both teams keep recapturing on the destination with their least valuable piece while it pays off.
+    ///
+    /// Pins are ignored. `m` must be a legal move.
+    ///
+    /// ```
+    /// use engine::{Board, Move, Square};
+    ///
+    /// // The pawn is defended by the rook: Rxe5 loses the rook for a pawn
+    /// let board = Board::from_fen("1k2r3/8/8/4p3/8/8/8/1K2R3 w - - 0 1").unwrap();
+    /// assert_eq!(board.see(Move::new(Square::E1, Square::E5)), -400);
+    ///
+    /// // Backed up by the queen, the rook wins the pawn as recapturing would lose the black rook
+    /// let board = Board::from_fen("1k2r3/8/8/4p3/8/8/4R3/1K2Q3 w - - 0 1").unwrap();
+    /// assert_eq!(board.see(Move::new(Square::E2, Square::E5)), 100);
+    ///
+    /// // Quiet moves to attacked squares lose the piece
+    /// let board = Board::from_fen("1k6/8/8/4p3/8/8/8/1K1R4 w - - 0 1").unwrap();
+    /// assert_eq!(board.see(Move::new(Square::D1, Square::D4)), -500);
+    /// ```
+    pub fn see(&self, m: Move) -> i32 {
+        use crate::eval::piece_value;
+
+        let Some(mut attacker) = self.piece_on(m.origin) else {
+            return 0;
+        };
+        let mut occupied = self.all_mask() ^ BitBoard::from_square(m.origin);
+
+        let mut gains = [0; 32];
+        gains[0] = match self.piece_on(m.destination) {
+            Some(victim) => piece_value(victim),
+            None if attacker == Piece::Pawn && m.origin.get_file() != m.destination.get_file() => {
+                // En passant, the captured pawn is next to the capturing one
+                occupied ^= BitBoard::from_square(Square::make_square(m.destination.get_file(), m.origin.get_rank()));
+                piece_value(Piece::Pawn)
+            }
+            None => 0,
+        };
+        if let Some(promotion) = m.promotion {
+            gains[0] += piece_value(promotion) - piece_value(Piece::Pawn);
+            attacker = promotion;
+        }
+
+        let mut team = !self.team_to_move;
+        let mut depth = 0;
+        while depth + 1 < gains.len() {
+            let attackers = self.attackers_to(m.destination, team, occupied);
+            let Some(piece) = Piece::ALL.into_iter().find(|piece| !(attackers & self.piece_mask(*piece)).is_empty()) else {
+                break;
+            };
+
+            // The king can't capture a defended piece
+            if piece == Piece::King && !self.attackers_to(m.destination, !team, occupied).is_empty() {
+                break;
+            }
+
+            depth += 1;
+            // The value of the piece captured, if the capturing piece is not recaptured
+            gains[depth] = piece_value(attacker) - gains[depth - 1];
+            attacker = piece;
+            occupied ^= BitBoard::from_square((attackers & self.piece_mask(piece)).to_square());
+            team = !team;
+        }
+
+        // Each team can stop capturing when it is not worth it
+        while depth > 0 {
+            gains[depth - 1] = -(-gains[depth - 1]).max(gains[depth]);
+            depth -= 1;
+        }
+
+        return gains[0];
+    }
+
+    /// The pieces added to and removed from the board by the last `make_move`, in order, to update what depends on
+    /// the placement of the pieces (e.g. the accumulator of `eval::nnue`) without going through every piece
+    ///
+    /// `None` when they are not known, e.g. for a position set up from a FEN string or edited since the last move.
+    ///
+    /// ```
+    /// use engine::{Board, Move, Piece, PieceChange, Square, Team};
+    ///
+    /// let mut board = Board::from_fen("4k3/8/8/8/8/8/8/4K2R w K - 0 1").unwrap();
+    /// assert_eq!(board.changes(), None);
+    ///
+    /// board.make_move(Move::new(Square::E1, Square::G1));
+    /// let changes = board.changes().unwrap();
+    /// assert_eq!(changes.len(), 4);
+    /// assert!(changes.contains(&PieceChange { piece: Piece::Rook, team: Team::White, square: Square::F1, added: true }));
+    /// ```
+    pub fn changes(&self) -> Option<&[PieceChange]> {
+        match self.changes.overflowed {
+            true => None,
+            false => Some(&self.changes.changes[..self.changes.len as usize]),
+        }
+    }
+
+    /// Recomputes the `checkers` and `pinned` masks for the team to move and the hash from scratch.
+    ///
+    /// Call this after editing the public fields of a `Board` directly.
+    pub fn refresh_state(&mut self) {
+        self.hash = self.compute_hash();
+        self.checkers = BitBoard::EMPTY;
+        self.pinned = BitBoard::EMPTY;
+
+        let my_king = self.get_mask(Piece::King, self.team_to_move);
+        if my_king.is_empty() {
+            return;
+        }
+
+        let king_square = my_king.to_square();
+        let combined_mask = self.all_mask();
+
+        self.checkers = self.attackers_to(king_square, !self.team_to_move, combined_mask);
+
+        let sliders = self.team_mask(!self.team_to_move)
+            & ((magic::bishop::get_rays(king_square)
+                & (self.piece_mask(Piece::Bishop) | self.piece_mask(Piece::Queen)))
+                | (magic::rook::get_rays(king_square)
+                    & (self.piece_mask(Piece::Rook) | self.piece_mask(Piece::Queen))));
+
+        for square in sliders {
+            let between = magic::rays::between(square, king_square) & combined_mask;
+            if between.count_bits() == 1 {
+                self.pinned |= between & self.team_mask(self.team_to_move);
+            }
+        }
+    }
+
     // [Game related methods]
 
     /// Make a chess move onto an already allocated `Board`.
@@ -247,8 +611,15 @@ impl Board {
     /// ```
     #[inline]
     pub fn make_move(&mut self, m: Move) -> &mut Self {
-        // *result = *self;
-        self.en_passant = None; // result.remove_ep();
+        // Remove the parts of the hash that are not related to piece placement,
+        // they are added back in once the move has been made
+        self.hash ^= self.en_passant_hash()
+            ^ magic::zobrist::castles(self.castle_rights(Team::White), Team::White)
+            ^ magic::zobrist::castles(self.castle_rights(Team::Black), Team::Black);
+
+        // The previous en passant square is needed to detect en passant captures
+        let en_passant = self.en_passant.take();
+        self.changes = PieceChanges::NONE;
 
         self.checkers = BitBoard::EMPTY;
         self.pinned = BitBoard::EMPTY;
@@ -263,10 +634,21 @@ impl Board {
         let dest_bb = BitBoard::from_square(destination);
         let move_bb = origin_bb ^ dest_bb;
         let moved_piece = self.piece_on(origin).unwrap();
+        let captured = self.get(destination);
+
+        // Update the move clocks
+        if moved_piece == Piece::Pawn || captured.is_some() {
+            self.halfmove_clock = 0;
+        } else {
+            self.halfmove_clock = self.halfmove_clock.saturating_add(1);
+        }
+        if self.team_to_move == Team::Black {
+            self.fullmove_clock = self.fullmove_clock.saturating_add(1);
+        }
 
         // Move the piece to the destination
         self.remove(moved_piece, origin, self.team_to_move);
-        if let Some(captured) = self.get(destination) {
+        if let Some(captured) = captured {
             self.remove(captured.variant, destination, captured.team);
         }
         self.set(moved_piece, destination, self.team_to_move);
@@ -327,7 +709,7 @@ impl Board {
                 self.en_passant = Some(destination);
                 self.checkers ^=
                     magic::pawn::get_attacks(opponent_king_square, !self.team_to_move, dest_bb);
-            } else if Some(destination.ubackward(self.team_to_move)) == self.en_passant {
+            } else if Some(destination.ubackward(self.team_to_move)) == en_passant {
                 self.xor(
                     Piece::Pawn,
                     BitBoard::from_square(destination.ubackward(self.team_to_move)),
@@ -373,6 +755,11 @@ impl Board {
 
         self.team_to_move = !self.team_to_move;
 
+        self.hash ^= magic::zobrist::side()
+            ^ self.en_passant_hash()
+            ^ magic::zobrist::castles(self.castle_rights(Team::White), Team::White)
+            ^ magic::zobrist::castles(self.castle_rights(Team::Black), Team::Black);
+
         return self;
     }
 
@@ -394,45 +781,30 @@ impl Board {
         MoveGen::new_legal(self).collect()
     }
 
-    /// Is this game Ongoing, is it Stalemate, or is it Checkmate?
+    /// Is this game Ongoing, is it Stalemate, Checkmate, or a draw that doesn't need to be claimed?
     ///
-    /// ```ignore
-    /// // TODO: fix this test
-    /// use engine::{Board, BoardStatus, Square, Move};
+    /// The draws that depend on the history of the game are handled by `Chess::status`.
     ///
-    /// let mut board = Board::default();
-    ///
-    /// assert_eq!(board.status(), BoardStatus::Ongoing);
-    ///
-    /// board = board.make_move_new(Move::new(Square::E2,
-    ///                                            Square::E4,
-    ///                                            None));
-    ///
-    /// assert_eq!(board.status(), BoardStatus::Ongoing);
+    /// ```
+    /// use engine::{Board, BoardStatus, DrawReason, Square, Move};
     ///
-    /// board = board.make_move_new(Move::new(Square::F7,
-    ///                                            Square::F6,
-    ///                                            None));
+    /// let mut board = Board::default();
     ///
     /// assert_eq!(board.status(), BoardStatus::Ongoing);
     ///
-    /// board = board.make_move_new(Move::new(Square::D2,
-    ///                                            Square::D4,
-    ///                                            None));
+    /// board.make_move(Move::new(Square::F2, Square::F3));
+    /// board.make_move(Move::new(Square::E7, Square::E5));
+    /// board.make_move(Move::new(Square::G2, Square::G4));
     ///
     /// assert_eq!(board.status(), BoardStatus::Ongoing);
     ///
-    /// board = board.make_move_new(Move::new(Square::G7,
-    ///                                            Square::G5,
-    ///                                            None));
+    /// board.make_move(Move::new(Square::D8, Square::H4));
     ///
-    /// assert_eq!(board.status(), BoardStatus::Ongoing);
+    /// assert_eq!(board.status(), BoardStatus::Checkmate);
     ///
-    /// board = board.make_move_new(Move::new(Square::D1,
-    ///                                            Square::H5,
-    ///                                            None));
+    /// let board = Board::from_fen("8/8/4k3/8/8/2B5/4K3/8 b - - 0 1").unwrap();
     ///
-    /// assert_eq!(board.status(), BoardStatus::Checkmate);
+    /// assert_eq!(board.status(), BoardStatus::Draw(DrawReason::InsufficientMaterial));
     /// ```
     #[inline]
     pub fn status(&self) -> BoardStatus {
@@ -445,14 +817,81 @@ impl Board {
                     BoardStatus::Checkmate
                 }
             }
+            _ if self.is_insufficient_material() => BoardStatus::Draw(DrawReason::InsufficientMaterial),
+            _ if self.halfmove_clock >= 150 => BoardStatus::Draw(DrawReason::SeventyFiveMoveRule),
             _ => BoardStatus::Ongoing,
         }
     }
+
+    /// Whether neither team can checkmate, whatever the moves played.
+    ///
+    /// That is the case with only kings left, with a single knight or bishop left,
+    /// or when all the remaining bishops are on squares of the same color.
+    ///
+    /// ```
+    /// use engine::Board;
+    ///
+    /// assert!(Board::from_fen("8/8/4k3/8/8/8/4K3/8 w - - 0 1").unwrap().is_insufficient_material());
+    /// assert!(Board::from_fen("8/2b5/4k3/8/8/2B5/4K3/8 w - - 0 1").unwrap().is_insufficient_material());
+    /// assert!(!Board::from_fen("8/3b4/4k3/8/8/2B5/4K3/8 w - - 0 1").unwrap().is_insufficient_material());
+    /// assert!(!Board::from_fen("8/8/4k3/8/8/2N5/4K1N1/8 w - - 0 1").unwrap().is_insufficient_material());
+    /// ```
+    pub fn is_insufficient_material(&self) -> bool {
+        let pawns_and_majors = self.piece_mask(Piece::Pawn) | self.piece_mask(Piece::Rook) | self.piece_mask(Piece::Queen);
+        if !pawns_and_majors.is_empty() {
+            return false;
+        }
+
+        let knights = self.piece_mask(Piece::Knight);
+        let bishops = self.piece_mask(Piece::Bishop);
+
+        return (knights | bishops).count_bits() <= 1
+            || (knights.is_empty()
+                && ((bishops & BitBoard::LIGHT_SQUARES).is_empty() || (bishops & BitBoard::DARK_SQUARES).is_empty()));
+    }
+
+    /// Whether `team` can't checkmate anymore, whatever the moves played, as the FIDE rules tell for a timeout:
+    /// it only has its king, or its king and a single knight or bishop against a bare king.
+    ///
+    /// A single knight or bishop can still checkmate when the opponent has other pieces, which can block its king.
+    ///
+    /// ```
+    /// use engine::{Board, Team};
+    ///
+    /// let board = Board::from_fen("4k3/8/8/8/8/8/4N3/4K3 b - - 0 1").unwrap();
+    /// assert!(board.has_insufficient_mating_material(Team::White));
+    /// assert!(board.has_insufficient_mating_material(Team::Black));
+    ///
+    /// let board = Board::from_fen("4k3/4p3/8/8/8/8/4N3/4K3 b - - 0 1").unwrap();
+    /// assert!(!board.has_insufficient_mating_material(Team::White));
+    /// assert!(!board.has_insufficient_mating_material(Team::Black));
+    ///
+    /// assert!(Board::from_fen("4k3/4p3/8/8/8/8/8/4K3 b - - 0 1").unwrap().has_insufficient_mating_material(Team::White));
+    /// assert!(!Board::default().has_insufficient_mating_material(Team::Black));
+    /// ```
+    pub fn has_insufficient_mating_material(&self, team: Team) -> bool {
+        let kings = self.piece_mask(Piece::King);
+        let pieces = self.team_mask(team) & !kings;
+        let opponent_pieces = self.team_mask(!team) & !kings;
+        let pawns_and_majors = self.piece_mask(Piece::Pawn) | self.piece_mask(Piece::Rook) | self.piece_mask(Piece::Queen);
+
+        return self.is_insufficient_material()
+            || pieces.is_empty()
+            || (opponent_pieces.is_empty() && (pieces & pawns_and_majors).is_empty() && pieces.count_bits() == 1);
+    }
 }
 
 impl Default for Board {
     fn default() -> Self {
-        Self::new(FEN::board_from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR").0)
+        Self::from_fen(FEN::START).expect("The starting position to be a valid FEN string")
+    }
+}
+
+impl std::str::FromStr for Board {
+    type Err = FEN::FenError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_fen(s)
     }
 }
 
@@ -501,3 +940,57 @@ impl Display for Board {
         return Ok(());
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn incremental_hash() {
+        [
+            FEN::START,
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+            "8/P1k5/K7/8/8/8/8/8 w - - 0 1",
+        ]
+        .into_iter()
+        .for_each(|fen| {
+            let mut board = Board::from_fen(fen).unwrap();
+            let mut seed = 0x2545F4914F6CDD1Du64;
+
+            for _ in 0..200 {
+                let moves = board.gen_moves();
+                if moves.is_empty() {
+                    break;
+                }
+
+                seed ^= seed << 13;
+                seed ^= seed >> 7;
+                seed ^= seed << 17;
+                board.make_move(moves[(seed % moves.len() as u64) as usize]);
+
+                assert_eq!(board.hash(), board.compute_hash(), "{}", board.to_fen());
+            }
+        });
+    }
+
+    #[test]
+    fn hash_includes_state() {
+        let hash = |fen: &str| Board::from_fen(fen).unwrap().hash();
+
+        let start = hash(FEN::START);
+        assert_ne!(start, hash("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR b KQkq - 0 1"));
+        assert_ne!(start, hash("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w Qkq - 0 1"));
+        assert_eq!(start, hash("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 5 9"));
+
+        // The en passant square only matters when it can be captured
+        assert_eq!(
+            hash("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1"),
+            hash("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1"),
+        );
+        assert_ne!(
+            hash("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3"),
+            hash("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq - 0 3"),
+        );
+    }
+}
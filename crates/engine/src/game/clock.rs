@@ -0,0 +1,581 @@
+use std::{
+    fmt::Display,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::{
+    game::{actions::EndOfGameState, Board, DrawReason},
+    primitives::team::Team,
+    utils::enums::ArrayEnum,
+};
+
+/// A period of a `TimeControl`, all times are in milliseconds (`u32` as rspc doesn't export 64 bit integers)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, rspc::Type, serde::Serialize, serde::Deserialize)]
+pub struct Period {
+    /// The number of moves to play within the period, `None` for the rest of the game
+    pub moves: Option<u32>,
+    /// The time added to the clock when the period starts
+    pub time: u32,
+    /// Fischer increment: the time added to the clock after every move
+    pub increment: u32,
+    /// Bronstein delay: the time used for a move is given back after it, up to this delay
+    pub delay: u32,
+}
+
+impl Period {
+    /// A period that lasts for the rest of the game, without increment or delay
+    pub fn new(time: Duration) -> Self {
+        Self {
+            moves: None,
+            time: millis(time),
+            increment: 0,
+            delay: 0,
+        }
+    }
+
+    /// Limits the period to the given number of moves
+    pub fn moves(mut self, moves: u32) -> Self {
+        self.moves = Some(moves);
+        self
+    }
+
+    /// Sets the Fischer increment of the period
+    pub fn increment(mut self, increment: Duration) -> Self {
+        self.increment = millis(increment);
+        self
+    }
+
+    /// Sets the Bronstein delay of the period
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay = millis(delay);
+        self
+    }
+}
+
+/// The milliseconds of the `duration`, saturating at the largest time a `Period` can store (about 49 days)
+fn millis(duration: Duration) -> u32 {
+    u32::try_from(duration.as_millis()).unwrap_or(u32::MAX)
+}
+
+/// The time each team gets to play its moves, as a list of periods.
+///
+/// When the moves of the last period are played, the last period starts again.
+#[derive(Debug, Clone, PartialEq, Eq, rspc::Type, serde::Serialize, serde::Deserialize)]
+pub struct TimeControl {
+    pub periods: Vec<Period>,
+}
+
+impl TimeControl {
+    /// Creates a time control from its periods
+    ///
+    /// Panics if there are no periods.
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use engine::game::clock::{Period, TimeControl};
+    ///
+    /// let minutes = |minutes: u64| Duration::from_secs(60 * minutes);
+    ///
+    /// // 40 moves in 90 minutes, then 30 minutes for the rest of the game, with a 30 seconds increment
+    /// let classical = TimeControl::new(vec![
+    ///     Period::new(minutes(90)).moves(40).increment(Duration::from_secs(30)),
+    ///     Period::new(minutes(30)).increment(Duration::from_secs(30)),
+    /// ]);
+    ///
+    /// assert_eq!(classical.to_string(), "40/5400+30:1800+30");
+    /// assert_eq!(TimeControl::fischer(minutes(3), Duration::from_secs(2)).to_string(), "180+2");
+    /// ```
+    pub fn new(periods: Vec<Period>) -> Self {
+        assert!(!periods.is_empty(), "A time control needs at least one period");
+
+        Self { periods }
+    }
+
+    /// The whole game has to be played within `time`
+    pub fn sudden_death(time: Duration) -> Self {
+        Self::new(vec![Period::new(time)])
+    }
+
+    /// The whole game has to be played within `time`, with `increment` added after every move
+    pub fn fischer(time: Duration, increment: Duration) -> Self {
+        Self::new(vec![Period::new(time).increment(increment)])
+    }
+
+    /// The whole game has to be played within `time`, the time used for a move is given back after it up to `delay`
+    pub fn bronstein(time: Duration, delay: Duration) -> Self {
+        Self::new(vec![Period::new(time).delay(delay)])
+    }
+
+    /// Gets the period with the given `index`, repeating the last one
+    pub fn period(&self, index: usize) -> &Period {
+        &self.periods[index.min(self.periods.len() - 1)]
+    }
+}
+
+/// Formats the time control like the PGN `TimeControl` tag, the delay being written with a `d` prefix.
+impl Display for TimeControl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (index, period) in self.periods.iter().enumerate() {
+            if index > 0 {
+                write!(f, ":")?;
+            }
+            if let Some(moves) = period.moves {
+                write!(f, "{moves}/")?;
+            }
+            write!(f, "{}", period.time as f64 / 1000.0)?;
+            if period.increment > 0 {
+                write!(f, "+{}", period.increment as f64 / 1000.0)?;
+            }
+            if period.delay > 0 {
+                write!(f, "d{}", period.delay as f64 / 1000.0)?;
+            }
+        }
+
+        return Ok(());
+    }
+}
+
+/// A time control that could not be parsed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimeControlError(pub String);
+
+impl Display for TimeControlError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, r#"Invalid time control "{}": expected periods like "40/5400+30:1800+30" or "180+2""#, self.0)
+    }
+}
+
+impl std::error::Error for TimeControlError {}
+
+/// Parses the format of the PGN `TimeControl` tag written by `Display`, times being in seconds
+///
+/// ```
+/// use std::time::Duration;
+/// use engine::game::clock::TimeControl;
+///
+/// let blitz = "180+2".parse::<TimeControl>().unwrap();
+/// assert_eq!(blitz, TimeControl::fischer(Duration::from_secs(180), Duration::from_secs(2)));
+///
+/// assert_eq!("40/5400+30:1800+30".parse::<TimeControl>().unwrap().to_string(), "40/5400+30:1800+30");
+/// assert_eq!("10+0.1".parse::<TimeControl>().unwrap().periods[0].increment, 100);
+/// assert!("10+".parse::<TimeControl>().is_err());
+/// assert!("1e20".parse::<TimeControl>().is_err());
+/// assert!("5000000".parse::<TimeControl>().is_err());
+/// ```
+impl FromStr for TimeControl {
+    type Err = TimeControlError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let periods = s
+            .split(':')
+            .map(|period| parse_period(period.trim()).ok_or_else(|| TimeControlError(s.to_owned())))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        return Ok(Self::new(periods));
+    }
+}
+
+/// Parses a period like `40/5400+30d5`
+fn parse_period(period: &str) -> Option<Period> {
+    // Negative, infinite or NaN times are rejected, as well as the ones a `Period` can't store
+    let seconds = |seconds: &str| {
+        let seconds = Duration::try_from_secs_f64(seconds.parse::<f64>().ok()?).ok()?;
+        (seconds.as_millis() <= u32::MAX.into()).then_some(seconds)
+    };
+
+    let (moves, period) = match period.split_once('/') {
+        Some((moves, period)) => (Some(moves.parse::<u32>().ok().filter(|moves| *moves > 0)?), period),
+        None => (None, period),
+    };
+    let (period, delay) = match period.split_once('d') {
+        Some((period, delay)) => (period, seconds(delay)?),
+        None => (period, Duration::ZERO),
+    };
+    let (time, increment) = match period.split_once('+') {
+        Some((time, increment)) => (seconds(time)?, seconds(increment)?),
+        None => (seconds(period)?, Duration::ZERO),
+    };
+
+    let mut period = Period::new(time).increment(increment).delay(delay);
+    period.moves = moves;
+
+    return Some(period);
+}
+
+// [Time sources]
+
+/// A monotonic source of time for the `Clock`
+pub trait TimeSource {
+    /// The time elapsed since an arbitrary (but fixed) point, it must never go backwards
+    fn now(&self) -> Duration;
+}
+
+/// The system monotonic time
+#[derive(Debug, Clone, Copy)]
+pub struct MonotonicTime(Instant);
+
+impl Default for MonotonicTime {
+    fn default() -> Self {
+        Self(Instant::now())
+    }
+}
+
+impl TimeSource for MonotonicTime {
+    fn now(&self) -> Duration {
+        self.0.elapsed()
+    }
+}
+
+/// A time source that only moves when it is told to, its clones share the same time
+///
+/// ```
+/// use std::time::Duration;
+/// use engine::game::clock::{ManualTime, TimeSource};
+///
+/// let time = ManualTime::default();
+/// let clone = time.clone();
+///
+/// time.advance(Duration::from_millis(1500));
+///
+/// assert_eq!(clone.now(), Duration::from_millis(1500));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ManualTime(Arc<AtomicU64>);
+
+impl ManualTime {
+    pub fn advance(&self, duration: Duration) {
+        self.0.fetch_add(duration.as_nanos() as u64, Ordering::SeqCst);
+    }
+}
+
+impl TimeSource for ManualTime {
+    fn now(&self) -> Duration {
+        Duration::from_nanos(self.0.load(Ordering::SeqCst))
+    }
+}
+
+// [Clock]
+
+/// Everything that can go wrong while using a `Clock`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClockError {
+    /// The clock was pressed while no team's time was running
+    NotRunning,
+    /// The `Team` ran out of time before pressing the clock
+    Flagged(Team),
+}
+
+impl Display for ClockError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotRunning => write!(f, "The clock is not running"),
+            Self::Flagged(team) => write!(f, "{team:?} ran out of time"),
+        }
+    }
+}
+
+impl std::error::Error for ClockError {}
+
+/// A snapshot of a `Clock`, with the remaining times in milliseconds
+#[derive(Debug, Clone, PartialEq, Eq, rspc::Type, serde::Serialize)]
+pub struct ClockState {
+    pub white: u32,
+    pub black: u32,
+    /// The team whose time is running
+    pub running: Option<Team>,
+    /// The team that ran out of time
+    pub flagged: Option<Team>,
+    #[serde(rename = "timeControl")]
+    pub time_control: TimeControl,
+}
+
+/// A chess clock, pressed after every move
+///
+/// ```
+/// use std::time::Duration;
+/// use engine::{game::clock::{Clock, ManualTime, TimeControl}, Team};
+///
+/// let time = ManualTime::default();
+/// let mut clock = Clock::with_time_source(TimeControl::fischer(Duration::from_secs(60), Duration::from_secs(1)), time.clone());
+///
+/// clock.start(Team::White);
+/// time.advance(Duration::from_secs(5));
+/// clock.press().unwrap();
+///
+/// assert_eq!(clock.remaining(Team::White), Duration::from_secs(56));
+/// assert_eq!(clock.running(), Some(Team::Black));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Clock<T: TimeSource = MonotonicTime> {
+    time_control: TimeControl,
+    time_source: T,
+    /// The remaining time of each team, not counting the current turn
+    remaining: [Duration; Team::SIZE],
+    /// The current period of each team and the number of moves played in it
+    periods: [(usize, u32); Team::SIZE],
+    /// The team whose time is running, and when its turn started
+    running: Option<(Team, Duration)>,
+    flagged: Option<Team>,
+}
+
+impl Clock {
+    /// Creates a stopped clock using the system monotonic time
+    pub fn new(time_control: TimeControl) -> Self {
+        Self::with_time_source(time_control, MonotonicTime::default())
+    }
+}
+
+impl<T: TimeSource> Clock<T> {
+    /// Creates a stopped clock using the given source of time
+    pub fn with_time_source(time_control: TimeControl, time_source: T) -> Self {
+        let time = Duration::from_millis(time_control.period(0).time.into());
+
+        Self {
+            time_control,
+            time_source,
+            remaining: [time; Team::SIZE],
+            periods: [(0, 0); Team::SIZE],
+            running: None,
+            flagged: None,
+        }
+    }
+
+    pub fn time_control(&self) -> &TimeControl {
+        &self.time_control
+    }
+
+    /// Starts (or resumes) the time of `team`, stopping the other one
+    pub fn start(&mut self, team: Team) {
+        self.stop();
+        self.running = Some((team, self.time_source.now()));
+    }
+
+    /// Stops the running time, without any increment or delay
+    pub fn stop(&mut self) {
+        if let Some((team, started)) = self.running.take() {
+            let elapsed = self.time_source.now().saturating_sub(started);
+            self.remaining[team.to_index()] = self.remaining[team.to_index()].saturating_sub(elapsed);
+        }
+    }
+
+    /// Ends the turn of the running team after it moved, starting the time of its opponent
+    pub fn press(&mut self) -> Result<(), ClockError> {
+        let Some((team, started)) = self.running else {
+            return Err(ClockError::NotRunning);
+        };
+
+        if let Some(team) = self.flagged() {
+            self.stop();
+            self.flagged = Some(team);
+            return Err(ClockError::Flagged(team));
+        }
+
+        let now = self.time_source.now();
+        let elapsed = now.saturating_sub(started);
+        let (period_index, moves) = &mut self.periods[team.to_index()];
+        let period = self.time_control.period(*period_index);
+        let remaining = &mut self.remaining[team.to_index()];
+
+        *remaining = remaining.saturating_sub(elapsed)
+            + elapsed.min(Duration::from_millis(period.delay.into()))
+            + Duration::from_millis(period.increment.into());
+
+        *moves += 1;
+        if period.moves == Some(*moves) {
+            *period_index += 1;
+            *moves = 0;
+            *remaining += Duration::from_millis(self.time_control.period(*period_index).time.into());
+        }
+
+        self.running = Some((!team, now));
+
+        return Ok(());
+    }
+
+    /// The period `team` is playing in
+    pub fn period(&self, team: Team) -> &Period {
+        self.time_control.period(self.periods[team.to_index()].0)
+    }
+
+    /// The number of moves `team` has to play before its next period, `None` if its period lasts for the rest of the
+    /// game
+    pub fn moves_to_go(&self, team: Team) -> Option<u32> {
+        let (period, moves) = self.periods[team.to_index()];
+
+        return self.time_control.period(period).moves.map(|total| total - moves);
+    }
+
+    /// The remaining time of `team`, including the current turn
+    pub fn remaining(&self, team: Team) -> Duration {
+        let remaining = self.remaining[team.to_index()];
+
+        match self.running {
+            Some((running, started)) if running == team => {
+                remaining.saturating_sub(self.time_source.now().saturating_sub(started))
+            }
+            _ => remaining,
+        }
+    }
+
+    /// The team whose time is running
+    pub fn running(&self) -> Option<Team> {
+        self.running.map(|(team, _)| team)
+    }
+
+    /// The team that ran out of time, if any
+    pub fn flagged(&self) -> Option<Team> {
+        self.flagged.or_else(|| {
+            self.running()
+                .filter(|team| self.remaining(*team).is_zero())
+        })
+    }
+
+    /// The result of the game if a team ran out of time.
+    ///
+    /// Running out of time only loses if the opponent could still checkmate (see `Board::has_insufficient_mating_material`),
+    /// otherwise it is a draw.
+    pub fn timeout(&self, board: &Board) -> Option<EndOfGameState> {
+        let team = self.flagged()?;
+
+        if board.has_insufficient_mating_material(!team) {
+            return Some(EndOfGameState::Draw(DrawReason::TimeoutVsInsufficientMaterial));
+        }
+
+        return Some(EndOfGameState::Timeout(team));
+    }
+
+    /// A serializable snapshot of the clock
+    pub fn state(&self) -> ClockState {
+        ClockState {
+            white: millis(self.remaining(Team::White)),
+            black: millis(self.remaining(Team::Black)),
+            running: self.running(),
+            flagged: self.flagged(),
+            time_control: self.time_control.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn secs(secs: u64) -> Duration {
+        Duration::from_secs(secs)
+    }
+
+    /// Plays a move of `seconds` for the running team
+    fn play(clock: &mut Clock<ManualTime>, time: &ManualTime, seconds: u64) -> Result<(), ClockError> {
+        time.advance(secs(seconds));
+        clock.press()
+    }
+
+    #[test]
+    fn sudden_death() {
+        let time = ManualTime::default();
+        let mut clock = Clock::with_time_source(TimeControl::sudden_death(secs(10)), time.clone());
+
+        assert_eq!(clock.press(), Err(ClockError::NotRunning));
+
+        clock.start(Team::White);
+        play(&mut clock, &time, 4).unwrap();
+        play(&mut clock, &time, 9).unwrap();
+        assert_eq!(clock.remaining(Team::White), secs(6));
+        assert_eq!(clock.remaining(Team::Black), secs(1));
+
+        // Stopping doesn't count the time
+        clock.stop();
+        time.advance(secs(60));
+        assert_eq!(clock.remaining(Team::White), secs(6));
+        assert_eq!(clock.flagged(), None);
+
+        clock.start(Team::White);
+        time.advance(secs(6));
+        assert_eq!(clock.flagged(), Some(Team::White));
+        assert_eq!(clock.press(), Err(ClockError::Flagged(Team::White)));
+        assert_eq!(clock.state().flagged, Some(Team::White));
+        assert_eq!(clock.state().running, None);
+    }
+
+    #[test]
+    fn bronstein_delay() {
+        let time = ManualTime::default();
+        let mut clock = Clock::with_time_source(TimeControl::bronstein(secs(60), secs(5)), time.clone());
+
+        clock.start(Team::White);
+        play(&mut clock, &time, 3).unwrap();
+        assert_eq!(clock.remaining(Team::White), secs(60));
+
+        play(&mut clock, &time, 8).unwrap();
+        assert_eq!(clock.remaining(Team::Black), secs(57));
+    }
+
+    #[test]
+    fn periods() {
+        let time = ManualTime::default();
+        let control = TimeControl::new(vec![
+            Period::new(secs(100)).moves(2),
+            Period::new(secs(50)).moves(1).increment(secs(10)),
+        ]);
+        let mut clock = Clock::with_time_source(control, time.clone());
+
+        clock.start(Team::White);
+        // White: 100 - 10, then 90 - 20 + 50
+        play(&mut clock, &time, 10).unwrap();
+        play(&mut clock, &time, 1).unwrap();
+        play(&mut clock, &time, 20).unwrap();
+        assert_eq!(clock.remaining(Team::White), secs(120));
+        assert_eq!(clock.remaining(Team::Black), secs(99));
+        assert_eq!(clock.moves_to_go(Team::White), Some(1));
+        assert_eq!(clock.moves_to_go(Team::Black), Some(1));
+        assert_eq!(clock.period(Team::White).increment, 10_000);
+        assert_eq!(clock.period(Team::Black).increment, 0);
+
+        // The last period repeats, with its increment
+        play(&mut clock, &time, 1).unwrap();
+        play(&mut clock, &time, 30).unwrap();
+        assert_eq!(clock.remaining(Team::White), secs(120 - 30 + 10 + 50));
+
+        let state = clock.state();
+        assert_eq!((state.white, state.black), (150_000, 148_000));
+        assert_eq!(state.running, Some(Team::Black));
+        assert_eq!(state.time_control.to_string(), "2/100:1/50+10");
+    }
+
+    #[test]
+    fn saturation() {
+        let period = Period::new(secs(5_000_000)).increment(secs(5_000_000));
+        assert_eq!((period.time, period.increment), (u32::MAX, u32::MAX));
+
+        let mut clock = Clock::with_time_source(TimeControl::new(vec![period]), ManualTime::default());
+        clock.start(Team::White);
+        clock.press().unwrap();
+        assert_eq!(clock.state().white, u32::MAX);
+    }
+
+    #[test]
+    fn timeout() {
+        let time = ManualTime::default();
+        let mut clock = Clock::with_time_source(TimeControl::sudden_death(secs(1)), time.clone());
+        clock.start(Team::Black);
+        time.advance(secs(2));
+
+        let board = Board::from_fen("4k3/8/8/8/8/8/4P3/4K3 b - - 0 1").unwrap();
+        assert_eq!(clock.timeout(&board), Some(EndOfGameState::Timeout(Team::Black)));
+
+        let board = Board::from_fen("4k3/8/8/8/8/8/4N3/4K3 b - - 0 1").unwrap();
+        assert_eq!(
+            clock.timeout(&board),
+            Some(EndOfGameState::Draw(DrawReason::TimeoutVsInsufficientMaterial))
+        );
+
+        // A lone knight can mate when the flagged team has a pawn to block its king
+        let board = Board::from_fen("4k3/4p3/8/8/8/8/4N3/4K3 b - - 0 1").unwrap();
+        assert_eq!(clock.timeout(&board), Some(EndOfGameState::Timeout(Team::Black)));
+    }
+}
@@ -0,0 +1,271 @@
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    io::{self, BufRead},
+};
+
+use crate::{
+    game::{Board, Move},
+    notations::{
+        PGN::GameResult,
+        SAN::{self, SanError},
+    },
+    primitives::team::Team,
+};
+
+/// The corpus of games shipped with the crate: one game per line, in bare SAN, ending with its result
+pub const GAMES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/data/SAN/games.txt");
+
+/// A game of the corpus that was replayed successfully
+#[derive(Debug, Clone)]
+pub struct CorpusGame {
+    /// The (1 based) line of the game in the corpus
+    pub line: usize,
+    pub moves: Vec<Move>,
+    /// The SAN of the first move, as written in the corpus
+    pub first_move: Option<String>,
+    pub result: GameResult,
+    pub final_position: Board,
+}
+
+/// Everything that can go wrong while replaying the corpus
+#[derive(Debug)]
+pub enum CorpusError {
+    /// The corpus could not be read
+    Io(io::Error),
+    /// The first move of a game that could not be parsed or played
+    Move {
+        /// The (1 based) line of the game in the corpus
+        line: usize,
+        /// The (1 based) ply of the move within the game
+        ply: usize,
+        /// The move as written in the corpus
+        san: String,
+        /// The FEN of the position the move was played in
+        fen: String,
+        error: SanError,
+    },
+}
+
+impl Display for CorpusError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "Could not read the corpus: {error}"),
+            Self::Move {
+                line,
+                ply,
+                san,
+                fen,
+                error,
+            } => write!(f, r#"Line {line}, ply {ply}: {error} (move "{san}" in "{fen}")"#),
+        }
+    }
+}
+
+impl std::error::Error for CorpusError {}
+
+impl From<io::Error> for CorpusError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+/// Replays a single game written in bare SAN (e.g. `e4 e5 Nf3 Nc6 1-0`) from the starting position.
+///
+/// Everything after the result is ignored. Games without a result are `GameResult::Ongoing`.
+///
+/// ```
+/// use engine::{game::corpus, notations::PGN::GameResult, Team};
+///
+/// let game = corpus::replay_game("f3 e5 g4 Qh4# 0-1", 1).unwrap();
+///
+/// assert_eq!(game.moves.len(), 4);
+/// assert_eq!(game.result, GameResult::Win(Team::Black));
+/// assert!(corpus::replay_game("e4 e5 Ke3", 1).is_err());
+/// ```
+pub fn replay_game(game: &str, line: usize) -> Result<CorpusGame, CorpusError> {
+    let mut board = Board::default();
+    let mut moves = Vec::new();
+    let mut result = GameResult::Ongoing;
+
+    for san in game.split_whitespace() {
+        if let Some(marker) = GameResult::from_marker(san) {
+            result = marker;
+            break;
+        }
+
+        let chess_move = SAN::parse(&board, san).map_err(|error| CorpusError::Move {
+            line,
+            ply: moves.len() + 1,
+            san: san.to_owned(),
+            fen: board.to_fen(),
+            error,
+        })?;
+
+        board.make_move(chess_move);
+        moves.push(chess_move);
+    }
+
+    return Ok(CorpusGame {
+        line,
+        moves,
+        first_move: game
+            .split_whitespace()
+            .next()
+            .filter(|san| GameResult::from_marker(san).is_none())
+            .map(str::to_owned),
+        result,
+        final_position: board,
+    });
+}
+
+/// Stream-parses a corpus, replaying every (non empty) line as a game.
+///
+/// ```
+/// use engine::game::corpus;
+///
+/// let games = corpus::read_games("e4 e5 1-0\n\nd4 d4 0-1\n".as_bytes()).collect::<Vec<_>>();
+///
+/// assert_eq!(games.len(), 2);
+/// assert!(games[0].is_ok());
+/// assert!(games[1].is_err());
+/// ```
+pub fn read_games(reader: impl BufRead) -> impl Iterator<Item = Result<CorpusGame, CorpusError>> {
+    reader
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !matches!(line, Ok(line) if line.trim().is_empty()))
+        .map(|(index, line)| replay_game(&line?, index + 1))
+}
+
+/// Aggregated statistics of a corpus replay
+#[derive(Debug, Default)]
+pub struct CorpusStats {
+    /// The number of games that were replayed successfully
+    pub games: usize,
+    /// The total number of plies of the replayed games
+    pub plies: usize,
+    pub results: HashMap<GameResult, usize>,
+    /// The number of games started by each first move
+    pub first_moves: HashMap<String, usize>,
+    /// The first bad move of every game that could not be replayed
+    pub errors: Vec<CorpusError>,
+}
+
+impl CorpusStats {
+    /// Adds a replayed game to the statistics
+    pub fn add(&mut self, game: &CorpusGame) {
+        self.games += 1;
+        self.plies += game.moves.len();
+        *self.results.entry(game.result).or_default() += 1;
+
+        if let Some(first_move) = &game.first_move {
+            *self.first_moves.entry(first_move.clone()).or_default() += 1;
+        }
+    }
+
+    /// The average number of plies of the replayed games
+    pub fn average_length(&self) -> f64 {
+        self.plies as f64 / self.games.max(1) as f64
+    }
+
+    /// The `count` most played first moves, most played first
+    pub fn top_first_moves(&self, count: usize) -> Vec<(&str, usize)> {
+        let mut first_moves = self
+            .first_moves
+            .iter()
+            .map(|(san, games)| (san.as_str(), *games))
+            .collect::<Vec<_>>();
+        first_moves.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        first_moves.truncate(count);
+
+        return first_moves;
+    }
+}
+
+impl Display for CorpusStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let percentage = |count: usize| 100.0 * count as f64 / self.games.max(1) as f64;
+
+        writeln!(f, "Games: {} replayed, {} failed", self.games, self.errors.len())?;
+        writeln!(f, "Plies: {} ({:.1} per game)", self.plies, self.average_length())?;
+
+        writeln!(f, "Results:")?;
+        for (label, result) in [
+            ("White wins", GameResult::Win(Team::White)),
+            ("Black wins", GameResult::Win(Team::Black)),
+            ("Draws", GameResult::Draw),
+            ("Unknown", GameResult::Ongoing),
+        ] {
+            let count = self.results.get(&result).copied().unwrap_or_default();
+            writeln!(f, "    {label:<12}{count:>8} ({:.1}%)", percentage(count))?;
+        }
+
+        writeln!(f, "First moves:")?;
+        for (san, count) in self.top_first_moves(8) {
+            writeln!(f, "    {san:<12}{count:>8} ({:.1}%)", percentage(count))?;
+        }
+
+        if !self.errors.is_empty() {
+            writeln!(f, "Errors:")?;
+            for error in self.errors.iter() {
+                writeln!(f, "    {error}")?;
+            }
+        }
+
+        return Ok(());
+    }
+}
+
+/// Replays every game of the corpus and collects the statistics, only failing if the corpus can't be read.
+pub fn replay(reader: impl BufRead) -> io::Result<CorpusStats> {
+    let mut stats = CorpusStats::default();
+
+    for game in read_games(reader) {
+        match game {
+            Ok(game) => stats.add(&game),
+            Err(CorpusError::Io(error)) => return Err(error),
+            Err(error) => stats.errors.push(error),
+        }
+    }
+
+    return Ok(stats);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn replay_corpus() {
+        let file = std::fs::File::open(GAMES).unwrap();
+        let stats = replay(io::BufReader::new(file)).unwrap();
+
+        assert!(stats.errors.is_empty(), "{stats}");
+        assert_eq!(stats.games, 7756);
+        assert_eq!(stats.results.values().sum::<usize>(), stats.games);
+        assert_eq!(stats.first_moves.values().sum::<usize>(), stats.games);
+    }
+
+    #[test]
+    fn first_bad_move() {
+        let stats = replay("e4 e5 Nf3 Nc6 1-0\nd4 d5 c4 dxc4 e4 Bxe4 1/2-1/2\n\ne4 e5 Ke2 Ke7 Kf3 0-1 Trailing".as_bytes()).unwrap();
+
+        assert_eq!(stats.games, 2);
+        assert_eq!(stats.plies, 9);
+        assert_eq!(stats.average_length(), 4.5);
+        assert_eq!(stats.top_first_moves(1), vec![("e4", 2)]);
+        assert_eq!(stats.results.get(&GameResult::Win(Team::Black)), Some(&1));
+
+        match &stats.errors[..] {
+            [CorpusError::Move {
+                line: 2,
+                ply: 6,
+                san,
+                error: SanError::IllegalMove(_),
+                ..
+            }] => assert_eq!(san, "Bxe4"),
+            errors => panic!("Unexpected errors: {errors:?}"),
+        }
+    }
+}
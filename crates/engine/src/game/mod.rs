@@ -1,61 +1,501 @@
 use std::fmt::Display;
 
-use crate::notations::FEN;
+use crate::{notations::FEN::FenError, primitives::Team};
 
-use self::actions::Action;
+use self::{
+    actions::{Action, EndOfGameState},
+    clock::{Clock, ClockError, MonotonicTime, TimeSource},
+};
 
 pub mod actions;
+pub mod clock;
+pub mod corpus;
 pub mod moves;
 pub use moves::Move;
 mod board;
 pub use board::*; 
+mod perft;
+
+/// Everything that can go wrong while playing a `Chess` game
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChessError {
+    /// The starting position is not a valid FEN string
+    Fen(FenError),
+    /// The move is not legal in the current position
+    IllegalMove(Move),
+    /// The game already ended
+    GameOver(EndOfGameState),
+    /// A draw was claimed while neither the threefold repetition nor the fifty-move rule apply
+    NoDrawToClaim,
+    /// A draw was accepted while none was offered
+    NoDrawOffer,
+    /// A draw was offered while the `Team` already offered one that is still pending
+    DrawAlreadyOffered(Team),
+    /// The `Team` accepted its own draw offer
+    OwnDrawOffer(Team),
+    /// The clock of the game could not be pressed
+    Clock(ClockError),
+}
+
+impl Display for ChessError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Fen(error) => write!(f, "Invalid starting position: {error}"),
+            Self::IllegalMove(chess_move) => write!(f, "Move {chess_move} is not legal"),
+            Self::GameOver(state) => write!(f, "The game is over ({state:?})"),
+            Self::NoDrawToClaim => write!(f, "There is no draw to claim"),
+            Self::NoDrawOffer => write!(f, "There is no draw offer to accept"),
+            Self::DrawAlreadyOffered(team) => write!(f, "{team:?} already offered a draw"),
+            Self::OwnDrawOffer(team) => write!(f, "{team:?} can't accept its own draw offer"),
+            Self::Clock(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl std::error::Error for ChessError {}
+
+impl From<FenError> for ChessError {
+    fn from(error: FenError) -> Self {
+        Self::Fen(error)
+    }
+}
+
+impl From<ClockError> for ChessError {
+    fn from(error: ClockError) -> Self {
+        Self::Clock(error)
+    }
+}
 
 // #[derive(Debug, rspc::Type, serde::Serialize)]
-pub struct Chess {
-    // /// The starting `ChessBoard` state
-    // pub starting_position: ChessBoard,
-    //
-    // /// The history of all actions taken in the current chess game
-    // pub history: Vec<Action>,
-    
-    /// The array of chessboard cells.
-    pub board: Board,
-
-    // /// The team to move
-    // pub team_to_move: Team,
-    // 
-    // /// Halfmove clock: The number of halfmoves since the last capture or pawn advance, used for the fifty-move rule.
-    // pub halfmove_clock: u8,
-    // // Fullmove number: The number of the full moves. It starts at 1 and is incremented after Black's move.
-    // pub fullmove_clock: u8,
-
-    // pub en_passant: Option<Square>,
+#[derive(Debug, Clone)]
+pub struct Chess<T: TimeSource = MonotonicTime> {
+    /// The starting `Board` state
+    starting_position: Board,
+
+    /// The history of all actions taken in the current chess game, with the position after each one.
+    ///
+    /// Undone actions are kept after `self.cursor` until they are redone or a new action is taken.
+    history: Vec<(Action, Board)>,
+
+    /// The number of actions of `self.history` that are applied to `self.board`
+    cursor: usize,
+
+    /// The current position, only changed by the actions and the history navigation
+    board: Board,
+
+    /// The clock of the game, if it is timed
+    clock: Option<Clock<T>>,
 }
 
 impl Chess {
-    // pub fn new(board_pieces: []) -> Self {
-    //     todo!()
-    // }
+    /// Creates a game without any history, starting from the given position
+    pub fn new(starting_position: Board) -> Self {
+        Self {
+            starting_position,
+            history: Vec::new(),
+            cursor: 0,
+            board: starting_position,
+            clock: None,
+        }
+    }
+
+    /// Creates a game without any history, starting from the given FEN string
+    pub fn from_fen(fen: &str) -> Result<Self, FenError> {
+        Ok(Self::new(Board::from_fen(fen)?))
+    }
+
+    /// Rebuilds a game by taking every action of the `history` from the starting position
+    pub fn new_from_history(
+        starting_position: Board,
+        history: impl IntoIterator<Item = Action>,
+    ) -> Result<Self, ChessError> {
+        let mut chess = Self::new(starting_position);
+
+        for action in history {
+            chess.act(action)?;
+        }
+
+        return Ok(chess);
+    }
+
+    /// Rebuilds a game from a starting FEN string and the moves played since
+    ///
+    /// ```
+    /// use engine::{game::Chess, notations::FEN, Move, Square};
+    ///
+    /// let chess = Chess::from_moves(FEN::START, [
+    ///     Move::new(Square::E2, Square::E4),
+    ///     Move::new(Square::E7, Square::E5),
+    /// ]).unwrap();
+    ///
+    /// assert_eq!(chess.ply(), 2);
+    /// assert_eq!(chess.board().to_fen(), "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2");
+    /// assert!(Chess::from_moves(FEN::START, [Move::new(Square::E2, Square::E5)]).is_err());
+    /// ```
+    pub fn from_moves(fen: &str, moves: impl IntoIterator<Item = Move>) -> Result<Self, ChessError> {
+        Self::new_from_history(Board::from_fen(fen)?, moves.into_iter().map(Action::Move))
+    }
+
+}
+
+impl<T: TimeSource> Chess<T> {
+    /// Times the game with the `clock`, which is pressed after every move once it is started with `Chess::clock_mut`.
+    ///
+    /// The team to move loses when its time runs out.
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use engine::{game::{actions::EndOfGameState, clock::{Clock, ManualTime, TimeControl}, Chess}, Move, Square, Team};
+    ///
+    /// let time = ManualTime::default();
+    /// let clock = Clock::with_time_source(TimeControl::sudden_death(Duration::from_secs(60)), time.clone());
+    /// let mut chess = Chess::default().with_clock(clock);
+    ///
+    /// chess.clock_mut().unwrap().start(Team::White);
+    /// chess.make_move(Move::new(Square::E2, Square::E4)).unwrap();
+    /// assert_eq!(chess.clock().unwrap().running(), Some(Team::Black));
+    ///
+    /// time.advance(Duration::from_secs(61));
+    /// assert_eq!(chess.outcome(), Some(EndOfGameState::Timeout(Team::Black)));
+    /// assert!(chess.make_move(Move::new(Square::E7, Square::E5)).is_err());
+    /// ```
+    pub fn with_clock<U: TimeSource>(self, clock: Clock<U>) -> Chess<U> {
+        Chess {
+            starting_position: self.starting_position,
+            history: self.history,
+            cursor: self.cursor,
+            board: self.board,
+            clock: Some(clock),
+        }
+    }
+
+    // [Actions]
+
+    /// Takes the `action`, dropping the actions that were undone
+    ///
+    /// ```
+    /// use engine::{game::{actions::{Action, EndOfGameState}, Chess, ChessError}, DrawReason, Move, Square, Team};
+    ///
+    /// let mut chess = Chess::default();
+    ///
+    /// assert_eq!(chess.act(Action::AcceptDraw(Team::Black)).unwrap_err(), ChessError::NoDrawOffer);
+    ///
+    /// chess.make_move(Move::new(Square::E2, Square::E4)).unwrap();
+    /// chess.act(Action::OfferDraw(Team::White)).unwrap();
+    /// assert_eq!(chess.pending_draw_offer(), Some(Team::White));
+    ///
+    /// assert_eq!(chess.act(Action::AcceptDraw(Team::White)).unwrap_err(), ChessError::OwnDrawOffer(Team::White));
+    /// chess.act(Action::AcceptDraw(Team::Black)).unwrap();
+    /// assert_eq!(chess.outcome(), Some(EndOfGameState::Draw(DrawReason::Agreement)));
+    /// assert!(chess.act(Action::Resign(Team::Black)).is_err());
+    /// ```
+    pub fn act(&mut self, action: Action) -> Result<&mut Self, ChessError> {
+        if let Some(state) = self.outcome() {
+            return Err(ChessError::GameOver(state));
+        }
 
-    pub fn new_from_history<const N: usize>(history: [Action; N]) -> Self {
-        let mut starting_position = Self::default();
+        let mut board = self.board;
 
-        todo!();
+        match action {
+            Action::Move(chess_move) => {
+                if !self.board.iter_moves().any(|legal_move| legal_move == chess_move) {
+                    return Err(ChessError::IllegalMove(chess_move));
+                }
 
-        // for x in history.iter() {
-        //     match *x {
-        //         Action::Move(m) => {
-        //             todo!()
-        //             // starting_position.make_move(m);
-        //         }
-        //         _ => {}
-        //     }
-        // }
+                // A stopped clock, e.g. before the game starts, isn't pressed
+                if let Some(clock) = self.clock.as_mut().filter(|clock| clock.running().is_some()) {
+                    clock.press()?;
+                }
 
-        return starting_position;
+                board.make_move(chess_move);
+            }
+            Action::DeclareDraw if self.claimable_draw().is_none() => return Err(ChessError::NoDrawToClaim),
+            Action::AcceptDraw(team) => match self.pending_draw_offer() {
+                None => return Err(ChessError::NoDrawOffer),
+                Some(offering) if offering == team => return Err(ChessError::OwnDrawOffer(team)),
+                Some(_) => {}
+            },
+            Action::OfferDraw(_) => {
+                if let Some(team) = self.pending_draw_offer() {
+                    return Err(ChessError::DrawAlreadyOffered(team));
+                }
+            }
+            _ => {}
+        }
+
+        self.history.truncate(self.cursor);
+        self.history.push((action, board));
+        self.cursor += 1;
+        self.board = board;
+
+        // The times are kept as they were when the game ended
+        if self.outcome().is_some() {
+            if let Some(clock) = self.clock.as_mut() {
+                clock.stop();
+            }
+        }
+
+        return Ok(self);
+    }
+
+    /// Plays the `chess_move` if it is legal
+    pub fn make_move(&mut self, chess_move: Move) -> Result<&mut Self, ChessError> {
+        self.act(Action::Move(chess_move))
+    }
+
+    /// Offers a draw to the opponent of `team`, which stays pending until it is accepted or the opponent moves
+    pub fn offer_draw(&mut self, team: Team) -> Result<&mut Self, ChessError> {
+        self.act(Action::OfferDraw(team))
+    }
+
+    /// Accepts the pending draw offer of the opponent of `team`, ending the game by agreement
+    pub fn accept_draw(&mut self, team: Team) -> Result<&mut Self, ChessError> {
+        self.act(Action::AcceptDraw(team))
+    }
+
+    /// Ends the game, `team` losing by resignation
+    pub fn resign(&mut self, team: Team) -> Result<&mut Self, ChessError> {
+        self.act(Action::Resign(team))
+    }
+
+    /// Ends the game in a draw by threefold repetition or the fifty-move rule, if one of them applies
+    pub fn claim_draw(&mut self) -> Result<DrawReason, ChessError> {
+        self.act(Action::DeclareDraw)?;
+
+        return Ok(self.claimable_draw().expect("The claimed draw to be claimable"));
+    }
+
+    // [History navigation]
+
+    /// Undoes the last action, returning it
+    ///
+    /// ```
+    /// use engine::{game::{actions::Action, Chess}, Board, Move, Square};
+    ///
+    /// let mut chess = Chess::default();
+    /// let e4 = Move::new(Square::E2, Square::E4);
+    ///
+    /// chess.make_move(e4).unwrap();
+    ///
+    /// assert_eq!(chess.undo(), Some(Action::Move(e4)));
+    /// assert_eq!(chess.board().to_fen(), Board::default().to_fen());
+    /// assert_eq!(chess.undo(), None);
+    ///
+    /// assert_eq!(chess.redo(), Some(Action::Move(e4)));
+    /// assert_eq!(chess.ply(), 1);
+    /// ```
+    pub fn undo(&mut self) -> Option<Action> {
+        let cursor = self.cursor.checked_sub(1)?;
+        self.set_cursor(cursor);
+
+        return Some(self.history[cursor].0);
+    }
+
+    /// Takes the last undone action again, returning it
+    pub fn redo(&mut self) -> Option<Action> {
+        let (action, _) = *self.history.get(self.cursor)?;
+        self.set_cursor(self.cursor + 1);
+
+        return Some(action);
+    }
+
+    /// Goes to the position after the `ply`th move (0 being the starting position), keeping the following actions to be redone.
+    ///
+    /// Returns `false`, without changing the position, if there are not enough moves in the history.
+    pub fn goto_ply(&mut self, ply: usize) -> bool {
+        let cursor = match ply {
+            0 => Some(0),
+            _ => self
+                .history
+                .iter()
+                .enumerate()
+                .filter(|(_, (action, _))| matches!(action, Action::Move(_)))
+                .nth(ply - 1)
+                .map(|(index, _)| index + 1),
+        };
+
+        match cursor {
+            Some(cursor) => self.set_cursor(cursor),
+            None => return false,
+        }
+
+        return true;
+    }
+
+    fn set_cursor(&mut self, cursor: usize) {
+        self.cursor = cursor;
+        self.board = match cursor {
+            0 => self.starting_position,
+            _ => self.history[cursor - 1].1,
+        };
+
+        // A running clock goes on with the time of the new team to move
+        let team = self.board.team_to_move;
+        if let Some(clock) = self.clock.as_mut().filter(|clock| clock.running().is_some_and(|running| running != team)) {
+            clock.start(team);
+        }
     }
 
     // [Getter methods]
+
+    /// Is this game Ongoing, or did it end, and how?
+    ///
+    /// Besides `Board::status`, this handles the fivefold repetition and the draws claimed with `Action::DeclareDraw`.
+    ///
+    /// ```
+    /// use engine::{game::Chess, BoardStatus, DrawReason, Move, Square};
+    ///
+    /// let mut chess = Chess::default();
+    /// let shuffle = [
+    ///     Move::new(Square::G1, Square::F3),
+    ///     Move::new(Square::G8, Square::F6),
+    ///     Move::new(Square::F3, Square::G1),
+    ///     Move::new(Square::F6, Square::G8),
+    /// ];
+    ///
+    /// for chess_move in shuffle.iter().cycle().take(8) {
+    ///     chess.make_move(*chess_move).unwrap();
+    /// }
+    /// assert_eq!(chess.claimable_draw(), Some(DrawReason::ThreefoldRepetition));
+    /// assert_eq!(chess.status(), BoardStatus::Ongoing);
+    ///
+    /// for chess_move in shuffle.iter().cycle().take(8) {
+    ///     chess.make_move(*chess_move).unwrap();
+    /// }
+    /// assert_eq!(chess.status(), BoardStatus::Draw(DrawReason::FivefoldRepetition));
+    /// ```
+    pub fn status(&self) -> BoardStatus {
+        if let (Some(Action::DeclareDraw), Some(reason)) = (self.actions().last(), self.claimable_draw()) {
+            return BoardStatus::Draw(reason);
+        }
+
+        match self.board.status() {
+            BoardStatus::Ongoing if self.repetitions() >= 5 => BoardStatus::Draw(DrawReason::FivefoldRepetition),
+            status => status,
+        }
+    }
+
+    /// How did the game end, if it did?
+    ///
+    /// Besides the endings of `Chess::status`, this handles the resignations, the draws by agreement and the timeouts.
+    pub fn outcome(&self) -> Option<EndOfGameState> {
+        match self.actions().last() {
+            Some(Action::Resign(team)) => return Some(EndOfGameState::Resignation(team)),
+            Some(Action::AcceptDraw(_)) => return Some(EndOfGameState::Draw(DrawReason::Agreement)),
+            _ => {}
+        }
+
+        if let Some(timeout) = self.clock.as_ref().and_then(|clock| clock.timeout(&self.board)) {
+            return Some(timeout);
+        }
+
+        match self.status() {
+            BoardStatus::Ongoing => None,
+            BoardStatus::Stalemate => Some(EndOfGameState::Stalemate),
+            // The team to move is the one that got checkmated
+            BoardStatus::Checkmate => Some(EndOfGameState::Checkmate(!self.board.team_to_move)),
+            BoardStatus::Draw(reason) => Some(EndOfGameState::Draw(reason)),
+        }
+    }
+
+    /// The position the game started from
+    pub fn starting_position(&self) -> &Board {
+        &self.starting_position
+    }
+
+    /// The current position
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    /// The clock of the game, if it is timed
+    pub fn clock(&self) -> Option<&Clock<T>> {
+        self.clock.as_ref()
+    }
+
+    /// The clock of the game, e.g. to start it or pause it
+    pub fn clock_mut(&mut self) -> Option<&mut Clock<T>> {
+        self.clock.as_mut()
+    }
+
+    /// The `Team` whose draw offer is pending, if any
+    pub fn pending_draw_offer(&self) -> Option<Team> {
+        let mut offer = None;
+
+        for (action, board) in self.history[..self.cursor].iter() {
+            match action {
+                Action::OfferDraw(team) => offer = Some(*team),
+                Action::AcceptDraw(_) => offer = None,
+                // The offer is declined once the opponent moves, giving the turn back to the offering team
+                Action::Move(_) if offer == Some(board.team_to_move) => offer = None,
+                _ => {}
+            }
+        }
+
+        return offer;
+    }
+
+    /// The draw that can be claimed in the current position, if any
+    pub fn claimable_draw(&self) -> Option<DrawReason> {
+        if self.repetitions() >= 3 {
+            Some(DrawReason::ThreefoldRepetition)
+        } else if self.board.halfmove_clock >= 100 {
+            Some(DrawReason::FiftyMoveRule)
+        } else {
+            None
+        }
+    }
+
+    /// How many times the current position occurred in the game (this occurrence included)
+    ///
+    /// Positions are compared by hash: same pieces, team to move, castling rights and en passant file.
+    /// Only the positions since the last capture or pawn move can be repeated.
+    pub fn repetitions(&self) -> usize {
+        let hash = self.board.hash();
+
+        std::iter::once(&self.starting_position)
+            .chain(self.moves().map(|(_, board)| board))
+            .rev()
+            .take(self.board.halfmove_clock as usize + 1)
+            .filter(|board| board.hash() == hash)
+            .count()
+    }
+
+    /// The number of moves played to reach the current position
+    pub fn ply(&self) -> usize {
+        self.moves().count()
+    }
+
+    /// Whether there are undone actions that can be redone
+    pub fn can_redo(&self) -> bool {
+        self.cursor < self.history.len()
+    }
+
+    /// The actions taken to reach the current position
+    pub fn actions(&self) -> impl Iterator<Item = Action> + '_ {
+        self.history[..self.cursor].iter().map(|(action, _)| *action)
+    }
+
+    /// Iterates over the moves played to reach the current position, with the position after each one
+    ///
+    /// ```
+    /// use engine::{game::Chess, notations::FEN, Move, Square};
+    ///
+    /// let chess = Chess::from_moves(FEN::START, [Move::new(Square::G1, Square::F3)]).unwrap();
+    /// let (chess_move, board) = chess.moves().next().unwrap();
+    ///
+    /// assert_eq!(chess_move, Move::new(Square::G1, Square::F3));
+    /// assert_eq!(board.to_fen(), "rnbqkbnr/pppppppp/8/8/8/5N2/PPPPPPPP/RNBQKB1R b KQkq - 1 1");
+    /// ```
+    pub fn moves(&self) -> impl DoubleEndedIterator<Item = (Move, &Board)> + '_ {
+        self.history[..self.cursor]
+            .iter()
+            .filter_map(|(action, board)| match action {
+                Action::Move(chess_move) => Some((*chess_move, board)),
+                _ => None,
+            })
+    }
+
     // pub fn team_to_move(&self)
 
 
@@ -100,34 +540,243 @@ impl Chess {
 
 impl Default for Chess {
     fn default() -> Self {
-        FEN::gamestate_from_fen(FEN::START).unwrap()
+        Self::new(Board::default())
     }
 }
 
-impl Display for Chess {
+impl<T: TimeSource> Display for Chess<T> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        todo!()
-        // write!(
-        //     f,
-        //     "{}",
-        //     self.board
-        //         .iter()
-        //         .enumerate()
-        //         .map(|(i, cell)| {
-        //             let mut string_repr = if let Some(piece) = cell {
-        //                 piece.to_string()
-        //             } else {
-        //                 ".".to_string()
-        //             };
-        //
-        //             if i % 8 == 0 && i != 0 {
-        //                 string_repr.insert_str(0, "\n");
-        //             }
-        //
-        //             return string_repr;
-        //         })
-        //         .collect::<Vec<String>>()
-        //         .join(" ")
-        // )
+        write!(f, "{}", self.board)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{
+        game::clock::{ManualTime, TimeControl},
+        notations::FEN,
+        primitives::Square,
+    };
+
+    #[test]
+    fn history_navigation() {
+        let moves = [
+            Move::new(Square::E2, Square::E4),
+            Move::new(Square::E7, Square::E5),
+            Move::new(Square::G1, Square::F3),
+        ];
+        let mut chess = Chess::default();
+        moves.iter().for_each(|chess_move| {
+            chess.make_move(*chess_move).unwrap();
+        });
+        chess.act(Action::OfferDraw(Team::Black)).unwrap();
+
+        let positions = chess.moves().map(|(_, board)| board.to_fen()).collect::<Vec<_>>();
+        assert_eq!(chess.moves().map(|(chess_move, _)| chess_move).collect::<Vec<_>>(), moves);
+        assert_eq!(positions.last(), Some(&chess.board().to_fen()));
+
+        assert!(chess.goto_ply(1));
+        assert_eq!(chess.board().to_fen(), positions[0]);
+        assert_eq!(chess.ply(), 1);
+        assert!(chess.can_redo());
+
+        assert!(chess.goto_ply(3));
+        assert_eq!(chess.actions().count(), 3);
+        assert_eq!(chess.redo(), Some(Action::OfferDraw(Team::Black)));
+        assert_eq!(chess.redo(), None);
+
+        assert!(!chess.goto_ply(4));
+        assert!(chess.goto_ply(0));
+        assert_eq!(chess.board().to_fen(), chess.starting_position().to_fen());
+
+        // Taking a new action drops the undone ones
+        chess.make_move(Move::new(Square::D2, Square::D4)).unwrap();
+        assert!(!chess.can_redo());
+        assert_eq!(chess.ply(), 1);
+        assert_eq!(chess.board().hash(), chess.moves().last().unwrap().1.hash());
+    }
+
+    #[test]
+    fn draws() {
+        let mut chess = Chess::from_fen("4k3/8/8/8/8/8/4P3/R3K3 w Q - 98 80").unwrap();
+        assert_eq!(chess.claim_draw(), Err(ChessError::NoDrawToClaim));
+
+        chess.make_move(Move::new(Square::A1, Square::A2)).unwrap();
+        assert_eq!(chess.claimable_draw(), None);
+        chess.make_move(Move::new(Square::E8, Square::D8)).unwrap();
+        assert_eq!(chess.claimable_draw(), Some(DrawReason::FiftyMoveRule));
+        assert_eq!(chess.status(), BoardStatus::Ongoing);
+
+        assert_eq!(chess.claim_draw(), Ok(DrawReason::FiftyMoveRule));
+        assert_eq!(chess.status(), BoardStatus::Draw(DrawReason::FiftyMoveRule));
+        assert_eq!(
+            chess.make_move(Move::new(Square::A2, Square::A3)).unwrap_err(),
+            ChessError::GameOver(EndOfGameState::Draw(DrawReason::FiftyMoveRule))
+        );
+
+        // Undoing the claim resumes the game, and a pawn move resets the clock
+        chess.undo();
+        chess.make_move(Move::new(Square::E2, Square::E4)).unwrap();
+        assert_eq!(chess.claimable_draw(), None);
+
+        let mut chess = Chess::from_fen("4k3/8/8/8/8/8/4P3/R3K3 w - - 149 100").unwrap();
+        chess.make_move(Move::new(Square::A1, Square::A2)).unwrap();
+        assert_eq!(chess.status(), BoardStatus::Draw(DrawReason::SeventyFiveMoveRule));
+
+        // Losing the castling rights changes the position
+        let mut chess = Chess::from_fen("4k3/8/8/8/8/8/4P3/R3K3 w Q - 0 1").unwrap();
+        [
+            Move::new(Square::A1, Square::A2),
+            Move::new(Square::E8, Square::D8),
+            Move::new(Square::A2, Square::A1),
+            Move::new(Square::D8, Square::E8),
+            Move::new(Square::A1, Square::A2),
+            Move::new(Square::E8, Square::D8),
+            Move::new(Square::A2, Square::A1),
+            Move::new(Square::D8, Square::E8),
+        ]
+        .into_iter()
+        .for_each(|chess_move| {
+            chess.make_move(chess_move).unwrap();
+        });
+        assert_eq!(chess.repetitions(), 2);
+        assert_eq!(chess.claimable_draw(), None);
+    }
+
+    #[test]
+    fn draw_offers() {
+        let mut chess = Chess::default();
+
+        // An offer stays pending while the offering team moves
+        chess.offer_draw(Team::White).unwrap();
+        chess.make_move(Move::new(Square::E2, Square::E4)).unwrap();
+        assert_eq!(chess.pending_draw_offer(), Some(Team::White));
+        assert_eq!(chess.offer_draw(Team::Black).unwrap_err(), ChessError::DrawAlreadyOffered(Team::White));
+
+        // And expires once the opponent moves
+        chess.make_move(Move::new(Square::E7, Square::E5)).unwrap();
+        assert_eq!(chess.pending_draw_offer(), None);
+        assert_eq!(chess.accept_draw(Team::White).unwrap_err(), ChessError::NoDrawOffer);
+
+        chess.offer_draw(Team::Black).unwrap();
+        chess.undo();
+        assert_eq!(chess.pending_draw_offer(), None);
+        chess.redo();
+        assert_eq!(chess.pending_draw_offer(), Some(Team::Black));
+
+        // Only the opponent of the offering team can accept
+        assert_eq!(chess.accept_draw(Team::Black).unwrap_err(), ChessError::OwnDrawOffer(Team::Black));
+        assert_eq!(chess.outcome(), None);
+
+        chess.accept_draw(Team::White).unwrap();
+        assert_eq!(chess.outcome(), Some(EndOfGameState::Draw(DrawReason::Agreement)));
+        assert_eq!(
+            chess.make_move(Move::new(Square::G1, Square::F3)).unwrap_err(),
+            ChessError::GameOver(EndOfGameState::Draw(DrawReason::Agreement))
+        );
+    }
+
+    #[test]
+    fn endings() {
+        let mut chess = Chess::default();
+        chess.resign(Team::Black).unwrap();
+        assert_eq!(chess.outcome(), Some(EndOfGameState::Resignation(Team::Black)));
+        assert_eq!(
+            chess.resign(Team::White).unwrap_err(),
+            ChessError::GameOver(EndOfGameState::Resignation(Team::Black))
+        );
+
+        chess.undo();
+        assert_eq!(chess.outcome(), None);
+
+        let mut chess = Chess::from_moves(
+            FEN::START,
+            [
+                Move::new(Square::F2, Square::F3),
+                Move::new(Square::E7, Square::E5),
+                Move::new(Square::G2, Square::G4),
+                Move::new(Square::D8, Square::H4),
+            ],
+        )
+        .unwrap();
+        assert_eq!(chess.outcome(), Some(EndOfGameState::Checkmate(Team::Black)));
+        assert!(chess.offer_draw(Team::White).is_err());
+
+        let chess = Chess::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        assert_eq!(chess.outcome(), Some(EndOfGameState::Stalemate));
+    }
+
+    #[test]
+    fn clock() {
+        let secs = Duration::from_secs;
+        let time = ManualTime::default();
+        let time_control = TimeControl::fischer(secs(60), secs(1));
+        let mut chess = Chess::default().with_clock(Clock::with_time_source(time_control, time.clone()));
+
+        // The moves aren't timed until the clock starts
+        time.advance(secs(10));
+        chess.make_move(Move::new(Square::E2, Square::E4)).unwrap();
+        assert_eq!(chess.clock().unwrap().remaining(Team::White), secs(60));
+
+        chess.clock_mut().unwrap().start(Team::Black);
+        time.advance(secs(5));
+        chess.make_move(Move::new(Square::E7, Square::E5)).unwrap();
+        let clock = chess.clock().unwrap();
+        assert_eq!(clock.running(), Some(Team::White));
+        assert_eq!(clock.remaining(Team::Black), secs(56));
+
+        // An illegal move doesn't press the clock
+        assert!(chess.make_move(Move::new(Square::E4, Square::E5)).is_err());
+        assert_eq!(chess.clock().unwrap().running(), Some(Team::White));
+
+        // After an undo, the time of the team to move runs instead
+        time.advance(secs(3));
+        chess.undo();
+        assert_eq!(chess.clock().unwrap().running(), Some(Team::Black));
+        time.advance(secs(2));
+        chess.make_move(Move::new(Square::D7, Square::D5)).unwrap();
+        assert_eq!(chess.clock().unwrap().running(), Some(Team::White));
+        assert_eq!(chess.clock().unwrap().remaining(Team::White), secs(57));
+        assert_eq!(chess.clock().unwrap().remaining(Team::Black), secs(55));
+
+        // The clock stops when the game ends
+        chess.resign(Team::White).unwrap();
+        time.advance(secs(100));
+        assert_eq!(chess.clock().unwrap().running(), None);
+        assert_eq!(chess.clock().unwrap().remaining(Team::White), secs(57));
+
+        // Running out of time loses, or draws against insufficient material
+        let time_control = TimeControl::sudden_death(secs(20));
+        let mut chess = Chess::from_fen("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1")
+            .unwrap()
+            .with_clock(Clock::with_time_source(time_control, time.clone()));
+        chess.clock_mut().unwrap().start(Team::White);
+        time.advance(secs(19));
+        assert_eq!(chess.outcome(), None);
+        time.advance(secs(1));
+        assert_eq!(chess.outcome(), Some(EndOfGameState::Draw(DrawReason::TimeoutVsInsufficientMaterial)));
+        assert_eq!(
+            chess.make_move(Move::new(Square::E1, Square::D1)).unwrap_err(),
+            ChessError::GameOver(EndOfGameState::Draw(DrawReason::TimeoutVsInsufficientMaterial))
+        );
+    }
+
+    #[test]
+    fn illegal_moves() {
+        let mut chess = Chess::default();
+        chess.make_move(Move::new(Square::E2, Square::E4)).unwrap();
+
+        let before = chess.board().to_fen();
+        assert_eq!(
+            chess.make_move(Move::new(Square::E4, Square::E5)).unwrap_err(),
+            ChessError::IllegalMove(Move::new(Square::E4, Square::E5))
+        );
+        assert_eq!(chess.board().to_fen(), before);
+        assert_eq!(chess.ply(), 1);
+
+        assert!(matches!(Chess::from_moves("8/8 w", []), Err(ChessError::Fen(_))));
     }
 }
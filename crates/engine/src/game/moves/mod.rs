@@ -7,7 +7,7 @@ pub use movegen::MoveGen;
 
 
 /// Data structure representing a single move.
-#[derive(Debug, rspc::Type, serde::Serialize, PartialEq)]
+#[derive(Debug, Clone, Copy, rspc::Type, serde::Serialize, PartialEq, Eq, Hash)]
 pub struct Move {
     /// The original board square index
     pub origin: Square,
@@ -61,3 +61,55 @@ impl Move {
         }
     }
 }
+
+impl std::fmt::Display for Move {
+    /// Formats the move in long algebraic notation, as used by UCI.
+    ///
+    /// ```
+    /// use engine::{Move, Piece, Square};
+    ///
+    /// assert_eq!(Move::new(Square::E2, Square::E4).to_string(), "e2e4");
+    /// assert_eq!(Move::new_with_promotion(Square::E7, Square::E8, Some(Piece::Knight)).to_string(), "e7e8n");
+    /// ```
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", self.origin, self.destination)?;
+
+        if let Some(promotion) = self.promotion {
+            write!(f, "{}", promotion.to_string().to_lowercase())?;
+        }
+
+        return Ok(());
+    }
+}
+
+impl std::str::FromStr for Move {
+    type Err = String;
+
+    /// Parses a move in long algebraic notation, as used by UCI.
+    ///
+    /// The move is not checked against any position.
+    ///
+    /// ```
+    /// use engine::{Move, Piece, Square};
+    ///
+    /// assert_eq!("e2e4".parse(), Ok(Move::new(Square::E2, Square::E4)));
+    /// assert_eq!("a7a8q".parse(), Ok(Move::new_with_promotion(Square::A7, Square::A8, Some(Piece::Queen))));
+    /// assert!("e2".parse::<Move>().is_err());
+    /// assert!("e2e4k".parse::<Move>().is_err());
+    /// ```
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if !s.is_ascii() || !(4..=5).contains(&s.len()) {
+            return Err(format!("Invalid move \"{s}\""));
+        }
+
+        let promotion = match s[4..].chars().next() {
+            None => None,
+            Some(piece) => match Piece::try_from(piece)? {
+                Piece::Pawn | Piece::King => return Err(format!("Invalid promotion in \"{s}\"")),
+                piece => Some(piece),
+            },
+        };
+
+        return Ok(Self::new_with_promotion(s[0..2].parse()?, s[2..4].parse()?, promotion));
+    }
+}
@@ -75,10 +75,29 @@ impl MoveGen {
         }
     }
 
-    /// Never, ever, iterate this move
+    /// Never, ever, iterate this move, returning whether it was left to iterate
+    ///
+    /// The promotions share their destinations: removing one removes the promotions to the other pieces too.
+    ///
+    /// ```
+    /// use engine::{game::moves::MoveGen, Board, Move, Square};
+    ///
+    /// let board = Board::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1").unwrap();
+    /// let mut movegen = MoveGen::new_legal(&board);
+    /// let en_passant = Move::new(Square::E5, Square::D6);
+    ///
+    /// assert!(movegen.remove_move(en_passant));
+    /// assert!(!movegen.remove_move(en_passant));
+    /// assert!(!movegen.contains(en_passant));
+    /// assert!(movegen.contains(Move::new(Square::E5, Square::E6)));
+    /// ```
     pub fn remove_move(&mut self, chess_move: Move) -> bool {
+        // A pawn can have its en passant capture in another entry than its other moves
         for x in 0..self.moves.len() {
-            if self.moves[x].origin == chess_move.origin {
+            if self.moves[x].origin == chess_move.origin
+                && self.moves[x].promotion == chess_move.promotion.is_some()
+                && self.moves[x].move_mask.has_square(chess_move.destination)
+            {
                 self.moves[x].move_mask &= !BitBoard::from_square(chess_move.destination);
                 return true;
             }
@@ -86,6 +105,15 @@ impl MoveGen {
         false
     }
 
+    /// Whether the move is one of the legal moves left to iterate, whatever the iterator mask
+    pub fn contains(&self, chess_move: Move) -> bool {
+        self.moves.iter().any(|moves| {
+            moves.origin == chess_move.origin
+                && moves.move_mask.has_square(chess_move.destination)
+                && moves.promotion == chess_move.promotion.is_some()
+        })
+    }
+
     /// For now, Only iterate moves that land on the following squares
     /// Note: Once iteration is completed, you can pass in a mask of ! `EMPTY`
     ///       to get the remaining moves, or another mask
@@ -0,0 +1,165 @@
+use super::{Board, Move};
+
+impl Board {
+    /// Counts the leaf nodes of the legal move tree of the given `depth`.
+    ///
+    /// Used to verify the move generation against known node counts.
+    ///
+    /// ```
+    /// use engine::Board;
+    ///
+    /// assert_eq!(Board::default().perft(3), 8_902);
+    /// ```
+    ///
+    /// @see [Perft results](https://www.chessprogramming.org/Perft_Results)
+    pub fn perft(&self, depth: u8) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+
+        let moves = self.iter_moves();
+
+        // Bulk counting: the leaf nodes don't have to be made
+        if depth == 1 {
+            return moves.len() as u64;
+        }
+
+        return moves
+            .map(|chess_move| {
+                let mut board = *self;
+                board.make_move(chess_move);
+
+                board.perft(depth - 1)
+            })
+            .sum();
+    }
+
+    /// Splits the `perft` node count of the given `depth` per legal move.
+    ///
+    /// Comparing this with another engine points to the move that is generated or made incorrectly.
+    pub fn divide(&self, depth: u8) -> Vec<(Move, u64)> {
+        if depth == 0 {
+            return Vec::new();
+        }
+
+        return self
+            .iter_moves()
+            .map(|chess_move| {
+                let mut board = *self;
+                board.make_move(chess_move);
+
+                (chess_move, board.perft(depth - 1))
+            })
+            .collect();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{notations::FEN, Board};
+
+    /// Checks the node counts of a position for every depth, starting at 1.
+    fn assert_perft(fen: &str, node_counts: &[u64]) {
+        let board = Board::from_fen(fen).unwrap();
+
+        for (depth, expected) in node_counts.iter().enumerate() {
+            let depth = depth as u8 + 1;
+            assert_eq!(board.perft(depth), *expected, "{fen} at depth {depth}");
+        }
+    }
+
+    #[test]
+    fn start_position() {
+        assert_perft(FEN::START, &[20, 400, 8_902, 197_281]);
+    }
+
+    #[test]
+    fn kiwipete() {
+        assert_perft(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            &[48, 2_039, 97_862],
+        );
+    }
+
+    #[test]
+    fn en_passant_pins() {
+        assert_perft("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", &[14, 191, 2_812, 43_238]);
+    }
+
+    #[test]
+    fn promotions_and_castling() {
+        assert_perft(
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            &[6, 264, 9_467],
+        );
+        assert_perft(
+            "r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1",
+            &[6, 264, 9_467],
+        );
+    }
+
+    #[test]
+    fn discovered_checks() {
+        assert_perft(
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            &[44, 1_486, 62_379],
+        );
+    }
+
+    #[test]
+    fn middlegame() {
+        assert_perft(
+            "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+            &[46, 2_079, 89_890],
+        );
+    }
+
+    #[test]
+    fn edge_cases() {
+        [
+            // Avoid illegal en passant capture
+            ("8/5bk1/8/2Pp4/8/1K6/8/8 w - d6 0 1", 6, 824_064),
+            ("8/8/1k6/8/2pP4/8/5BK1/8 b - d3 0 1", 6, 824_064),
+            // En passant capture checks opponent
+            ("8/8/1k6/2b5/2pP4/8/5K2/8 b - d3 0 1", 6, 1_440_467),
+            ("8/5k2/8/2Pp4/2B5/1K6/8/8 w - d6 0 1", 6, 1_440_467),
+            // Short castling gives check
+            ("5k2/8/8/8/8/8/8/4K2R w K - 0 1", 6, 661_072),
+            ("4k2r/8/8/8/8/8/8/5K2 b k - 0 1", 6, 661_072),
+            // Long castling gives check
+            ("3k4/8/8/8/8/8/8/R3K3 w Q - 0 1", 6, 803_711),
+            ("r3k3/8/8/8/8/8/8/3K4 b q - 0 1", 6, 803_711),
+            // Castling (including losing castling rights due to rook capture)
+            ("r3k2r/1b4bq/8/8/8/8/7B/R3K2R w KQkq - 0 1", 4, 1_274_206),
+            ("r3k2r/7b/8/8/8/8/1B4BQ/R3K2R b KQkq - 0 1", 4, 1_274_206),
+            // Castling prevented
+            ("r3k2r/8/3Q4/8/8/5q2/8/R3K2R b KQkq - 0 1", 4, 1_720_476),
+            ("r3k2r/8/5Q2/8/8/3q4/8/R3K2R w KQkq - 0 1", 4, 1_720_476),
+            // Promote out of check
+            ("2K2r2/4P3/8/8/8/8/8/3k4 w - - 0 1", 6, 3_821_001),
+            ("3K4/8/8/8/8/8/4p3/2k2R2 b - - 0 1", 6, 3_821_001),
+            // Discovered check
+            ("8/8/1P2K3/8/2n5/1q6/8/5k2 b - - 0 1", 5, 1_004_658),
+            ("5K2/8/1Q6/2N5/8/1p2k3/8/8 w - - 0 1", 5, 1_004_658),
+            // Promote to give check
+            ("4k3/1P6/8/8/8/8/K7/8 w - - 0 1", 6, 217_342),
+            ("8/k7/8/8/8/8/1p6/4K3 b - - 0 1", 6, 217_342),
+            // Under promote to give check
+            ("8/P1k5/K7/8/8/8/8/8 w - - 0 1", 6, 92_683),
+            ("8/8/8/8/8/k7/p1K5/8 b - - 0 1", 6, 92_683),
+            // Self stalemate
+            ("K1k5/8/P7/8/8/8/8/8 w - - 0 1", 6, 2_217),
+            ("8/8/8/8/8/p7/8/k1K5 b - - 0 1", 6, 2_217),
+            // Stalemate and checkmate
+            ("8/k1P5/8/1K6/8/8/8/8 w - - 0 1", 7, 567_584),
+            ("8/8/8/8/1k6/8/K1p5/8 b - - 0 1", 7, 567_584),
+            // Double check
+            ("8/8/2k5/5q2/5n2/8/5K2/8 b - - 0 1", 4, 23_527),
+            ("8/5k2/8/5N2/5Q2/2K5/8/8 w - - 0 1", 4, 23_527),
+        ]
+        .into_iter()
+        .for_each(|(fen, depth, expected)| {
+            assert_eq!(Board::from_fen(fen).unwrap().perft(depth), expected, "{fen} at depth {depth}");
+        });
+    }
+}
@@ -169,3 +169,41 @@ pub mod rays {
         }
     }
 }
+
+pub mod zobrist {
+    use super::*;
+    use crate::primitives::{CastleRights, Piece, Square, Team};
+
+    /// Get the Zobrist key for a `team` `piece` on a particular square.
+    #[inline]
+    pub fn piece(piece: Piece, square: Square, team: Team) -> u64 {
+        unsafe {
+            *ZOBRIST_PIECES
+                .get_unchecked(team.to_index())
+                .get_unchecked(piece.to_index())
+                .get_unchecked(square.to_index())
+        }
+    }
+
+    /// Get the Zobrist key for the castle rights of a team.
+    #[inline]
+    pub fn castles(rights: CastleRights, team: Team) -> u64 {
+        unsafe {
+            *ZOBRIST_CASTLES
+                .get_unchecked(team.to_index())
+                .get_unchecked(rights.to_index())
+        }
+    }
+
+    /// Get the Zobrist key for an en passant capture on a particular file.
+    #[inline]
+    pub fn en_passant(file: File) -> u64 {
+        unsafe { *ZOBRIST_EN_PASSANT.get_unchecked(file.to_index()) }
+    }
+
+    /// Get the Zobrist key that is toggled when black is to move.
+    #[inline]
+    pub fn side() -> u64 {
+        ZOBRIST_SIDE
+    }
+}
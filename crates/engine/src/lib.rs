@@ -2,6 +2,7 @@
 #![feature(const_for)]
 #![feature(const_mut_refs)]
 #![feature(inline_const)]
+#![feature(portable_simd)]
 pub mod primitives;
 pub use primitives::*;
 pub mod boards;
@@ -14,3 +15,11 @@ pub use generated::*;
 
 pub mod game;
 pub use game::*;
+
+pub mod arena;
+pub mod book;
+pub mod datagen;
+pub mod eval;
+pub mod search;
+pub mod syzygy;
+pub mod uci;
@@ -1,231 +1,502 @@
-// use engine::bitboard::magic::find_magic_number;
-// #[allow(unused_imports)]
-// use engine::{primitives::game::Chess, notations::FEN};
-//
-// #[allow(unused_imports)]
-// use engine::{
-//     bitboard::BitBoard,
-//     primitives::{
-//         board::{ASCIIBoard, ChessBoard, File, Square},
-//         team::Team,
-//     },
-// };
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, Write},
+    sync::Arc,
+    time::Instant,
+};
 
+use anyhow::{anyhow, Context};
 use engine::{
-    bitboard::BitBoard,
-    boards::ASCIIBoard,
-    game::{Move, Board},
-    notations::FEN::board_from_fen,
-    primitives::{Piece, Square, Team},
+    arena::{
+        self,
+        engine::{EngineConfig, UciEngine},
+        sprt::Sprt,
+        Match, Resign,
+    },
+    book::{Book, BookBuilder},
+    datagen::{self, Generator},
+    eval::{self, nnue::Network, tune::Tuner},
+    game::{clock::TimeControl, corpus, Board},
+    notations::{FEN, PGN},
+    search::Limits,
+    syzygy::Tablebase,
+    uci::Uci,
 };
 
-// include!("../data/magic_gen.rs");
-
-fn main() -> () {
-    let mut board = Board::default();
-
-    // board.set(Piece::Pawn, Square::A2, Team::White);
-    // board.set(Piece::Pawn, Square::G7, Team::Black);
-
-    // board.make_move(Move {
-    //     origin: Square::B2,
-    //     destination: Square::B4,
-    //     promotion: None,
-    // });
-    //
-    // board.make_move(Move {
-    //     origin: Square::H8,
-    //     destination: Square::H7,
-    //     promotion: None,
-    // });
-    //
-    // board.make_move(Move {
-    //     origin: Square::B4,
-    //     destination: Square::B5,
-    //     promotion: None,
-    // });
-    //
-    // board.make_move(Move {
-    //     origin: Square::H7,
-    //     destination: Square::H6,
-    //     promotion: None,
-    // });
-    //
-    // board.make_move(Move {
-    //     origin: Square::B5,
-    //     destination: Square::B6,
-    //     promotion: None,
-    // });
-    //
-    // board.make_move(Move {
-    //     origin: Square::H6,
-    //     destination: Square::H5,
-    //     promotion: None,
-    // });
-    // board.make_move(Move {
-    //     origin: Square::B6,
-    //     destination: Square::C7,
-    //     promotion: None,
-    // });
-    // board.make_move(Move {
-    //     origin: Square::G7,
-    //     destination: Square::G6,
-    //     promotion: None,
-    // });
-    // board.make_move(Move {
-    //     origin: Square::C7,
-    //     destination: Square::B8,
-    //     promotion: Some(Piece::Knight),
-    // });
-    // println!("{}\n", board.combined_mask);
-    // println!("{}\n", board.piece_mask(Piece::Knight));
-    // println!("{}\n", board.team_mask(Team::Black));
-
-    board.iter_moves().for_each(|chess_move| {
-        let mut ascii = ASCIIBoard::default();
-
-        ascii.title(format!(
-            "{:?} {} to {}\n    Promotion: {:?}",
-            board.piece_on(chess_move.origin).unwrap(),
-            chess_move.origin,
-            chess_move.destination,
-            chess_move.promotion
-        ));
-
-        ascii.set('A', chess_move.origin);
-        ascii.set('B', chess_move.destination);
-
-        println!("{ascii}")
-    });
+const USAGE: &str = "Usage:
+    engine-bin [uci]                    Speak UCI over stdin and stdout
+    engine-bin perft <depth> [fen]      Count the leaf nodes of the move tree
+    engine-bin divide <depth> [fen]     Count the leaf nodes of the move tree per move
+    engine-bin corpus [path]            Replay a file of bare SAN games (defaults to data/SAN/games.txt)
+    engine-bin eval [fen]               Print the evaluation of a position, term by term
+    engine-bin eval --net <file> [fen]  Print the evaluation of a position by a neural network
+    engine-bin book build <book> [games] [--min-games <n>] [--min-score <0-1>] [--max-ply <n>]
+                                        Build a Polyglot book from a PGN file or a file of bare SAN games
+                                        (defaults to data/SAN/games.txt)
+    engine-bin book probe <book> [fen]  Print the book moves of a position
+    engine-bin tablebase <path> [fen]   Probe the Syzygy tablebases of the directories in <path>
+    engine-bin datagen <output> [--games <n>] [--depth <n>] [--nodes <n>] [--seed <n>] [--random-plies <n>]
+                                        Write the positions of self-play games with their scores and results
+                                        (defaults to 100 games at depth 6)
+    engine-bin tune [--records <file>] [--corpus <games>] [--epochs <n>] [--learning-rate <x>] [--output <file>]
+                                        Tune the evaluation weights on labelled positions and write them as the
+                                        source of src/eval/tables.rs (defaults to the positions of data/SAN/games.txt,
+                                        1000 epochs and tables.rs)
+    engine-bin match --engine cmd=<path> [name=<name>] [arg=<arg>]... [option.<name>=<value>]... --engine ...
+                     [--tc <time control>] [--games <n>] [--concurrency <n>] [--openings <file>] [--pgn <file>]
+                     [--depth <n>] [--nodes <n>] [--max-plies <n>] [--tablebase <path>] [--resign score=<cp> moves=<n>]
+                     [--sprt elo0=<elo> elo1=<elo> [alpha=<a>] [beta=<b>]] [--ponder]
+                                        Play a match between two UCI engines and report the Elo difference
+                                        (defaults to 2 games at 10+0.1 from the starting position)";
+
+fn main() -> anyhow::Result<()> {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
 
-    // println!("{board}");
-
-    // for (origin, bb) in PAWN_ATTACKS[Team::White.to_index()]
-    //     .iter()
-    //     .enumerate()
-    //     .map(|(i, bb)| (Square::new(i as u8), bb))
-    // {
-    //     println!("\nOrigin: {origin}\n{bb}");
-    //
-    //     term::pause();
-    // }
-
-    // for (origin, array) in BETWEEN.iter().enumerate().map(|(i, bb_array)| {
-    //     (
-    //         Square::new(i as u8),
-    //         bb_array
-    //             .iter()
-    //             .enumerate()
-    //             .map(|(i, bb)| (Square::new(i as u8), bb)),
-    //     )
-    // }) {
-    //     for (dest, bb) in array {
-    //         println!("\nOrigin: {origin}, Destination: {dest}\n{bb}");
-    //
-    //         term::pause();
-    //     }
-    // }
-
-    // fn init_magic_numbers() {
-    //     use engine::bitboard::attack_tables;
-    //     use engine::primitives::piece::SlidingDirection;
-    //
-    //
-    //     Square::ALL.iter().for_each(|square| {
-    //         println!("{square}");
-    //         let attack_map = attack_tables::bishop::mask_attacks(*square);
-    //         let relevant_bits = attack_map.count_bits();
-    //         let magic_number = find_magic_number(*square, relevant_bits, SlidingDirection::Diagonal);
-    //
-    //         println!("{:X}", magic_number.0);
-    //
-    //
-    //     });
-    // }
-    //
-    // init_magic_numbers();
-
-    // for rank in Rank::ALL.iter() {
-    //     for file in File::ALL.iter() {
-    //         let square = Square::make_square(*file, *rank);
-    //
-    //         let attacks = engine::bitboard::attack_tables::rook::mask_attacks(square);
-    //         print!("{}, ", attacks.count_bits());
-    //
-    //     }
-    //
-    //     print!("\n")
-    // }
-    // println!("{}", gen_random_u32_number());
-    // println!("{}", gen_random_u32_number() & 0xFFFF);
-    // println!("{}", gen_random_u64_number());
-    // for i in 0..3 {
-    //     println!("{}", gen_random_u64_numbers());
-    //
-    // }
+    match args.first().map(String::as_str) {
+        None | Some("uci") => uci()?,
+        Some("perft") => perft(&args[1..], false)?,
+        Some("divide") => perft(&args[1..], true)?,
+        Some("corpus") => corpus(&args[1..])?,
+        Some("eval") => evaluate(&args[1..])?,
+        Some("book") => book(&args[1..])?,
+        Some("tablebase") => tablebase(&args[1..])?,
+        Some("datagen") => datagen(&args[1..])?,
+        Some("tune") => tune(&args[1..])?,
+        Some("match") => play_match(&args[1..])?,
+        Some(_) => println!("{USAGE}"),
+    }
+
+    return Ok(());
 }
 
-fn debug_square(square: Square) {
+/// Runs `Board::perft` or `Board::divide` with the `<depth> [fen]` arguments
+fn perft(args: &[String], divide: bool) -> anyhow::Result<()> {
+    let depth = args
+        .first()
+        .ok_or_else(|| anyhow!("Missing depth argument\n\n{USAGE}"))?
+        .parse::<u8>()
+        .context("Invalid depth argument")?;
+    let fen = match args.get(1..) {
+        Some(fen) if !fen.is_empty() => fen.join(" "),
+        _ => FEN::START.to_owned(),
+    };
+    let board = Board::from_fen(&fen)?;
+
+    let start = Instant::now();
+    let nodes = if divide {
+        let moves = board.divide(depth);
+
+        for (chess_move, nodes) in moves.iter() {
+            println!("{chess_move}: {nodes}");
+        }
+        println!();
+
+        moves.iter().map(|(_, nodes)| nodes).sum()
+    } else {
+        board.perft(depth)
+    };
+    let elapsed = start.elapsed();
+
+    println!("Nodes: {nodes}");
     println!(
-        "Square({square}): {{ file: {}, rank: {}, index: {} }}",
-        square.get_file(),
-        square.get_rank(),
-        square.to_index(),
+        "Time: {:.3}s ({:.0} nodes/s)",
+        elapsed.as_secs_f64(),
+        nodes as f64 / elapsed.as_secs_f64().max(f64::EPSILON)
     );
+
+    return Ok(());
 }
 
-// #[allow(dead_code)]
-// fn sim_game() -> () {
-//     // let mut game = Chess::default();
-//     let mut ascii = ASCIIBoard::new();
-//
-//     let mut game =
-//         FEN::gamestate_from_fen("rnbqkb1r/ppp1pppp/5n2/3p4/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3")
-//             .unwrap();
-//
-//     game.make_move(Square::B2, Square::B3).unwrap();
-//
-//     ascii.print(&game.board);
-//
-//     // let origin = Square::E6;
-//
-//     game.generate_legal_moves()
-//         .iter()
-//         .filter(|m| m.origin == Square::D8)
-//         .for_each(|m| {
-//             println!("{:#?}", &m);
-//             ascii.mark(m.destination);
-//         });
-//
-//     // let mut board = ChessBoard::empty();
-//
-//     // let piece = ChessPiece::try_from('P').unwrap();
-//     //
-//     // board.set_piece(origin.to_index(), piece);
-//     //
-//     // let x = board
-//     //     .get(origin.to_index())
-//     //     .as_ref()
-//     //     .unwrap()
-//     //     .pseudo_legal_moves(origin);
-//     //
-//     // x.iter().for_each(|m| {
-//     //     board.set_piece(m.destination.to_index(), ChessPiece::try_from('n').unwrap());
-//     // });
-//
-//     // ascii.mark(Square::C4);
-//
-//     ascii.print(&game.board);
-// }
-
-#[allow(dead_code)]
-mod term {
-    use std::io::{stdin, stdout, Read, Write};
-
-    pub fn pause() {
-        let mut stdout = stdout();
-        stdout.write(b"\nPress Enter to continue...").unwrap();
-        stdout.flush().unwrap();
-        stdin().read(&mut [0]).unwrap();
+/// Replays every game of the corpus at `[path]` and prints the statistics
+fn corpus(args: &[String]) -> anyhow::Result<()> {
+    let path = args.first().map(String::as_str).unwrap_or(corpus::GAMES);
+    let file = File::open(path).with_context(|| format!("Could not open {path}"))?;
+
+    let start = Instant::now();
+    let stats = corpus::replay(BufReader::new(file))?;
+
+    print!("{stats}");
+    println!("Time: {:.3}s", start.elapsed().as_secs_f64());
+
+    if !stats.errors.is_empty() {
+        return Err(anyhow!("{} games could not be replayed", stats.errors.len()));
     }
+
+    return Ok(());
+}
+
+/// Runs the UCI loop until `quit` or the end of the input
+fn uci() -> anyhow::Result<()> {
+    let mut uci = Uci::new(io::stdout());
+    uci.run(io::stdin().lock())?;
+
+    return Ok(());
+}
+
+/// Prints the evaluation breakdown of the `[fen]` position, or its evaluation by the network of `--net <file>`
+fn evaluate(args: &[String]) -> anyhow::Result<()> {
+    let (network, args) = match args.first().map(String::as_str) {
+        Some("--net") => {
+            let path = args.get(1).ok_or_else(|| anyhow!("Missing network argument\n\n{USAGE}"))?;
+            (Some(Network::open(path)?), &args[2..])
+        }
+        _ => (None, args),
+    };
+    let fen = match args.is_empty() {
+        true => FEN::START.to_owned(),
+        false => args.join(" "),
+    };
+    let board = Board::from_fen(&fen)?;
+
+    match network {
+        Some(network) => println!("Network evaluation: {} (team to move)", network.evaluate_board(&board)),
+        None => println!("{}", eval::breakdown(&board)),
+    }
+
+    return Ok(());
+}
+
+/// Builds a book with `build <book> [games] [filters]`, or prints the book moves of a position with `probe <book> [fen]`
+fn book(args: &[String]) -> anyhow::Result<()> {
+    let path = args.get(1).ok_or_else(|| anyhow!("Missing book argument\n\n{USAGE}"))?;
+
+    match args[0].as_str() {
+        "build" => {
+            let mut builder = BookBuilder::new();
+            let mut games = corpus::GAMES;
+
+            let mut options = args[2..].iter();
+            while let Some(option) = options.next() {
+                let mut value = || options.next().ok_or_else(|| anyhow!("Missing value for {option}"));
+
+                match option.as_str() {
+                    "--min-games" => builder = builder.with_min_games(value()?.parse().context("Invalid --min-games")?),
+                    "--min-score" => builder = builder.with_min_score(value()?.parse().context("Invalid --min-score")?),
+                    "--max-ply" => builder = builder.with_max_ply(value()?.parse().context("Invalid --max-ply")?),
+                    _ => games = option,
+                }
+            }
+
+            let count = match games.ends_with(".pgn") {
+                true => builder.add_pgn(&std::fs::read_to_string(games).with_context(|| format!("Could not open {games}"))?)?,
+                false => builder.add_corpus(BufReader::new(File::open(games).with_context(|| format!("Could not open {games}"))?))?,
+            };
+            let book = builder.build();
+            book.save(path).with_context(|| format!("Could not write {path}"))?;
+
+            println!("{count} games, {} entries written to {path}", book.len());
+        }
+        "probe" => {
+            let book = Book::open(path)?;
+            let fen = match args.get(2..) {
+                Some(fen) if !fen.is_empty() => fen.join(" "),
+                _ => FEN::START.to_owned(),
+            };
+            let board = Board::from_fen(&fen)?;
+
+            let moves = book.moves(&board);
+            let total = moves.iter().map(|book_move| book_move.weight as f64).sum::<f64>().max(1.0);
+            for book_move in moves.iter() {
+                println!(
+                    "{} weight {} ({:.1}%)",
+                    book_move.chess_move,
+                    book_move.weight,
+                    100.0 * book_move.weight as f64 / total
+                );
+            }
+            if moves.is_empty() {
+                println!("Out of book");
+            }
+        }
+        _ => println!("{USAGE}"),
+    }
+
+    return Ok(());
+}
+
+/// Prints the outcome, distance to zeroing and best moves of the `[fen]` position according to the tablebase at `<path>`
+fn tablebase(args: &[String]) -> anyhow::Result<()> {
+    let path = args.first().ok_or_else(|| anyhow!("Missing path argument\n\n{USAGE}"))?;
+    let tablebase = Tablebase::open(path)?;
+    let fen = match args.get(1..) {
+        Some(fen) if !fen.is_empty() => fen.join(" "),
+        _ => FEN::START.to_owned(),
+    };
+    let board = Board::from_fen(&fen)?;
+
+    println!("{} tablebase files, up to {} pieces", tablebase.len(), tablebase.max_pieces());
+    let Some(wdl) = board.probe_wdl(&tablebase) else {
+        println!("Not in the tablebase");
+        return Ok(());
+    };
+
+    println!("WDL: {wdl}");
+    if let Some(dtz) = board.probe_dtz(&tablebase) {
+        println!("DTZ: {dtz}");
+    }
+    if let Some(moves) = tablebase.best_moves(&board) {
+        println!("Best moves: {}", moves.iter().map(ToString::to_string).collect::<Vec<_>>().join(" "));
+    }
+    println!("{}", wdl.review(board.team_to_move));
+
+    return Ok(());
+}
+
+/// Plays self-play games with `<output> [options]`, writing their positions to `<output>`
+fn datagen(args: &[String]) -> anyhow::Result<()> {
+    let path = args.first().ok_or_else(|| anyhow!("Missing output argument\n\n{USAGE}"))?;
+    let mut limits = Limits::depth(6);
+    let mut games = 100;
+    let mut seed = 0;
+    let mut random_plies = None;
+
+    let mut options = args[1..].iter();
+    while let Some(option) = options.next() {
+        let mut value = || options.next().ok_or_else(|| anyhow!("Missing value for {option}"));
+
+        match option.as_str() {
+            "--games" => games = value()?.parse().context("Invalid --games")?,
+            "--depth" => limits.depth = Some(value()?.parse().context("Invalid --depth")?),
+            "--nodes" => {
+                limits.nodes = Some(value()?.parse().context("Invalid --nodes")?);
+                limits.depth = None;
+            }
+            "--seed" => seed = value()?.parse().context("Invalid --seed")?,
+            "--random-plies" => random_plies = Some(value()?.parse().context("Invalid --random-plies")?),
+            _ => return Err(anyhow!("Unknown option {option}\n\n{USAGE}")),
+        }
+    }
+
+    let mut generator = Generator::new(limits).with_seed(seed);
+    if let Some(plies) = random_plies {
+        generator = generator.with_random_plies(plies);
+    }
+    let file = File::create(path).with_context(|| format!("Could not create {path}"))?;
+
+    let start = Instant::now();
+    let stats = generator.generate(games, BufWriter::new(file))?;
+
+    print!("{stats}");
+    println!("Time: {:.3}s", start.elapsed().as_secs_f64());
+
+    return Ok(());
+}
+
+/// Tunes the evaluation on datagen records and corpus games, and writes the weights to the output
+fn tune(args: &[String]) -> anyhow::Result<()> {
+    let mut records = Vec::new();
+    let mut games = Vec::new();
+    let mut epochs = 1000;
+    let mut learning_rate = 1.0;
+    let mut output = "tables.rs".to_owned();
+
+    let mut options = args.iter();
+    while let Some(option) = options.next() {
+        let mut value = || options.next().ok_or_else(|| anyhow!("Missing value for {option}"));
+
+        match option.as_str() {
+            "--records" => records.push(value()?.clone()),
+            "--corpus" => games.push(value()?.clone()),
+            "--epochs" => epochs = value()?.parse().context("Invalid --epochs")?,
+            "--learning-rate" => learning_rate = value()?.parse().context("Invalid --learning-rate")?,
+            "--output" => output.clone_from(value()?),
+            _ => return Err(anyhow!("Unknown option {option}\n\n{USAGE}")),
+        }
+    }
+    if records.is_empty() && games.is_empty() {
+        games.push(corpus::GAMES.to_owned());
+    }
+
+    let mut tuner = Tuner::new().with_learning_rate(learning_rate);
+    for path in records.iter() {
+        let file = File::open(path).with_context(|| format!("Could not open {path}"))?;
+        tuner.add_records(&datagen::read(BufReader::new(file)).map_err(|error| anyhow!("{path}: {error}"))?);
+    }
+    for path in games.iter() {
+        let file = File::open(path).with_context(|| format!("Could not open {path}"))?;
+        tuner.add_corpus(BufReader::new(file))?;
+    }
+    if tuner.is_empty() {
+        return Err(anyhow!("No positions to tune on"));
+    }
+
+    let start = Instant::now();
+    println!("Positions: {}", tuner.len());
+    println!("K: {:.4}", tuner.fit_k());
+    println!("Error: {:.6}", tuner.error());
+    tuner.tune(epochs, |epoch, error| {
+        if epoch % 10 == 0 || epoch == epochs {
+            println!("Epoch {epoch}: error {error:.6} ({:.1}s)", start.elapsed().as_secs_f64());
+        }
+    });
+
+    std::fs::write(&output, tuner.source()).with_context(|| format!("Could not write {output}"))?;
+    println!("Weights written to {output}");
+
+    return Ok(());
+}
+
+/// Plays a match between the two `--engine`s, printing every result and writing the games to `--pgn`
+fn play_match(args: &[String]) -> anyhow::Result<()> {
+    let mut engines = Vec::new();
+    let mut time_control = "10+0.1".parse::<TimeControl>()?;
+    let mut games = 2;
+    let mut concurrency = 1;
+    let mut openings = Vec::new();
+    let mut pgn = None;
+    let mut depth = None;
+    let mut nodes = None;
+    let mut max_plies = None;
+    let mut tablebase = None;
+    let mut resign = None;
+    let mut sprt = None;
+    let mut ponder = false;
+
+    let mut args = args.iter().peekable();
+    while let Some(option) = args.next() {
+        // Either a single value, or `key=value` parameters
+        let mut parameters = Vec::new();
+        while let Some(parameter) = args.next_if(|arg| !arg.starts_with("--")) {
+            parameters.push(parameter.split_once('=').unwrap_or(("", parameter)));
+        }
+        let value = || match parameters[..] {
+            [("", value)] => Ok(value),
+            _ => Err(anyhow!("Expected a single value for {option}")),
+        };
+
+        match option.as_str() {
+            "--engine" => engines.push(engine_config(&parameters)?),
+            "--tc" => time_control = value()?.parse()?,
+            "--games" => games = value()?.parse().context("Invalid --games")?,
+            "--concurrency" => concurrency = value()?.parse().context("Invalid --concurrency")?,
+            "--openings" => openings = arena::read_openings(value()?)?,
+            "--pgn" => pgn = Some(value()?.to_owned()),
+            "--depth" => depth = Some(value()?.parse().context("Invalid --depth")?),
+            "--nodes" => nodes = Some(value()?.parse().context("Invalid --nodes")?),
+            "--max-plies" => max_plies = Some(value()?.parse().context("Invalid --max-plies")?),
+            "--tablebase" => tablebase = Some(Arc::new(Tablebase::open(value()?)?)),
+            "--resign" => {
+                let mut threshold = Resign { score: 600, moves: 3 };
+                for (key, value) in parameters.iter() {
+                    match *key {
+                        "score" => threshold.score = value.parse().context("Invalid resign score")?,
+                        "moves" => threshold.moves = value.parse().context("Invalid resign moves")?,
+                        _ => return Err(anyhow!("Unknown parameter {key} of --resign")),
+                    }
+                }
+                resign = Some(threshold);
+            }
+            "--sprt" => {
+                let mut test = Sprt::new(0.0, 5.0);
+                for (key, value) in parameters.iter() {
+                    let value = value.parse::<f64>().with_context(|| format!("Invalid SPRT {key}"))?;
+                    match *key {
+                        "elo0" => test.elo0 = value,
+                        "elo1" => test.elo1 = value,
+                        "alpha" => test.alpha = value,
+                        "beta" => test.beta = value,
+                        _ => return Err(anyhow!("Unknown parameter {key} of --sprt")),
+                    }
+                }
+                sprt = Some(test);
+            }
+            "--ponder" => ponder = true,
+            _ => return Err(anyhow!("Unknown option {option}\n\n{USAGE}")),
+        }
+    }
+
+    let [first, second] = <[EngineConfig; 2]>::try_from(engines)
+        .map_err(|engines| anyhow!("Expected 2 engines, got {}\n\n{USAGE}", engines.len()))?;
+
+    let mut arena = Match::new(time_control)
+        .with_games(games)
+        .with_concurrency(concurrency)
+        .with_openings(openings)
+        .with_ponder(ponder)
+        .with_event(&format!("{} vs {}", first.name, second.name));
+    if let Some(depth) = depth {
+        arena = arena.with_depth(depth);
+    }
+    if let Some(nodes) = nodes {
+        arena = arena.with_nodes(nodes);
+    }
+    if let Some(plies) = max_plies {
+        arena = arena.with_max_plies(plies);
+    }
+    if let Some(tablebase) = tablebase {
+        arena = arena.with_tablebase(tablebase);
+    }
+    if let Some(resign) = resign {
+        arena = arena.with_resign(resign);
+    }
+    if let Some(sprt) = sprt {
+        arena = arena.with_sprt(sprt);
+    }
+
+    let mut output = match &pgn {
+        Some(path) => Some(BufWriter::new(File::create(path).with_context(|| format!("Could not create {path}"))?)),
+        None => None,
+    };
+    let mut write_error = None;
+
+    let start = Instant::now();
+    let engines = || Ok([UciEngine::spawn(&first)?, UciEngine::spawn(&second)?]);
+    let stats = arena.run(engines, |game, stats| {
+        println!(
+            "Game {} ({} vs {}): {} {{{}}}",
+            game.index + 1,
+            game.pgn.tag("White").unwrap_or("?"),
+            game.pgn.tag("Black").unwrap_or("?"),
+            game.result,
+            game.reason
+        );
+        println!("{stats}");
+        if let Some(sprt) = &sprt {
+            println!("{}", sprt.report(stats));
+        }
+
+        if let Some(output) = &mut output {
+            let written = writeln!(output, "{}", PGN::format(&game.pgn)).and_then(|_| output.flush());
+            if let Err(error) = written {
+                write_error.get_or_insert(error);
+            }
+        }
+    })?;
+
+    println!();
+    println!("{} vs {}", first.name, second.name);
+    println!("{stats}");
+    if let Some(sprt) = &sprt {
+        println!("{}", sprt.report(&stats));
+    }
+    println!("Time: {:.3}s", start.elapsed().as_secs_f64());
+
+    if let Some(error) = write_error {
+        return Err(anyhow!(error).context(format!("Could not write the games to {}", pgn.unwrap_or_default())));
+    }
+
+    return Ok(());
+}
+
+/// The engine of the `cmd=`, `name=`, `arg=` and `option.<name>=` parameters of `--engine`
+fn engine_config(parameters: &[(&str, &str)]) -> anyhow::Result<EngineConfig> {
+    let command = parameters
+        .iter()
+        .find_map(|(key, value)| (*key == "cmd").then_some(*value))
+        .ok_or_else(|| anyhow!("Missing cmd=<path> for --engine"))?;
+    let mut config = EngineConfig::new(command);
+
+    for (key, value) in parameters.iter() {
+        match *key {
+            "cmd" => {}
+            "name" => config = config.with_name(value),
+            "arg" => config = config.with_arg(value),
+            _ => match key.strip_prefix("option.") {
+                Some(name) => config = config.with_option(name, value),
+                None => return Err(anyhow!("Unknown parameter {key} of --engine")),
+            },
+        }
+    }
+
+    return Ok(config);
 }
@@ -1,78 +1,408 @@
 /// The starting FEN string.
 pub const START: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
 
+use std::fmt::Display;
+
 use crate::{
+    bitboard::BitBoard,
     boards::ChessBoard,
-    game::Chess,
+    game::{Board, Chess},
     primitives::{
-        board::{File, Rank, Square},
-        piece::ChessPiece,
+        board::{CastleRights, File, Rank, Square},
+        piece::{ChessPiece, Piece},
         team::Team,
-    }, utils::enums::ArrayEnum,
+    },
+    utils::enums::ArrayEnum,
 };
 
+/// Everything that can go wrong while parsing a FEN string.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FenError {
+    /// A FEN string has 6 space separated fields (the last 2 being optional)
+    FieldCount(usize),
+    /// The piece placement field is malformed
+    PiecePlacement(String),
+    /// The active color field is not `w` or `b`
+    Team(String),
+    /// The castling availability field is malformed or doesn't match the piece placement
+    Castling(String),
+    /// The en passant target square is malformed or can't be the result of a double pawn push
+    EnPassant(String),
+    /// The halfmove clock is not a valid number
+    HalfmoveClock(String),
+    /// The fullmove number is not a valid number (it starts at 1)
+    FullmoveNumber(String),
+    /// The `Team` has no king on the board
+    MissingKing(Team),
+    /// The `Team` has more than one king on the board
+    TooManyKings(Team),
+    /// There is a pawn on the first or eighth rank
+    PawnOnBackRank(Square),
+    /// The team that is not to move is in check, meaning its king could be captured
+    OpponentInCheck,
+}
+
+impl Display for FenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::FieldCount(count) => write!(f, "Expected 4 or 6 FEN fields, found {count}"),
+            Self::PiecePlacement(reason) => write!(f, "Invalid piece placement: {reason}"),
+            Self::Team(token) => write!(f, r#"Token "{token}" is not a valid team identifier"#),
+            Self::Castling(reason) => write!(f, "Invalid castling availability: {reason}"),
+            Self::EnPassant(reason) => write!(f, "Invalid en passant target square: {reason}"),
+            Self::HalfmoveClock(token) => write!(f, r#"Invalid halfmove clock "{token}""#),
+            Self::FullmoveNumber(token) => write!(f, r#"Invalid fullmove number "{token}""#),
+            Self::MissingKing(team) => write!(f, "{team:?} has no king"),
+            Self::TooManyKings(team) => write!(f, "{team:?} has more than one king"),
+            Self::PawnOnBackRank(square) => write!(f, "Pawn on back rank square {square}"),
+            Self::OpponentInCheck => write!(f, "The team that is not to move is in check"),
+        }
+    }
+}
+
+impl std::error::Error for FenError {}
+
+/// Parses the piece placement field of a FEN string.
+///
+/// Panics when the given string is not valid, use `parse_piece_placement` to handle errors.
 pub fn board_from_fen(board_str: &str) -> ChessBoard {
+    parse_piece_placement(board_str).unwrap()
+}
+
+/// Parses the piece placement field of a FEN string.
+///
+/// Each rank is described, starting with rank 8 and ending with rank 1, with a "/" between each one.
+/// Within each rank, the contents of the squares are described in order from the a-file to the h-file.
+/// White pieces are designated using uppercase letters ("PNBRQK"), while black pieces use lowercase letters ("pnbrqk").
+/// A set of one or more consecutive empty squares within a rank is denoted by a digit from "1" to "8".
+pub fn parse_piece_placement(board_str: &str) -> Result<ChessBoard, FenError> {
     let mut board = ChessBoard::empty();
 
-    let mut offset;
-    for (rank, row) in board_str.split("/").enumerate() {
-        // Keeps track of the amount of empty squares of the current, indicated by a number in the FEN string
-        offset = 0;
+    let rows = board_str.split('/').collect::<Vec<_>>();
+    if rows.len() != Rank::SIZE {
+        return Err(FenError::PiecePlacement(format!(
+            "expected {} ranks, found {}",
+            Rank::SIZE,
+            rows.len()
+        )));
+    }
+
+    for (rank_index, row) in rows.into_iter().enumerate() {
+        let rank = Rank::from_index(Rank::SIZE - 1 - rank_index);
+
+        // The file index of the next square to fill in
+        let mut file_index = 0;
+
+        for char in row.chars() {
+            if let Some(empty_squares) = char.to_digit(10) {
+                if !(1..=8).contains(&empty_squares) {
+                    return Err(FenError::PiecePlacement(format!(
+                        r#"invalid empty square count "{char}" on rank {rank}"#
+                    )));
+                }
+
+                file_index += empty_squares as usize;
+            } else {
+                let piece = ChessPiece::try_from(char).map_err(|_| {
+                    FenError::PiecePlacement(format!(r#"invalid piece "{char}" on rank {rank}"#))
+                })?;
+
+                if file_index >= File::SIZE {
+                    return Err(FenError::PiecePlacement(format!(
+                        "rank {rank} has more than {} squares",
+                        File::SIZE
+                    )));
+                }
 
-        for (file, char) in row.chars().enumerate() {
-            if char.is_numeric() {
-                offset += char.to_digit(10).unwrap() as usize - 1;
-                continue;
+                board.set(Square::make_square(File::from_index(file_index), rank), piece);
+                file_index += 1;
             }
 
-            board.set(
-                Square::make_square(
-                    File::from_index(file + offset),
-                    Rank::from_index(Rank::SIZE - 1 - rank),
-                ),
-                ChessPiece::try_from(char).unwrap(),
-            );
+            if file_index > File::SIZE {
+                return Err(FenError::PiecePlacement(format!(
+                    "rank {rank} has more than {} squares",
+                    File::SIZE
+                )));
+            }
+        }
+
+        if file_index != File::SIZE {
+            return Err(FenError::PiecePlacement(format!(
+                "rank {rank} has {file_index} squares instead of {}",
+                File::SIZE
+            )));
         }
     }
 
-    return board;
+    return Ok(board);
 }
 
-/// This function parses a FEN string and returns the chess game state represented by it.
+/// This function parses a FEN string and returns the `Board` represented by it.
 ///
 /// FEN is a notation standard to represent the game state of a chess game.
+/// The halfmove clock and fullmove number fields are optional and default to `0` and `1`.
+///
+/// The position is validated: both teams need exactly one king, there can't be pawns on the back
+/// ranks, the castling rights need an unmoved king and rook, the en passant square needs a pawn that
+/// just double moved and the team that is not to move can't be in check.
 ///
-/// @TODO: Add better validaton
-/// @TODO: Finish parsing every bit of info about it.
+/// ```
+/// use engine::{notations::FEN, Board, Team};
+///
+/// let board = FEN::parse(FEN::START).unwrap();
+///
+/// assert_eq!(board.team_to_move, Team::White);
+/// assert_eq!(FEN::format(&board), FEN::START);
+/// assert!(FEN::parse("8/8/8/8/8/8/8/8 w - - 0 1").is_err());
+/// ```
 ///
 /// @see [Specs](https://en.wikipedia.org/wiki/Forsyth%E2%80%93Edwards_Notation)
-pub fn gamestate_from_fen(fen_string: &str) -> anyhow::Result<Chess> {
-    let parts = Vec::from_iter(fen_string.split(" "));
+pub fn parse(fen_string: &str) -> Result<Board, FenError> {
+    let parts = fen_string.split_whitespace().collect::<Vec<_>>();
+
+    if parts.len() != 4 && parts.len() != 6 {
+        return Err(FenError::FieldCount(parts.len()));
+    }
+
+    let mut board = Board::new(parse_piece_placement(parts[0])?.0);
+
+    for team in Team::ALL {
+        match board.get_mask(Piece::King, team).count_bits() {
+            0 => return Err(FenError::MissingKing(team)),
+            1 => {}
+            _ => return Err(FenError::TooManyKings(team)),
+        }
+    }
+
+    let back_ranks = BitBoard::mask_rank(Rank::First) | BitBoard::mask_rank(Rank::Eighth);
+    if let Some(square) = (board.piece_mask(Piece::Pawn) & back_ranks).ls1b_square() {
+        return Err(FenError::PawnOnBackRank(square));
+    }
 
-    // Piece placement data: Each rank is described, starting with rank 8 and ending with rank 1, with a "/" between each one; within each rank, the contents of the squares are described in order from the a-file to the h-file. Each piece is identified by a single letter taken from the standard English names in algebraic notation (pawn = "P", knight = "N", bishop = "B", rook = "R", queen = "Q" and king = "K"). White pieces are designated using uppercase letters ("PNBRQK"), while black pieces use lowercase letters ("pnbrqk"). A set of one or more consecutive empty squares within a rank is denoted by a digit from "1" to "8", corresponding to the number of squares.
-    let board_str = parts[0];
     //  "w" means that White is to move; "b" means that Black is to move.
-    let team_to_move = match parts[1] {
-        "w" => Ok(Team::White),
-        "b" => Ok(Team::Black),
-        token => Err(format!(r#"Token "{token}" is not a valid team identifier"#)),
-    }.unwrap();
-
-    // Castling availability: If neither side has the ability to castle, this field uses the character "-". Otherwise, this field contains one or more letters: "K" if White can castle kingside, "Q" if White can castle queenside, "k" if Black can castle kingside, and "q" if Black can castle queenside. A situation that temporarily prevents castling does not prevent the use of this notation.
-    // TODO
-    let _castling_availability = parts[2];
-
-    // En passant target square: This is a square over which a pawn has just passed while moving two squares; it is given in algebraic notation. If there is no en passant target square, this field uses the character "-". This is recorded regardless of whether there is a pawn in position to capture en passant.[6] An updated version of the spec has since made it so the target square is only recorded if a legal en passant move is possible but the old version of the standard is the one most commonly used.
-    // TODO
-    let _en_passant = parts[3];
-
-    let board = board_from_fen(board_str);
-
-    Ok(Chess {
-        board: todo!(),
-        // team_to_move,
-        // halfmove_clock: parts[4].parse()?,
-        // fullmove_clock: parts[5].parse()?,
-        // en_passant: todo!(),
-    })
+    board.team_to_move = match parts[1] {
+        "w" => Team::White,
+        "b" => Team::Black,
+        token => return Err(FenError::Team(token.to_owned())),
+    };
+
+    // If neither side has the ability to castle, this field uses the character "-".
+    // Otherwise, this field contains one or more letters: "K" if White can castle kingside,
+    // "Q" if White can castle queenside, "k" if Black can castle kingside, and "q" if Black can castle queenside.
+    if parts[2] != "-" {
+        for char in parts[2].chars() {
+            let team = match char {
+                'K' | 'Q' => Team::White,
+                'k' | 'q' => Team::Black,
+                _ => {
+                    return Err(FenError::Castling(format!(
+                        r#"unknown castling character "{char}""#
+                    )))
+                }
+            };
+            let rights = match char.to_ascii_uppercase() {
+                'K' => CastleRights::KingSide,
+                _ => CastleRights::QueenSide,
+            };
+
+            if board.castle_rights(team).add(rights) == board.castle_rights(team) {
+                return Err(FenError::Castling(format!(r#"duplicate character "{char}""#)));
+            }
+
+            let backrank = team.get_backrank();
+            if board.king_square(team) != Square::make_square(File::E, backrank)
+                || (rights.unmoved_rooks(team) & board.get_mask(Piece::Rook, team)).is_empty()
+            {
+                return Err(FenError::Castling(format!(
+                    r#""{char}" needs an unmoved king and rook"#
+                )));
+            }
+
+            board.castle_rights[team.to_index()] = board.castle_rights(team).add(rights);
+        }
+    }
+
+    // This is a square over which a pawn has just passed while moving two squares.
+    // `Board` keeps track of the square of the pawn itself.
+    if parts[3] != "-" {
+        let target = parts[3]
+            .parse::<Square>()
+            .map_err(|_| FenError::EnPassant(format!(r#"invalid square "{}""#, parts[3])))?;
+
+        let opponent = !board.team_to_move;
+        let pawn_square = target.ubackward(board.team_to_move);
+
+        if target.get_rank() != opponent.get_nth_rank(3)
+            || board.piece_on(target).is_some()
+            || board.piece_on(target.ubackward(opponent)).is_some()
+            || (board.get_mask(Piece::Pawn, opponent) & BitBoard::from_square(pawn_square)).is_empty()
+        {
+            return Err(FenError::EnPassant(format!(
+                "{target} is not behind a pawn that just moved two squares"
+            )));
+        }
+
+        board.en_passant = Some(pawn_square);
+    }
+
+    if parts.len() == 6 {
+        board.halfmove_clock = parts[4]
+            .parse()
+            .map_err(|_| FenError::HalfmoveClock(parts[4].to_owned()))?;
+        board.fullmove_clock = match parts[5].parse() {
+            Ok(0) | Err(_) => return Err(FenError::FullmoveNumber(parts[5].to_owned())),
+            Ok(number) => number,
+        };
+    }
+
+    board.refresh_state();
+
+    let opponent = !board.team_to_move;
+    if !board
+        .attackers_to(board.king_square(opponent), board.team_to_move, board.all_mask())
+        .is_empty()
+    {
+        return Err(FenError::OpponentInCheck);
+    }
+
+    return Ok(board);
+}
+
+/// Formats the given `Board` as a FEN string, including the halfmove clock and fullmove number.
+pub fn format(board: &Board) -> String {
+    let mut placement = String::new();
+
+    for rank in Rank::ALL.iter().rev() {
+        let mut empty_squares = 0;
+
+        for file in File::ALL.iter() {
+            let square = Square::make_square(*file, *rank);
+
+            match board.get(square) {
+                Some(piece) => {
+                    if empty_squares != 0 {
+                        placement.push_str(&empty_squares.to_string());
+                        empty_squares = 0;
+                    }
+
+                    placement.push_str(&piece.to_string());
+                }
+                None => empty_squares += 1,
+            }
+        }
+
+        if empty_squares != 0 {
+            placement.push_str(&empty_squares.to_string());
+        }
+
+        if *rank != Rank::First {
+            placement.push('/');
+        }
+    }
+
+    let team = match board.team_to_move {
+        Team::White => "w",
+        Team::Black => "b",
+    };
+
+    let mut castling = Team::ALL
+        .iter()
+        .map(|team| board.castle_rights(*team).to_string(*team))
+        .collect::<String>();
+    if castling.is_empty() {
+        castling.push('-');
+    }
+
+    let en_passant = match board.en_passant {
+        Some(square) => square.uforward(board.team_to_move).to_string(),
+        None => "-".to_owned(),
+    };
+
+    return format!(
+        "{placement} {team} {castling} {en_passant} {} {}",
+        board.halfmove_clock, board.fullmove_clock
+    );
+}
+
+/// This function parses a FEN string and returns the chess game state represented by it.
+///
+/// @see `parse`
+pub fn gamestate_from_fen(fen_string: &str) -> Result<Chess, FenError> {
+    Ok(Chess::new(parse(fen_string)?))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        [
+            START,
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+            "rnbqkbnr/p1pppppp/8/8/PpP4P/8/1P1PPPP1/RNBQKBNR b KQkq c3 0 3",
+            "4k3/8/8/8/8/8/8/4K2R b K - 12 40",
+        ]
+        .into_iter()
+        .for_each(|fen| assert_eq!(format(&parse(fen).unwrap()), fen));
+    }
+
+    #[test]
+    fn fields() {
+        let board = parse("r3k2r/8/8/8/3pP3/8/8/R3K2R b Qk e3 7 21").unwrap();
+
+        assert_eq!(board.team_to_move, Team::Black);
+        assert_eq!(board.castle_rights(Team::White), CastleRights::QueenSide);
+        assert_eq!(board.castle_rights(Team::Black), CastleRights::KingSide);
+        assert_eq!(board.en_passant, Some(Square::E4));
+        assert_eq!(board.halfmove_clock, 7);
+        assert_eq!(board.fullmove_clock, 21);
+
+        let board = parse("4k3/8/8/8/8/8/8/4K3 w - -").unwrap();
+        assert_eq!((board.halfmove_clock, board.fullmove_clock), (0, 1));
+    }
+
+    #[test]
+    fn check_state() {
+        let board = parse("4k3/8/8/8/1b6/8/3P4/4K2r w - - 0 1").unwrap();
+
+        assert_eq!(board.checkers, BitBoard::from_square(Square::H1));
+        assert_eq!(board.pinned, BitBoard::from_square(Square::D2));
+    }
+
+    #[test]
+    fn invalid() {
+        [
+            ("", FenError::FieldCount(0)),
+            ("8/8/8/8/8/8/8/8 w - - 0 1", FenError::MissingKing(Team::White)),
+            ("4k3/8/8/8/8/8/8/3KK3 w - - 0 1", FenError::TooManyKings(Team::White)),
+            ("4k3/8/8/8/8/8/8/4K2P w - - 0 1", FenError::PawnOnBackRank(Square::H1)),
+            ("4k3/8/8/8/8/8/8/4K3 x - - 0 1", FenError::Team("x".to_owned())),
+            ("4k3/8/8/8/8/8/8/4K3 w - - x 1", FenError::HalfmoveClock("x".to_owned())),
+            ("4k3/8/8/8/8/8/8/4K3 w - - 0 0", FenError::FullmoveNumber("0".to_owned())),
+            ("4k2R/8/8/8/8/8/8/4K3 w - - 0 1", FenError::OpponentInCheck),
+        ]
+        .into_iter()
+        .for_each(|(fen, error)| assert_eq!(parse(fen).unwrap_err(), error));
+
+        [
+            "4k3/8/8/8/8/8/8/4K3/8 w - - 0 1",
+            "4k3/8/8/8/8/8/8/4K4 w - - 0 1",
+            "4k3/8/8/8/8/8/8/4K2 w - - 0 1",
+            "4k3/8/8/8/8/8/8/4X3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/09 w - - 0 1",
+        ]
+        .into_iter()
+        .for_each(|fen| assert!(matches!(parse(fen), Err(FenError::PiecePlacement(_)))));
+
+        ["4k3/8/8/8/8/8/8/4K3 w K - 0 1", "4k3/8/8/8/8/8/8/R3K3 w KK - 0 1", "4k3/8/8/8/8/8/8/R3K3 w X - 0 1"]
+            .into_iter()
+            .for_each(|fen| assert!(matches!(parse(fen), Err(FenError::Castling(_)))));
+
+        ["4k3/8/8/8/8/8/8/4K3 w - e6 0 1", "4k3/8/8/8/4P3/8/8/4K3 b - e4 0 1", "4k3/8/8/8/4P3/8/8/4K3 b - z9 0 1"]
+            .into_iter()
+            .for_each(|fen| assert!(matches!(parse(fen), Err(FenError::EnPassant(_)))));
+    }
 }
@@ -2,42 +2,874 @@
    http://www.saremba.de/chessgml/standards/pgn/pgn-complete.htm
 */
 
+use std::{fmt::Display, iter::Peekable, str::Chars};
 
-// use crate::game::CHESSBOARD_WIDTH;
-//
-//
-// /** Generates a chessboard position string from the position index (0..64).
-//  * Example: index `56` = `"a7"` = top left corner */
-// pub fn position_to_string(position: usize) -> String {
-//     let x = position % CHESSBOARD_WIDTH;
-//     let y = position / CHESSBOARD_WIDTH;
-//
-//     return format!(
-//         "{}{y}",
-//         char::from_digit((10 + x) as u32, 36).unwrap().to_string()
-//     );
-// }
-//
-// pub fn position_from_string(position: &str) -> usize {
-//     let mut chars = position.chars();
-//
-//     let x = chars.next().unwrap() as usize - 97;
-//     let y = chars.next().unwrap().to_digit(10).unwrap() as usize;
-//
-//     x + y * CHESSBOARD_WIDTH
-// }
-//
-// #[cfg(test)]
-// mod tests {
-//     use super::*;
-//
-//     #[test]
-//     fn positions() {
-//         (0..64).for_each(|index| {
-//             let str_repr = position_to_string(index);
-//             let num_repr = position_from_string(&str_repr);
-//
-//             assert_eq!(index, num_repr);
-//         });
-//     }
-// }
+use crate::{
+    game::{Board, Move},
+    notations::{
+        FEN::{self, FenError},
+        SAN::{self, SanError},
+    },
+    primitives::team::Team,
+};
+
+/// The tags every exported game starts with, in order, with their default values
+pub const SEVEN_TAG_ROSTER: [(&str, &str); 7] = [
+    ("Event", "?"),
+    ("Site", "?"),
+    ("Date", "????.??.??"),
+    ("Round", "?"),
+    ("White", "?"),
+    ("Black", "?"),
+    ("Result", "*"),
+];
+
+/// Export format lines are wrapped so they never exceed this amount of characters
+pub const MAX_LINE_LENGTH: usize = 80;
+
+/// The outcome recorded by a game termination marker
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum GameResult {
+    /// The `Team` that won the game (`1-0` or `0-1`)
+    Win(Team),
+    /// `1/2-1/2`
+    Draw,
+    /// The game is still in progress, was abandoned or its result is unknown (`*`)
+    #[default]
+    Ongoing,
+}
+
+impl GameResult {
+    /// Parses a game termination marker
+    ///
+    /// ```
+    /// use engine::{notations::PGN::GameResult, Team};
+    ///
+    /// assert_eq!(GameResult::from_marker("0-1"), Some(GameResult::Win(Team::Black)));
+    /// assert_eq!(GameResult::from_marker("1/2-1/2"), Some(GameResult::Draw));
+    /// assert_eq!(GameResult::from_marker("1-1"), None);
+    /// ```
+    pub fn from_marker(marker: &str) -> Option<Self> {
+        match marker {
+            "1-0" => Some(Self::Win(Team::White)),
+            "0-1" => Some(Self::Win(Team::Black)),
+            "1/2-1/2" => Some(Self::Draw),
+            "*" => Some(Self::Ongoing),
+            _ => None,
+        }
+    }
+}
+
+impl Display for GameResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Win(Team::White) => "1-0",
+            Self::Win(Team::Black) => "0-1",
+            Self::Draw => "1/2-1/2",
+            Self::Ongoing => "*",
+        })
+    }
+}
+
+/// A move of the movetext, with its annotations
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PgnMove {
+    pub chess_move: Move,
+    /// Numeric Annotation Glyphs (`$1`), move suffix annotations (`!`, `?!`, ...) are stored as their NAG equivalent
+    pub nags: Vec<u8>,
+    /// Comments that come before the move, only used at the start of a game or variation
+    pub comments_before: Vec<String>,
+    /// Comments that come after the move
+    pub comments: Vec<String>,
+    /// Recursive Annotation Variations, played instead of this move
+    pub variations: Vec<Vec<PgnMove>>,
+}
+
+impl PgnMove {
+    pub fn new(chess_move: Move) -> Self {
+        Self {
+            chess_move,
+            nags: Vec::new(),
+            comments_before: Vec::new(),
+            comments: Vec::new(),
+            variations: Vec::new(),
+        }
+    }
+}
+
+/// A complete game: its tag pairs and the main line of moves with their annotations
+#[derive(Debug, Clone)]
+pub struct PgnGame {
+    /// All the tag pairs in the order they were read
+    pub tags: Vec<(String, String)>,
+    /// The position the game starts from, set by the `FEN` tag
+    pub starting_position: Board,
+    pub moves: Vec<PgnMove>,
+    pub result: GameResult,
+}
+
+impl PgnGame {
+    /// Creates a game without any tags or moves, starting from the given position
+    pub fn new(starting_position: Board) -> Self {
+        Self {
+            tags: Vec::new(),
+            starting_position,
+            moves: Vec::new(),
+            result: GameResult::Ongoing,
+        }
+    }
+
+    /// Gets the value of the tag with the given `name`
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Sets the value of the tag with the given `name`, keeping its position if it was already set
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self.tags.iter_mut().find(|(tag, _)| tag == name) {
+            Some((_, old_value)) => *old_value = value.to_owned(),
+            None => self.tags.push((name.to_owned(), value.to_owned())),
+        }
+    }
+
+    /// Iterates over the moves of the main line
+    pub fn mainline(&self) -> impl Iterator<Item = Move> + '_ {
+        self.moves.iter().map(|pgn_move| pgn_move.chess_move)
+    }
+
+    /// The position reached after playing the main line
+    pub fn final_position(&self) -> Board {
+        let mut board = self.starting_position;
+        self.mainline().for_each(|chess_move| {
+            board.make_move(chess_move);
+        });
+
+        return board;
+    }
+}
+
+impl Default for PgnGame {
+    fn default() -> Self {
+        Self::new(Board::default())
+    }
+}
+
+impl Display for PgnGame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&format(self))
+    }
+}
+
+// [Errors]
+
+/// Everything that can go wrong while reading PGN
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PgnErrorKind {
+    /// A character that can't start any token
+    UnexpectedChar(char),
+    /// A token that is not valid at its place
+    UnexpectedToken(String),
+    /// The text ended in the middle of a string, comment, tag or variation
+    UnexpectedEnd,
+    /// A NAG that is not a number between 0 and 255
+    InvalidNag(String),
+    /// The `FEN` tag is not valid
+    Fen(FenError),
+    /// A move of the movetext could not be resolved
+    San(SanError),
+}
+
+/// An error while reading PGN, with the (1 based) line and column where it happened
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PgnError {
+    pub line: usize,
+    pub column: usize,
+    pub kind: PgnErrorKind,
+}
+
+impl Display for PgnError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}: ", self.line, self.column)?;
+        match &self.kind {
+            PgnErrorKind::UnexpectedChar(char) => write!(f, "Unexpected character {char:?}"),
+            PgnErrorKind::UnexpectedToken(token) => write!(f, r#"Unexpected token "{token}""#),
+            PgnErrorKind::UnexpectedEnd => write!(f, "Unexpected end of input"),
+            PgnErrorKind::InvalidNag(nag) => write!(f, r#"Invalid NAG "{nag}""#),
+            PgnErrorKind::Fen(error) => write!(f, "Invalid FEN tag: {error}"),
+            PgnErrorKind::San(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl std::error::Error for PgnError {}
+
+// [Reading]
+
+/// Parses the first game of the PGN text.
+///
+/// ```
+/// use engine::{notations::PGN::{self, GameResult}, Team};
+///
+/// let game = PGN::parse(r#"
+/// [Event "Casual game"]
+/// [Result "1-0"]
+///
+/// 1. e4 e5 2. Bc4 Nc6 3. Qh5 Nf6?? 4. Qxf7# 1-0
+/// "#).unwrap();
+///
+/// assert_eq!(game.tag("Event"), Some("Casual game"));
+/// assert_eq!(game.moves.len(), 7);
+/// assert_eq!(game.moves[5].nags, vec![4]);
+/// assert_eq!(game.result, GameResult::Win(Team::White));
+/// ```
+pub fn parse(pgn: &str) -> Result<PgnGame, PgnError> {
+    let mut reader = PgnReader::new(pgn);
+
+    match reader.next() {
+        Some(game) => game,
+        None => Err(reader.parser.error(PgnErrorKind::UnexpectedEnd)),
+    }
+}
+
+/// Parses all the games of the PGN text, stopping at the first error.
+pub fn parse_all(pgn: &str) -> Result<Vec<PgnGame>, PgnError> {
+    PgnReader::new(pgn).collect()
+}
+
+/// Iterates over the games of a PGN text.
+///
+/// When a game can't be read, its error is returned and the reader skips to the start of the next game.
+///
+/// ```
+/// use engine::notations::PGN::PgnReader;
+///
+/// let games = PgnReader::new("1. e4 e5 1-0\n\n1. e4 e4 0-1\n\n1. d4 *\n").collect::<Vec<_>>();
+///
+/// assert!(games[0].is_ok());
+/// assert_eq!(games[1].as_ref().unwrap_err().line, 3);
+/// assert!(games[2].is_ok());
+/// ```
+pub struct PgnReader<'a> {
+    parser: Parser<'a>,
+}
+
+impl<'a> PgnReader<'a> {
+    pub fn new(pgn: &'a str) -> Self {
+        Self {
+            parser: Parser {
+                lexer: Lexer::new(pgn),
+                peeked: None,
+            },
+        }
+    }
+}
+
+impl Iterator for PgnReader<'_> {
+    type Item = Result<PgnGame, PgnError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.parser.parse_game() {
+            Ok(game) => game.map(Ok),
+            Err(error) => {
+                self.parser.skip_to_next_game();
+                Some(Err(error))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    OpenBracket,
+    CloseBracket,
+    OpenParen,
+    CloseParen,
+    Period,
+    Asterisk,
+    String(String),
+    Symbol(String),
+    Nag(String),
+    Comment(String),
+}
+
+impl Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::OpenBracket => write!(f, "["),
+            Self::CloseBracket => write!(f, "]"),
+            Self::OpenParen => write!(f, "("),
+            Self::CloseParen => write!(f, ")"),
+            Self::Period => write!(f, "."),
+            Self::Asterisk => write!(f, "*"),
+            Self::String(string) => write!(f, "{string:?}"),
+            Self::Symbol(symbol) => write!(f, "{symbol}"),
+            Self::Nag(nag) => write!(f, "${nag}"),
+            Self::Comment(comment) => write!(f, "{{{comment}}}"),
+        }
+    }
+}
+
+/// The (1 based) line and column of a token
+type Position = (usize, usize);
+
+struct Lexer<'a> {
+    chars: Peekable<Chars<'a>>,
+    line: usize,
+    column: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(pgn: &'a str) -> Self {
+        Self {
+            chars: pgn.chars().peekable(),
+            line: 1,
+            column: 1,
+        }
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let char = self.chars.next()?;
+        if char == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+
+        return Some(char);
+    }
+
+    fn skip_line(&mut self) -> String {
+        let mut line = String::new();
+        while let Some(char) = self.bump() {
+            if char == '\n' {
+                break;
+            }
+            line.push(char);
+        }
+
+        return line;
+    }
+
+    fn is_symbol_char(char: char) -> bool {
+        // Move suffix annotations are read as part of the move
+        char.is_ascii_alphanumeric() || "_+#=:-/!?".contains(char)
+    }
+
+    fn next_token(&mut self) -> Result<Option<(Token, Position)>, PgnError> {
+        loop {
+            match self.chars.peek() {
+                None => return Ok(None),
+                Some(char) if char.is_whitespace() => {
+                    self.bump();
+                }
+                // Escape mechanism: lines starting with "%" are ignored
+                Some('%') if self.column == 1 => {
+                    self.skip_line();
+                }
+                Some(_) => break,
+            }
+        }
+
+        let position = (self.line, self.column);
+        let error = |kind| PgnError {
+            line: position.0,
+            column: position.1,
+            kind,
+        };
+
+        let token = match self.bump().expect("A character to be peeked") {
+            '[' => Token::OpenBracket,
+            ']' => Token::CloseBracket,
+            '(' => Token::OpenParen,
+            ')' => Token::CloseParen,
+            '.' => Token::Period,
+            '*' => Token::Asterisk,
+            '"' => {
+                let mut string = String::new();
+                loop {
+                    match self.bump() {
+                        Some('"') => break,
+                        Some('\\') => match self.bump() {
+                            Some(char) => string.push(char),
+                            None => return Err(error(PgnErrorKind::UnexpectedEnd)),
+                        },
+                        Some(char) => string.push(char),
+                        None => return Err(error(PgnErrorKind::UnexpectedEnd)),
+                    }
+                }
+                Token::String(string)
+            }
+            '{' => {
+                let mut comment = String::new();
+                loop {
+                    match self.bump() {
+                        Some('}') => break,
+                        Some(char) => comment.push(char),
+                        None => return Err(error(PgnErrorKind::UnexpectedEnd)),
+                    }
+                }
+                Token::Comment(comment)
+            }
+            ';' => Token::Comment(self.skip_line()),
+            '$' => {
+                let mut nag = String::new();
+                while let Some(char) = self.chars.next_if(char::is_ascii_digit) {
+                    self.column += 1;
+                    nag.push(char);
+                }
+                Token::Nag(nag)
+            }
+            char if Self::is_symbol_char(char) => {
+                let mut symbol = char.to_string();
+                while let Some(char) = self.chars.next_if(|char| Self::is_symbol_char(*char)) {
+                    self.column += 1;
+                    symbol.push(char);
+                }
+                Token::Symbol(symbol)
+            }
+            char => return Err(error(PgnErrorKind::UnexpectedChar(char))),
+        };
+
+        return Ok(Some((token, position)));
+    }
+}
+
+struct Parser<'a> {
+    lexer: Lexer<'a>,
+    peeked: Option<(Token, Position)>,
+}
+
+impl Parser<'_> {
+    fn peek(&mut self) -> Result<Option<&Token>, PgnError> {
+        if self.peeked.is_none() {
+            self.peeked = self.lexer.next_token()?;
+        }
+
+        return Ok(self.peeked.as_ref().map(|(token, _)| token));
+    }
+
+    fn next(&mut self) -> Result<(Token, Position), PgnError> {
+        match self.peeked.take() {
+            Some(token) => Ok(token),
+            None => self
+                .lexer
+                .next_token()?
+                .ok_or_else(|| self.error(PgnErrorKind::UnexpectedEnd)),
+        }
+    }
+
+    /// Creates an error at the current position of the lexer
+    fn error(&self, kind: PgnErrorKind) -> PgnError {
+        PgnError {
+            line: self.lexer.line,
+            column: self.lexer.column,
+            kind,
+        }
+    }
+
+    /// Drops the rest of the current game, up to its termination marker or the tags of the next game
+    fn skip_to_next_game(&mut self) {
+        self.peeked = None;
+        loop {
+            match self.lexer.next_token() {
+                Ok(None) => return,
+                Ok(Some((Token::Asterisk, _))) => return,
+                Ok(Some((Token::Symbol(symbol), _))) if GameResult::from_marker(&symbol).is_some() => return,
+                Ok(Some((Token::OpenBracket, position))) if position.1 == 1 => {
+                    self.peeked = Some((Token::OpenBracket, position));
+                    return;
+                }
+                Ok(Some(_)) => {}
+                Err(_) => {
+                    self.lexer.skip_line();
+                }
+            }
+        }
+    }
+
+    fn parse_game(&mut self) -> Result<Option<PgnGame>, PgnError> {
+        if self.peek()?.is_none() {
+            return Ok(None);
+        }
+
+        let mut tags = Vec::new();
+        let mut fen = None;
+        while self.peek()? == Some(&Token::OpenBracket) {
+            self.next()?;
+            let (name, position) = match self.next()? {
+                (Token::Symbol(name), position) => (name, position),
+                (token, (line, column)) => return Err(unexpected_token(token, line, column)),
+            };
+            let value = match self.next()? {
+                (Token::String(value), _) => value,
+                (token, (line, column)) => return Err(unexpected_token(token, line, column)),
+            };
+            match self.next()? {
+                (Token::CloseBracket, _) => {}
+                (token, (line, column)) => return Err(unexpected_token(token, line, column)),
+            };
+
+            if name == "FEN" {
+                fen = Some((value.clone(), position));
+            }
+            tags.push((name, value));
+        }
+
+        let starting_position = match fen {
+            Some((fen, (line, column))) => Board::from_fen(&fen).map_err(|error| PgnError {
+                line,
+                column,
+                kind: PgnErrorKind::Fen(error),
+            })?,
+            None => Board::default(),
+        };
+
+        let (moves, result) = self.parse_line(starting_position, false)?;
+
+        let mut game = PgnGame {
+            tags,
+            starting_position,
+            moves,
+            result: GameResult::Ongoing,
+        };
+        // Without a termination marker, fallback to the "Result" tag
+        game.result = result
+            .or_else(|| game.tag("Result").and_then(GameResult::from_marker))
+            .unwrap_or_default();
+
+        return Ok(Some(game));
+    }
+
+    /// Parses the moves of a line (the main line or a variation), replaying them from the `board`
+    fn parse_line(
+        &mut self,
+        mut board: Board,
+        is_variation: bool,
+    ) -> Result<(Vec<PgnMove>, Option<GameResult>), PgnError> {
+        let mut moves: Vec<PgnMove> = Vec::new();
+        let mut comments_before = Vec::new();
+        // The position before the last move, where its variations start from
+        let mut previous_board = board;
+
+        loop {
+            match self.peek()? {
+                None if is_variation => return Err(self.error(PgnErrorKind::UnexpectedEnd)),
+                // The game ended without a termination marker
+                None | Some(Token::OpenBracket) if !is_variation => return Ok((moves, None)),
+                _ => {}
+            }
+
+            let (token, (line, column)) = self.next()?;
+            match token {
+                Token::Comment(comment) => {
+                    let comment = comment.split_whitespace().collect::<Vec<_>>().join(" ");
+                    match moves.last_mut() {
+                        Some(last_move) => last_move.comments.push(comment),
+                        None => comments_before.push(comment),
+                    }
+                }
+                Token::Nag(nag) => {
+                    let nag = nag.parse().map_err(|_| PgnError {
+                        line,
+                        column,
+                        kind: PgnErrorKind::InvalidNag(nag),
+                    })?;
+                    match moves.last_mut() {
+                        Some(last_move) => last_move.nags.push(nag),
+                        None => return Err(unexpected_token(Token::Nag(nag.to_string()), line, column)),
+                    }
+                }
+                Token::OpenParen => {
+                    if moves.is_empty() {
+                        return Err(unexpected_token(token, line, column));
+                    }
+
+                    let (variation, _) = self.parse_line(previous_board, true)?;
+                    if !variation.is_empty() {
+                        moves.last_mut().unwrap().variations.push(variation);
+                    }
+                }
+                Token::CloseParen if is_variation => return Ok((moves, None)),
+                // Move number indications
+                Token::Period => {}
+                Token::Symbol(symbol) if symbol.chars().all(|char| char.is_ascii_digit()) => {}
+                Token::Asterisk if !is_variation => return Ok((moves, Some(GameResult::Ongoing))),
+                Token::Symbol(symbol) if !is_variation && GameResult::from_marker(&symbol).is_some() => {
+                    return Ok((moves, GameResult::from_marker(&symbol)));
+                }
+                Token::Symbol(symbol) => {
+                    let san = symbol.trim_end_matches(|char| char == '!' || char == '?');
+                    let chess_move = SAN::parse(&board, san).map_err(|error| PgnError {
+                        line,
+                        column,
+                        kind: PgnErrorKind::San(error),
+                    })?;
+
+                    let mut pgn_move = PgnMove::new(chess_move);
+                    pgn_move.comments_before = std::mem::take(&mut comments_before);
+                    pgn_move.nags.extend(suffix_annotation_nag(&symbol[san.len()..]));
+                    moves.push(pgn_move);
+
+                    previous_board = board;
+                    board.make_move(chess_move);
+                }
+                token => return Err(unexpected_token(token, line, column)),
+            }
+        }
+    }
+}
+
+fn unexpected_token(token: Token, line: usize, column: usize) -> PgnError {
+    PgnError {
+        line,
+        column,
+        kind: PgnErrorKind::UnexpectedToken(token.to_string()),
+    }
+}
+
+/// Converts a move suffix annotation to its NAG
+fn suffix_annotation_nag(suffix: &str) -> Option<u8> {
+    match suffix {
+        "!" => Some(1),
+        "?" => Some(2),
+        "!!" => Some(3),
+        "??" => Some(4),
+        "!?" => Some(5),
+        "?!" => Some(6),
+        _ => None,
+    }
+}
+
+// [Writing]
+
+/// Formats the game in PGN export format.
+///
+/// The seven tag roster comes first, followed by the other tags and the movetext wrapped at `MAX_LINE_LENGTH` columns.
+///
+/// ```
+/// use engine::notations::PGN;
+///
+/// let game = PGN::parse("1. e4 {Best by test} (1. d4 d5) 1... e5 $1 2. Nf3 1-0").unwrap();
+///
+/// assert_eq!(PGN::format(&game), r#"[Event "?"]
+/// [Site "?"]
+/// [Date "????.??.??"]
+/// [Round "?"]
+/// [White "?"]
+/// [Black "?"]
+/// [Result "1-0"]
+///
+/// 1. e4 {Best by test} (1. d4 d5) 1... e5 $1 2. Nf3 1-0
+/// "#);
+/// ```
+pub fn format(game: &PgnGame) -> String {
+    let mut pgn = String::new();
+
+    let mut write_tag = |name: &str, value: &str| {
+        let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+        pgn.push_str(&format!("[{name} \"{value}\"]\n"));
+    };
+
+    for (name, default) in SEVEN_TAG_ROSTER {
+        match name {
+            "Result" => write_tag(name, &game.result.to_string()),
+            _ => write_tag(name, game.tag(name).unwrap_or(default)),
+        }
+    }
+
+    if game.tag("FEN").is_none() && game.starting_position.to_fen() != FEN::START {
+        write_tag("SetUp", "1");
+        write_tag("FEN", &game.starting_position.to_fen());
+    }
+
+    game.tags
+        .iter()
+        .filter(|(name, _)| SEVEN_TAG_ROSTER.iter().all(|(roster_name, _)| roster_name != name))
+        .for_each(|(name, value)| write_tag(name, value));
+
+    pgn.push('\n');
+
+    let mut tokens = Vec::new();
+    write_moves(&mut tokens, game.starting_position, &game.moves);
+    tokens.push(game.result.to_string());
+
+    let mut line = String::new();
+    for token in tokens {
+        if !line.is_empty() && line.len() + 1 + token.len() > MAX_LINE_LENGTH {
+            pgn.push_str(&line);
+            pgn.push('\n');
+            line.clear();
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(&token);
+    }
+    pgn.push_str(&line);
+    pgn.push('\n');
+
+    return pgn;
+}
+
+/// Formats all the games in PGN export format, separated by an empty line
+pub fn format_all<'a>(games: impl IntoIterator<Item = &'a PgnGame>) -> String {
+    games.into_iter().map(format).collect::<Vec<_>>().join("\n")
+}
+
+/// Pushes the movetext tokens of the `moves` played from `board`
+fn write_moves(tokens: &mut Vec<String>, mut board: Board, moves: &[PgnMove]) {
+    // Black moves only need a move number after a comment or variation
+    let mut needs_move_number = true;
+
+    let write_comment = |tokens: &mut Vec<String>, comment: &str| {
+        let mut words = comment.split_whitespace().map(str::to_owned).collect::<Vec<_>>();
+        match words.as_mut_slice() {
+            [] => tokens.push("{}".to_owned()),
+            [first, ..] => {
+                first.insert(0, '{');
+                words.last_mut().unwrap().push('}');
+                tokens.extend(words);
+            }
+        }
+    };
+
+    for pgn_move in moves {
+        for comment in &pgn_move.comments_before {
+            write_comment(tokens, comment);
+            needs_move_number = true;
+        }
+
+        match board.team_to_move {
+            Team::White => tokens.push(format!("{}.", board.fullmove_clock)),
+            Team::Black if needs_move_number => tokens.push(format!("{}...", board.fullmove_clock)),
+            Team::Black => {}
+        }
+        tokens.push(SAN::format(&board, pgn_move.chess_move));
+        tokens.extend(pgn_move.nags.iter().map(|nag| format!("${nag}")));
+        needs_move_number = false;
+
+        for comment in &pgn_move.comments {
+            write_comment(tokens, comment);
+            needs_move_number = true;
+        }
+
+        for variation in pgn_move.variations.iter().filter(|variation| !variation.is_empty()) {
+            let start = tokens.len();
+            write_moves(tokens, board, variation);
+            tokens[start].insert(0, '(');
+            tokens.last_mut().unwrap().push(')');
+            needs_move_number = true;
+        }
+
+        board.make_move(pgn_move.chess_move);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::primitives::board::Square;
+
+    const GAME: &str = r#"[Event "F/S Return Match"]
+[Site "Belgrade, Serbia JUG"]
+[Date "1992.11.04"]
+[Round "29"]
+[White "Fischer, Robert J."]
+[Black "Spassky, Boris V."]
+[Result "1/2-1/2"]
+
+1. e4 e5 2. Nf3 Nc6 3. Bb5 {This opening is called the Ruy Lopez.} 3... a6 4.
+Ba4 Nf6 5. O-O Be7 6. Re1 b5 7. Bb3 d6 8. c3 O-O 9. h3 Nb8 10. d4 Nbd7 11. c4 c6
+12. cxb5 axb5 13. Nc3 Bb7 14. Bg5 b4 15. Nb1 h6 16. Bh4 c5 17. dxe5 Nxe4 18.
+Bxe7 Qxe7 19. exd6 Qf6 20. Nbd2 Nxd6 21. Nc4 Nxc4 22. Bxc4 Nb6 23. Ne5 Rae8 24.
+Bxf7+ Rxf7 25. Nxf7 Rxe1+ 26. Qxe1 Kxf7 27. Qe3 Qg5 28. Qxg5 hxg5 29. b3 Ke6 30.
+a3 Kd6 31. axb4 cxb4 32. Ra5 Nd5 33. f3 Bc8 34. Kf2 Bf5 35. Ra7 g6 36. Ra6+ Kc5
+37. Ke1 Nf4 38. g3 Nxh3 39. Kd2 Kb5 40. Rd6 Kc5 41. Ra6 Nf2 42. g4 Bd3 43. Re6
+1/2-1/2
+"#;
+
+    #[test]
+    fn round_trip() {
+        let game = parse(GAME).unwrap();
+
+        assert_eq!(game.tag("White"), Some("Fischer, Robert J."));
+        assert_eq!(game.moves.len(), 85);
+        assert_eq!(game.moves[4].comments, vec!["This opening is called the Ruy Lopez."]);
+        assert_eq!(game.result, GameResult::Draw);
+        assert_eq!(
+            game.final_position().to_fen(),
+            "8/8/4R1p1/2k3p1/1p4P1/1P1b1P2/3K1n2/8 b - - 2 43"
+        );
+
+        assert_eq!(format(&game), GAME);
+        assert!(format(&game).lines().all(|line| line.len() <= MAX_LINE_LENGTH));
+    }
+
+    #[test]
+    fn variations() {
+        let pgn = r#"[Event "Test"]
+[SetUp "1"]
+[FEN "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1"]
+
+{Start} 1. e4 $14 (1. e3 Kd7 (1... Ke7 2. Kf2) 2. Ke2 {Slow}) 1... Kd7!? ; Eol comment
+2. Ke2 *"#;
+        let game = parse(pgn).unwrap();
+
+        assert_eq!(game.starting_position.to_fen(), "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1");
+        assert_eq!(game.moves[0].comments_before, vec!["Start"]);
+        assert_eq!(game.moves[0].nags, vec![14]);
+        assert_eq!(game.moves[1].nags, vec![5]);
+        assert_eq!(game.moves[1].comments, vec!["Eol comment"]);
+        assert_eq!(game.result, GameResult::Ongoing);
+
+        let variation = &game.moves[0].variations[0];
+        assert_eq!(variation[0].chess_move, Move::new(Square::E2, Square::E3));
+        assert_eq!(variation[1].variations[0][0].chess_move, Move::new(Square::E8, Square::E7));
+        assert_eq!(variation[2].comments, vec!["Slow"]);
+
+        let exported = format(&game);
+        assert!(exported.ends_with(
+            "\n\n{Start} 1. e4 $14 (1. e3 Kd7 (1... Ke7 2. Kf2) 2. Ke2 {Slow}) 1... Kd7 $5 {Eol\ncomment} 2. Ke2 *\n"
+        ));
+
+        let reparsed = parse(&exported).unwrap();
+        assert_eq!(reparsed.moves, game.moves);
+        assert_eq!(format(&reparsed), exported);
+    }
+
+    #[test]
+    fn multiple_games() {
+        let games = parse_all(&format!("{GAME}\n1. d4 d5 0-1\n\n[Event \"Quoted \\\"name\\\"\"]\n1. c4 1-0\n")).unwrap();
+
+        assert_eq!(games.len(), 3);
+        assert_eq!(games[1].result, GameResult::Win(Team::Black));
+        assert_eq!(games[2].tag("Event"), Some(r#"Quoted "name""#));
+        assert!(format_all(&games).contains(r#"[Event "Quoted \"name\""]"#));
+    }
+
+    #[test]
+    fn errors() {
+        let error_at = |pgn: &str| {
+            let error = parse(pgn).unwrap_err();
+            (error.line, error.column, error.kind)
+        };
+
+        assert_eq!(
+            error_at("1. e4 e5\n2. Ke3 *"),
+            (2, 4, PgnErrorKind::San(SanError::IllegalMove("Ke3".to_owned())))
+        );
+        assert_eq!(error_at("[Event \"Unterminated]\n"), (1, 8, PgnErrorKind::UnexpectedEnd));
+        assert_eq!(error_at("1. e4 {Unterminated"), (1, 7, PgnErrorKind::UnexpectedEnd));
+        assert_eq!(error_at("1. e4 (1. d4"), (1, 13, PgnErrorKind::UnexpectedEnd));
+        assert_eq!(error_at("1. e4 e5 &"), (1, 10, PgnErrorKind::UnexpectedChar('&')));
+        assert_eq!(error_at("(1. d4) *"), (1, 1, PgnErrorKind::UnexpectedToken("(".to_owned())));
+        assert_eq!(error_at("[Event]"), (1, 7, PgnErrorKind::UnexpectedToken("]".to_owned())));
+        assert!(matches!(error_at("[FEN \"8/8 w\"]\n*").2, PgnErrorKind::Fen(_)));
+        assert_eq!(error_at("1. e4 $300"), (1, 7, PgnErrorKind::InvalidNag("300".to_owned())));
+        assert_eq!(error_at("   "), (1, 4, PgnErrorKind::UnexpectedEnd));
+    }
+}
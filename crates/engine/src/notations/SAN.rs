@@ -0,0 +1,351 @@
+use std::fmt::Display;
+
+use crate::{
+    bitboard::BitBoard,
+    game::{Board, Move},
+    primitives::{
+        board::{File, Rank, Square},
+        piece::Piece,
+    },
+    utils::enums::StrEnum,
+};
+
+/// Everything that can go wrong while parsing a SAN move.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SanError {
+    /// The SAN string doesn't follow the notation
+    Syntax(String),
+    /// No legal move matches the SAN string
+    IllegalMove(String),
+    /// More than one legal move matches the SAN string
+    AmbiguousMove(String),
+}
+
+impl Display for SanError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Syntax(san) => write!(f, r#""{san}" is not a valid SAN move"#),
+            Self::IllegalMove(san) => write!(f, r#""{san}" is not a legal move"#),
+            Self::AmbiguousMove(san) => write!(f, r#""{san}" matches more than one legal move"#),
+        }
+    }
+}
+
+impl std::error::Error for SanError {}
+
+/// Parses a Standard Algebraic Notation (SAN) string into a legal `Move` of the given `board`.
+///
+/// SAN is used to represent chess moves with as litle characters as needed.
+/// It relies heavilly on the context of the game state, so the move is resolved against the legal moves.
+///
+/// Check (`+`) and mate (`#`) suffixes, annotations (`!`, `?`) and an `e.p.` suffix are accepted and ignored.
+///
+/// ```
+/// use engine::{notations::SAN, Board, Move, Square};
+///
+/// let board = Board::default();
+///
+/// assert_eq!(SAN::parse(&board, "Nf3").unwrap(), Move::new(Square::G1, Square::F3));
+/// assert_eq!(SAN::parse(&board, "e4!?").unwrap(), Move::new(Square::E2, Square::E4));
+/// assert!(SAN::parse(&board, "e5").is_err());
+/// ```
+///
+/// @see https://www.chessprogramming.org/Algebraic_Chess_Notation#SAN
+/// @see https://en.wikipedia.org/wiki/Algebraic_notation_(chess)
+pub fn parse(board: &Board, san_move: &str) -> Result<Move, SanError> {
+    let syntax_error = || SanError::Syntax(san_move.to_owned());
+
+    let mut san = san_move.trim();
+    san = san.strip_suffix("e.p.").unwrap_or(san).trim_end();
+    san = san.trim_end_matches(|char| matches!(char, '+' | '#' | '!' | '?'));
+
+    // Castling moves
+    if let Some(kingside) = match san {
+        "O-O" | "0-0" => Some(true),
+        "O-O-O" | "0-0-0" => Some(false),
+        _ => None,
+    } {
+        let origin = board.king_square(board.team_to_move);
+        let destination = match kingside {
+            true => origin.uright().uright(),
+            false => origin.uleft().uleft(),
+        };
+
+        return find_move(board, san_move, |chess_move| {
+            chess_move.origin == origin
+                && chess_move.destination == destination
+                && board.piece_on(origin) == Some(Piece::King)
+        });
+    }
+
+    let mut chars = san.chars().collect::<Vec<_>>();
+
+    // Example full move: Nc3xd5
+    // The origin (c3) is left (fully/partially: rank or file) out if the there are no other pieces that could go to the destination square
+    let piece = match chars.first() {
+        Some(first_char) if first_char.is_ascii_uppercase() => {
+            let piece = Piece::from_str(&first_char.to_string()).map_err(|_| syntax_error())?;
+            chars.remove(0);
+            piece
+        }
+        Some(_) => Piece::Pawn,
+        None => return Err(syntax_error()),
+    };
+
+    // E.g.: e8=Q or e8Q
+    let promotion = match chars.last() {
+        Some(last_char) if last_char.is_ascii_uppercase() => {
+            let promotion = Piece::from_str(&last_char.to_string())
+                .ok()
+                .filter(|promotion| Piece::PROMOTION_TARGETS.contains(promotion))
+                .ok_or_else(syntax_error)?;
+            chars.pop();
+            if chars.last() == Some(&'=') {
+                chars.pop();
+            }
+            Some(promotion)
+        }
+        _ => None,
+    };
+
+    // E.g. ..e7
+    // NOTE: .pop() order is important here
+    let (Some(rank), Some(file)) = (chars.pop(), chars.pop()) else {
+        return Err(syntax_error());
+    };
+    let destination = Square::make_square(
+        File::try_from(file).map_err(|_| syntax_error())?,
+        Rank::try_from(rank).map_err(|_| syntax_error())?,
+    );
+
+    if chars.last() == Some(&'x') {
+        chars.pop();
+    }
+
+    // The partial or full origin description
+    let mut origin_file = None;
+    let mut origin_rank = None;
+    for char in chars {
+        if let (None, Ok(file)) = (origin_file, File::try_from(char)) {
+            origin_file = Some(file);
+        } else if let (None, Ok(rank)) = (origin_rank, Rank::try_from(char)) {
+            origin_rank = Some(rank);
+        } else {
+            return Err(syntax_error());
+        }
+    }
+
+    return find_move(board, san_move, |chess_move| {
+        chess_move.destination == destination
+            && chess_move.promotion == promotion
+            && board.piece_on(chess_move.origin) == Some(piece)
+            && origin_file.map_or(true, |file| chess_move.origin.get_file() == file)
+            && origin_rank.map_or(true, |rank| chess_move.origin.get_rank() == rank)
+    });
+}
+
+/// Finds the one legal move that matches the `predicate`
+fn find_move(board: &Board, san_move: &str, predicate: impl Fn(&Move) -> bool) -> Result<Move, SanError> {
+    let mut candidates = board.iter_moves().filter(predicate);
+
+    match (candidates.next(), candidates.next()) {
+        (Some(chess_move), None) => Ok(chess_move),
+        (None, _) => Err(SanError::IllegalMove(san_move.to_owned())),
+        (Some(_), Some(_)) => Err(SanError::AmbiguousMove(san_move.to_owned())),
+    }
+}
+
+/// Formats a legal `Move` of the given `board` in Standard Algebraic Notation.
+///
+/// The origin is only disambiguated when needed (file first, then rank, then the full square)
+/// and a `+` or `#` suffix is added when the move checks or mates.
+///
+/// ```
+/// use engine::{notations::SAN, Board, Move, Square};
+///
+/// let board = Board::from_fen("4k3/8/8/8/8/8/8/R3K2R w KQ - 0 1").unwrap();
+///
+/// assert_eq!(SAN::format(&board, Move::new(Square::E1, Square::G1)), "O-O");
+/// assert_eq!(SAN::format(&board, Move::new(Square::A1, Square::A8)), "Ra8+");
+/// assert_eq!(SAN::format(&board, Move::new(Square::A1, Square::D1)), "Rd1");
+/// ```
+pub fn format(board: &Board, chess_move: Move) -> String {
+    let Move {
+        origin,
+        destination,
+        promotion,
+    } = chess_move;
+    let piece = board.piece_on(origin).expect("Move origin to contain a piece");
+
+    let mut san = String::new();
+
+    if piece == Piece::King && origin.get_file().to_index().abs_diff(destination.get_file().to_index()) == 2 {
+        san.push_str(match destination.get_file() {
+            File::G => "O-O",
+            _ => "O-O-O",
+        });
+    } else {
+        let is_capture = board.piece_on(destination).is_some()
+            || (piece == Piece::Pawn && origin.get_file() != destination.get_file());
+
+        san.push_str(piece.to_str());
+
+        if piece == Piece::Pawn {
+            if is_capture {
+                san.push(origin.get_file().to_char());
+            }
+        } else {
+            // The origins of the other pieces of the same type that can move to the destination
+            let others = board
+                .iter_moves()
+                .filter(|other| {
+                    other.destination == destination
+                        && other.origin != origin
+                        && board.piece_on(other.origin) == Some(piece)
+                })
+                .fold(BitBoard::EMPTY, |mask, other| mask | BitBoard::from_square(other.origin));
+
+            if !others.is_empty() {
+                if (others & BitBoard::FILES[origin.get_file().to_index()]).is_empty() {
+                    san.push(origin.get_file().to_char());
+                } else if (others & BitBoard::mask_rank(origin.get_rank())).is_empty() {
+                    san.push_str(&origin.get_rank().to_string());
+                } else {
+                    san.push_str(&origin.to_string());
+                }
+            }
+        }
+
+        if is_capture {
+            san.push('x');
+        }
+
+        san.push_str(&destination.to_string());
+
+        if let Some(promotion) = promotion {
+            san.push('=');
+            san.push_str(promotion.to_str());
+        }
+    }
+
+    let mut next = *board;
+    next.make_move(chess_move);
+
+    if !next.checkers.is_empty() {
+        san.push(match next.iter_moves().len() {
+            0 => '#',
+            _ => '+',
+        });
+    }
+
+    return san;
+}
+
+// Implementing str enums
+impl StrEnum for Piece {
+    type Error = String;
+    fn to_str(&self) -> &str {
+        use Piece::*;
+        match self {
+            Pawn => "",
+            Bishop => "B",
+            King => "K",
+            Knight => "N",
+            Queen => "Q",
+            Rook => "R",
+        }
+    }
+    fn from_str(value: &str) -> Result<Self, Self::Error> {
+        use Piece::*;
+        match value {
+            "" => Ok(Pawn),
+            "B" => Ok(Bishop),
+            "K" => Ok(King),
+            "N" => Ok(Knight),
+            "Q" => Ok(Queen),
+            "R" => Ok(Rook),
+            _ => Err(format!("invalid piece: {}", value)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn disambiguation() {
+        let board = Board::from_fen("6k1/8/2N3N1/8/2N5/R6R/8/4K3 w - - 0 1").unwrap();
+
+        [
+            ("Rae3", Move::new(Square::A3, Square::E3)),
+            ("Rhe3", Move::new(Square::H3, Square::E3)),
+            ("N4e5", Move::new(Square::C4, Square::E5)),
+            ("Nge5", Move::new(Square::G6, Square::E5)),
+            ("Nc6e5", Move::new(Square::C6, Square::E5)),
+            ("Ne3", Move::new(Square::C4, Square::E3)),
+            ("Kd1", Move::new(Square::E1, Square::D1)),
+        ]
+        .into_iter()
+        .for_each(|(san, chess_move)| {
+            assert_eq!(parse(&board, san).unwrap(), chess_move);
+            assert_eq!(format(&board, chess_move), san);
+        });
+
+        assert_eq!(parse(&board, "Nc4e3").unwrap(), Move::new(Square::C4, Square::E3));
+        assert_eq!(parse(&board, "Re3"), Err(SanError::AmbiguousMove("Re3".to_owned())));
+        assert_eq!(parse(&board, "Nce5"), Err(SanError::AmbiguousMove("Nce5".to_owned())));
+        assert_eq!(parse(&board, "Nb5"), Err(SanError::IllegalMove("Nb5".to_owned())));
+    }
+
+    #[test]
+    fn pawns() {
+        let board = Board::from_fen("1n2k3/P7/8/3pP3/8/8/8/4K3 w - d6 0 1").unwrap();
+
+        [
+            ("exd6", Move::new(Square::E5, Square::D6)),
+            ("e6", Move::new(Square::E5, Square::E6)),
+            ("a8=Q", Move::new_with_promotion(Square::A7, Square::A8, Some(Piece::Queen))),
+            ("axb8=N", Move::new_with_promotion(Square::A7, Square::B8, Some(Piece::Knight))),
+        ]
+        .into_iter()
+        .for_each(|(san, chess_move)| {
+            assert_eq!(parse(&board, san).unwrap(), chess_move);
+            assert_eq!(format(&board, chess_move), san);
+        });
+
+        assert_eq!(parse(&board, "exd6e.p.").unwrap(), Move::new(Square::E5, Square::D6));
+        assert_eq!(
+            parse(&board, "bxa8Q").unwrap_err(),
+            SanError::IllegalMove("bxa8Q".to_owned())
+        );
+        assert_eq!(
+            parse(&board, "a8").unwrap_err(),
+            SanError::IllegalMove("a8".to_owned())
+        );
+        assert_eq!(
+            parse(&board, "axb8=R").unwrap(),
+            Move::new_with_promotion(Square::A7, Square::B8, Some(Piece::Rook))
+        );
+    }
+
+    #[test]
+    fn castling_and_mate() {
+        let board = Board::from_fen("r3k2r/8/8/8/8/8/5PPP/6K1 b kq - 0 1").unwrap();
+
+        assert_eq!(parse(&board, "O-O").unwrap(), Move::new(Square::E8, Square::G8));
+        assert_eq!(parse(&board, "0-0-0").unwrap(), Move::new(Square::E8, Square::C8));
+        assert_eq!(format(&board, Move::new(Square::E8, Square::C8)), "O-O-O");
+        assert_eq!(format(&board, Move::new(Square::A8, Square::A1)), "Ra1#");
+        assert_eq!(parse(&board, "Ra1#").unwrap(), Move::new(Square::A8, Square::A1));
+    }
+
+    #[test]
+    fn syntax_errors() {
+        let board = Board::default();
+
+        ["", "Xe4", "e9", "Ni3", "e8=K", "+"]
+            .into_iter()
+            .for_each(|san| assert_eq!(parse(&board, san), Err(SanError::Syntax(san.to_owned()))));
+    }
+}
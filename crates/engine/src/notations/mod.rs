@@ -2,5 +2,4 @@
 
 pub mod PGN;
 pub mod FEN;
-// TODO
-// pub mod SAN;
+pub mod SAN;
@@ -80,10 +80,11 @@ impl TryFrom<char> for File {
     type Error = String;
 
     fn try_from(value: char) -> Result<Self, Self::Error> {
-        let index = (value as usize) - ('a' as usize);
-
-        // Prevent wrapping. E.g.: value > 'hfile'
-        if index > File::SIZE {
+        // Prevent wrapping. E.g.: value > 'h'
+        let Some(index) = (value as usize)
+            .checked_sub('a' as usize)
+            .filter(|index| *index < File::SIZE)
+        else {
             return Err(format!("Invalid file \"{value}\""));
         };
 
@@ -1,7 +1,7 @@
 use crate::primitives::team::Team;
 use crate::utils::enums::ArrayEnum;
 
-#[derive(Debug, PartialEq, Clone, Copy, rspc::Type, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, rspc::Type, serde::Serialize, serde::Deserialize)]
 pub enum Piece {
     Pawn,
     Knight,
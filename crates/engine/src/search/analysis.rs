@@ -0,0 +1,122 @@
+use std::{
+    sync::mpsc::{Receiver, TryRecvError},
+    thread::JoinHandle,
+};
+
+use super::{SearchResult, StopSignal};
+
+/// A search running on its own thread (see `Searcher::analyze`), streaming the result of every iteration
+///
+/// The results are received in order, waiting for the next one with `recv` or iterating over them until the search is
+/// over. Dropping the analysis stops the search.
+///
+/// ```
+/// use engine::{search::{Limits, Searcher}, Board};
+///
+/// let mut analysis = Searcher::new(Limits::default()).analyze(&Board::default());
+///
+/// let first = analysis.recv().unwrap();
+/// let second = analysis.recv().unwrap();
+/// assert_eq!((first.depth, second.depth), (1, 2));
+///
+/// let result = analysis.stop();
+/// assert!(result.depth >= 2 && result.best_move.is_some());
+/// ```
+pub struct Analysis {
+    updates: Receiver<SearchResult>,
+    stop: StopSignal,
+    handle: Option<JoinHandle<SearchResult>>,
+}
+
+impl Analysis {
+    pub(super) fn new(updates: Receiver<SearchResult>, stop: StopSignal, handle: JoinHandle<SearchResult>) -> Self {
+        Self {
+            updates,
+            stop,
+            handle: Some(handle),
+        }
+    }
+
+    /// Waits for the result of the next iteration, `None` once the search is over
+    pub fn recv(&self) -> Option<SearchResult> {
+        self.updates.recv().ok()
+    }
+
+    /// The result of the next iteration if it is already there, without waiting
+    pub fn try_recv(&self) -> Option<SearchResult> {
+        match self.updates.try_recv() {
+            Ok(result) => Some(result),
+            Err(TryRecvError::Empty | TryRecvError::Disconnected) => None,
+        }
+    }
+
+    /// The signal that stops the search, e.g. from another thread than the one receiving the results
+    pub fn stop_signal(&self) -> StopSignal {
+        self.stop.clone()
+    }
+
+    /// Whether the search is over, either stopped or done with its limits
+    pub fn is_finished(&self) -> bool {
+        self.handle.as_ref().map_or(true, JoinHandle::is_finished)
+    }
+
+    /// Stops the search and returns its result, the one of its last completed iteration
+    pub fn stop(mut self) -> SearchResult {
+        self.stop.stop();
+
+        let handle = self.handle.take().expect("The search to be running until stopped");
+        return match handle.join() {
+            Ok(result) => result,
+            Err(panic) => std::panic::resume_unwind(panic),
+        };
+    }
+}
+
+impl Iterator for Analysis {
+    type Item = SearchResult;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.recv()
+    }
+}
+
+impl Drop for Analysis {
+    fn drop(&mut self) {
+        self.stop.stop();
+
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use crate::{
+        search::{Limits, Searcher, MATE},
+        Board,
+    };
+
+    #[test]
+    fn analysis() {
+        // A limited search ends the stream on its own
+        let board = Board::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        let analysis = Searcher::new(Limits::depth(4)).with_multi_pv(2).analyze(&board);
+        let results = analysis.collect::<Vec<_>>();
+        assert_eq!(results.iter().map(|result| result.depth).collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+        assert!(results.iter().all(|result| result.lines.len() == 2 && result.score == MATE - 1));
+
+        // An infinite one goes on until stopped
+        let analysis = Searcher::new(Limits::default()).analyze(&Board::default());
+        std::thread::sleep(Duration::from_millis(100));
+        assert!(!analysis.is_finished());
+        assert!(analysis.try_recv().is_some());
+
+        // The stream ends once the search is stopped
+        analysis.stop_signal().stop();
+        while analysis.recv().is_some() {}
+        assert!(analysis.stop().best_move.is_some());
+    }
+}
@@ -0,0 +1,1163 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        mpsc, Arc, OnceLock,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    eval::{
+        self,
+        nnue::{Accumulator, Network},
+        Score,
+    },
+    game::{moves::MoveGen, Board, Move},
+    syzygy::{Tablebase, Wdl},
+};
+
+pub mod analysis;
+pub mod picker;
+pub mod skill;
+pub mod tt;
+use analysis::Analysis;
+use picker::{History, Killers, MovePicker};
+use skill::Skill;
+use tt::{Bound, TranspositionTable};
+
+/// The score of a checkmate on the board, mates found `n` plies away score `MATE - n`
+pub const MATE: Score = 32_000;
+/// A bound greater than any score
+pub const INFINITY: Score = 32_001;
+/// The maximum depth (in plies) the search can reach, quiescence included
+pub const MAX_PLY: usize = 128;
+/// The score of a position won according to the tablebase, wins found `n` plies away score `TB_WIN - n`
+pub const TB_WIN: Score = MATE - 2 * MAX_PLY as Score;
+
+/// The number of nodes between two checks of the time limit
+const CHECK_INTERVAL: u64 = 2048;
+
+/// Whether the `score` is a mate score (for either team)
+#[inline]
+pub fn is_mate(score: Score) -> bool {
+    score.abs() >= MATE - MAX_PLY as Score
+}
+
+/// The number of moves until mate: positive if the team to move mates, negative if it gets mated
+///
+/// ```
+/// use engine::search::{mate_in, MATE};
+///
+/// assert_eq!(mate_in(MATE - 1), Some(1));
+/// assert_eq!(mate_in(MATE - 3), Some(2));
+/// assert_eq!(mate_in(-(MATE - 2)), Some(-1));
+/// assert_eq!(mate_in(150), None);
+/// ```
+pub fn mate_in(score: Score) -> Option<Score> {
+    if !is_mate(score) {
+        return None;
+    }
+
+    let moves = (MATE - score.abs() + 1) / 2;
+
+    return Some(score.signum() * moves);
+}
+
+/// When the search has to stop, the unset limits being unbounded
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Limits {
+    /// The maximum depth of the iterative deepening, in plies
+    pub depth: Option<u8>,
+    /// The maximum number of nodes to search
+    pub nodes: Option<u64>,
+    /// The maximum time to search for
+    pub time: Option<Duration>,
+}
+
+impl Limits {
+    /// Only limits the search by depth
+    pub fn depth(depth: u8) -> Self {
+        Self {
+            depth: Some(depth),
+            ..Default::default()
+        }
+    }
+
+    /// Only limits the search by the number of nodes
+    pub fn nodes(nodes: u64) -> Self {
+        Self {
+            nodes: Some(nodes),
+            ..Default::default()
+        }
+    }
+
+    /// Only limits the search by time
+    pub fn time(time: Duration) -> Self {
+        Self {
+            time: Some(time),
+            ..Default::default()
+        }
+    }
+}
+
+/// A flag to stop a running search from another thread, its clones share the same flag
+#[derive(Debug, Clone, Default)]
+pub struct StopSignal(Arc<AtomicBool>);
+
+impl StopSignal {
+    /// Asks the search to stop as soon as possible
+    pub fn stop(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Clears the flag, so it can be used for another search
+    pub fn reset(&self) {
+        self.0.store(false, Ordering::Relaxed);
+    }
+}
+
+/// Tells a pondering search that the predicted move was played, its clones share the same flag
+///
+/// ```
+/// use engine::search::PonderSignal;
+///
+/// let ponder = PonderSignal::default();
+/// assert!(!ponder.is_hit());
+///
+/// ponder.clone().hit();
+/// assert!(ponder.is_hit());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct PonderSignal(Arc<OnceLock<Instant>>);
+
+impl PonderSignal {
+    /// Turns the pondering search into a normal one, its time limit starting now
+    pub fn hit(&self) {
+        let _ = self.0.set(Instant::now());
+    }
+
+    pub fn is_hit(&self) -> bool {
+        self.0.get().is_some()
+    }
+
+    /// When the signal was hit, if it was
+    pub fn hit_time(&self) -> Option<Instant> {
+        self.0.get().copied()
+    }
+}
+
+/// The result of a (possibly partial) search
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchResult {
+    /// The best move found, `None` if there are no legal moves
+    pub best_move: Option<Move>,
+    /// The score of the best move, from the point of view of the team to move
+    pub score: Score,
+    /// The depth of the last completed iteration
+    pub depth: u8,
+    /// The number of nodes searched, quiescence included
+    pub nodes: u64,
+    /// The principal variation, starting with the best move
+    pub pv: Vec<Move>,
+    pub time: Duration,
+    /// The permille of the transposition table used by the search
+    pub hashfull: usize,
+    /// The best lines of a MultiPV search, best first, `lines[0]` being the principal variation
+    pub lines: Vec<PvLine>,
+    /// The number of positions found in the tablebase
+    pub tb_hits: u64,
+}
+
+impl SearchResult {
+    /// The nodes searched per second
+    pub fn nps(&self) -> u64 {
+        self.nodes * 1000 / (self.time.as_millis() as u64).max(1)
+    }
+}
+
+/// One of the lines of a MultiPV search
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PvLine {
+    pub score: Score,
+    /// The moves of the line, starting with its root move
+    pub pv: Vec<Move>,
+}
+
+/// A negamax alpha-beta search with iterative deepening and a quiescence search over captures
+///
+/// The searched positions are kept in a `TranspositionTable`, which can be shared between searches.
+///
+/// With several threads (lazy SMP), helper threads search the same position at staggered depths, sharing the
+/// transposition table with the main thread. Only the main thread reports its iterations and picks the move.
+///
+/// In MultiPV mode, each iteration searches the root once per line, excluding the root moves of the previous lines.
+///
+/// With a limited `Skill`, the search is shallower and the move is picked among the best lines, at random.
+///
+/// With a `Network`, the positions are evaluated by the network rather than by `eval::evaluate`, its accumulator
+/// being updated move by move.
+///
+/// With a `Tablebase`, only the root moves that keep the best outcome are searched, and the positions it knows are
+/// scored without being searched, right after a capture or a pawn move.
+///
+/// A pondering search (`with_ponder`) searches the predicted position on the opponent's time, the time limit only
+/// applying once its `PonderSignal` is hit.
+///
+/// ```
+/// use engine::{search::{Limits, Searcher, MATE}, Board, Move, Square};
+///
+/// let board = Board::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+/// let result = Searcher::new(Limits::depth(3)).search(&board);
+///
+/// assert_eq!(result.best_move, Some(Move::new(Square::A1, Square::A8)));
+/// assert_eq!(result.score, MATE - 1);
+/// ```
+pub struct Searcher {
+    limits: Limits,
+    stop: StopSignal,
+    /// Keeps the time limit from applying until it is hit
+    ponder: Option<PonderSignal>,
+    start: Instant,
+    nodes: u64,
+    stopped: bool,
+    /// The hashes of the positions of the game before the searched one, and of the current search path
+    hashes: Vec<u64>,
+    /// Triangular principal variation table, `pv[ply]` being the best line found from `ply`
+    pv: Vec<Vec<Move>>,
+    /// The principal variation of the previous iteration, searched first
+    previous_pv: Vec<Move>,
+    follow_pv: bool,
+    /// Created when the search starts if none was given, as the table is large to allocate
+    tt: Option<Arc<TranspositionTable>>,
+    /// The quiet moves that caused a beta cutoff, by ply
+    killers: Vec<Killers>,
+    history: History,
+    threads: usize,
+    multi_pv: usize,
+    /// The root moves not to search, the ones of the lines already found in the current iteration
+    excluded: Vec<Move>,
+    tablebase: Option<Arc<Tablebase>>,
+    /// The root moves that lose (or win slower than others) according to the tablebase
+    tablebase_excluded: Vec<Move>,
+    tb_hits: u64,
+    skill: Skill,
+    /// The seed of the moves picked by a limited `skill`
+    seed: u64,
+    network: Option<Arc<Network>>,
+    /// The accumulators of the `network` for the positions of the current search path, by ply
+    accumulators: Vec<Accumulator>,
+    /// 0 for the main thread, the helpers being numbered from 1
+    thread_index: usize,
+    /// The nodes searched by the helper threads so far, shared with them
+    helper_nodes: Arc<AtomicU64>,
+    /// The nodes of this helper already added to `helper_nodes`
+    published_nodes: u64,
+}
+
+impl Searcher {
+    pub fn new(limits: Limits) -> Self {
+        Self {
+            limits,
+            stop: StopSignal::default(),
+            ponder: None,
+            start: Instant::now(),
+            nodes: 0,
+            stopped: false,
+            hashes: Vec::new(),
+            pv: vec![Vec::new(); MAX_PLY + 1],
+            previous_pv: Vec::new(),
+            follow_pv: false,
+            tt: None,
+            killers: vec![[None; 2]; MAX_PLY + 1],
+            history: History::default(),
+            threads: 1,
+            multi_pv: 1,
+            excluded: Vec::new(),
+            tablebase: None,
+            tablebase_excluded: Vec::new(),
+            tb_hits: 0,
+            skill: Skill::default(),
+            seed: 0,
+            network: None,
+            accumulators: Vec::new(),
+            thread_index: 0,
+            helper_nodes: Arc::new(AtomicU64::new(0)),
+            published_nodes: 0,
+        }
+    }
+
+    /// Uses the given `StopSignal`, to stop the search from another thread
+    pub fn with_stop_signal(mut self, stop: StopSignal) -> Self {
+        self.stop = stop;
+        self
+    }
+
+    /// Ponders until `ponder` is hit: the time limit is then counted from the hit, as if the search had just started,
+    /// while the other limits apply throughout
+    pub fn with_ponder(mut self, ponder: PonderSignal) -> Self {
+        self.ponder = Some(ponder);
+        self
+    }
+
+    /// Sets the hashes (`Board::hash`) of the positions played before the searched one, to detect repetitions
+    pub fn with_history(mut self, hashes: Vec<u64>) -> Self {
+        self.hashes = hashes;
+        self
+    }
+
+    /// Uses the given `TranspositionTable`, e.g. to keep it from one move of the game to the next
+    pub fn with_transposition_table(mut self, tt: Arc<TranspositionTable>) -> Self {
+        self.tt = Some(tt);
+        self
+    }
+
+    /// Searches with `threads` threads (at least one), the calling one included
+    ///
+    /// Only the searches with a single thread are deterministic. The node limit counts the nodes of all the threads.
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    /// Searches the best `lines` root moves (at least one) with their own scores and principal variations
+    ///
+    /// The helper threads only search the principal variation.
+    pub fn with_multi_pv(mut self, lines: usize) -> Self {
+        self.multi_pv = lines.max(1);
+        self
+    }
+
+    /// Probes the given `Tablebase` at the root and during the search
+    pub fn with_tablebase(mut self, tablebase: Arc<Tablebase>) -> Self {
+        self.tablebase = Some(tablebase);
+        self
+    }
+
+    /// Plays at the given `skill`, the moves of the limited levels being picked with the given `seed`
+    ///
+    /// The limited levels search at least `Skill::CANDIDATES` lines.
+    pub fn with_skill(mut self, skill: Skill, seed: u64) -> Self {
+        self.skill = skill;
+        self.seed = seed;
+        self
+    }
+
+    /// Evaluates the positions with the given `Network` rather than with `eval::evaluate`
+    pub fn with_network(mut self, network: Arc<Network>) -> Self {
+        self.network = Some(network);
+        self
+    }
+
+    /// The `TranspositionTable` of the search, `None` if none was given and no search started yet
+    pub fn transposition_table(&self) -> Option<&Arc<TranspositionTable>> {
+        self.tt.as_ref()
+    }
+
+    /// The signal that stops this search
+    pub fn stop_signal(&self) -> StopSignal {
+        self.stop.clone()
+    }
+
+    /// Searches the `board` until one of the limits is reached, returning the result of the last completed iteration
+    pub fn search(&mut self, board: &Board) -> SearchResult {
+        self.search_with(board, |_| {})
+    }
+
+    /// Searches the `board`, calling `on_iteration` with the result of every completed iteration
+    pub fn search_with(&mut self, board: &Board, on_iteration: impl FnMut(&SearchResult)) -> SearchResult {
+        if !self.skill.is_limited() {
+            return self.search_threads(board, on_iteration);
+        }
+
+        let (limits, multi_pv) = (self.limits, self.multi_pv);
+        self.limits = self.skill.limits(limits);
+        self.multi_pv = multi_pv.max(Skill::CANDIDATES);
+        let mut result = self.search_threads(board, on_iteration);
+        (self.limits, self.multi_pv) = (limits, multi_pv);
+
+        let picked = self.skill.pick(&result.lines, self.seed ^ board.hash());
+        if let Some(line) = picked.and_then(|index| result.lines.get(index)) {
+            result.best_move = line.pv.first().copied();
+            result.score = line.score;
+            result.pv.clone_from(&line.pv);
+        }
+
+        return result;
+    }
+
+    /// Searches the `board` on a new thread, streaming the result of every iteration through the returned `Analysis`
+    ///
+    /// With the default `Limits`, the analysis goes on until it is stopped.
+    pub fn analyze(mut self, board: &Board) -> Analysis {
+        let (sender, updates) = mpsc::channel();
+        let stop = self.stop_signal();
+        let board = *board;
+
+        let handle = thread::spawn(move || {
+            self.search_with(&board, |result| {
+                let _ = sender.send(result.clone());
+            })
+        });
+
+        return Analysis::new(updates, stop, handle);
+    }
+
+    /// Searches the `board` with the helper threads, if any
+    fn search_threads(&mut self, board: &Board, on_iteration: impl FnMut(&SearchResult)) -> SearchResult {
+        self.tt.get_or_insert_with(Default::default).new_search();
+        self.helper_nodes.store(0, Ordering::Relaxed);
+
+        if self.threads == 1 {
+            return self.iterate(board, on_iteration);
+        }
+
+        // The helpers stop once the main thread is done, whatever stopped it
+        let helpers_stop = StopSignal::default();
+
+        return thread::scope(|scope| {
+            let helpers = (1..self.threads)
+                .map(|index| {
+                    let mut helper = self.helper(index, helpers_stop.clone());
+                    scope.spawn(move || {
+                        helper.iterate(board, |_| {});
+                        (helper.nodes, helper.tb_hits)
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            let mut result = self.iterate(board, on_iteration);
+            helpers_stop.stop();
+
+            let mut helper_nodes = 0;
+            for helper in helpers {
+                let (nodes, tb_hits) = helper.join().unwrap_or_default();
+                helper_nodes += nodes;
+                result.tb_hits += tb_hits;
+            }
+            result.nodes = self.nodes + helper_nodes;
+
+            result
+        });
+    }
+
+    /// A helper thread of this search, stopped by `stop`
+    fn helper(&self, index: usize, stop: StopSignal) -> Self {
+        Self {
+            stop,
+            hashes: self.hashes.clone(),
+            tt: self.tt.clone(),
+            tablebase: self.tablebase.clone(),
+            network: self.network.clone(),
+            thread_index: index,
+            helper_nodes: Arc::clone(&self.helper_nodes),
+            ..Self::new(Limits {
+                depth: self.limits.depth,
+                ..Default::default()
+            })
+        }
+    }
+
+    fn tt(&self) -> &TranspositionTable {
+        self.tt.as_deref().expect("The transposition table to be created when the search starts")
+    }
+
+    /// The iterative deepening of a single thread
+    fn iterate(&mut self, board: &Board, mut on_iteration: impl FnMut(&SearchResult)) -> SearchResult {
+        self.start = Instant::now();
+        self.nodes = 0;
+        self.published_nodes = 0;
+        self.stopped = false;
+        self.previous_pv.clear();
+        self.killers.fill([None; 2]);
+        self.excluded.clear();
+        self.tb_hits = 0;
+        if let Some(network) = &self.network {
+            self.accumulators = vec![network.accumulator(board); MAX_PLY + 1];
+        }
+
+        // Only the moves that keep the outcome of the tablebase are searched
+        let root_moves = self.tablebase.as_ref().and_then(|tablebase| tablebase.best_moves(board));
+        self.tablebase_excluded = match &root_moves {
+            Some(moves) => MoveGen::new_legal(board).filter(|chess_move| !moves.contains(chess_move)).collect(),
+            None => Vec::new(),
+        };
+
+        let mut result = SearchResult {
+            best_move: MoveGen::new_legal(board).find(|chess_move| !self.tablebase_excluded.contains(chess_move)),
+            score: 0,
+            depth: 0,
+            nodes: 0,
+            pv: Vec::new(),
+            time: Duration::ZERO,
+            hashfull: 0,
+            lines: Vec::new(),
+            tb_hits: 0,
+        };
+
+        if result.best_move.is_none() {
+            result.score = eval_terminal(board, 0);
+            return result;
+        }
+
+        let line_count = self.multi_pv.min(MoveGen::new_legal(board).len() - self.tablebase_excluded.len());
+        let max_depth = self.limits.depth.unwrap_or(MAX_PLY as u8).min(MAX_PLY as u8);
+        for depth in 1..=max_depth {
+            // Half of the helpers search one ply deeper, so that the threads don't all search the same tree
+            let depth = (depth + (self.thread_index % 2) as u8).min(max_depth);
+
+            let lines = self.search_lines(board, depth, line_count, &result.lines);
+
+            // An interrupted iteration can't be trusted
+            if self.stopped {
+                break;
+            }
+
+            result = SearchResult {
+                best_move: lines[0].pv.first().copied(),
+                score: lines[0].score,
+                depth,
+                nodes: self.nodes + self.helper_nodes.load(Ordering::Relaxed),
+                pv: lines[0].pv.clone(),
+                time: self.start.elapsed(),
+                hashfull: self.tt().hashfull(),
+                lines,
+                tb_hits: self.tb_hits,
+            };
+            on_iteration(&result);
+
+            // The next iteration would most likely not finish in time
+            if let (Some(time), Some(elapsed)) = (self.limits.time, self.clock()) {
+                if elapsed >= time / 2 {
+                    break;
+                }
+            }
+        }
+
+        result.nodes = self.nodes + self.helper_nodes.load(Ordering::Relaxed);
+        result.time = self.start.elapsed();
+        result.tb_hits = self.tb_hits;
+
+        return result;
+    }
+
+    /// Searches the `count` best lines at `depth`, each one following its line of the `previous` iteration first
+    fn search_lines(&mut self, board: &Board, depth: u8, count: usize, previous: &[PvLine]) -> Vec<PvLine> {
+        let mut lines = Vec::with_capacity(count);
+        self.excluded.clone_from(&self.tablebase_excluded);
+
+        for index in 0..count {
+            self.previous_pv = previous.get(index).map(|line| line.pv.clone()).unwrap_or_default();
+            self.follow_pv = true;
+
+            let score = self.negamax(board, depth, 0, -INFINITY, INFINITY);
+            if self.stopped {
+                break;
+            }
+
+            self.excluded.extend(self.pv[0].first());
+            lines.push(PvLine {
+                score,
+                pv: self.pv[0].clone(),
+            });
+        }
+        self.excluded.clear();
+
+        // The best lines of the previous iteration may not be the best ones anymore
+        lines.sort_by_key(|line| -line.score);
+
+        return lines;
+    }
+
+    /// Checks the limits and the stop signal, returning whether the search has to stop
+    fn should_stop(&mut self) -> bool {
+        if self.stopped {
+            return true;
+        }
+
+        if self.nodes % CHECK_INTERVAL == 0 {
+            if self.thread_index > 0 {
+                self.helper_nodes.fetch_add(self.nodes - self.published_nodes, Ordering::Relaxed);
+                self.published_nodes = self.nodes;
+            }
+
+            self.stopped = self.stop.is_stopped()
+                || self.limits.time.is_some_and(|time| self.clock().is_some_and(|elapsed| elapsed >= time));
+        }
+        // The helpers have no node limit, the main thread counting their nodes with its own
+        let helper_nodes = || self.helper_nodes.load(Ordering::Relaxed);
+        if self.limits.nodes.is_some_and(|nodes| self.nodes + helper_nodes() >= nodes) {
+            self.stopped = true;
+        }
+
+        return self.stopped;
+    }
+
+    /// The time counted against the time limit, `None` while pondering
+    fn clock(&self) -> Option<Duration> {
+        match &self.ponder {
+            Some(ponder) => ponder.hit_time().map(|hit| hit.elapsed()),
+            None => Some(self.start.elapsed()),
+        }
+    }
+
+    /// Whether the position is drawn by repetition or the fifty-move rule, or can't be won by either team
+    fn is_draw(&self, board: &Board) -> bool {
+        if board.halfmove_clock >= 100 || board.is_insufficient_material() {
+            return true;
+        }
+
+        // Only the positions since the last irreversible move can be repeated, with the same team to move
+        let hash = board.hash();
+        return self
+            .hashes
+            .iter()
+            .rev()
+            .take(board.halfmove_clock as usize)
+            .skip(1)
+            .step_by(2)
+            .any(|previous| *previous == hash);
+    }
+
+    fn negamax(&mut self, board: &Board, depth: u8, ply: usize, mut alpha: Score, beta: Score) -> Score {
+        self.pv[ply].clear();
+
+        if self.should_stop() {
+            return 0;
+        }
+
+        if ply > 0 && self.is_draw(board) {
+            return 0;
+        }
+
+        let in_check = !board.checkers.is_empty();
+        // Check extension, so that mates are not hidden behind a check
+        let depth = if in_check { depth + 1 } else { depth };
+
+        if depth == 0 || ply >= MAX_PLY {
+            return self.quiescence(board, ply, alpha, beta);
+        }
+
+        self.nodes += 1;
+
+        let hash = board.hash();
+        let entry = self.tt().probe(hash, ply);
+        if let Some(entry) = entry.filter(|entry| ply > 0 && entry.depth >= depth) {
+            let cutoff = match entry.bound {
+                Bound::Exact => true,
+                Bound::Lower => entry.score >= beta,
+                Bound::Upper => entry.score <= alpha,
+            };
+
+            if cutoff {
+                self.pv[ply].extend(entry.best_move.filter(|_| entry.bound == Bound::Exact));
+                return entry.score;
+            }
+        }
+
+        let original_alpha = alpha;
+        let mut best_score = -INFINITY;
+        let mut max_score = INFINITY;
+
+        // Right after a capture or a pawn move, the fifty-move rule can't spoil the outcome of the tablebase
+        if let Some(wdl) = self.probe_tablebase(board, ply) {
+            self.tb_hits += 1;
+
+            let (score, bound) = match wdl {
+                Wdl::Win => (TB_WIN - ply as Score, Bound::Lower),
+                Wdl::Loss => (-TB_WIN + ply as Score, Bound::Upper),
+                _ => (0, Bound::Exact),
+            };
+            let cutoff = match bound {
+                Bound::Exact => true,
+                Bound::Lower => score >= beta,
+                Bound::Upper => score <= alpha,
+            };
+
+            if cutoff {
+                self.tt().store(hash, depth, score, bound, None, ply);
+                return score;
+            }
+
+            // Otherwise, the search finds the moves that keep the outcome, within its bound
+            match bound {
+                Bound::Lower => {
+                    best_score = score;
+                    alpha = alpha.max(score);
+                }
+                _ => max_score = score,
+            }
+        }
+
+        let pv_move = match self.follow_pv {
+            true => self.previous_pv.get(ply).copied(),
+            false => None,
+        };
+        let hash_move = pv_move.or(entry.and_then(|entry| entry.best_move));
+        let mut picker = MovePicker::new(board, hash_move, self.killers[ply]);
+        if ply == 0 {
+            picker = picker.excluding(&self.excluded);
+        }
+
+        let mut best_move = None;
+        let mut searched = false;
+        let mut quiets = Vec::new();
+        while let Some(chess_move) = picker.next(&self.history) {
+            // Only the first move of the previous principal variation is followed
+            if !searched && Some(chess_move) != pv_move {
+                self.follow_pv = false;
+            }
+            searched = true;
+
+            let mut next = *board;
+            next.make_move(chess_move);
+            self.update_accumulator(&next, ply + 1);
+
+            self.hashes.push(board.hash());
+            let score = -self.negamax(&next, depth - 1, ply + 1, -beta, -alpha);
+            self.hashes.pop();
+            self.follow_pv = false;
+
+            if self.stopped {
+                return 0;
+            }
+
+            if score > best_score {
+                best_score = score;
+                best_move = Some(chess_move);
+
+                if score > alpha {
+                    alpha = score;
+                    self.update_pv(ply, chess_move);
+
+                    if alpha >= beta {
+                        if is_quiet(board, chess_move) {
+                            self.update_quiets(board, ply, depth, chess_move, &quiets);
+                        }
+                        break;
+                    }
+                }
+            }
+
+            if is_quiet(board, chess_move) {
+                quiets.push(chess_move);
+            }
+        }
+
+        if !searched {
+            return eval_terminal(board, ply);
+        }
+        best_score = best_score.min(max_score);
+
+        let bound = if best_score >= beta {
+            Bound::Lower
+        } else if best_score > original_alpha {
+            Bound::Exact
+        } else {
+            Bound::Upper
+        };
+        // The root moves of the other lines were skipped, this isn't the result of the position
+        if ply > 0 || self.excluded.is_empty() {
+            self.tt().store(hash, depth, best_score, bound, best_move, ply);
+        }
+
+        return best_score;
+    }
+
+    /// The outcome of the `board` according to the tablebase, if the search has to probe it
+    fn probe_tablebase(&self, board: &Board, ply: usize) -> Option<Wdl> {
+        let tablebase = self.tablebase.as_ref()?;
+        if ply == 0 || board.halfmove_clock > 0 || !tablebase.can_probe(board) {
+            return None;
+        }
+
+        return tablebase.probe_wdl(board);
+    }
+
+    /// Evaluates the `board` at `ply` with the network if there is one, from its accumulator
+    #[inline]
+    fn evaluate(&self, board: &Board, ply: usize) -> Score {
+        match &self.network {
+            Some(network) => network.evaluate(&self.accumulators[ply], board.team_to_move),
+            None => eval::evaluate(board),
+        }
+    }
+
+    /// Updates the accumulator of the network at `ply` to the `board`, reached with a move from the position at
+    /// `ply - 1`
+    #[inline]
+    fn update_accumulator(&mut self, board: &Board, ply: usize) {
+        if let Some(network) = &self.network {
+            let (parents, children) = self.accumulators.split_at_mut(ply);
+            network.update(&mut children[0], &parents[ply - 1], board);
+        }
+    }
+
+    /// Searches the captures until the position is quiet, to avoid misjudging the positions in the middle of an exchange
+    fn quiescence(&mut self, board: &Board, ply: usize, mut alpha: Score, beta: Score) -> Score {
+        self.pv[ply].clear();
+
+        if self.should_stop() {
+            return 0;
+        }
+
+        self.nodes += 1;
+
+        if ply >= MAX_PLY {
+            return self.evaluate(board, ply);
+        }
+
+        // When in check, every evasion is searched as standing pat is not an option
+        let in_check = !board.checkers.is_empty();
+        let mut best_score = -INFINITY;
+        let mut picker = match in_check {
+            true => MovePicker::new(board, None, [None; 2]),
+            false => {
+                best_score = self.evaluate(board, ply);
+                if best_score >= beta {
+                    return best_score;
+                }
+                alpha = alpha.max(best_score);
+
+                MovePicker::captures(board)
+            }
+        };
+
+        let mut searched = false;
+        while let Some(chess_move) = picker.next(&self.history) {
+            searched = true;
+
+            let mut next = *board;
+            next.make_move(chess_move);
+            self.update_accumulator(&next, ply + 1);
+
+            let score = -self.quiescence(&next, ply + 1, -beta, -alpha);
+
+            if self.stopped {
+                return 0;
+            }
+
+            if score > best_score {
+                best_score = score;
+
+                if score > alpha {
+                    alpha = score;
+                    self.update_pv(ply, chess_move);
+
+                    if alpha >= beta {
+                        break;
+                    }
+                }
+            }
+        }
+
+        if in_check && !searched {
+            return eval_terminal(board, ply);
+        }
+
+        return best_score;
+    }
+
+    /// Rewards the quiet move that caused a beta cutoff, and penalizes the `quiets` searched before it
+    fn update_quiets(&mut self, board: &Board, ply: usize, depth: u8, chess_move: Move, quiets: &[Move]) {
+        let killers = &mut self.killers[ply];
+        if killers[0] != Some(chess_move) {
+            killers[1] = killers[0];
+            killers[0] = Some(chess_move);
+        }
+
+        let bonus = depth as i32 * depth as i32;
+        self.history.update(board.team_to_move, chess_move, bonus);
+        for quiet in quiets {
+            self.history.update(board.team_to_move, *quiet, -bonus);
+        }
+    }
+
+    fn update_pv(&mut self, ply: usize, chess_move: Move) {
+        let (current, next) = self.pv.split_at_mut(ply + 1);
+        let pv = &mut current[ply];
+
+        pv.clear();
+        pv.push(chess_move);
+        pv.extend_from_slice(&next[0]);
+    }
+}
+
+/// The score of a position without legal moves: mated or stalemate
+fn eval_terminal(board: &Board, ply: usize) -> Score {
+    match board.checkers.is_empty() {
+        true => 0,
+        false => -MATE + ply as Score,
+    }
+}
+
+/// Whether the move neither captures nor promotes
+pub fn is_quiet(board: &Board, chess_move: Move) -> bool {
+    let en_passant = board.en_passant.is_some_and(|square| square.uforward(board.team_to_move) == chess_move.destination)
+        && board.piece_on(chess_move.origin) == Some(crate::primitives::piece::Piece::Pawn);
+
+    return chess_move.promotion.is_none() && board.piece_on(chess_move.destination).is_none() && !en_passant;
+}
+
+/// Searches the `board` with the given `limits`
+pub fn search(board: &Board, limits: Limits) -> SearchResult {
+    Searcher::new(limits).search(board)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::primitives::{piece::Piece, Square};
+
+    fn search_fen(fen: &str, depth: u8) -> SearchResult {
+        search(&Board::from_fen(fen).unwrap(), Limits::depth(depth))
+    }
+
+    #[test]
+    fn mates() {
+        let result = search_fen("r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4", 2);
+        assert_eq!(result.best_move, Some(Move::new(Square::H5, Square::F7)));
+        assert_eq!(mate_in(result.score), Some(1));
+
+        let result = search_fen("r5k1/5ppp/8/8/8/8/3R1PPP/3R2K1 w - - 0 1", 4);
+        assert_eq!(result.best_move, Some(Move::new(Square::D2, Square::D8)));
+        assert_eq!(mate_in(result.score), Some(2));
+        assert_eq!(result.pv.len(), 3);
+
+        // Already mated
+        let result = search_fen("7k/8/8/8/8/8/5q2/6rK w - - 0 1", 2);
+        assert_eq!((result.best_move, result.score), (None, -MATE));
+
+        // Stalemate
+        let result = search_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1", 3);
+        assert_eq!((result.best_move, result.score), (None, 0));
+    }
+
+    #[test]
+    fn material() {
+        let result = search_fen("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1", 2);
+        assert_eq!(result.best_move, Some(Move::new(Square::D2, Square::D5)));
+
+        // Taking the defended pawn loses the queen
+        let result = search_fen("4k3/4r3/8/4p3/8/8/8/4QK2 w - - 0 1", 3);
+        assert_ne!(result.best_move, Some(Move::new(Square::E1, Square::E5)));
+    }
+
+    #[test]
+    fn limits() {
+        let board = Board::default();
+
+        let result = search(&board, Limits::nodes(5_000));
+        assert!(result.best_move.is_some());
+        assert!(result.nodes <= 5_000);
+
+        let mut depths = Vec::new();
+        let result = Searcher::new(Limits::depth(3)).search_with(&board, |result| depths.push(result.depth));
+        assert_eq!(depths, vec![1, 2, 3]);
+        assert_eq!(result.pv.first(), result.best_move.as_ref());
+
+        let stop = StopSignal::default();
+        let mut searcher = Searcher::new(Limits::default()).with_stop_signal(stop.clone());
+        let handle = std::thread::spawn(move || searcher.search(&Board::default()));
+        std::thread::sleep(Duration::from_millis(100));
+        stop.stop();
+        let result = handle.join().unwrap();
+        assert!(result.best_move.is_some());
+        assert!(result.depth >= 1);
+    }
+
+    #[test]
+    fn ponder() {
+        let ponder = PonderSignal::default();
+        let mut searcher = Searcher::new(Limits::time(Duration::from_millis(50))).with_ponder(ponder.clone());
+        let handle = std::thread::spawn(move || searcher.search(&Board::default()));
+
+        // The time limit doesn't apply while pondering
+        std::thread::sleep(Duration::from_millis(200));
+        assert!(!handle.is_finished());
+
+        // The same search goes on after the hit, for the time of its limit
+        ponder.hit();
+        let result = handle.join().unwrap();
+        assert!(result.best_move.is_some());
+        assert!(result.time >= Duration::from_millis(200), "{:?}", result.time);
+        assert!(ponder.hit_time().unwrap().elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn threads() {
+        let board = Board::from_fen("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3").unwrap();
+
+        // A single thread always searches the same tree
+        let first = search(&board, Limits::depth(5));
+        let second = Searcher::new(Limits::depth(5)).with_threads(1).search(&board);
+        assert_eq!(first, SearchResult { time: first.time, ..second });
+
+        let board = Board::from_fen("r5k1/5ppp/8/8/8/8/3R1PPP/3R2K1 w - - 0 1").unwrap();
+        let mut depths = Vec::new();
+        let result = Searcher::new(Limits::depth(4))
+            .with_threads(4)
+            .search_with(&board, |result| depths.push(result.depth));
+        assert_eq!(depths, vec![1, 2, 3, 4]);
+        assert_eq!(result.best_move, Some(Move::new(Square::D2, Square::D8)));
+        assert_eq!(mate_in(result.score), Some(2));
+
+        // The node limit counts the nodes of the helpers, give or take the ones they did not report yet
+        let result = Searcher::new(Limits::nodes(50_000)).with_threads(4).search(&Board::default());
+        assert!(result.nodes < 2 * 50_000, "{}", result.nodes);
+
+        // Stopping the main thread stops the helpers
+        let stop = StopSignal::default();
+        let mut searcher = Searcher::new(Limits::default()).with_threads(3).with_stop_signal(stop.clone());
+        let handle = std::thread::spawn(move || searcher.search(&Board::default()));
+        std::thread::sleep(Duration::from_millis(100));
+        stop.stop();
+        let result = handle.join().unwrap();
+        assert!(result.best_move.is_some());
+    }
+
+    #[test]
+    fn multi_pv() {
+        // Only one move mates
+        let board = Board::from_fen("6k1/5ppp/8/8/8/8/1n3PPP/R5K1 w - - 0 1").unwrap();
+        let result = Searcher::new(Limits::depth(3)).with_multi_pv(3).search(&board);
+
+        assert_eq!(result.lines.len(), 3);
+        assert_eq!(result.lines[0].pv, result.pv);
+        assert_eq!(result.best_move, Some(Move::new(Square::A1, Square::A8)));
+        assert_eq!(result.score, MATE - 1);
+        assert!(result.lines.windows(2).all(|lines| lines[0].score >= lines[1].score));
+
+        assert!(!is_mate(result.lines[1].score));
+
+        let roots = result.lines.iter().map(|line| line.pv[0]).collect::<Vec<_>>();
+        assert!(roots.iter().enumerate().all(|(index, root)| !roots[..index].contains(root)));
+
+        // The first line is the result of a single PV search
+        let single = search(&board, Limits::depth(3));
+        assert_eq!((single.best_move, single.score), (result.best_move, result.score));
+
+        // There are fewer lines than asked when there are fewer legal moves
+        let board = Board::from_fen("7k/8/8/8/8/8/6r1/7K w - - 0 1").unwrap();
+        let result = Searcher::new(Limits::depth(2)).with_multi_pv(4).search(&board);
+        assert_eq!(result.lines.len(), MoveGen::new_legal(&board).len());
+
+        // Each promotion is a root move of its own, underpromotions included
+        let board = Board::from_fen("8/4P3/8/8/8/k7/8/7K w - - 0 1").unwrap();
+        let result = Searcher::new(Limits::depth(3)).with_multi_pv(20).search(&board);
+        assert_eq!(result.lines.len(), MoveGen::new_legal(&board).len());
+        let roots = result.lines.iter().map(|line| line.pv[0]).collect::<Vec<_>>();
+        for piece in Piece::PROMOTION_TARGETS {
+            let promotion = Move {
+                origin: Square::E7,
+                destination: Square::E8,
+                promotion: Some(piece),
+            };
+            assert!(roots.contains(&promotion), "{promotion}");
+        }
+    }
+
+    #[test]
+    fn skill() {
+        let board = Board::from_fen("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3").unwrap();
+        let full = search(&board, Limits::depth(3));
+        assert_eq!(Searcher::new(Limits::depth(3)).with_skill(Skill::default(), 1).search(&board).best_move, full.best_move);
+
+        let beginner = |seed| Searcher::new(Limits::depth(3)).with_skill(Skill::new(0), seed).search(&board);
+        let result = beginner(1);
+        assert_eq!(result.depth, 1);
+        assert_eq!(result.lines.len(), Skill::CANDIDATES);
+        assert_eq!(result.pv.first(), result.best_move.as_ref());
+        assert_eq!(beginner(1).best_move, result.best_move);
+
+        // The beginner doesn't always play the same move
+        let moves = (0..20).map(|seed| beginner(seed).best_move).collect::<Vec<_>>();
+        assert!(moves.iter().any(|chess_move| *chess_move != moves[0]));
+
+        // Even a beginner sees a mate in one
+        let board = Board::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        let result = Searcher::new(Limits::default()).with_skill(Skill::new(0), 3).search(&board);
+        assert_eq!(result.best_move, Some(Move::new(Square::A1, Square::A8)));
+    }
+
+    #[test]
+    fn network() {
+        let network = Arc::new(Network::open(eval::nnue::TEST_NETWORK).unwrap());
+
+        // The network only knows about material, which is enough to win the queen
+        let board = Board::from_fen("4k3/8/8/1q6/8/8/8/1R2K3 w - - 0 1").unwrap();
+        let result = Searcher::new(Limits::depth(4)).with_network(Arc::clone(&network)).search(&board);
+        assert_eq!(result.best_move, Some(Move::new(Square::B1, Square::B5)));
+        assert!(result.score > 300, "{}", result.score);
+
+        // The quiet positions are scored by the network
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/3QK3 w - - 0 1").unwrap();
+        let mut searcher = Searcher::new(Limits::depth(1)).with_network(Arc::clone(&network));
+        searcher.search(&board);
+        assert_eq!(searcher.evaluate(&board, 0), network.evaluate_board(&board));
+        assert_ne!(searcher.evaluate(&board, 0), eval::evaluate(&board));
+    }
+
+    #[test]
+    fn tablebase() {
+        let tablebase = Arc::new(Tablebase::open(crate::syzygy::TABLES).unwrap());
+
+        // Only taking the rook wins, the other root moves are not searched
+        let board = Board::from_fen("8/4r3/8/K7/4R3/8/8/5k2 w - - 0 1").unwrap();
+        let result = Searcher::new(Limits::depth(2))
+            .with_tablebase(Arc::clone(&tablebase))
+            .with_multi_pv(3)
+            .search(&board);
+        assert_eq!(result.best_move, Some(Move::new(Square::E4, Square::E7)));
+        assert_eq!(result.lines.len(), 1);
+        assert_eq!(result.score, TB_WIN - 1);
+        assert!(result.tb_hits > 0);
+
+        // Too many pieces
+        let result = Searcher::new(Limits::depth(2)).with_tablebase(tablebase).search(&Board::default());
+        assert_eq!(result.tb_hits, 0);
+    }
+
+    #[test]
+    fn transpositions() {
+        let board = Board::from_fen("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3").unwrap();
+        let tt = Arc::new(TranspositionTable::new(1));
+
+        let first = Searcher::new(Limits::depth(4))
+            .with_transposition_table(Arc::clone(&tt))
+            .search(&board);
+        assert!(tt.stats().hits > 0);
+        assert!(first.hashfull > 0);
+
+        // The second search starts with the results of the first
+        let second = Searcher::new(Limits::depth(4))
+            .with_transposition_table(Arc::clone(&tt))
+            .search(&board);
+        assert!(second.nodes < first.nodes);
+
+        // A given table is used as is, the default one only being created once the search starts
+        let searcher = Searcher::new(Limits::depth(1)).with_transposition_table(Arc::clone(&tt));
+        assert!(searcher.transposition_table().is_some_and(|table| Arc::ptr_eq(table, &tt)));
+        let mut searcher = Searcher::new(Limits::depth(1)).with_threads(2);
+        assert!(searcher.transposition_table().is_none());
+        searcher.search(&board);
+        assert!(searcher.transposition_table().is_some());
+    }
+
+    #[test]
+    fn repetitions() {
+        let mut board = Board::default();
+        let mut hashes = Vec::new();
+        for (origin, destination) in [(Square::G1, Square::F3), (Square::G8, Square::F6), (Square::F3, Square::G1), (Square::F6, Square::G8)] {
+            hashes.push(board.hash());
+            board.make_move(Move::new(origin, destination));
+        }
+
+        assert!(!Searcher::new(Limits::default()).is_draw(&board));
+        assert!(Searcher::new(Limits::default()).with_history(hashes).is_draw(&board));
+    }
+}
@@ -0,0 +1,316 @@
+use crate::{
+    bitboard::BitBoard,
+    eval::piece_value,
+    game::{moves::MoveGen, Board, Move},
+    primitives::{piece::Piece, team::Team, Square},
+    utils::enums::ArrayEnum,
+};
+
+/// The bound of the history scores
+const MAX_HISTORY: i32 = 16_384;
+
+/// The quiet moves that caused beta cutoffs, by team, origin and destination
+///
+/// The scores are kept in `[-MAX_HISTORY, MAX_HISTORY]`: each update moves the score towards the bound by a fraction
+/// of the distance left, so that old cutoffs fade away.
+pub struct History(Box<[[[i32; Square::AMOUNT]; Square::AMOUNT]; Team::SIZE]>);
+
+impl History {
+    pub fn get(&self, team: Team, chess_move: Move) -> i32 {
+        self.0[team.to_index()][chess_move.origin.to_index()][chess_move.destination.to_index()]
+    }
+
+    /// Rewards (positive `bonus`) or penalizes (negative `bonus`) the move
+    pub fn update(&mut self, team: Team, chess_move: Move, bonus: i32) {
+        let bonus = bonus.clamp(-MAX_HISTORY, MAX_HISTORY);
+        let score = &mut self.0[team.to_index()][chess_move.origin.to_index()][chess_move.destination.to_index()];
+
+        *score += bonus - *score * bonus.abs() / MAX_HISTORY;
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self(Box::new([[[0; Square::AMOUNT]; Square::AMOUNT]; Team::SIZE]))
+    }
+}
+
+/// The two last quiet moves that caused a beta cutoff at a given ply
+pub type Killers = [Option<Move>; 2];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Stage {
+    HashMove,
+    GenerateCaptures,
+    GoodCaptures,
+    Killers(usize),
+    GenerateQuiets,
+    Quiets,
+    BadCaptures,
+    Done,
+}
+
+/// Yields the legal moves of a position from the most to the least promising, sorting them lazily:
+///
+/// 1. the hash move
+/// 2. the captures that don't lose material (see `Board::see`), most valuable victim and least valuable attacker first
+/// 3. the killer moves
+/// 4. the other quiet moves, by `History` score (queen promotions first)
+/// 5. the captures that lose material
+///
+/// ```
+/// use engine::{search::picker::{History, MovePicker}, Board, Move, Square};
+///
+/// let board = Board::from_fen("4k3/8/8/3p4/4P3/8/8/R3K3 w - - 0 1").unwrap();
+/// let killer = Move::new(Square::A1, Square::A8);
+/// let mut picker = MovePicker::new(&board, None, [Some(killer), None]);
+///
+/// assert_eq!(picker.next(&History::default()), Some(Move::new(Square::E4, Square::D5)));
+/// assert_eq!(picker.next(&History::default()), Some(killer));
+/// ```
+pub struct MovePicker {
+    board: Board,
+    movegen: MoveGen,
+    stage: Stage,
+    hash_move: Option<Move>,
+    killers: Killers,
+    /// The scored moves of the current stage, the best one last
+    moves: Vec<(i32, Move)>,
+    bad_captures: Vec<(i32, Move)>,
+    captures_only: bool,
+    /// The promotions not to yield, kept apart as `MoveGen::remove_move` removes the promotions to every piece
+    excluded_promotions: Vec<Move>,
+}
+
+impl MovePicker {
+    pub fn new(board: &Board, hash_move: Option<Move>, killers: Killers) -> Self {
+        let movegen = MoveGen::new_legal(board);
+        let hash_move = hash_move.filter(|chess_move| movegen.contains(*chess_move));
+
+        Self {
+            board: *board,
+            movegen,
+            stage: Stage::HashMove,
+            hash_move,
+            killers,
+            moves: Vec::new(),
+            bad_captures: Vec::new(),
+            captures_only: false,
+            excluded_promotions: Vec::new(),
+        }
+    }
+
+    /// Only yields the captures that don't lose material, for the quiescence search
+    pub fn captures(board: &Board) -> Self {
+        Self {
+            stage: Stage::GenerateCaptures,
+            captures_only: true,
+            ..Self::new(board, None, [None; 2])
+        }
+    }
+
+    /// Never yields the given moves, e.g. the root moves of the lines already found by a MultiPV search
+    pub fn excluding(mut self, moves: &[Move]) -> Self {
+        for chess_move in moves {
+            match chess_move.promotion {
+                Some(_) => self.excluded_promotions.push(*chess_move),
+                None => {
+                    self.movegen.remove_move(*chess_move);
+                }
+            }
+        }
+        self.hash_move = self.hash_move.filter(|chess_move| self.is_left(*chess_move));
+
+        return self;
+    }
+
+    /// The next move to search, the `history` scoring the quiet moves
+    pub fn next(&mut self, history: &History) -> Option<Move> {
+        loop {
+            match self.stage {
+                Stage::HashMove => {
+                    self.stage = Stage::GenerateCaptures;
+                    if self.hash_move.is_some() {
+                        return self.hash_move;
+                    }
+                }
+                Stage::GenerateCaptures => {
+                    self.movegen.set_iterator_mask(self.capture_mask());
+
+                    let board = &self.board;
+                    for chess_move in self.movegen.by_ref() {
+                        if Some(chess_move) == self.hash_move || self.excluded_promotions.contains(&chess_move) {
+                            continue;
+                        }
+
+                        match board.see(chess_move) {
+                            see if see < 0 => self.bad_captures.push((see, chess_move)),
+                            _ => self.moves.push((mvv_lva(board, chess_move), chess_move)),
+                        }
+                    }
+                    self.moves.sort_unstable_by_key(|(score, _)| *score);
+                    self.bad_captures.sort_unstable_by_key(|(score, _)| *score);
+
+                    self.stage = Stage::GoodCaptures;
+                }
+                Stage::GoodCaptures => match self.moves.pop() {
+                    Some((_, chess_move)) => return Some(chess_move),
+                    None if self.captures_only => self.stage = Stage::Done,
+                    None => self.stage = Stage::Killers(0),
+                },
+                Stage::Killers(index) => {
+                    self.stage = match index + 1 < self.killers.len() {
+                        true => Stage::Killers(index + 1),
+                        false => Stage::GenerateQuiets,
+                    };
+
+                    let killer = self.killers[index].filter(|killer| {
+                        Some(*killer) != self.hash_move
+                            && !self.capture_mask().has_square(killer.destination)
+                            && self.is_left(*killer)
+                    });
+                    if killer.is_some() {
+                        return killer;
+                    }
+                }
+                Stage::GenerateQuiets => {
+                    // The captures were consumed by the previous stage
+                    self.movegen.set_iterator_mask(BitBoard::FULL);
+
+                    let team = self.board.team_to_move;
+                    for chess_move in self.movegen.by_ref() {
+                        if Some(chess_move) == self.hash_move
+                            || self.killers.contains(&Some(chess_move))
+                            || self.excluded_promotions.contains(&chess_move)
+                        {
+                            continue;
+                        }
+
+                        let score = match chess_move.promotion {
+                            Some(Piece::Queen) => i32::MAX,
+                            Some(_) => i32::MIN,
+                            None => history.get(team, chess_move),
+                        };
+                        self.moves.push((score, chess_move));
+                    }
+                    self.moves.sort_unstable_by_key(|(score, _)| *score);
+
+                    self.stage = Stage::Quiets;
+                }
+                Stage::Quiets => match self.moves.pop() {
+                    Some((_, chess_move)) => return Some(chess_move),
+                    None => self.stage = Stage::BadCaptures,
+                },
+                Stage::BadCaptures => match self.bad_captures.pop() {
+                    Some((_, chess_move)) => return Some(chess_move),
+                    None => self.stage = Stage::Done,
+                },
+                Stage::Done => return None,
+            }
+        }
+    }
+
+    /// Whether the move is one of the legal moves left to yield
+    fn is_left(&self, chess_move: Move) -> bool {
+        self.movegen.contains(chess_move) && !self.excluded_promotions.contains(&chess_move)
+    }
+
+    /// The destinations of the captures, en passant included
+    fn capture_mask(&self) -> BitBoard {
+        let mut mask = self.board.team_mask(!self.board.team_to_move);
+
+        // `Board::en_passant` is the square of the pawn that can be taken, which is moved past
+        if let Some(square) = self.board.en_passant {
+            mask.set_square(square.uforward(self.board.team_to_move));
+        }
+
+        return mask;
+    }
+}
+
+/// Most valuable victim, least valuable attacker
+fn mvv_lva(board: &Board, chess_move: Move) -> i32 {
+    let attacker = board.piece_on(chess_move.origin).unwrap_or(Piece::Pawn);
+    let victim = board.piece_on(chess_move.destination).unwrap_or(Piece::Pawn);
+    let promotion = chess_move.promotion.map_or(0, piece_value);
+
+    return 10 * (piece_value(victim) + promotion) - piece_value(attacker) / 10;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn pick_all(picker: &mut MovePicker, history: &History) -> Vec<Move> {
+        std::iter::from_fn(|| picker.next(history)).collect()
+    }
+
+    #[test]
+    fn stages() {
+        // The knight takes a defended rook, the queen an undefended pawn and a defended one
+        let board = Board::from_fen("4k3/8/2p5/1r2p2p/6p1/2N2Q2/8/4K3 w - - 0 1").unwrap();
+        let legal = MoveGen::new_legal(&board).collect::<Vec<_>>();
+        let hash_move = Move::new(Square::E1, Square::D2);
+        let killer = Move::new(Square::F3, Square::H3);
+        let mut history = History::default();
+        history.update(Team::White, Move::new(Square::F3, Square::D3), 500);
+
+        let mut picker = MovePicker::new(&board, Some(hash_move), [Some(killer), Some(hash_move)]);
+        let moves = pick_all(&mut picker, &history);
+
+        assert_eq!(moves.len(), legal.len());
+        assert!(legal.iter().all(|chess_move| moves.contains(chess_move)));
+        assert_eq!(
+            moves[..5],
+            [
+                hash_move,
+                Move::new(Square::C3, Square::B5),
+                Move::new(Square::F3, Square::C6),
+                killer,
+                Move::new(Square::F3, Square::D3),
+            ]
+        );
+        assert_eq!(moves.last(), Some(&Move::new(Square::F3, Square::G4)));
+
+        let mut picker = MovePicker::captures(&board);
+        assert_eq!(
+            pick_all(&mut picker, &history),
+            [Move::new(Square::C3, Square::B5), Move::new(Square::F3, Square::C6)]
+        );
+
+        // Illegal hash moves and killers are skipped
+        let mut picker = MovePicker::new(&board, Some(Move::new(Square::E1, Square::E3)), [Some(Move::new(Square::C3, Square::C4)), None]);
+        assert_eq!(pick_all(&mut picker, &history).len(), legal.len());
+    }
+
+    #[test]
+    fn en_passant() {
+        let board = Board::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1").unwrap();
+        let mut picker = MovePicker::captures(&board);
+
+        assert_eq!(pick_all(&mut picker, &History::default()), [Move::new(Square::E5, Square::D6)]);
+        assert_eq!(board.see(Move::new(Square::E5, Square::D6)), 100);
+    }
+
+    #[test]
+    fn history() {
+        let mut history = History::default();
+        let chess_move = Move::new(Square::G1, Square::F3);
+
+        for _ in 0..100 {
+            history.update(Team::White, chess_move, 5000);
+        }
+        assert!(history.get(Team::White, chess_move) <= MAX_HISTORY);
+        assert!(history.get(Team::White, chess_move) > MAX_HISTORY * 9 / 10);
+        assert_eq!(history.get(Team::Black, chess_move), 0);
+
+        // Penalties pull the score down faster when it is high
+        let score = history.get(Team::White, chess_move);
+        history.update(Team::White, chess_move, -1000);
+        assert!(history.get(Team::White, chess_move) < score - 1000);
+    }
+}
@@ -0,0 +1,159 @@
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+
+use super::{Limits, PvLine};
+
+/// How strongly the engine plays, from level 0 (a beginner can beat it) to `Skill::MAX_LEVEL` (full strength)
+///
+/// The weaker levels search shallower, fewer nodes, and pick their move among the best lines of a MultiPV search,
+/// each line being picked with a probability that decreases with its score. The lower the level, the more likely the
+/// worse lines are.
+///
+/// ```
+/// use engine::search::{skill::Skill, Limits};
+///
+/// let skill = Skill::from_elo(1500);
+/// assert_eq!(skill.level(), 7);
+/// assert_eq!(skill.elo(), 1500);
+///
+/// assert!(skill.limits(Limits::default()).depth.is_some());
+/// assert_eq!(Skill::default().limits(Limits::default()), Limits::default());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Skill {
+    level: u8,
+}
+
+impl Skill {
+    pub const MAX_LEVEL: u8 = 20;
+    /// The approximate Elo rating of level 0
+    pub const MIN_ELO: u32 = 800;
+    /// The approximate Elo rating of the full strength
+    pub const MAX_ELO: u32 = 2800;
+    /// The number of lines the weakened levels pick their move from
+    pub const CANDIDATES: usize = 4;
+
+    /// The skill of the given level, at most `Skill::MAX_LEVEL`
+    pub fn new(level: u8) -> Self {
+        Self {
+            level: level.min(Self::MAX_LEVEL),
+        }
+    }
+
+    /// The level closest to an approximate Elo rating, clamped to `[MIN_ELO, MAX_ELO]`
+    pub fn from_elo(elo: u32) -> Self {
+        let elo = elo.clamp(Self::MIN_ELO, Self::MAX_ELO) - Self::MIN_ELO;
+        let range = Self::MAX_ELO - Self::MIN_ELO;
+
+        return Self::new(((elo * Self::MAX_LEVEL as u32 + range / 2) / range) as u8);
+    }
+
+    pub fn level(&self) -> u8 {
+        self.level
+    }
+
+    /// The approximate Elo rating of the level, each level being worth 100 points
+    pub fn elo(&self) -> u32 {
+        Self::MIN_ELO + (Self::MAX_ELO - Self::MIN_ELO) * self.level as u32 / Self::MAX_LEVEL as u32
+    }
+
+    /// Whether the engine plays weaker than it can
+    pub fn is_limited(&self) -> bool {
+        self.level < Self::MAX_LEVEL
+    }
+
+    /// The `limits` of a search at this level, the stricter of both being kept
+    pub fn limits(&self, limits: Limits) -> Limits {
+        if !self.is_limited() {
+            return limits;
+        }
+
+        let depth = 1 + self.level / 2;
+        let nodes = (250.0 * 1.7_f64.powi(self.level as i32)) as u64;
+
+        return Limits {
+            depth: Some(limits.depth.map_or(depth, |limit| limit.min(depth))),
+            nodes: Some(limits.nodes.map_or(nodes, |limit| limit.min(nodes))),
+            time: limits.time,
+        };
+    }
+
+    /// How much worse (in centipawns) a line can be while keeping a fair chance to be picked, 0 at full strength
+    pub fn temperature(&self) -> f64 {
+        (Self::MAX_LEVEL - self.level) as f64 * 10.0
+    }
+
+    /// Picks the index of one of the `lines` (best first), with a probability of `exp(-loss / temperature)` relative
+    /// to the best one, the same seed always picking the same line
+    pub fn pick(&self, lines: &[PvLine], seed: u64) -> Option<usize> {
+        let best = lines.first()?.score;
+        let temperature = self.temperature();
+        if temperature <= 0.0 {
+            return Some(0);
+        }
+
+        let weights = lines
+            .iter()
+            .map(|line| (-((best - line.score).max(0) as f64) / temperature).exp())
+            .collect::<Vec<_>>();
+        let mut target = SmallRng::seed_from_u64(seed).gen::<f64>() * weights.iter().sum::<f64>();
+
+        return Some(
+            weights
+                .iter()
+                .position(|weight| {
+                    target -= weight;
+                    target < 0.0
+                })
+                .unwrap_or(0),
+        );
+    }
+}
+
+/// Full strength
+impl Default for Skill {
+    fn default() -> Self {
+        Self::new(Self::MAX_LEVEL)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::search::MATE;
+
+    fn line(score: i32) -> PvLine {
+        PvLine { score, pv: Vec::new() }
+    }
+
+    #[test]
+    fn levels() {
+        assert_eq!(Skill::from_elo(0), Skill::new(0));
+        assert_eq!(Skill::from_elo(5000), Skill::default());
+        assert_eq!(Skill::new(200), Skill::default());
+        assert!((0..=Skill::MAX_LEVEL).all(|level| Skill::from_elo(Skill::new(level).elo()) == Skill::new(level)));
+
+        // Stricter limits are kept
+        let limits = Skill::new(10).limits(Limits::depth(3));
+        assert_eq!(limits.depth, Some(3));
+        assert!(limits.nodes.is_some());
+        assert!(Skill::new(2).limits(Limits::default()).nodes < Skill::new(12).limits(Limits::default()).nodes);
+    }
+
+    #[test]
+    fn pick() {
+        let lines = [line(50), line(20), line(-300), line(-MATE + 3)];
+        let picks = |skill: Skill| (0..500).map(|seed| skill.pick(&lines, seed).unwrap()).collect::<Vec<_>>();
+
+        assert!(picks(Skill::default()).iter().all(|index| *index == 0));
+        assert_eq!(Skill::new(0).pick(&lines, 7), Skill::new(0).pick(&lines, 7));
+        assert_eq!(Skill::new(0).pick(&[], 7), None);
+
+        // The weaker levels play the second best move more often, and never walk into a mate
+        let beginner = picks(Skill::new(0));
+        let club = picks(Skill::new(15));
+        let seconds = |picks: &[usize]| picks.iter().filter(|index| **index == 1).count();
+        assert!(seconds(&beginner) > seconds(&club));
+        assert!(seconds(&beginner) > 100);
+        assert!(!beginner.contains(&3));
+    }
+}
@@ -0,0 +1,374 @@
+use std::{
+    fmt::Display,
+    sync::atomic::{AtomicU64, AtomicU8, Ordering},
+};
+
+use crate::{
+    eval::Score,
+    game::Move,
+    primitives::{piece::Piece, Square},
+    utils::enums::ArrayEnum,
+};
+
+use super::{is_mate, MAX_PLY};
+
+/// The default size of the table, in MB
+pub const DEFAULT_SIZE_MB: usize = 16;
+
+/// How the stored score relates to the real score of the position
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bound {
+    /// The score is exact
+    Exact,
+    /// The search failed high: the real score is at least the stored one
+    Lower,
+    /// The search failed low: the real score is at most the stored one
+    Upper,
+}
+
+/// What the table knows about a position
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TtEntry {
+    pub best_move: Option<Move>,
+    /// The score, mate scores being relative to the probing ply
+    pub score: Score,
+    pub depth: u8,
+    pub bound: Bound,
+    /// The search (see `TranspositionTable::new_search`) the entry was stored in
+    pub age: u8,
+}
+
+impl TtEntry {
+    /// Packs the entry in 48 bits: move (16), score (16), depth (8), bound (2) and age (6)
+    fn pack(&self) -> u64 {
+        let chess_move = match self.best_move {
+            None => 0,
+            Some(chess_move) => {
+                let promotion = chess_move.promotion.map_or(0, |piece| piece.to_index() as u64 + 1);
+
+                1 << 15 | promotion << 12 | (chess_move.origin.to_int() as u64) << 6 | chess_move.destination.to_int() as u64
+            }
+        };
+        let bound = match self.bound {
+            Bound::Exact => 0,
+            Bound::Lower => 1,
+            Bound::Upper => 2,
+        };
+
+        return chess_move
+            | (self.score as i16 as u16 as u64) << 16
+            | (self.depth as u64) << 32
+            | bound << 40
+            | ((self.age & 0x3F) as u64) << 42;
+    }
+
+    fn unpack(data: u64) -> Self {
+        let best_move = match data & 1 << 15 {
+            0 => None,
+            _ => Some(Move::new_with_promotion(
+                Square::new((data >> 6 & 0x3F) as u8),
+                Square::new((data & 0x3F) as u8),
+                match data >> 12 & 0x7 {
+                    0 => None,
+                    piece => Some(Piece::ALL[piece as usize - 1]),
+                },
+            )),
+        };
+        let bound = match data >> 40 & 0x3 {
+            0 => Bound::Exact,
+            1 => Bound::Lower,
+            _ => Bound::Upper,
+        };
+
+        return Self {
+            best_move,
+            score: (data >> 16) as u16 as i16 as Score,
+            depth: (data >> 32) as u8,
+            bound,
+            age: (data >> 42 & 0x3F) as u8,
+        };
+    }
+}
+
+/// A slot of the table, storing the key xor-ed with the data so that torn writes from other threads are detected
+#[derive(Default)]
+struct Slot {
+    key: AtomicU64,
+    data: AtomicU64,
+}
+
+/// The usage statistics of the table, since it was created or cleared
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TtStats {
+    pub probes: u64,
+    pub hits: u64,
+    pub stores: u64,
+    /// The stores that replaced an entry of another position
+    pub overwrites: u64,
+}
+
+impl TtStats {
+    /// The ratio of probes that found their position, in `[0, 1]`
+    pub fn hit_rate(&self) -> f64 {
+        self.hits as f64 / self.probes.max(1) as f64
+    }
+}
+
+impl Display for TtStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "probes {} hits {} ({:.1}%) stores {} overwrites {}",
+            self.probes,
+            self.hits,
+            100.0 * self.hit_rate(),
+            self.stores,
+            self.overwrites
+        )
+    }
+}
+
+/// A fixed-size, lock-free hash table of the positions searched, indexed by `Board::hash`
+///
+/// It can be shared between threads, the entries whose key and data were not written together are ignored.
+///
+/// ```
+/// use engine::{search::tt::{Bound, TranspositionTable}, Board, Move, Square};
+///
+/// let table = TranspositionTable::new(1);
+/// let hash = Board::default().hash();
+/// let best_move = Some(Move::new(Square::E2, Square::E4));
+///
+/// assert_eq!(table.probe(hash, 0), None);
+/// table.store(hash, 5, 30, Bound::Exact, best_move, 0);
+///
+/// let entry = table.probe(hash, 0).unwrap();
+/// assert_eq!((entry.best_move, entry.score, entry.depth, entry.bound), (best_move, 30, 5, Bound::Exact));
+/// assert_eq!(table.stats().hits, 1);
+///
+/// table.clear();
+/// assert_eq!(table.probe(hash, 0), None);
+/// ```
+pub struct TranspositionTable {
+    slots: Vec<Slot>,
+    age: AtomicU8,
+    probes: AtomicU64,
+    hits: AtomicU64,
+    stores: AtomicU64,
+    overwrites: AtomicU64,
+}
+
+impl TranspositionTable {
+    /// Creates a table using about `size_mb` MB of memory (at least one entry)
+    pub fn new(size_mb: usize) -> Self {
+        let count = (size_mb * 1024 * 1024 / std::mem::size_of::<Slot>()).max(1);
+
+        Self {
+            slots: (0..count).map(|_| Slot::default()).collect(),
+            age: AtomicU8::new(0),
+            probes: AtomicU64::new(0),
+            hits: AtomicU64::new(0),
+            stores: AtomicU64::new(0),
+            overwrites: AtomicU64::new(0),
+        }
+    }
+
+    /// The number of entries the table can hold
+    pub fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// The size of the table, in MB
+    pub fn size_mb(&self) -> usize {
+        self.slots.len() * std::mem::size_of::<Slot>() / (1024 * 1024)
+    }
+
+    /// Empties the table and resets the statistics, e.g. between two games
+    pub fn clear(&self) {
+        for slot in self.slots.iter() {
+            slot.key.store(0, Ordering::Relaxed);
+            slot.data.store(0, Ordering::Relaxed);
+        }
+
+        self.age.store(0, Ordering::Relaxed);
+        for counter in [&self.probes, &self.hits, &self.stores, &self.overwrites] {
+            counter.store(0, Ordering::Relaxed);
+        }
+    }
+
+    /// Ages the table, so that the entries of the previous searches get replaced first
+    pub fn new_search(&self) {
+        let age = self.age.load(Ordering::Relaxed);
+        self.age.store((age + 1) & 0x3F, Ordering::Relaxed);
+    }
+
+    #[inline]
+    fn slot(&self, hash: u64) -> &Slot {
+        let index = ((hash as u128 * self.slots.len() as u128) >> 64) as usize;
+
+        return &self.slots[index];
+    }
+
+    /// Looks the position up, adjusting the mate scores to be relative to `ply`
+    pub fn probe(&self, hash: u64, ply: usize) -> Option<TtEntry> {
+        let slot = self.slot(hash);
+        let data = slot.data.load(Ordering::Relaxed);
+        let key = slot.key.load(Ordering::Relaxed);
+
+        self.probes.fetch_add(1, Ordering::Relaxed);
+        if data == 0 || key ^ data != hash {
+            return None;
+        }
+        self.hits.fetch_add(1, Ordering::Relaxed);
+
+        let mut entry = TtEntry::unpack(data);
+        entry.score = score_from_tt(entry.score, ply);
+
+        return Some(entry);
+    }
+
+    /// Stores the result of a search of the position at `ply`
+    ///
+    /// The entries of the current search are only replaced by deeper or exact ones, or by the same position.
+    pub fn store(&self, hash: u64, depth: u8, score: Score, bound: Bound, best_move: Option<Move>, ply: usize) {
+        let slot = self.slot(hash);
+        let age = self.age.load(Ordering::Relaxed);
+
+        let old_data = slot.data.load(Ordering::Relaxed);
+        let old_key = slot.key.load(Ordering::Relaxed) ^ old_data;
+
+        let mut best_move = best_move;
+        if old_data != 0 {
+            let old = TtEntry::unpack(old_data);
+
+            if old_key == hash {
+                // Keep the move of a previous search that failed low
+                best_move = best_move.or(old.best_move);
+            } else if old.age == age && old.depth > depth && bound != Bound::Exact {
+                return;
+            } else {
+                self.overwrites.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        let entry = TtEntry {
+            best_move,
+            score: score_to_tt(score, ply),
+            depth,
+            bound,
+            age,
+        };
+        let data = entry.pack();
+
+        slot.key.store(hash ^ data, Ordering::Relaxed);
+        slot.data.store(data, Ordering::Relaxed);
+        self.stores.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// The permille of the table used by the current search, estimated on the first 1000 entries as in UCI's `hashfull`
+    pub fn hashfull(&self) -> usize {
+        let age = self.age.load(Ordering::Relaxed);
+        let sample = self.slots.len().min(1000);
+
+        let used = self.slots[..sample]
+            .iter()
+            .map(|slot| slot.data.load(Ordering::Relaxed))
+            .filter(|data| *data != 0 && TtEntry::unpack(*data).age == age)
+            .count();
+
+        return used * 1000 / sample;
+    }
+
+    pub fn stats(&self) -> TtStats {
+        TtStats {
+            probes: self.probes.load(Ordering::Relaxed),
+            hits: self.hits.load(Ordering::Relaxed),
+            stores: self.stores.load(Ordering::Relaxed),
+            overwrites: self.overwrites.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        Self::new(DEFAULT_SIZE_MB)
+    }
+}
+
+/// Mate scores are stored relative to the position, rather than to the root
+fn score_to_tt(score: Score, ply: usize) -> Score {
+    match is_mate(score) {
+        true => score + score.signum() * ply as Score,
+        false => score,
+    }
+}
+
+fn score_from_tt(score: Score, ply: usize) -> Score {
+    match is_mate(score) {
+        true => score - score.signum() * ply.min(MAX_PLY) as Score,
+        false => score,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::search::MATE;
+
+    #[test]
+    fn entries() {
+        for entry in [
+            TtEntry {
+                best_move: None,
+                score: -MATE,
+                depth: 0,
+                bound: Bound::Upper,
+                age: 63,
+            },
+            TtEntry {
+                best_move: Some(Move::new_with_promotion(Square::H7, Square::G8, Some(Piece::Knight))),
+                score: 12345,
+                depth: 255,
+                bound: Bound::Lower,
+                age: 1,
+            },
+        ] {
+            assert_eq!(TtEntry::unpack(entry.pack()), entry);
+        }
+
+        let table = TranspositionTable::new(1);
+        assert_eq!(table.size_mb(), 1);
+
+        // Mate in 3 plies from the root, found at ply 2: the position itself is a mate in 1 ply
+        table.store(42, 3, MATE - 3, Bound::Exact, None, 2);
+        assert_eq!(table.probe(42, 2).unwrap().score, MATE - 3);
+        assert_eq!(table.probe(42, 0).unwrap().score, MATE - 1);
+        assert_eq!(table.probe(42, 4).unwrap().score, MATE - 5);
+    }
+
+    #[test]
+    fn replacement() {
+        let table = TranspositionTable::new(0);
+        assert_eq!(table.capacity(), 1);
+
+        table.store(1, 8, 10, Bound::Lower, Some(Move::new(Square::E2, Square::E4)), 0);
+        // Shallower entries of other positions don't replace the ones of the current search...
+        table.store(2, 4, 20, Bound::Lower, None, 0);
+        assert_eq!(table.probe(2, 0), None);
+        assert_eq!(table.probe(1, 0).unwrap().depth, 8);
+
+        // ... unless they are exact, or the position is the same
+        table.store(1, 2, 30, Bound::Upper, None, 0);
+        let entry = table.probe(1, 0).unwrap();
+        assert_eq!((entry.depth, entry.best_move), (2, Some(Move::new(Square::E2, Square::E4))));
+
+        // Old entries are always replaced
+        table.new_search();
+        table.store(2, 1, 20, Bound::Upper, None, 0);
+        assert_eq!(table.probe(2, 0).unwrap().age, 1);
+        assert_eq!(table.hashfull(), 1000);
+
+        let stats = table.stats();
+        assert_eq!((stats.probes, stats.hits, stats.stores, stats.overwrites), (4, 3, 3, 1));
+    }
+}
@@ -0,0 +1,666 @@
+use std::{
+    collections::HashMap,
+    ffi::OsStr,
+    fmt::Display,
+    io,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+use crate::{
+    game::{moves::MoveGen, Board, BoardStatus, Move},
+    primitives::{CastleRights, Piece, Team},
+};
+
+mod table;
+pub use table::MAX_PIECES;
+use table::{Material, Table, TableKind};
+
+/// The 3 and 4 piece tables shipped with the crate, a small subset of the Syzygy tables used by the tests
+pub const TABLES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/data/syzygy");
+
+/// The outcome of a position with perfect play, for the team to move
+///
+/// The tables tell the wins that can't be forced before the fifty-move rule applies from the other ones.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Serialize, serde::Deserialize, rspc::Type,
+)]
+pub enum Wdl {
+    Loss,
+    /// A loss that is a draw under the fifty-move rule
+    BlessedLoss,
+    Draw,
+    /// A win that is a draw under the fifty-move rule
+    CursedWin,
+    Win,
+}
+
+impl Wdl {
+    /// The value of the outcome, from -2 (loss) to 2 (win)
+    pub fn value(self) -> i32 {
+        self as i32 - 2
+    }
+
+    /// The verdict of the endgame review, `team_to_move` being the team the outcome is for
+    ///
+    /// ```
+    /// use engine::{syzygy::Wdl, Team};
+    ///
+    /// assert_eq!(Wdl::Loss.review(Team::Black), "This is a tablebase win for White");
+    /// assert_eq!(Wdl::Draw.review(Team::White), "This is a tablebase draw");
+    /// ```
+    pub fn review(self, team_to_move: Team) -> String {
+        match self {
+            Self::Win => format!("This is a tablebase win for {team_to_move:?}"),
+            Self::Loss => format!("This is a tablebase win for {:?}", !team_to_move),
+            Self::CursedWin => {
+                format!("This is a tablebase win for {team_to_move:?}, but the fifty-move rule makes it a draw")
+            }
+            Self::BlessedLoss => format!(
+                "This is a tablebase win for {:?}, but the fifty-move rule makes it a draw",
+                !team_to_move
+            ),
+            Self::Draw => "This is a tablebase draw".to_owned(),
+        }
+    }
+
+    fn from_value(value: i32) -> Self {
+        match value {
+            ..=-2 => Self::Loss,
+            -1 => Self::BlessedLoss,
+            0 => Self::Draw,
+            1 => Self::CursedWin,
+            2.. => Self::Win,
+        }
+    }
+}
+
+/// The same outcome, for the other team
+impl std::ops::Neg for Wdl {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self::from_value(-self.value())
+    }
+}
+
+impl Display for Wdl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let outcome = match self {
+            Self::Loss => "loss",
+            Self::BlessedLoss => "blessed loss",
+            Self::Draw => "draw",
+            Self::CursedWin => "cursed win",
+            Self::Win => "win",
+        };
+
+        write!(f, "{outcome}")
+    }
+}
+
+/// Everything that can go wrong while opening tablebases
+#[derive(Debug)]
+pub enum TablebaseError {
+    Io(io::Error),
+    /// One of the paths is not a directory
+    NotADirectory(PathBuf),
+}
+
+impl Display for TablebaseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "Could not read the tablebases: {error}"),
+            Self::NotADirectory(path) => write!(f, "{} is not a directory", path.display()),
+        }
+    }
+}
+
+impl std::error::Error for TablebaseError {}
+
+impl From<io::Error> for TablebaseError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+/// The WDL and DTZ files of a material, read on their first probe
+struct Entry {
+    material: Material,
+    paths: [Option<PathBuf>; 2],
+    tables: [OnceLock<Option<Table>>; 2],
+}
+
+impl Entry {
+    fn table(&self, kind: TableKind) -> Option<&Table> {
+        let index = kind as usize;
+        let path = self.paths[index].as_ref()?;
+
+        return self.tables[index]
+            .get_or_init(|| {
+                let data = std::fs::read(path).ok()?;
+                Table::parse(data, kind, self.material)
+            })
+            .as_ref();
+    }
+}
+
+/// Syzygy endgame tablebases: the outcome (WDL) and distance to zeroing (DTZ) of every position with few pieces
+///
+/// The distance to zeroing is the number of plies to the next capture or pawn move, with the best play for the
+/// outcome. Positions with castling rights are never probed. The files are read on their first probe.
+///
+/// ```
+/// use engine::{syzygy::{Tablebase, Wdl, TABLES}, Board};
+///
+/// let tablebase = Tablebase::open(TABLES).unwrap();
+///
+/// let board = Board::from_fen("8/8/8/2R5/1K6/8/5k2/8 w - - 0 1").unwrap();
+/// assert_eq!(board.probe_wdl(&tablebase), Some(Wdl::Win));
+/// assert_eq!(board.probe_dtz(&tablebase), Some(21));
+///
+/// // Too many pieces
+/// assert_eq!(Board::default().probe_wdl(&tablebase), None);
+/// ```
+#[derive(Default)]
+pub struct Tablebase {
+    /// The tables, by material key of the names of their files
+    entries: HashMap<u64, Entry>,
+    files: usize,
+    max_pieces: usize,
+}
+
+impl Tablebase {
+    /// Creates a tablebase without any table
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Opens the tables of the directories in `paths`, separated like in the `PATH` environment variable
+    pub fn open(paths: impl AsRef<OsStr>) -> Result<Self, TablebaseError> {
+        let mut tablebase = Self::new();
+
+        for path in std::env::split_paths(&paths).filter(|path| !path.as_os_str().is_empty()) {
+            tablebase.add_directory(path)?;
+        }
+
+        return Ok(tablebase);
+    }
+
+    /// Adds the `.rtbw` and `.rtbz` files of a directory, returning the number of files added
+    pub fn add_directory(&mut self, path: impl AsRef<Path>) -> Result<usize, TablebaseError> {
+        let path = path.as_ref();
+        if !path.is_dir() {
+            return Err(TablebaseError::NotADirectory(path.to_owned()));
+        }
+
+        let mut count = 0;
+        for file in std::fs::read_dir(path)? {
+            let path = file?.path();
+            let (Some(name), Some(extension)) = (path.file_stem().and_then(OsStr::to_str), path.extension()) else {
+                continue;
+            };
+            let Some(kind) = [TableKind::Wdl, TableKind::Dtz]
+                .into_iter()
+                .find(|kind| extension == kind.extension())
+            else {
+                continue;
+            };
+            // The names are canonical, e.g. `KRvKQ` is not a table
+            let Some(material) = Material::parse(name).filter(|material| material.name() == name) else {
+                continue;
+            };
+
+            let entry = self.entries.entry(material.key()).or_insert_with(|| Entry {
+                material,
+                paths: [None, None],
+                tables: [OnceLock::new(), OnceLock::new()],
+            });
+            if entry.paths[kind as usize].replace(path).is_none() {
+                self.files += 1;
+                count += 1;
+            }
+            if kind == TableKind::Wdl {
+                self.max_pieces = self.max_pieces.max(material.len());
+            }
+        }
+
+        return Ok(count);
+    }
+
+    /// The number of files of the tablebase
+    pub fn len(&self) -> usize {
+        self.files
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files == 0
+    }
+
+    /// The number of pieces, kings included, of the largest WDL tables
+    pub fn max_pieces(&self) -> usize {
+        self.max_pieces
+    }
+
+    /// Whether the `board` has few enough pieces, and no castling rights, to be probed
+    pub fn can_probe(&self, board: &Board) -> bool {
+        board
+            .castle_rights
+            .iter()
+            .all(|rights| *rights == CastleRights::NoRights)
+            && board.all_mask().count_bits() as usize <= self.max_pieces
+    }
+
+    /// The outcome of the `board` for the team to move, `None` if it isn't in the tablebase
+    ///
+    /// The halfmove clock of the `board` is not taken into account.
+    pub fn probe_wdl(&self, board: &Board) -> Option<Wdl> {
+        if !self.can_probe(board) {
+            return None;
+        }
+
+        return self.wdl(board).map(|(wdl, _)| Wdl::from_value(wdl));
+    }
+
+    /// The distance to zeroing of the `board` in plies, positive when the team to move wins, negative when it loses
+    /// and 0 for draws, `None` if it isn't in the tablebase
+    ///
+    /// A win in `n` plies may be reported as a win in `n + 1` plies when that doesn't matter under the fifty-move rule.
+    /// Cursed wins and blessed losses are more than 100 plies away from zeroing.
+    pub fn probe_dtz(&self, board: &Board) -> Option<i32> {
+        if !self.can_probe(board) {
+            return None;
+        }
+
+        return self.dtz(board);
+    }
+
+    /// The legal moves that keep the best outcome of the `board`, `None` if it isn't in the tablebase
+    ///
+    /// With the DTZ tables, only the moves that zero the fastest when winning (or the slowest when losing) are kept,
+    /// so that a search among them makes progress. The halfmove clock of the `board` decides whether a win can still
+    /// be forced before the fifty-move rule applies.
+    pub fn best_moves(&self, board: &Board) -> Option<Vec<Move>> {
+        if !self.can_probe(board) {
+            return None;
+        }
+
+        let moves = MoveGen::new_legal(board).collect::<Vec<_>>();
+        let ranks = self
+            .rank_by_dtz(board, &moves)
+            .or_else(|| self.rank_by_wdl(&moves, board))?;
+        let best = ranks.iter().copied().max()?;
+
+        return Some(
+            moves
+                .into_iter()
+                .zip(ranks)
+                .filter(|(_, rank)| *rank == best)
+                .map(|(chess_move, _)| chess_move)
+                .collect(),
+        );
+    }
+
+    /// Ranks the `moves` by the distance to zeroing of the positions they lead to, the greater the better
+    fn rank_by_dtz(&self, board: &Board, moves: &[Move]) -> Option<Vec<i32>> {
+        const BIG: i32 = 0x40000;
+        let halfmove_clock = board.halfmove_clock as i32;
+
+        return moves
+            .iter()
+            .map(|chess_move| {
+                let mut next = *board;
+                next.make_move(*chess_move);
+
+                let mut dtz = match next.halfmove_clock {
+                    0 => dtz_before_zeroing(-self.wdl(&next)?.0),
+                    _ => {
+                        let dtz = -self.dtz(&next)?;
+                        dtz + dtz.signum()
+                    }
+                };
+                if dtz == 2 && next.status() == BoardStatus::Checkmate {
+                    dtz = 1;
+                }
+
+                let rank = match dtz {
+                    1.. if dtz + halfmove_clock <= 100 => 2 * BIG - dtz,
+                    1.. => BIG - dtz,
+                    ..=-1 if -dtz + halfmove_clock <= 100 => -2 * BIG - dtz,
+                    ..=-1 => -BIG - dtz,
+                    0 => 0,
+                };
+                Some(rank)
+            })
+            .collect();
+    }
+
+    /// Ranks the `moves` by the outcome of the positions they lead to
+    fn rank_by_wdl(&self, moves: &[Move], board: &Board) -> Option<Vec<i32>> {
+        moves
+            .iter()
+            .map(|chess_move| {
+                let mut next = *board;
+                next.make_move(*chess_move);
+                self.wdl(&next).map(|(wdl, _)| -wdl)
+            })
+            .collect()
+    }
+
+    /// The table of the material of the `board`
+    fn table(&self, board: &Board, kind: TableKind) -> Option<&Table> {
+        let material = Material::from_board(board);
+        let entry = self
+            .entries
+            .get(&material.key())
+            .or_else(|| self.entries.get(&material.flipped().key()))?;
+
+        return entry.table(kind);
+    }
+
+    /// The WDL value stored in the table of the `board`, 0 with only the kings left
+    fn probe_table(&self, board: &Board) -> Option<i32> {
+        if board.all_mask().count_bits() == 2 {
+            return Some(0);
+        }
+
+        return self.table(board, TableKind::Wdl)?.probe(board, 0);
+    }
+
+    /// The best of `alpha` and the outcomes of the captures, the tables not accounting for them
+    fn probe_ab(&self, board: &Board, mut alpha: i32, beta: i32) -> Option<i32> {
+        for chess_move in MoveGen::new_legal(board).filter(|chess_move| is_capture(board, *chess_move)) {
+            let mut next = *board;
+            next.make_move(chess_move);
+
+            let value = -self.probe_ab(&next, -beta, -alpha)?;
+            if value > alpha {
+                if value >= beta {
+                    return Some(value);
+                }
+                alpha = value;
+            }
+        }
+
+        let value = self.probe_table(board)?;
+        return Some(alpha.max(value));
+    }
+
+    /// The WDL value of the `board`, and whether a capture (so a zeroing move) is the best move
+    ///
+    /// The tables ignore en passant captures, which may be the only legal moves.
+    fn wdl(&self, board: &Board) -> Option<(i32, bool)> {
+        let mut best_capture = -3;
+        let mut best_en_passant = -3;
+        let mut others = false;
+
+        for chess_move in MoveGen::new_legal(board) {
+            if !is_capture(board, chess_move) {
+                others = true;
+                continue;
+            }
+
+            let mut next = *board;
+            next.make_move(chess_move);
+
+            let value = -self.probe_ab(&next, -2, -best_capture)?;
+            if value == 2 {
+                return Some((2, true));
+            }
+            match is_en_passant(board, chess_move) {
+                false => {
+                    others = true;
+                    best_capture = best_capture.max(value);
+                }
+                true => best_en_passant = best_en_passant.max(value),
+            }
+        }
+
+        let value = self.probe_table(board)?;
+
+        if best_en_passant > best_capture {
+            if best_en_passant > value {
+                return Some((best_en_passant, true));
+            }
+            best_capture = best_en_passant;
+        }
+
+        if best_capture >= value {
+            return Some((best_capture, best_capture > 0));
+        }
+
+        // Stalemate but for the en passant captures
+        if best_en_passant > -3 && value == 0 && !others && board.checkers.is_empty() {
+            return Some((best_en_passant, true));
+        }
+
+        return Some((value, false));
+    }
+
+    fn dtz(&self, board: &Board) -> Option<i32> {
+        let (wdl, zeroing) = self.wdl(board)?;
+        if wdl == 0 {
+            return Some(0);
+        }
+        if zeroing {
+            return Some(dtz_before_zeroing(wdl));
+        }
+
+        // The best move may be a pawn move, the tables not accounting for them either
+        if wdl > 0 {
+            for chess_move in MoveGen::new_legal(board) {
+                if board.piece_on(chess_move.origin) != Some(Piece::Pawn) || is_capture(board, chess_move) {
+                    continue;
+                }
+
+                let mut next = *board;
+                next.make_move(chess_move);
+                if -self.wdl(&next)?.0 == wdl {
+                    return Some(dtz_before_zeroing(wdl));
+                }
+            }
+        }
+
+        if let Some(dtz) = self.table(board, TableKind::Dtz)?.probe(board, wdl) {
+            return Some(dtz_before_zeroing(wdl) + wdl.signum() * dtz);
+        }
+
+        // The table only stores the other team to move: one ply more than the best move
+        let mut best = match wdl > 0 {
+            true => i32::MAX,
+            false => dtz_before_zeroing(wdl),
+        };
+        for chess_move in MoveGen::new_legal(board) {
+            let zeroing = is_capture(board, chess_move) || board.piece_on(chess_move.origin) == Some(Piece::Pawn);
+            if wdl > 0 && zeroing {
+                continue;
+            }
+
+            let mut next = *board;
+            next.make_move(chess_move);
+
+            let value = match zeroing {
+                true => dtz_before_zeroing(-self.wdl(&next)?.0),
+                false => {
+                    let dtz = -self.dtz(&next)?;
+                    dtz + dtz.signum()
+                }
+            };
+            if value.signum() == wdl.signum() && value < best {
+                best = value;
+            }
+        }
+
+        return Some(if best == i32::MAX { 1 } else { best });
+    }
+}
+
+impl std::fmt::Debug for Tablebase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Tablebase")
+            .field("files", &self.files)
+            .field("max_pieces", &self.max_pieces)
+            .finish()
+    }
+}
+
+/// The distance to zeroing of a position with the `wdl` value whose best move zeroes
+fn dtz_before_zeroing(wdl: i32) -> i32 {
+    match wdl {
+        2 => 1,
+        1 => 101,
+        -1 => -101,
+        -2 => -1,
+        _ => 0,
+    }
+}
+
+fn is_en_passant(board: &Board, chess_move: Move) -> bool {
+    board
+        .en_passant
+        .is_some_and(|square| square.uforward(board.team_to_move) == chess_move.destination)
+        && board.piece_on(chess_move.origin) == Some(Piece::Pawn)
+}
+
+fn is_capture(board: &Board, chess_move: Move) -> bool {
+    board.team_on(chess_move.destination) == Some(!board.team_to_move) || is_en_passant(board, chess_move)
+}
+
+impl Board {
+    /// The outcome of the position for the team to move, if it is in the `tablebase` (see `Tablebase::probe_wdl`)
+    pub fn probe_wdl(&self, tablebase: &Tablebase) -> Option<Wdl> {
+        tablebase.probe_wdl(self)
+    }
+
+    /// The distance to zeroing of the position, if it is in the `tablebase` (see `Tablebase::probe_dtz`)
+    pub fn probe_dtz(&self, tablebase: &Tablebase) -> Option<i32> {
+        tablebase.probe_dtz(self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::primitives::Square;
+
+    fn tablebase() -> Tablebase {
+        Tablebase::open(TABLES).unwrap()
+    }
+
+    /// Positions and their WDL and DTZ values, the DTZ being `None` without the DTZ table
+    const POSITIONS: [(&str, i32, Option<i32>); 16] = [
+        ("8/4r3/8/K7/4R3/8/8/5k2 w - - 0 1", 2, Some(1)),
+        ("8/8/R2k4/8/8/K7/8/4r3 b - - 0 1", 0, Some(0)),
+        ("R7/8/6k1/8/8/8/2K4r/8 w - - 0 1", 0, Some(0)),
+        ("8/3r4/7K/5R2/8/8/8/5k2 b - - 0 1", 0, Some(0)),
+        ("8/8/1k6/8/2R2R2/8/6K1/8 w - - 0 1", 2, None),
+        ("8/5p2/6k1/K7/8/8/8/8 w - - 0 1", -2, Some(-2)),
+        ("7k/1R6/8/8/8/8/4r3/K7 w - - 0 1", 0, Some(0)),
+        ("8/8/8/2K5/5kp1/8/8/8 b - - 0 1", 2, Some(1)),
+        ("8/8/8/8/4k3/8/8/1R2R1K1 b - - 0 1", -2, None),
+        ("8/6K1/6R1/6R1/7k/8/8/8 w - - 0 1", 2, None),
+        ("8/8/2r5/8/k7/3K4/6r1/8 w - - 0 1", -2, None),
+        ("8/7k/K7/6r1/8/5R2/8/8 w - - 0 1", 0, Some(0)),
+        ("8/8/8/2R5/1K6/8/5k2/8 w - - 0 1", 2, Some(21)),
+        ("R7/8/6R1/8/7k/1K6/8/8 b - - 0 1", -2, None),
+        ("8/3k4/8/8/8/8/4P3/3K4 w - - 0 1", 0, Some(0)),
+        ("6k1/8/8/8/8/4n3/8/K7 b - - 0 1", 0, Some(0)),
+    ];
+
+    #[test]
+    fn open() {
+        let tablebase = tablebase();
+        assert_eq!(tablebase.len(), 13);
+        assert_eq!(tablebase.max_pieces(), 4);
+
+        assert!(matches!(
+            Tablebase::open("Cargo.toml"),
+            Err(TablebaseError::NotADirectory(_))
+        ));
+        assert!(Tablebase::open("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn probes() {
+        let tablebase = tablebase();
+
+        for (fen, wdl, dtz) in POSITIONS {
+            let board = Board::from_fen(fen).unwrap();
+            assert_eq!(board.probe_wdl(&tablebase), Some(Wdl::from_value(wdl)), "{fen}");
+            assert_eq!(board.probe_dtz(&tablebase), dtz, "{fen}");
+        }
+
+        // Castling rights, and material without tables
+        assert_eq!(
+            Board::from_fen("4k3/8/8/8/8/8/8/R3K3 w Q - 0 1")
+                .unwrap()
+                .probe_wdl(&tablebase),
+            None
+        );
+        assert_eq!(
+            Board::from_fen("4k3/8/8/8/8/8/8/QQ2K3 w - - 0 1")
+                .unwrap()
+                .probe_wdl(&tablebase),
+            None
+        );
+        assert_eq!(
+            Board::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1")
+                .unwrap()
+                .probe_dtz(&tablebase),
+            Some(0)
+        );
+    }
+
+    #[test]
+    fn colors() {
+        let tablebase = tablebase();
+
+        // The same positions, with the colors swapped and the board mirrored
+        for (white, black) in [
+            ("8/8/8/8/8/8/2P5/k1K5 w - - 0 1", "K1k5/2p5/8/8/8/8/8/8 b - - 0 1"),
+            ("8/8/8/8/4k3/8/8/3QK3 b - - 0 1", "3qk3/8/8/4K3/8/8/8/8 w - - 0 1"),
+        ] {
+            let white = Board::from_fen(white).unwrap();
+            let black = Board::from_fen(black).unwrap();
+            assert!(white.probe_wdl(&tablebase).is_some());
+            assert_eq!(white.probe_wdl(&tablebase), black.probe_wdl(&tablebase));
+            assert_eq!(white.probe_dtz(&tablebase), black.probe_dtz(&tablebase));
+        }
+    }
+
+    #[test]
+    fn best_moves() {
+        let tablebase = tablebase();
+
+        // Only the rook takes the rook
+        let board = Board::from_fen("8/4r3/8/K7/4R3/8/8/5k2 w - - 0 1").unwrap();
+        assert_eq!(
+            tablebase.best_moves(&board),
+            Some(vec![crate::Move::new(Square::E4, Square::E7)])
+        );
+
+        // Every move of a lost position loses, the best ones the slowest
+        let board = Board::from_fen("8/8/8/2R5/1K6/8/5k2/8 b - - 0 1").unwrap();
+        let moves = tablebase.best_moves(&board).unwrap();
+        assert!(!moves.is_empty());
+        for chess_move in moves {
+            let mut next = board;
+            next.make_move(chess_move);
+            assert_eq!(next.probe_wdl(&tablebase), Some(Wdl::Win));
+        }
+
+        // Without the DTZ tables, the moves that keep the win: not the one that stalemates
+        let board = Board::from_fen("k7/8/1K6/8/8/8/8/1R5R w - - 0 1").unwrap();
+        let moves = tablebase.best_moves(&board).unwrap();
+        assert_eq!(moves.len(), MoveGen::new_legal(&board).len() - 1);
+        assert!(!moves.contains(&crate::Move::new(Square::B6, Square::A6)));
+    }
+
+    #[test]
+    fn wdl() {
+        assert_eq!(-Wdl::CursedWin, Wdl::BlessedLoss);
+        assert_eq!(-Wdl::Draw, Wdl::Draw);
+        assert!(Wdl::Win > Wdl::CursedWin);
+        assert_eq!(Wdl::Loss.value(), -2);
+        assert_eq!(Wdl::BlessedLoss.to_string(), "blessed loss");
+    }
+}
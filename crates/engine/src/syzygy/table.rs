@@ -0,0 +1,1073 @@
+use crate::{
+    game::Board,
+    primitives::{piece::Piece, team::Team},
+    utils::enums::ArrayEnum,
+};
+
+/// The maximum number of pieces of a table, kings included
+pub const MAX_PIECES: usize = 7;
+
+/// The two kinds of table files
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TableKind {
+    /// Win, draw or loss, for both teams to move
+    Wdl,
+    /// Distance to zeroing (capture or pawn move), for one of the teams to move
+    Dtz,
+}
+
+impl TableKind {
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Wdl => "rtbw",
+            Self::Dtz => "rtbz",
+        }
+    }
+
+    fn magic(self) -> [u8; 4] {
+        match self {
+            Self::Wdl => [0x71, 0xe8, 0x23, 0x5d],
+            Self::Dtz => [0xd7, 0x66, 0x0c, 0xa5],
+        }
+    }
+}
+
+/// The pieces of a table, by team and piece
+///
+/// The team named first in the file name (e.g. `KQ` in `KQvKR`) is called White, whatever the team of the probed
+/// position: the positions whose White has the material of Black are probed with the colors flipped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Material {
+    counts: [[u8; Piece::SIZE]; Team::SIZE],
+}
+
+impl Material {
+    /// Parses the name of a table, e.g. `KRPvKR`
+    pub fn parse(name: &str) -> Option<Self> {
+        let (white, black) = name.split_once('v')?;
+        let mut counts = [[0; Piece::SIZE]; Team::SIZE];
+
+        for (team, pieces) in [(Team::White, white), (Team::Black, black)] {
+            for letter in pieces.chars() {
+                let piece = match letter {
+                    'P' => Piece::Pawn,
+                    'N' => Piece::Knight,
+                    'B' => Piece::Bishop,
+                    'R' => Piece::Rook,
+                    'Q' => Piece::Queen,
+                    'K' => Piece::King,
+                    _ => return None,
+                };
+                counts[team.to_index()][piece.to_index()] += 1;
+            }
+        }
+
+        let material = Self { counts };
+        let valid =
+            Team::ALL.iter().all(|team| material.count(*team, Piece::King) == 1) && material.len() <= MAX_PIECES;
+
+        return valid.then_some(material);
+    }
+
+    pub fn from_board(board: &Board) -> Self {
+        let mut counts = [[0; Piece::SIZE]; Team::SIZE];
+
+        for team in Team::ALL {
+            for piece in Piece::ALL {
+                counts[team.to_index()][piece.to_index()] = board.get_mask(piece, team).count_bits() as u8;
+            }
+        }
+
+        return Self { counts };
+    }
+
+    /// The same material, with the teams swapped
+    pub fn flipped(&self) -> Self {
+        Self {
+            counts: [self.counts[1], self.counts[0]],
+        }
+    }
+
+    pub fn count(&self, team: Team, piece: Piece) -> usize {
+        self.counts[team.to_index()][piece.to_index()] as usize
+    }
+
+    /// The number of pieces, kings included
+    pub fn len(&self) -> usize {
+        self.counts.iter().flatten().map(|count| *count as usize).sum()
+    }
+
+    pub fn has_pawns(&self) -> bool {
+        self.count(Team::White, Piece::Pawn) + self.count(Team::Black, Piece::Pawn) > 0
+    }
+
+    /// A key identifying the material, four bits per team and piece
+    pub fn key(&self) -> u64 {
+        self.counts
+            .iter()
+            .flatten()
+            .enumerate()
+            .fold(0, |key, (index, count)| key | ((*count as u64).min(0xF) << (4 * index)))
+    }
+
+    /// Whether both teams have the same pieces
+    pub fn is_symmetric(&self) -> bool {
+        self.counts[0] == self.counts[1]
+    }
+
+    /// The name of the table, e.g. `KRPvKR`
+    pub fn name(&self) -> String {
+        let mut name = String::new();
+
+        for team in Team::ALL {
+            if team == Team::Black {
+                name.push('v');
+            }
+            for (piece, letter) in [
+                (Piece::King, 'K'),
+                (Piece::Queen, 'Q'),
+                (Piece::Rook, 'R'),
+                (Piece::Bishop, 'B'),
+                (Piece::Knight, 'N'),
+                (Piece::Pawn, 'P'),
+            ] {
+                name.extend(std::iter::repeat(letter).take(self.count(team, piece)));
+            }
+        }
+
+        return name;
+    }
+}
+
+/// How the pieces of a sub-table are turned into an index
+#[derive(Debug, Clone, Copy, Default)]
+struct Encoding {
+    /// The pieces in the order they are indexed, white pawn to king being 1 to 6 and black ones 9 to 14
+    pieces: [u8; MAX_PIECES],
+    /// The size of each group of pieces indexed together, at the index of the first piece of the group
+    norm: [u8; MAX_PIECES],
+    /// The factor of the index of each group, at the index of the first piece of the group
+    factor: [u64; MAX_PIECES],
+}
+
+/// The Huffman-like compressed values of a sub-table, as positions in the file
+#[derive(Debug, Clone)]
+enum Pairs {
+    /// Every position has the same value
+    Constant([u8; 2]),
+    Compressed {
+        block_size: u8,
+        index_bits: u8,
+        min_length: u8,
+        index_table: usize,
+        size_table: usize,
+        blocks: usize,
+        /// The first symbol of each code length, from `min_length`
+        offsets: usize,
+        symbols: usize,
+        /// The number of values covered by each symbol, minus one
+        symbol_lengths: Vec<u8>,
+        /// The smallest code of each length, left-aligned, from `min_length`
+        base: Vec<u64>,
+    },
+}
+
+/// A sub-table: the positions with a given side to move and, in pawn tables, a given file of the leading pawn
+#[derive(Debug, Clone)]
+struct SubTable {
+    encoding: Encoding,
+    pairs: Pairs,
+}
+
+/// The DTZ values of a file are stored for one side to move only, and may be mapped through small tables
+#[derive(Debug, Clone, Copy, Default)]
+struct DtzMap {
+    flags: u8,
+    /// The positions of the maps in the file, by WDL value
+    maps: [usize; 4],
+}
+
+impl DtzMap {
+    /// The DTZ values are stored for Black to move
+    const BLACK: u8 = 1;
+    const MAPPED: u8 = 2;
+    /// The winning values are stored in plies rather than moves
+    const WIN_PLIES: u8 = 4;
+    const LOSS_PLIES: u8 = 8;
+    /// The maps store 16-bit values
+    const WIDE: u8 = 16;
+}
+
+/// A table file, read in memory
+pub struct Table {
+    kind: TableKind,
+    data: Vec<u8>,
+    /// The material of the table as named, White being the left side
+    material: Material,
+    /// Whether the table only has two unique pieces, the kings, whose positions are then indexed together
+    kings_only_unique: bool,
+    /// The pawns of the leading team (the one with the fewest pawns, but some) and of the other one
+    pawns: [usize; 2],
+    /// The sub-tables, by file of the leading pawn (a single one without pawns) then by side to move
+    sub_tables: Vec<[Option<SubTable>; 2]>,
+    /// The DTZ maps, by file of the leading pawn
+    dtz_maps: Vec<DtzMap>,
+}
+
+impl Table {
+    /// Reads the content of a file of the given kind and material, `None` if it isn't a valid table
+    pub fn parse(data: Vec<u8>, kind: TableKind, material: Material) -> Option<Self> {
+        if data.get(..4)? != kind.magic() {
+            return None;
+        }
+
+        let piece_count = material.len();
+        let has_pawns = material.has_pawns();
+        let unique = Team::ALL
+            .iter()
+            .flat_map(|team| Piece::ALL.iter().map(|piece| material.count(*team, *piece)))
+            .filter(|count| *count == 1)
+            .count();
+
+        let (white_pawns, black_pawns) = (
+            material.count(Team::White, Piece::Pawn),
+            material.count(Team::Black, Piece::Pawn),
+        );
+        let pawns = match black_pawns > 0 && (white_pawns == 0 || white_pawns > black_pawns) {
+            true => [black_pawns, white_pawns],
+            false => [white_pawns, black_pawns],
+        };
+
+        let mut table = Self {
+            kind,
+            data: Vec::new(),
+            material,
+            kings_only_unique: !has_pawns && unique == 2,
+            pawns,
+            sub_tables: Vec::new(),
+            dtz_maps: Vec::new(),
+        };
+
+        let split = kind == TableKind::Wdl && data[4] & 1 != 0;
+        let files = if has_pawns { 4 } else { 1 };
+        let more_pawns = has_pawns && pawns[1] > 0;
+        let mut position = 5;
+
+        // The order of the pieces of each sub-table, for White to move in the low nibbles and Black in the high ones
+        let mut encodings = Vec::with_capacity(files);
+        for file in 0..files {
+            let header = data.get(position..position + piece_count + 1 + more_pawns as usize)?;
+            let white = table.encoding(header, 0, file)?;
+            let black = match split {
+                true => Some(table.encoding(header, 4, file)?),
+                false => None,
+            };
+
+            encodings.push([Some(white), black]);
+            position += header.len();
+        }
+        position += position & 1;
+
+        let mut sizes = Vec::with_capacity(files);
+        for encodings in encodings.iter() {
+            let mut file_pairs = [None, None];
+            let mut file_sizes = [[0; 3]; 2];
+
+            for side in 0..2 {
+                let Some((_, size)) = encodings[side] else {
+                    continue;
+                };
+
+                let (pairs, flags, pair_sizes) = setup_pairs(&data, &mut position, size, kind)?;
+                if kind == TableKind::Dtz {
+                    table.dtz_maps.push(DtzMap {
+                        flags,
+                        ..Default::default()
+                    });
+                }
+
+                file_pairs[side] = Some(pairs);
+                file_sizes[side] = pair_sizes;
+            }
+
+            table.sub_tables.push([
+                encodings[0]
+                    .zip(file_pairs[0].take())
+                    .map(|((encoding, _), pairs)| SubTable { encoding, pairs }),
+                encodings[1]
+                    .zip(file_pairs[1].take())
+                    .map(|((encoding, _), pairs)| SubTable { encoding, pairs }),
+            ]);
+            sizes.push(file_sizes);
+        }
+
+        if kind == TableKind::Dtz {
+            for map in table.dtz_maps.iter_mut().filter(|map| map.flags & DtzMap::MAPPED != 0) {
+                for index in 0..4 {
+                    match map.flags & DtzMap::WIDE {
+                        0 => {
+                            map.maps[index] = position + 1;
+                            position += 1 + *data.get(position)? as usize;
+                        }
+                        _ => {
+                            position += position & 1;
+                            map.maps[index] = position + 2;
+                            position += 2 + 2 * read_u16(&data, position) as usize;
+                        }
+                    }
+                }
+            }
+            position += position & 1;
+        }
+
+        // The index tables of every sub-table, then their size tables, then their 64-byte aligned data
+        for (part, align) in [(0, 1), (1, 1), (2, 64)] {
+            for (file, file_sizes) in sizes.iter().enumerate() {
+                for (side, size) in file_sizes.iter().enumerate() {
+                    let Some(SubTable {
+                        pairs:
+                            Pairs::Compressed {
+                                index_table,
+                                size_table,
+                                blocks,
+                                ..
+                            },
+                        ..
+                    }) = &mut table.sub_tables[file][side]
+                    else {
+                        continue;
+                    };
+
+                    position = position.next_multiple_of(align);
+                    *[index_table, size_table, blocks][part] = position;
+                    position += size[part];
+                }
+            }
+        }
+
+        if position > data.len() {
+            return None;
+        }
+        table.data = data;
+
+        return Some(table);
+    }
+
+    /// Reads the order of the pieces and computes the factors of their indexes, returning them with the size of the
+    /// sub-table
+    fn encoding(&self, header: &[u8], shift: u8, file: usize) -> Option<(Encoding, u64)> {
+        let piece_count = self.material.len();
+        let has_pawns = self.material.has_pawns();
+        let more_pawns = has_pawns && self.pawns[1] > 0;
+        let mut encoding = Encoding::default();
+
+        for index in 0..piece_count {
+            encoding.pieces[index] = header[index + 1 + more_pawns as usize] >> shift & 0xF;
+        }
+        let order = (header[0] >> shift & 0xF) as usize;
+        let order2 = match more_pawns {
+            true => (header[1] >> shift & 0xF) as usize,
+            false => 0xF,
+        };
+
+        encoding.norm[0] = match (has_pawns, self.kings_only_unique) {
+            (true, _) => self.pawns[0] as u8,
+            (false, true) => 2,
+            (false, false) => 3,
+        };
+        let mut k = encoding.norm[0] as usize;
+        if more_pawns {
+            encoding.norm[k] = self.pawns[1] as u8;
+            k += encoding.norm[k] as usize;
+        }
+
+        let mut index = k;
+        while index < piece_count {
+            let group = encoding.pieces[index..piece_count]
+                .iter()
+                .take_while(|piece| **piece == encoding.pieces[index])
+                .count();
+            encoding.norm[index] = group as u8;
+            index += group;
+        }
+
+        let mut free = 64 - k as u64;
+        let mut size = 1;
+        let mut group = 0;
+        while k < piece_count || group == order || group == order2 {
+            if group == order {
+                encoding.factor[0] = size;
+                size *= match (has_pawns, self.kings_only_unique) {
+                    (true, _) => PAWN_FACTOR[encoding.norm[0] as usize - 1][file],
+                    (false, true) => 462,
+                    (false, false) => 31332,
+                };
+            } else if group == order2 {
+                let leading = encoding.norm[0] as usize;
+                encoding.factor[leading] = size;
+                size *= BINOMIAL[encoding.norm[leading] as usize][48 - leading];
+            } else {
+                encoding.factor[k] = size;
+                size *= BINOMIAL[encoding.norm[k] as usize][free as usize];
+                free -= encoding.norm[k] as u64;
+                k += encoding.norm[k] as usize;
+            }
+
+            group += 1;
+            if group > MAX_PIECES + 1 || k > piece_count {
+                return None;
+            }
+        }
+
+        return Some((encoding, size));
+    }
+
+    /// The value stored for the `board`: the WDL value in `[-2, 2]`, or the DTZ value for a position whose WDL value
+    /// is `wdl`
+    ///
+    /// `None` when the DTZ table only stores the other side to move.
+    pub fn probe(&self, board: &Board, wdl: i32) -> Option<i32> {
+        let symmetric = self.material.is_symmetric();
+        let flip = match symmetric {
+            true => board.team_to_move == Team::Black,
+            false => Material::from_board(board).key() != self.material.key(),
+        };
+        // The side to move, in the colors of the table
+        let black_to_move = !symmetric && (board.team_to_move == Team::White) == flip;
+        // The pawn tables are only symmetric along the files, the ranks are mirrored with the colors
+        let mirror = if flip && self.material.has_pawns() { 0x38 } else { 0 };
+
+        let mut squares = [0; MAX_PIECES];
+        let mut filled = 0;
+        let mut file = 0;
+        if self.material.has_pawns() {
+            let encoding = &self.sub_tables[0][0].as_ref()?.encoding;
+            filled = fill_squares(board, &encoding.pieces, flip, mirror, &mut squares, 0);
+            file = self.leading_pawn_file(&mut squares);
+        }
+
+        let side = match self.kind {
+            TableKind::Wdl => black_to_move as usize,
+            TableKind::Dtz => {
+                let stored_black = self.dtz_maps[file].flags & DtzMap::BLACK != 0;
+                if !symmetric && stored_black != black_to_move {
+                    return None;
+                }
+                0
+            }
+        };
+
+        let sub_table = self.sub_tables[file][side].as_ref()?;
+        let piece_count = self.material.len();
+        while filled < piece_count {
+            filled = fill_squares(board, &sub_table.encoding.pieces, flip, mirror, &mut squares, filled);
+        }
+
+        let index = self.index(&sub_table.encoding, &mut squares[..piece_count]);
+        let value = self.decompress(&sub_table.pairs, index);
+
+        return Some(match self.kind {
+            TableKind::Wdl => value[0] as i32 - 2,
+            TableKind::Dtz => self.dtz_value(file, wdl, value[0] as usize | (value[1] as usize & 0xF) << 8),
+        });
+    }
+
+    /// Maps the stored DTZ `value` of a position whose WDL value is `wdl`, in plies
+    fn dtz_value(&self, file: usize, wdl: i32, value: usize) -> i32 {
+        let map = self.dtz_maps[file];
+        let mut value = value as i32;
+
+        if map.flags & DtzMap::MAPPED != 0 {
+            let start = map.maps[[1, 3, 0, 2, 0][(wdl + 2) as usize]];
+            value = match map.flags & DtzMap::WIDE {
+                0 => self.data.get(start + value as usize).copied().unwrap_or(0) as i32,
+                _ => read_u16(&self.data, start + 2 * value as usize) as i32,
+            };
+        }
+
+        let plies = match wdl {
+            2 => map.flags & DtzMap::WIN_PLIES != 0,
+            -2 => map.flags & DtzMap::LOSS_PLIES != 0,
+            _ => false,
+        };
+
+        return if plies { value } else { 2 * value };
+    }
+
+    /// Moves the leading pawn with the smallest index first, returning the index of its file (a to d)
+    fn leading_pawn_file(&self, squares: &mut [usize]) -> usize {
+        for index in 1..self.pawns[0] {
+            if FLAP[squares[0]] > FLAP[squares[index]] {
+                squares.swap(0, index);
+            }
+        }
+
+        let file = squares[0] & 7;
+        return file.min(7 - file);
+    }
+
+    /// The index of the position in the sub-table, the `squares` being in the order of the `encoding`
+    fn index(&self, encoding: &Encoding, squares: &mut [usize]) -> u64 {
+        let piece_count = squares.len();
+
+        // The first piece is always on the queen side
+        if squares[0] & 0x4 != 0 {
+            squares.iter_mut().for_each(|square| *square ^= 0x7);
+        }
+
+        let mut index;
+        let mut k;
+        if !self.material.has_pawns() {
+            // ... and on the first ranks
+            if squares[0] & 0x20 != 0 {
+                squares.iter_mut().for_each(|square| *square ^= 0x38);
+            }
+
+            // ... and below the a1-h8 diagonal, the first piece that isn't on it deciding
+            let leading = if self.kings_only_unique { 2 } else { 3 };
+            if let Some(index) = squares.iter().position(|square| OFF_DIAGONAL[*square] != 0) {
+                if OFF_DIAGONAL[squares[index]] > 0 && index < leading {
+                    squares
+                        .iter_mut()
+                        .for_each(|square| *square = FLIP_DIAGONAL[*square] as usize);
+                }
+            }
+
+            index = match self.kings_only_unique {
+                true => KK_INDEX[TRIANGLE[squares[0]] as usize][squares[1]] as u64,
+                false => {
+                    let s1 = (squares[1] > squares[0]) as usize;
+                    let s2 = (squares[2] > squares[0]) as usize + (squares[2] > squares[1]) as usize;
+
+                    (if OFF_DIAGONAL[squares[0]] != 0 {
+                        TRIANGLE[squares[0]] as usize * 63 * 62 + (squares[1] - s1) * 62 + (squares[2] - s2)
+                    } else if OFF_DIAGONAL[squares[1]] != 0 {
+                        6 * 63 * 62
+                            + DIAGONAL[squares[0]] as usize * 28 * 62
+                            + LOWER[squares[1]] as usize * 62
+                            + squares[2]
+                            - s2
+                    } else if OFF_DIAGONAL[squares[2]] != 0 {
+                        6 * 63 * 62
+                            + 4 * 28 * 62
+                            + DIAGONAL[squares[0]] as usize * 7 * 28
+                            + (DIAGONAL[squares[1]] as usize - s1) * 28
+                            + LOWER[squares[2]] as usize
+                    } else {
+                        6 * 63 * 62
+                            + 4 * 28 * 62
+                            + 4 * 7 * 28
+                            + DIAGONAL[squares[0]] as usize * 7 * 6
+                            + (DIAGONAL[squares[1]] as usize - s1) * 6
+                            + (DIAGONAL[squares[2]] as usize - s2)
+                    }) as u64
+                }
+            };
+            k = if self.kings_only_unique { 2 } else { 3 };
+            index *= encoding.factor[0];
+        } else {
+            // The other leading pawns, by decreasing index
+            k = self.pawns[0];
+            squares[1..k].sort_unstable_by_key(|square| std::cmp::Reverse(PAWN_TWIST[*square]));
+
+            index = PAWN_INDEX[k - 1][FLAP[squares[0]] as usize];
+            for (position, square) in squares.iter().enumerate().take(k).skip(1) {
+                index += BINOMIAL[k - position][PAWN_TWIST[*square] as usize];
+            }
+            index *= encoding.factor[0];
+
+            // The pawns of the other team, on the 48 squares of the ranks 2 to 7
+            if self.pawns[1] > 0 {
+                let end = k + self.pawns[1];
+                index += group_index(squares, k, end, 8) * encoding.factor[k];
+                k = end;
+            }
+        }
+
+        while k < piece_count {
+            let end = k + encoding.norm[k] as usize;
+            index += group_index(squares, k, end, 0) * encoding.factor[k];
+            k = end;
+        }
+
+        return index;
+    }
+
+    /// The value of the position at `index`, as the two bytes of its symbol
+    fn decompress(&self, pairs: &Pairs, index: u64) -> [u8; 2] {
+        let Pairs::Compressed {
+            block_size,
+            index_bits,
+            min_length,
+            index_table,
+            size_table,
+            blocks,
+            offsets,
+            symbols,
+            symbol_lengths,
+            base,
+        } = pairs
+        else {
+            let Pairs::Constant(value) = pairs else { unreachable!() };
+            return *value;
+        };
+        let data = &self.data;
+
+        // The index table points to a block and the offset of the position in it, every 2^index_bits positions
+        let main_index = (index >> index_bits) as usize;
+        let mut literal = (index & ((1 << index_bits) - 1)) as i64 - (1 << (index_bits - 1));
+        let mut block = read_u32(data, index_table + 6 * main_index) as usize;
+        literal += read_u16(data, index_table + 6 * main_index + 4) as i64;
+
+        let block_len = |block: usize| read_u16(data, size_table + 2 * block) as i64 + 1;
+        while literal < 0 && block > 0 {
+            block -= 1;
+            literal += block_len(block);
+        }
+        while literal >= block_len(block) && block + 1 < (data.len() - size_table) / 2 {
+            literal -= block_len(block);
+            block += 1;
+        }
+
+        // Reads the symbols of the block until the one covering the position
+        let mut position = blocks + (block << block_size);
+        let mut code = read_u64_be(data, position);
+        position += 8;
+        let mut bits = 0;
+        let mut symbol;
+        loop {
+            let mut length = 0;
+            while length + 1 < base.len() && code < base[length] {
+                length += 1;
+            }
+
+            symbol = read_u16(data, offsets + 2 * length) as usize
+                + ((code - base[length]) >> (64 - (length + *min_length as usize))) as usize;
+            let covered = symbol_lengths.get(symbol).map_or(1, |length| *length as i64 + 1);
+            if literal < covered {
+                break;
+            }
+
+            literal -= covered;
+            code <<= length + *min_length as usize;
+            bits += length + *min_length as usize;
+            if bits >= 32 {
+                bits -= 32;
+                code |= (read_u32_be(data, position) as u64) << bits;
+                position += 4;
+            }
+        }
+
+        // Symbols are pairs of symbols, down to the value of the position
+        while symbol_lengths.get(symbol).is_some_and(|length| *length > 0) {
+            let pattern = symbols + 3 * symbol;
+            let left = (data[pattern + 1] as usize & 0xF) << 8 | data[pattern] as usize;
+
+            if literal < symbol_lengths[left] as i64 + 1 {
+                symbol = left;
+            } else {
+                literal -= symbol_lengths[left] as i64 + 1;
+                symbol = (data[pattern + 2] as usize) << 4 | (data[pattern + 1] as usize) >> 4;
+            }
+        }
+
+        let pattern = symbols + 3 * symbol;
+        return [
+            data.get(pattern).copied().unwrap_or(0),
+            data.get(pattern + 1).copied().unwrap_or(0),
+        ];
+    }
+}
+
+/// Reads the header of the compressed values of a sub-table of `size` positions, returning them with the flags of the
+/// sub-table and the sizes of their index table, size table and data
+fn setup_pairs(data: &[u8], position: &mut usize, size: u64, kind: TableKind) -> Option<(Pairs, u8, [usize; 3])> {
+    let header = data.get(*position..*position + 10)?;
+    let flags = header[0];
+
+    if flags & 0x80 != 0 {
+        let value = match kind {
+            TableKind::Wdl => header[1],
+            TableKind::Dtz => 0,
+        };
+        *position += 2;
+
+        return Some((Pairs::Constant([value, 0]), flags, [0; 3]));
+    }
+
+    let block_size = header[1];
+    let index_bits = header[2];
+    let real_blocks = u32::from_le_bytes(header[4..8].try_into().unwrap()) as usize;
+    let block_count = real_blocks + header[3] as usize;
+    let (max_length, min_length) = (header[8], header[9]);
+    if index_bits == 0 || min_length == 0 || max_length < min_length || max_length > 64 || block_size > 32 {
+        return None;
+    }
+
+    let lengths = (max_length - min_length + 1) as usize;
+    let offsets = *position + 10;
+    let symbol_count = read_u16(data, offsets + 2 * lengths) as usize;
+    let symbols = offsets + 2 * lengths + 2;
+    if symbols + 3 * symbol_count > data.len() || symbol_count >= 4096 {
+        return None;
+    }
+
+    // base[i] is the first code of length min_length + i, left-aligned
+    let mut base = vec![0u64; lengths];
+    for length in (0..lengths - 1).rev() {
+        let first = read_u16(data, offsets + 2 * length) as u64;
+        let next = read_u16(data, offsets + 2 * length + 2) as u64;
+        base[length] = (base[length + 1] + first - next) / 2;
+    }
+    for (length, base) in base.iter_mut().enumerate() {
+        *base = base.checked_shl(64 - (min_length as u32 + length as u32)).unwrap_or(0);
+    }
+
+    let mut symbol_lengths = vec![0; symbol_count];
+    let mut visited = vec![false; symbol_count];
+    for symbol in 0..symbol_count {
+        symbol_length(data, symbols, symbol, &mut symbol_lengths, &mut visited);
+    }
+
+    *position = symbols + 3 * symbol_count + (symbol_count & 1);
+    let sizes = [
+        6 * size.div_ceil(1 << index_bits) as usize,
+        2 * block_count,
+        real_blocks << block_size,
+    ];
+    let pairs = Pairs::Compressed {
+        block_size,
+        index_bits,
+        min_length,
+        index_table: 0,
+        size_table: 0,
+        blocks: 0,
+        offsets,
+        symbols,
+        symbol_lengths,
+        base,
+    };
+
+    return Some((pairs, flags, sizes));
+}
+
+/// Computes the number of values covered by the `symbol`, minus one, once those of the symbols it is made of are known
+fn symbol_length(data: &[u8], symbols: usize, symbol: usize, lengths: &mut [u8], visited: &mut [bool]) {
+    if visited[symbol] {
+        return;
+    }
+    visited[symbol] = true;
+
+    let pattern = &data[symbols + 3 * symbol..symbols + 3 * symbol + 3];
+    let right = (pattern[2] as usize) << 4 | (pattern[1] as usize) >> 4;
+    if right == 0xFFF {
+        lengths[symbol] = 0;
+        return;
+    }
+
+    let left = (pattern[1] as usize & 0xF) << 8 | pattern[0] as usize;
+    if left >= lengths.len() || right >= lengths.len() {
+        return;
+    }
+    symbol_length(data, symbols, left, lengths, visited);
+    symbol_length(data, symbols, right, lengths, visited);
+    lengths[symbol] = lengths[left].saturating_add(lengths[right]).saturating_add(1);
+}
+
+/// Writes the squares of the pieces of the same kind as `pieces[start]`, returning the index after the last one
+fn fill_squares(board: &Board, pieces: &[u8], flip: bool, mirror: usize, squares: &mut [usize], start: usize) -> usize {
+    let code = pieces[start];
+    let team = match (code >> 3 == 0) != flip {
+        true => Team::White,
+        false => Team::Black,
+    };
+    let piece = Piece::ALL[(code & 0x7) as usize - 1];
+
+    let mut index = start;
+    for square in board.get_mask(piece, team) {
+        squares[index] = square.to_index() ^ mirror;
+        index += 1;
+    }
+
+    return index;
+}
+
+/// The index of the combination of the squares of a group of identical pieces, skipping the squares of the pieces
+/// before them and the first `offset` squares
+fn group_index(squares: &mut [usize], start: usize, end: usize, offset: usize) -> u64 {
+    squares[start..end].sort_unstable();
+
+    let mut index = 0;
+    for position in start..end {
+        let square = squares[position];
+        let skips = squares[..start].iter().filter(|other| square > **other).count();
+        index += BINOMIAL[position - start + 1][square - skips - offset];
+    }
+
+    return index;
+}
+
+fn read_u16(data: &[u8], position: usize) -> u16 {
+    data.get(position..position + 2)
+        .map_or(0, |bytes| u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_u32(data: &[u8], position: usize) -> u32 {
+    data.get(position..position + 4)
+        .map_or(0, |bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+fn read_u32_be(data: &[u8], position: usize) -> u32 {
+    let mut bytes = [0; 4];
+    for (index, byte) in bytes.iter_mut().enumerate() {
+        *byte = data.get(position + index).copied().unwrap_or(0);
+    }
+
+    return u32::from_be_bytes(bytes);
+}
+
+fn read_u64_be(data: &[u8], position: usize) -> u64 {
+    (read_u32_be(data, position) as u64) << 32 | read_u32_be(data, position + 4) as u64
+}
+
+// [Index tables]
+/// `BINOMIAL[k][n]` is the number of ways to pick `k` squares among `n`
+const BINOMIAL: [[u64; 64]; MAX_PIECES] = {
+    let mut table = [[0; 64]; MAX_PIECES];
+    let mut k = 0;
+    while k < MAX_PIECES {
+        let mut n = 0;
+        while n < 64 {
+            let (mut numerator, mut denominator) = (1u64, 1u64);
+            let mut i = 0;
+            while i < k {
+                numerator *= (n as u64).saturating_sub(i as u64);
+                denominator *= i as u64 + 1;
+                i += 1;
+            }
+            table[k][n] = numerator / denominator;
+            n += 1;
+        }
+        k += 1;
+    }
+    table
+};
+
+/// The first index of the leading pawns by count (minus one) and `FLAP` index of the first pawn
+const PAWN_INDEX: [[u64; 24]; 6] = pawn_indexes().0;
+/// The number of positions of the leading pawns by count (minus one) and file (a to d)
+const PAWN_FACTOR: [[u64; 4]; 6] = pawn_indexes().1;
+
+const fn pawn_indexes() -> ([[u64; 24]; 6], [[u64; 4]; 6]) {
+    let mut indexes = [[0; 24]; 6];
+    let mut factors = [[0; 4]; 6];
+
+    let mut count = 0;
+    while count < 6 {
+        let mut sum = 0;
+        let mut index = 0;
+        while index < 24 {
+            indexes[count][index] = sum;
+            sum += BINOMIAL[count][PAWN_TWIST[(1 + index % 6) * 8 + index / 6] as usize];
+            if (index + 1) % 6 == 0 {
+                factors[count][index / 6] = sum;
+                sum = 0;
+            }
+            index += 1;
+        }
+        count += 1;
+    }
+
+    return (indexes, factors);
+}
+
+#[rustfmt::skip]
+const OFF_DIAGONAL: [i8; 64] = [
+    0, -1, -1, -1, -1, -1, -1, -1,
+    1,  0, -1, -1, -1, -1, -1, -1,
+    1,  1,  0, -1, -1, -1, -1, -1,
+    1,  1,  1,  0, -1, -1, -1, -1,
+    1,  1,  1,  1,  0, -1, -1, -1,
+    1,  1,  1,  1,  1,  0, -1, -1,
+    1,  1,  1,  1,  1,  1,  0, -1,
+    1,  1,  1,  1,  1,  1,  1,  0,
+];
+
+#[rustfmt::skip]
+const TRIANGLE: [u8; 64] = [
+    6, 0, 1, 2, 2, 1, 0, 6,
+    0, 7, 3, 4, 4, 3, 7, 0,
+    1, 3, 8, 5, 5, 8, 3, 1,
+    2, 4, 5, 9, 9, 5, 4, 2,
+    2, 4, 5, 9, 9, 5, 4, 2,
+    1, 3, 8, 5, 5, 8, 3, 1,
+    0, 7, 3, 4, 4, 3, 7, 0,
+    6, 0, 1, 2, 2, 1, 0, 6,
+];
+
+#[rustfmt::skip]
+const FLIP_DIAGONAL: [u8; 64] = [
+    0,  8, 16, 24, 32, 40, 48, 56,
+    1,  9, 17, 25, 33, 41, 49, 57,
+    2, 10, 18, 26, 34, 42, 50, 58,
+    3, 11, 19, 27, 35, 43, 51, 59,
+    4, 12, 20, 28, 36, 44, 52, 60,
+    5, 13, 21, 29, 37, 45, 53, 61,
+    6, 14, 22, 30, 38, 46, 54, 62,
+    7, 15, 23, 31, 39, 47, 55, 63,
+];
+
+#[rustfmt::skip]
+const LOWER: [u8; 64] = [
+    28,  0,  1,  2,  3,  4,  5,  6,
+     0, 29,  7,  8,  9, 10, 11, 12,
+     1,  7, 30, 13, 14, 15, 16, 17,
+     2,  8, 13, 31, 18, 19, 20, 21,
+     3,  9, 14, 18, 32, 22, 23, 24,
+     4, 10, 15, 19, 22, 33, 25, 26,
+     5, 11, 16, 20, 23, 25, 34, 27,
+     6, 12, 17, 21, 24, 26, 27, 35,
+];
+
+#[rustfmt::skip]
+const DIAGONAL: [u8; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  8,
+     0,  1,  0,  0,  0,  0,  9,  0,
+     0,  0,  2,  0,  0, 10,  0,  0,
+     0,  0,  0,  3, 11,  0,  0,  0,
+     0,  0,  0, 12,  4,  0,  0,  0,
+     0,  0, 13,  0,  0,  5,  0,  0,
+     0, 14,  0,  0,  0,  0,  6,  0,
+    15,  0,  0,  0,  0,  0,  0,  7,
+];
+
+/// The index of the square of the first leading pawn, by file then rank
+#[rustfmt::skip]
+const FLAP: [u8; 64] = [
+    0,  0,  0,  0,  0,  0,  0, 0,
+    0,  6, 12, 18, 18, 12,  6, 0,
+    1,  7, 13, 19, 19, 13,  7, 1,
+    2,  8, 14, 20, 20, 14,  8, 2,
+    3,  9, 15, 21, 21, 15,  9, 3,
+    4, 10, 16, 22, 22, 16, 10, 4,
+    5, 11, 17, 23, 23, 17, 11, 5,
+    0,  0,  0,  0,  0,  0,  0, 0,
+];
+
+/// The order of the squares of the other leading pawns
+#[rustfmt::skip]
+const PAWN_TWIST: [u8; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+    47, 35, 23, 11, 10, 22, 34, 46,
+    45, 33, 21,  9,  8, 20, 32, 44,
+    43, 31, 19,  7,  6, 18, 30, 42,
+    41, 29, 17,  5,  4, 16, 28, 40,
+    39, 27, 15,  3,  2, 14, 26, 38,
+    37, 25, 13,  1,  0, 12, 24, 36,
+     0,  0,  0,  0,  0,  0,  0,  0,
+];
+
+/// The index of the positions of both kings, by `TRIANGLE` index of the first one and square of the second one
+#[rustfmt::skip]
+const KK_INDEX: [[i16; 64]; 10] = [
+    [
+        -1, -1, -1,  0,  1,  2,  3,  4,
+        -1, -1, -1,  5,  6,  7,  8,  9,
+        10, 11, 12, 13, 14, 15, 16, 17,
+        18, 19, 20, 21, 22, 23, 24, 25,
+        26, 27, 28, 29, 30, 31, 32, 33,
+        34, 35, 36, 37, 38, 39, 40, 41,
+        42, 43, 44, 45, 46, 47, 48, 49,
+        50, 51, 52, 53, 54, 55, 56, 57,
+    ],
+    [
+         58,  -1,  -1,  -1,  59,  60,  61,  62,
+         63,  -1,  -1,  -1,  64,  65,  66,  67,
+         68,  69,  70,  71,  72,  73,  74,  75,
+         76,  77,  78,  79,  80,  81,  82,  83,
+         84,  85,  86,  87,  88,  89,  90,  91,
+         92,  93,  94,  95,  96,  97,  98,  99,
+        100, 101, 102, 103, 104, 105, 106, 107,
+        108, 109, 110, 111, 112, 113, 114, 115,
+    ],
+    [
+        116, 117,  -1,  -1,  -1, 118, 119, 120,
+        121, 122,  -1,  -1,  -1, 123, 124, 125,
+        126, 127, 128, 129, 130, 131, 132, 133,
+        134, 135, 136, 137, 138, 139, 140, 141,
+        142, 143, 144, 145, 146, 147, 148, 149,
+        150, 151, 152, 153, 154, 155, 156, 157,
+        158, 159, 160, 161, 162, 163, 164, 165,
+        166, 167, 168, 169, 170, 171, 172, 173,
+    ],
+    [
+        174,  -1,  -1,  -1, 175, 176, 177, 178,
+        179,  -1,  -1,  -1, 180, 181, 182, 183,
+        184,  -1,  -1,  -1, 185, 186, 187, 188,
+        189, 190, 191, 192, 193, 194, 195, 196,
+        197, 198, 199, 200, 201, 202, 203, 204,
+        205, 206, 207, 208, 209, 210, 211, 212,
+        213, 214, 215, 216, 217, 218, 219, 220,
+        221, 222, 223, 224, 225, 226, 227, 228,
+    ],
+    [
+        229, 230,  -1,  -1,  -1, 231, 232, 233,
+        234, 235,  -1,  -1,  -1, 236, 237, 238,
+        239, 240,  -1,  -1,  -1, 241, 242, 243,
+        244, 245, 246, 247, 248, 249, 250, 251,
+        252, 253, 254, 255, 256, 257, 258, 259,
+        260, 261, 262, 263, 264, 265, 266, 267,
+        268, 269, 270, 271, 272, 273, 274, 275,
+        276, 277, 278, 279, 280, 281, 282, 283,
+    ],
+    [
+        284, 285, 286, 287, 288, 289, 290, 291,
+        292, 293,  -1,  -1,  -1, 294, 295, 296,
+        297, 298,  -1,  -1,  -1, 299, 300, 301,
+        302, 303,  -1,  -1,  -1, 304, 305, 306,
+        307, 308, 309, 310, 311, 312, 313, 314,
+        315, 316, 317, 318, 319, 320, 321, 322,
+        323, 324, 325, 326, 327, 328, 329, 330,
+        331, 332, 333, 334, 335, 336, 337, 338,
+    ],
+    [
+         -1,  -1, 339, 340, 341, 342, 343, 344,
+         -1,  -1, 345, 346, 347, 348, 349, 350,
+         -1,  -1, 441, 351, 352, 353, 354, 355,
+         -1,  -1,  -1, 442, 356, 357, 358, 359,
+         -1,  -1,  -1,  -1, 443, 360, 361, 362,
+         -1,  -1,  -1,  -1,  -1, 444, 363, 364,
+         -1,  -1,  -1,  -1,  -1,  -1, 445, 365,
+         -1,  -1,  -1,  -1,  -1,  -1,  -1, 446,
+    ],
+    [
+         -1,  -1,  -1, 366, 367, 368, 369, 370,
+         -1,  -1,  -1, 371, 372, 373, 374, 375,
+         -1,  -1,  -1, 376, 377, 378, 379, 380,
+         -1,  -1,  -1, 447, 381, 382, 383, 384,
+         -1,  -1,  -1,  -1, 448, 385, 386, 387,
+         -1,  -1,  -1,  -1,  -1, 449, 388, 389,
+         -1,  -1,  -1,  -1,  -1,  -1, 450, 390,
+         -1,  -1,  -1,  -1,  -1,  -1,  -1, 451,
+    ],
+    [
+        452, 391, 392, 393, 394, 395, 396, 397,
+         -1,  -1,  -1,  -1, 398, 399, 400, 401,
+         -1,  -1,  -1,  -1, 402, 403, 404, 405,
+         -1,  -1,  -1,  -1, 406, 407, 408, 409,
+         -1,  -1,  -1,  -1, 453, 410, 411, 412,
+         -1,  -1,  -1,  -1,  -1, 454, 413, 414,
+         -1,  -1,  -1,  -1,  -1,  -1, 455, 415,
+         -1,  -1,  -1,  -1,  -1,  -1,  -1, 456,
+    ],
+    [
+        457, 416, 417, 418, 419, 420, 421, 422,
+         -1, 458, 423, 424, 425, 426, 427, 428,
+         -1,  -1,  -1,  -1,  -1, 429, 430, 431,
+         -1,  -1,  -1,  -1,  -1, 432, 433, 434,
+         -1,  -1,  -1,  -1,  -1, 435, 436, 437,
+         -1,  -1,  -1,  -1,  -1, 459, 438, 439,
+         -1,  -1,  -1,  -1,  -1,  -1, 460, 440,
+         -1,  -1,  -1,  -1,  -1,  -1,  -1, 461,
+    ],
+];
@@ -0,0 +1,1017 @@
+use std::{
+    fmt::Display,
+    io::{self, BufRead, Write},
+    str::FromStr,
+    sync::{Arc, Mutex},
+    thread::{self, JoinHandle},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    book::{Book, Selection},
+    eval::{nnue::Network, Score},
+    game::{Board, Move},
+    primitives::team::Team,
+    search::{
+        mate_in,
+        skill::Skill,
+        tt::{self, TranspositionTable},
+        Limits, PonderSignal, SearchResult, Searcher, StopSignal,
+    },
+    syzygy::Tablebase,
+};
+
+pub const NAME: &str = concat!(env!("CARGO_PKG_NAME"), " ", env!("CARGO_PKG_VERSION"));
+pub const AUTHOR: &str = "Chess contributors";
+
+/// The number of moves left in the game assumed by the time management when the GUI does not say
+const DEFAULT_MOVES_TO_GO: u32 = 30;
+
+/// Everything that can go wrong while reading a command
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UciError {
+    UnknownCommand(String),
+    /// The name of the missing argument
+    MissingArgument(&'static str),
+    InvalidArgument(String),
+    Fen(String),
+    /// The move that can't be played, in long algebraic notation
+    IllegalMove(String),
+}
+
+impl Display for UciError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnknownCommand(command) => write!(f, "Unknown command: {command}"),
+            Self::MissingArgument(argument) => write!(f, "Missing argument: {argument}"),
+            Self::InvalidArgument(argument) => write!(f, "Invalid argument: {argument}"),
+            Self::Fen(error) => write!(f, "Invalid FEN: {error}"),
+            Self::IllegalMove(chess_move) => write!(f, "Illegal move: {chess_move}"),
+        }
+    }
+}
+
+impl std::error::Error for UciError {}
+
+/// The parameters of the `go` command, times being in milliseconds
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Go {
+    pub depth: Option<u8>,
+    pub nodes: Option<u64>,
+    pub movetime: Option<u64>,
+    pub wtime: Option<u64>,
+    pub btime: Option<u64>,
+    pub winc: Option<u64>,
+    pub binc: Option<u64>,
+    pub movestogo: Option<u32>,
+    pub infinite: bool,
+    /// Whether to search the position of the predicted move on the opponent's time, until `ponderhit` or `stop`
+    pub ponder: bool,
+}
+
+impl Go {
+    /// The search limits for the `team` to move, keeping `overhead` for the communication with the GUI
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use engine::{search::Limits, uci::Go, Team};
+    ///
+    /// let go = Go { wtime: Some(60_000), winc: Some(1_000), ..Default::default() };
+    /// let limits = go.limits(Team::White, Duration::ZERO);
+    ///
+    /// assert_eq!(limits.time, Some(Duration::from_millis(60_000 / 30 + 750)));
+    /// assert_eq!(go.limits(Team::Black, Duration::ZERO), Limits::default());
+    /// ```
+    pub fn limits(&self, team: Team, overhead: Duration) -> Limits {
+        let mut limits = Limits {
+            depth: self.depth,
+            nodes: self.nodes,
+            time: None,
+        };
+
+        if self.infinite {
+            return limits;
+        }
+
+        let (time, increment) = match team {
+            Team::White => (self.wtime, self.winc),
+            Team::Black => (self.btime, self.binc),
+        };
+
+        limits.time = match (self.movetime, time) {
+            (Some(movetime), _) => Some(Duration::from_millis(movetime)),
+            (None, Some(time)) => {
+                let moves_to_go = self.movestogo.unwrap_or(DEFAULT_MOVES_TO_GO).max(1) as u64;
+                let allocated = time / moves_to_go + increment.unwrap_or(0) * 3 / 4;
+
+                Some(Duration::from_millis(allocated.min(time)))
+            }
+            (None, None) => None,
+        }
+        .map(|time| time.saturating_sub(overhead).max(Duration::from_millis(1)));
+
+        return limits;
+    }
+}
+
+/// Formats the parameters as the `go` command sending them
+///
+/// ```
+/// use engine::uci::{Go, UciCommand};
+///
+/// let mut go = Go { wtime: Some(60_000), btime: Some(59_000), winc: Some(100), binc: Some(100), ..Default::default() };
+///
+/// assert_eq!(go.to_string(), "go wtime 60000 btime 59000 winc 100 binc 100");
+/// assert!(matches!(go.to_string().parse(), Ok(UciCommand::Go(parsed)) if parsed == go));
+///
+/// go.ponder = true;
+/// assert_eq!(go.to_string(), "go ponder wtime 60000 btime 59000 winc 100 binc 100");
+/// assert!(matches!(go.to_string().parse(), Ok(UciCommand::Go(parsed)) if parsed == go));
+/// ```
+impl Display for Go {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let parameters = [
+            ("depth", self.depth.map(u64::from)),
+            ("nodes", self.nodes),
+            ("movetime", self.movetime),
+            ("wtime", self.wtime),
+            ("btime", self.btime),
+            ("winc", self.winc),
+            ("binc", self.binc),
+            ("movestogo", self.movestogo.map(u64::from)),
+        ];
+
+        write!(f, "go")?;
+        if self.ponder {
+            write!(f, " ponder")?;
+        }
+        for (name, value) in parameters {
+            if let Some(value) = value {
+                write!(f, " {name} {value}")?;
+            }
+        }
+        if self.infinite {
+            write!(f, " infinite")?;
+        }
+
+        return Ok(());
+    }
+}
+
+/// A command sent by the GUI
+#[derive(Debug, Clone)]
+pub enum UciCommand {
+    Uci,
+    Debug(bool),
+    IsReady,
+    SetOption { name: String, value: Option<String> },
+    UciNewGame,
+    /// The position to search, and the moves that were played to reach it
+    Position { board: Board, moves: Vec<Move> },
+    Go(Go),
+    Stop,
+    /// The opponent played the move the engine is pondering on
+    PonderHit,
+    Quit,
+}
+
+impl FromStr for UciCommand {
+    type Err = UciError;
+
+    /// Parses a line sent by the GUI
+    ///
+    /// ```
+    /// use engine::uci::{Go, UciCommand};
+    ///
+    /// assert!(matches!("isready".parse(), Ok(UciCommand::IsReady)));
+    /// assert!(matches!(
+    ///     "go depth 6 wtime 1000".parse(),
+    ///     Ok(UciCommand::Go(go)) if go == Go { depth: Some(6), wtime: Some(1000), ..Default::default() }
+    /// ));
+    /// assert!(matches!(
+    ///     "setoption name Move Overhead value 50".parse(),
+    ///     Ok(UciCommand::SetOption { name, value: Some(value) }) if name == "Move Overhead" && value == "50"
+    /// ));
+    /// assert!("position startpos moves e2e5".parse::<UciCommand>().is_err());
+    /// ```
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut tokens = s.split_whitespace();
+
+        let command = match tokens.next() {
+            Some("uci") => Self::Uci,
+            Some("debug") => Self::Debug(tokens.next() == Some("on")),
+            Some("isready") => Self::IsReady,
+            Some("setoption") => parse_setoption(tokens)?,
+            Some("ucinewgame") => Self::UciNewGame,
+            Some("position") => parse_position(tokens)?,
+            Some("go") => Self::Go(parse_go(tokens)?),
+            Some("stop") => Self::Stop,
+            Some("ponderhit") => Self::PonderHit,
+            Some("quit") => Self::Quit,
+            _ => return Err(UciError::UnknownCommand(s.trim().to_owned())),
+        };
+
+        return Ok(command);
+    }
+}
+
+fn parse_setoption<'a>(tokens: impl Iterator<Item = &'a str>) -> Result<UciCommand, UciError> {
+    let mut name = Vec::new();
+    let mut value = None::<Vec<&str>>;
+
+    for token in tokens.skip_while(|token| *token != "name").skip(1) {
+        match (token, &mut value) {
+            ("value", None) => value = Some(Vec::new()),
+            (token, Some(value)) => value.push(token),
+            (token, None) => name.push(token),
+        }
+    }
+
+    if name.is_empty() {
+        return Err(UciError::MissingArgument("name"));
+    }
+
+    return Ok(UciCommand::SetOption {
+        name: name.join(" "),
+        value: value.map(|value| value.join(" ")),
+    });
+}
+
+fn parse_position<'a>(mut tokens: impl Iterator<Item = &'a str>) -> Result<UciCommand, UciError> {
+    let mut board = match tokens.next() {
+        Some("startpos") => Board::default(),
+        Some("fen") => {
+            let fen = tokens.by_ref().take_while(|token| *token != "moves").collect::<Vec<_>>().join(" ");
+
+            Board::from_fen(&fen).map_err(|error| UciError::Fen(format!("{error:?}")))?
+        }
+        Some(token) => return Err(UciError::InvalidArgument(token.to_owned())),
+        None => return Err(UciError::MissingArgument("startpos | fen")),
+    };
+
+    let mut moves = Vec::new();
+    let starting_position = board;
+    for token in tokens.skip_while(|token| *token == "moves") {
+        let chess_move = token
+            .parse::<Move>()
+            .ok()
+            .filter(|chess_move| board.iter_moves().any(|legal| legal == *chess_move))
+            .ok_or_else(|| UciError::IllegalMove(token.to_owned()))?;
+
+        board.make_move(chess_move);
+        moves.push(chess_move);
+    }
+
+    return Ok(UciCommand::Position {
+        board: starting_position,
+        moves,
+    });
+}
+
+fn parse_go<'a>(mut tokens: impl Iterator<Item = &'a str>) -> Result<Go, UciError> {
+    fn value<'a, T: FromStr>(name: &'static str, tokens: &mut impl Iterator<Item = &'a str>) -> Result<T, UciError> {
+        let token = tokens.next().ok_or(UciError::MissingArgument(name))?;
+
+        return token
+            .parse()
+            .map_err(|_| UciError::InvalidArgument(format!("{name} {token}")));
+    }
+
+    let mut go = Go::default();
+    while let Some(token) = tokens.next() {
+        match token {
+            "depth" => go.depth = Some(value("depth", &mut tokens)?),
+            "nodes" => go.nodes = Some(value("nodes", &mut tokens)?),
+            "movetime" => go.movetime = Some(value("movetime", &mut tokens)?),
+            "wtime" => go.wtime = Some(value::<i64>("wtime", &mut tokens)?.max(0) as u64),
+            "btime" => go.btime = Some(value::<i64>("btime", &mut tokens)?.max(0) as u64),
+            "winc" => go.winc = Some(value("winc", &mut tokens)?),
+            "binc" => go.binc = Some(value("binc", &mut tokens)?),
+            "movestogo" => go.movestogo = Some(value("movestogo", &mut tokens)?),
+            "infinite" => go.infinite = true,
+            "ponder" => go.ponder = true,
+            // Unsupported parameters (e.g. `searchmoves`, `mate`) are ignored
+            _ => {}
+        }
+    }
+
+    return Ok(go);
+}
+
+/// The options of the engine, set with `setoption`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Options {
+    /// The time kept for the communication with the GUI, when playing with a clock
+    pub move_overhead: Duration,
+    /// The size of the transposition table, in MB
+    pub hash: usize,
+    /// The number of threads of the search
+    pub threads: usize,
+    /// The number of best lines to report
+    pub multi_pv: usize,
+    /// Whether the GUI may ask to ponder, which the engine supports either way
+    pub ponder: bool,
+    /// Whether to play the moves of the opening book, when there is one
+    pub own_book: bool,
+    /// The path of the Polyglot opening book
+    pub book_file: Option<String>,
+    /// The directories of the Syzygy tablebases, separated like in the `PATH` environment variable
+    pub syzygy_path: Option<String>,
+    /// The path of the neural network evaluating the positions, the hand-crafted evaluation being used without one
+    pub eval_file: Option<String>,
+    /// Whether to play at the strength of `elo` rather than at full strength
+    pub limit_strength: bool,
+    /// The approximate Elo rating to play at, when `limit_strength` is set
+    pub elo: u32,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            move_overhead: Duration::from_millis(30),
+            hash: tt::DEFAULT_SIZE_MB,
+            threads: 1,
+            multi_pv: 1,
+            ponder: false,
+            own_book: false,
+            book_file: None,
+            syzygy_path: None,
+            eval_file: None,
+            limit_strength: false,
+            elo: 1500,
+        }
+    }
+}
+
+/// The maximum size of the transposition table, in MB
+const MAX_HASH: usize = 65536;
+/// The maximum number of search threads
+const MAX_THREADS: usize = 1024;
+/// The maximum number of lines of a MultiPV search, more than the number of legal moves of any position
+const MAX_MULTI_PV: usize = 256;
+
+impl Options {
+    /// Prints the `option` lines of the `uci` command
+    fn write(&self, output: &mut impl Write) -> io::Result<()> {
+        let default = Options::default();
+
+        writeln!(output, "option name Hash type spin default {} min 1 max {MAX_HASH}", default.hash)?;
+        writeln!(output, "option name Clear Hash type button")?;
+        writeln!(output, "option name Threads type spin default {} min 1 max {MAX_THREADS}", default.threads)?;
+        writeln!(output, "option name MultiPV type spin default {} min 1 max {MAX_MULTI_PV}", default.multi_pv)?;
+        writeln!(output, "option name Ponder type check default {}", default.ponder)?;
+        writeln!(output, "option name OwnBook type check default {}", default.own_book)?;
+        writeln!(output, "option name Book File type string default <empty>")?;
+        writeln!(output, "option name SyzygyPath type string default <empty>")?;
+        writeln!(output, "option name EvalFile type string default <empty>")?;
+        writeln!(output, "option name UCI_LimitStrength type check default {}", default.limit_strength)?;
+        writeln!(
+            output,
+            "option name UCI_Elo type spin default {} min {} max {}",
+            default.elo,
+            Skill::MIN_ELO,
+            Skill::MAX_ELO
+        )?;
+        writeln!(
+            output,
+            "option name Move Overhead type spin default {} min 0 max 5000",
+            default.move_overhead.as_millis()
+        )
+    }
+
+    fn set(&mut self, name: &str, value: Option<&str>) -> Result<(), UciError> {
+        let invalid = || UciError::InvalidArgument(format!("{name} {}", value.unwrap_or_default()));
+
+        match name.to_lowercase().as_str() {
+            "hash" => {
+                let hash = value.and_then(|value| value.parse::<usize>().ok()).ok_or_else(invalid)?;
+                self.hash = hash.clamp(1, MAX_HASH);
+            }
+            "threads" => {
+                let threads = value.and_then(|value| value.parse::<usize>().ok()).ok_or_else(invalid)?;
+                self.threads = threads.clamp(1, MAX_THREADS);
+            }
+            "multipv" => {
+                let lines = value.and_then(|value| value.parse::<usize>().ok()).ok_or_else(invalid)?;
+                self.multi_pv = lines.clamp(1, MAX_MULTI_PV);
+            }
+            "ponder" => {
+                self.ponder = value.and_then(|value| value.parse::<bool>().ok()).ok_or_else(invalid)?;
+            }
+            "ownbook" => {
+                self.own_book = value.and_then(|value| value.parse::<bool>().ok()).ok_or_else(invalid)?;
+            }
+            "book file" => {
+                self.book_file = value
+                    .filter(|path| !path.is_empty() && *path != "<empty>")
+                    .map(str::to_owned);
+            }
+            "syzygypath" => {
+                self.syzygy_path = value
+                    .filter(|path| !path.is_empty() && *path != "<empty>")
+                    .map(str::to_owned);
+            }
+            "evalfile" => {
+                self.eval_file = value
+                    .filter(|path| !path.is_empty() && *path != "<empty>")
+                    .map(str::to_owned);
+            }
+            "uci_limitstrength" => {
+                self.limit_strength = value.and_then(|value| value.parse::<bool>().ok()).ok_or_else(invalid)?;
+            }
+            "uci_elo" => {
+                let elo = value.and_then(|value| value.parse::<u32>().ok()).ok_or_else(invalid)?;
+                self.elo = elo.clamp(Skill::MIN_ELO, Skill::MAX_ELO);
+            }
+            "move overhead" => {
+                let overhead = value.and_then(|value| value.parse::<u64>().ok()).ok_or_else(invalid)?;
+                self.move_overhead = Duration::from_millis(overhead.min(5000));
+            }
+            _ => return Err(UciError::InvalidArgument(format!("Unknown option {name}"))),
+        }
+
+        return Ok(());
+    }
+}
+
+/// Formats the `info` line of a search iteration, or one line per PV in MultiPV mode
+///
+/// ```
+/// use std::time::Duration;
+/// use engine::{search::{PvLine, SearchResult, MATE}, uci, Move, Square};
+///
+/// let mut result = SearchResult {
+///     best_move: Some(Move::new(Square::E2, Square::E4)),
+///     score: MATE - 3,
+///     depth: 4,
+///     nodes: 1000,
+///     pv: vec![Move::new(Square::E2, Square::E4)],
+///     time: Duration::from_millis(500),
+///     hashfull: 12,
+///     lines: Vec::new(),
+///     tb_hits: 0,
+/// };
+///
+/// assert_eq!(
+///     uci::info(&result),
+///     "info depth 4 score mate 2 nodes 1000 nps 2000 hashfull 12 time 500 pv e2e4"
+/// );
+///
+/// result.lines = vec![
+///     PvLine { score: MATE - 3, pv: vec![Move::new(Square::E2, Square::E4)] },
+///     PvLine { score: -20, pv: vec![Move::new(Square::D2, Square::D4)] },
+/// ];
+/// assert_eq!(
+///     uci::info(&result),
+///     "info depth 4 multipv 1 score mate 2 nodes 1000 nps 2000 hashfull 12 time 500 pv e2e4\n\
+///      info depth 4 multipv 2 score cp -20 nodes 1000 nps 2000 hashfull 12 time 500 pv d2d4"
+/// );
+///
+/// result.lines.clear();
+/// result.tb_hits = 3;
+/// assert_eq!(
+///     uci::info(&result),
+///     "info depth 4 score mate 2 nodes 1000 nps 2000 hashfull 12 tbhits 3 time 500 pv e2e4"
+/// );
+/// ```
+pub fn info(result: &SearchResult) -> String {
+    if result.lines.len() <= 1 {
+        return info_line(result, None, result.score, &result.pv);
+    }
+
+    return result
+        .lines
+        .iter()
+        .enumerate()
+        .map(|(index, line)| info_line(result, Some(index + 1), line.score, &line.pv))
+        .collect::<Vec<_>>()
+        .join("\n");
+}
+
+fn info_line(result: &SearchResult, multi_pv: Option<usize>, score: Score, pv: &[Move]) -> String {
+    let multi_pv = multi_pv.map(|index| format!(" multipv {index}")).unwrap_or_default();
+    let score = match mate_in(score) {
+        Some(moves) => format!("mate {moves}"),
+        None => format!("cp {score}"),
+    };
+    let millis = result.time.as_millis() as u64;
+    let pv = pv.iter().map(Move::to_string).collect::<Vec<_>>().join(" ");
+    let tb_hits = match result.tb_hits {
+        0 => String::new(),
+        hits => format!(" tbhits {hits}"),
+    };
+
+    return format!(
+        "info depth {}{multi_pv} score {score} nodes {} nps {} hashfull {}{tb_hits} time {millis} pv {pv}",
+        result.depth,
+        result.nodes,
+        result.nps(),
+        result.hashfull,
+    )
+    .trim_end()
+    .to_owned();
+}
+
+/// A UCI engine, writing its answers to `W`
+///
+/// The searches run on a separate thread, so that `stop` and `isready` can be answered while searching.
+///
+/// ```
+/// use engine::uci::Uci;
+///
+/// let mut uci = Uci::new(Vec::new());
+/// uci.run("position startpos moves e2e4\ngo depth 2\n".as_bytes()).unwrap();
+///
+/// let output = String::from_utf8(uci.output().clone()).unwrap();
+/// assert!(output.lines().last().unwrap().starts_with("bestmove "));
+/// ```
+pub struct Uci<W: Write + Send + 'static> {
+    output: Arc<Mutex<W>>,
+    options: Options,
+    debug: bool,
+    /// Kept from one search to the next, until `ucinewgame`
+    tt: Arc<TranspositionTable>,
+    /// The position to search
+    board: Board,
+    /// The hashes of the positions played before `board`, to detect repetitions
+    history: Vec<u64>,
+    search: Option<(JoinHandle<()>, StopSignal)>,
+    /// Whether the running search only stops when asked to
+    infinite: bool,
+    /// The signal of the running search, if it was started pondering
+    ponder: Option<PonderSignal>,
+    /// The book of `Options::book_file`, if it could be read
+    book: Option<Book>,
+    /// The seed of the book moves and of the moves of a limited strength, changed every game so that the engine doesn't
+    /// always play the same opening or make the same mistakes
+    seed: u64,
+    /// The tablebase of `Options::syzygy_path`, if it could be opened
+    tablebase: Option<Arc<Tablebase>>,
+    /// The network of `Options::eval_file`, if it could be read
+    network: Option<Arc<Network>>,
+}
+
+impl<W: Write + Send + 'static> Uci<W> {
+    pub fn new(output: W) -> Self {
+        Self {
+            output: Arc::new(Mutex::new(output)),
+            options: Options::default(),
+            debug: false,
+            tt: Arc::new(TranspositionTable::default()),
+            board: Board::default(),
+            history: Vec::new(),
+            search: None,
+            infinite: false,
+            ponder: None,
+            book: None,
+            seed: new_seed(),
+            tablebase: None,
+            network: None,
+        }
+    }
+
+    /// The output of the engine, only complete once the searches are over (see `wait`)
+    pub fn output(&self) -> std::sync::MutexGuard<'_, W> {
+        self.output.lock().unwrap()
+    }
+
+    pub fn options(&self) -> &Options {
+        &self.options
+    }
+
+    /// Reads and handles the commands until `quit` or the end of the input, then waits for the search to finish
+    pub fn run(&mut self, input: impl BufRead) -> io::Result<()> {
+        for line in input.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let command = match line.parse::<UciCommand>() {
+                Ok(command) => command,
+                Err(error) => {
+                    self.send(format!("info string {error}"))?;
+                    continue;
+                }
+            };
+
+            if !self.handle(command)? {
+                return Ok(());
+            }
+        }
+
+        // Without a GUI to stop it, an infinite or pondering search would never end
+        match self.infinite || self.is_pondering() {
+            true => self.stop(),
+            false => self.wait(),
+        }
+
+        return Ok(());
+    }
+
+    /// Handles a single command, returning `false` once the engine has to quit
+    pub fn handle(&mut self, command: UciCommand) -> io::Result<bool> {
+        match command {
+            UciCommand::Uci => {
+                let mut output = self.output();
+                writeln!(output, "id name {NAME}")?;
+                writeln!(output, "id author {AUTHOR}")?;
+                self.options.write(&mut *output)?;
+                writeln!(output, "uciok")?;
+                output.flush()?;
+            }
+            UciCommand::Debug(debug) => self.debug = debug,
+            UciCommand::IsReady => self.send("readyok")?,
+            UciCommand::SetOption { name, .. } if name.eq_ignore_ascii_case("clear hash") => {
+                self.stop();
+                self.tt.clear();
+            }
+            UciCommand::SetOption { name, value } => {
+                self.stop();
+                if let Err(error) = self.options.set(&name, value.as_deref()) {
+                    self.send(format!("info string {error}"))?;
+                }
+
+                if self.options.hash != self.tt.size_mb() {
+                    self.tt = Arc::new(TranspositionTable::new(self.options.hash));
+                }
+                if name.eq_ignore_ascii_case("book file") {
+                    self.load_book()?;
+                }
+                if name.eq_ignore_ascii_case("syzygypath") {
+                    self.load_tablebase()?;
+                }
+                if name.eq_ignore_ascii_case("evalfile") {
+                    self.load_network()?;
+                }
+            }
+            UciCommand::UciNewGame => {
+                self.stop();
+                self.tt.clear();
+                self.seed = new_seed();
+                self.board = Board::default();
+                self.history.clear();
+            }
+            UciCommand::Position { board, moves } => {
+                self.stop();
+                self.board = board;
+                self.history.clear();
+
+                for chess_move in moves {
+                    self.history.push(self.board.hash());
+                    self.board.make_move(chess_move);
+                }
+            }
+            UciCommand::Go(go) => self.go(go)?,
+            UciCommand::Stop => self.stop(),
+            UciCommand::PonderHit => {
+                if let Some(ponder) = &self.ponder {
+                    ponder.hit();
+                }
+            }
+            UciCommand::Quit => {
+                self.stop();
+                return Ok(false);
+            }
+        }
+
+        return Ok(true);
+    }
+
+    /// Reads the book of `Options::book_file`
+    fn load_book(&mut self) -> io::Result<()> {
+        self.book = None;
+
+        let Some(path) = self.options.book_file.as_ref() else {
+            return Ok(());
+        };
+        match Book::open(path) {
+            Ok(book) => {
+                self.send(format!("info string Book {path} loaded ({} entries)", book.len()))?;
+                self.book = Some(book);
+            }
+            Err(error) => self.send(format!("info string {error}"))?,
+        }
+
+        return Ok(());
+    }
+
+    /// Opens the tablebase of `Options::syzygy_path`
+    fn load_tablebase(&mut self) -> io::Result<()> {
+        self.tablebase = None;
+
+        let Some(path) = self.options.syzygy_path.as_ref() else {
+            return Ok(());
+        };
+        match Tablebase::open(path) {
+            Ok(tablebase) => {
+                self.send(format!("info string Found {} tablebase files (up to {} pieces)", tablebase.len(), tablebase.max_pieces()))?;
+                self.tablebase = Some(Arc::new(tablebase));
+            }
+            Err(error) => self.send(format!("info string {error}"))?,
+        }
+
+        return Ok(());
+    }
+
+    /// Reads the network of `Options::eval_file`
+    fn load_network(&mut self) -> io::Result<()> {
+        self.network = None;
+
+        let Some(path) = self.options.eval_file.as_ref() else {
+            return Ok(());
+        };
+        match Network::open(path) {
+            Ok(network) => {
+                self.send(format!("info string Network {path} loaded ({} hidden neurons)", network.hidden_size()))?;
+                self.network = Some(Arc::new(network));
+            }
+            Err(error) => self.send(format!("info string {error}"))?,
+        }
+
+        return Ok(());
+    }
+
+    /// Starts searching the current position on a new thread, or plays a move of the book right away
+    fn go(&mut self, go: Go) -> io::Result<()> {
+        self.stop();
+
+        let book_move = match &self.book {
+            Some(book) if self.options.own_book && !go.infinite && !go.ponder => book.pick(&self.board, Selection::Weighted(self.seed)),
+            _ => None,
+        };
+        if let Some(chess_move) = book_move {
+            self.send("info string book move")?;
+            return self.send(format!("bestmove {chess_move}"));
+        }
+
+        let limits = go.limits(self.board.team_to_move, self.options.move_overhead);
+        if self.debug {
+            self.send(format!("info string limits {limits:?}"))?;
+        }
+
+        let stop = StopSignal::default();
+        let mut searcher = Searcher::new(limits)
+            .with_stop_signal(stop.clone())
+            .with_history(self.history.clone())
+            .with_transposition_table(Arc::clone(&self.tt))
+            .with_threads(self.options.threads)
+            .with_multi_pv(self.options.multi_pv);
+        if let Some(tablebase) = &self.tablebase {
+            searcher = searcher.with_tablebase(Arc::clone(tablebase));
+        }
+        if let Some(network) = &self.network {
+            searcher = searcher.with_network(Arc::clone(network));
+        }
+        if self.options.limit_strength {
+            searcher = searcher.with_skill(Skill::from_elo(self.options.elo), self.seed);
+        }
+        self.ponder = go.ponder.then(PonderSignal::default);
+        if let Some(ponder) = &self.ponder {
+            searcher = searcher.with_ponder(ponder.clone());
+        }
+        let ponder = self.ponder.clone();
+        let board = self.board;
+        let debug = self.debug;
+        let output = Arc::clone(&self.output);
+        let infinite = go.infinite;
+        self.infinite = infinite;
+        let signal = stop.clone();
+
+        let handle = thread::spawn(move || {
+            let result = searcher.search_with(&board, |result| {
+                let mut output = output.lock().unwrap();
+                let _ = writeln!(output, "{}", info(result)).and_then(|_| output.flush());
+            });
+
+            // An infinite search only reports its move once asked to stop, and a pondering one once the predicted move
+            // is played
+            let waiting = || infinite || ponder.as_ref().is_some_and(|ponder| !ponder.is_hit());
+            while waiting() && !signal.is_stopped() {
+                thread::sleep(Duration::from_millis(1));
+            }
+
+            let mut output = output.lock().unwrap();
+            if let Some(tt) = searcher.transposition_table().filter(|_| debug) {
+                let _ = writeln!(output, "info string tt {}", tt.stats());
+            }
+
+            let best_move = match result.best_move {
+                Some(chess_move) => chess_move.to_string(),
+                None => "0000".to_owned(),
+            };
+            let _ = match result.pv.get(1) {
+                Some(ponder) => writeln!(output, "bestmove {best_move} ponder {ponder}"),
+                None => writeln!(output, "bestmove {best_move}"),
+            }
+            .and_then(|_| output.flush());
+        });
+
+        self.search = Some((handle, stop));
+
+        return Ok(());
+    }
+
+    /// Stops the running search, if any, and waits for its `bestmove`
+    pub fn stop(&mut self) {
+        if let Some((_, stop)) = &self.search {
+            stop.stop();
+        }
+
+        self.wait();
+    }
+
+    /// Whether the running search ponders, the predicted move not being played yet
+    fn is_pondering(&self) -> bool {
+        self.search.is_some() && self.ponder.as_ref().is_some_and(|ponder| !ponder.is_hit())
+    }
+
+    /// Waits for the running search, if any, to finish
+    pub fn wait(&mut self) {
+        if let Some((handle, _)) = self.search.take() {
+            let _ = handle.join();
+        }
+    }
+
+    fn send(&self, line: impl Display) -> io::Result<()> {
+        let mut output = self.output();
+        writeln!(output, "{line}")?;
+
+        return output.flush();
+    }
+}
+
+/// A seed for the book moves and the limited strength, different from one game to the next
+fn new_seed() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_nanos() as u64)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{book::BookBuilder, primitives::Square};
+
+    fn run(input: &str) -> Vec<String> {
+        let mut uci = Uci::new(Vec::new());
+        uci.run(input.as_bytes()).unwrap();
+
+        let output = String::from_utf8(uci.output().clone()).unwrap();
+        return output.lines().map(str::to_owned).collect();
+    }
+
+    #[test]
+    fn commands() {
+        let output = run("uci\nisready\nsetoption name Move Overhead value 10\nsetoption name Nope value 1\nfoo\n");
+
+        assert_eq!(output[0], format!("id name {NAME}"));
+        assert!(output.contains(&"uciok".to_owned()));
+        assert!(output.contains(&"readyok".to_owned()));
+        assert_eq!(
+            output.iter().filter(|line| line.starts_with("info string")).count(),
+            2,
+            "{output:?}"
+        );
+
+        let mut uci = Uci::new(Vec::new());
+        uci.run("setoption name move overhead value 10\nsetoption name Hash value 2\nsetoption name Threads value 2".as_bytes()).unwrap();
+        assert_eq!(uci.options().move_overhead, Duration::from_millis(10));
+        assert_eq!(uci.options().threads, 2);
+        assert_eq!(uci.tt.size_mb(), 2);
+        uci.run("setoption name UCI_Elo value 9000\n".as_bytes()).unwrap();
+        assert_eq!(uci.options().elo, Skill::MAX_ELO);
+
+        uci.run("go depth 3\n".as_bytes()).unwrap();
+        assert!(uci.tt.stats().stores > 0);
+        uci.run("setoption name Clear Hash\n".as_bytes()).unwrap();
+        assert_eq!(uci.tt.stats().stores, 0);
+    }
+
+    #[test]
+    fn position() {
+        let command = "position fen 4k3/8/8/8/8/8/4P3/4K3 w - - 0 1 moves e2e4 e8d7".parse::<UciCommand>();
+        let Ok(UciCommand::Position { board, moves }) = command else {
+            panic!("{command:?}");
+        };
+        assert_eq!(board.to_fen(), "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1");
+        assert_eq!(moves, vec![Move::new(Square::E2, Square::E4), Move::new(Square::E8, Square::D7)]);
+
+        assert_eq!(
+            "position startpos moves e2e4 e2e4".parse::<UciCommand>().unwrap_err(),
+            UciError::IllegalMove("e2e4".to_owned())
+        );
+        assert_eq!(
+            "position".parse::<UciCommand>().unwrap_err(),
+            UciError::MissingArgument("startpos | fen")
+        );
+        assert!(matches!("position fen 8/8 w".parse::<UciCommand>(), Err(UciError::Fen(_))));
+
+        // Mate in one, after the moves
+        let output = run("position startpos moves f2f3 e7e5 g2g4\ngo depth 3\n");
+        assert_eq!(output.last().unwrap(), "bestmove d8h4");
+        assert!(output.iter().any(|line| line.starts_with("info depth 3 score mate 1")));
+    }
+
+    #[test]
+    fn book() {
+        let mut builder = BookBuilder::new();
+        builder.add_pgn("1. e4 e5 2. Nf3 1-0").unwrap();
+        let path = std::env::temp_dir().join(format!("uci-book-{}.bin", std::process::id()));
+        builder.build().save(&path).unwrap();
+
+        let options = format!("setoption name Book File value {}\nsetoption name OwnBook value true\n", path.display());
+        let output = run(&format!("{options}go depth 4\n"));
+        assert!(output[0].starts_with("info string Book"), "{output:?}");
+        assert_eq!(output[1..], ["info string book move", "bestmove e2e4"]);
+
+        // Out of book
+        let output = run(&format!("{options}position startpos moves d2d4\ngo depth 1\n"));
+        assert!(output.iter().any(|line| line.starts_with("info depth 1")));
+
+        let output = run("setoption name Book File value /nonexistent.bin\nsetoption name OwnBook value true\ngo depth 1\n");
+        assert!(output[0].starts_with("info string Could not read the book"), "{output:?}");
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn tablebase() {
+        let options = format!("setoption name SyzygyPath value {}\n", crate::syzygy::TABLES);
+        let output = run(&format!("{options}position fen 8/4r3/8/K7/4R3/8/8/5k2 w - - 0 1\ngo depth 3\n"));
+        assert_eq!(output[0], "info string Found 13 tablebase files (up to 4 pieces)");
+        assert!(output.iter().any(|line| line.contains(" tbhits ")), "{output:?}");
+        assert!(output.last().unwrap().starts_with("bestmove e4e7"));
+
+        let output = run("setoption name SyzygyPath value /nonexistent\n");
+        assert_eq!(output, ["info string /nonexistent is not a directory"]);
+    }
+
+    #[test]
+    fn network() {
+        let options = format!("setoption name EvalFile value {}\n", crate::eval::nnue::TEST_NETWORK);
+        let output = run(&format!("{options}position fen 4k3/8/8/1q6/8/8/8/1R2K3 w - - 0 1\ngo depth 3\n"));
+        assert!(output[0].starts_with("info string Network ") && output[0].ends_with("(16 hidden neurons)"));
+        assert!(output.last().unwrap().starts_with("bestmove b1b5"));
+
+        let output = run("setoption name EvalFile value /nonexistent.nnue\n");
+        assert!(output[0].starts_with("info string Could not read the network"), "{output:?}");
+    }
+
+    #[test]
+    fn go() {
+        let output = run("go movetime 100\n");
+        assert!(output.last().unwrap().starts_with("bestmove "));
+
+        // Stopped before the end of the input
+        let output = run("go infinite\nisready\nstop\n");
+        assert!(output.last().unwrap().starts_with("bestmove "));
+        assert!(output.contains(&"readyok".to_owned()));
+
+        let output = run("setoption name UCI_LimitStrength value true\nsetoption name UCI_Elo value 800\ngo depth 5\n");
+        assert!(output.iter().all(|line| !line.starts_with("info depth 2 ")), "{output:?}");
+        assert!(output.last().unwrap().starts_with("bestmove "));
+
+        let output = run("setoption name MultiPV value 2\ngo depth 2\n");
+        let infos = output.iter().filter(|line| line.starts_with("info depth 2 ")).collect::<Vec<_>>();
+        assert!(infos[0].contains(" multipv 1 ") && infos[1].contains(" multipv 2 "), "{infos:?}");
+
+        // No legal moves
+        let output = run("position fen 7k/5Q2/6K1/8/8/8/8/8 b - - 0 1\ngo depth 1\n");
+        assert_eq!(output.last().unwrap(), "bestmove 0000");
+
+        let go = Go {
+            btime: Some(100),
+            movestogo: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(go.limits(Team::Black, Duration::from_millis(30)).time, Some(Duration::from_millis(70)));
+        assert_eq!(go.limits(Team::Black, Duration::from_secs(1)).time, Some(Duration::from_millis(1)));
+    }
+
+    #[test]
+    fn ponder() {
+        let mut uci = Uci::new(Vec::new());
+        uci.handle("setoption name Ponder value true".parse().unwrap()).unwrap();
+        assert!(uci.options().ponder);
+
+        // The move is only reported once the predicted move is played
+        uci.handle("position startpos moves e2e4 e7e5".parse().unwrap()).unwrap();
+        uci.handle("go ponder depth 2 wtime 1000 btime 1000".parse().unwrap()).unwrap();
+        std::thread::sleep(Duration::from_millis(200));
+        assert!(uci.is_pondering());
+        assert!(!String::from_utf8(uci.output().clone()).unwrap().contains("bestmove"));
+
+        uci.handle(UciCommand::PonderHit).unwrap();
+        uci.wait();
+        assert!(!uci.is_pondering());
+        let output = String::from_utf8(uci.output().clone()).unwrap();
+        assert!(output.lines().last().unwrap().starts_with("bestmove "), "{output}");
+
+        // Or once stopped, when the opponent played another move
+        let output = run("go ponder movetime 10\nstop\n");
+        assert!(output.last().unwrap().starts_with("bestmove "));
+        let output = run("go ponder movetime 10\nponderhit\n");
+        assert!(output.last().unwrap().starts_with("bestmove "));
+    }
+}